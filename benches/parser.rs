@@ -0,0 +1,37 @@
+//! Parsing of a fixed 64 KiB corpus of mixed input: ASCII and multibyte UTF-8 text,
+//! cursor, editing and function keys with and without modifiers, Alt-modified letters
+//! and control characters. The corpus is `assets/bench_input`.
+//!
+//! Run with `cargo bench --bench parser`. Compare results against a run of the base
+//! commit on the same machine, absolute numbers don't carry over between machines.
+//! The throughput of the corpus repeated 8 times should match the single corpus,
+//! a lower one points at super-linear parsing.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use nixtui_core::input::InputParser;
+
+const CORPUS: &[u8] = include_bytes!("../assets/bench_input");
+
+fn parser() -> InputParser {
+    let mut parser = InputParser::new();
+    parser.push_default();
+    parser
+}
+
+fn parse_corpus(c: &mut Criterion) {
+    let parser = parser();
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(CORPUS.len() as u64));
+    group.bench_function("mixed corpus", |b| {
+        b.iter(|| parser.parse(std::hint::black_box(CORPUS)))
+    });
+    let large = CORPUS.repeat(8);
+    group.throughput(Throughput::Bytes(large.len() as u64));
+    group.bench_function("mixed corpus x8", |b| {
+        b.iter(|| parser.parse(std::hint::black_box(&large)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_corpus);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! Expansion of terminfo capabilities for a frame of 10 000 positioned, styled cells,
+//! each moved to, colored, optionally made bold, written and reset, then flushed.
+//! Uses the kitty entry in `assets/test_kitty_database`.
+//!
+//! Run with `cargo bench --bench sequences`. Compare results against a run of the base
+//! commit on the same machine, absolute numbers don't carry over between machines.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use nixtui_core::tty::TerminfoWrapper;
+use terminfo::Database;
+
+const CELLS: usize = 10_000;
+const COLS: usize = 200;
+
+fn styled_cells(terminfo: &mut TerminfoWrapper, out: &mut Vec<u8>) {
+    for i in 0..CELLS {
+        terminfo.move_cursor(i / COLS, i % COLS).unwrap();
+        terminfo.set_a_foreground((i % 256) as u8).unwrap();
+        if i % 2 == 0 {
+            terminfo.enter_bold_mode().unwrap();
+        }
+        terminfo.append(&[b'a' + (i % 26) as u8]);
+        terminfo.exit_attribute_mode().unwrap();
+    }
+    terminfo.flush_to(out).unwrap();
+}
+
+fn emit_cells(c: &mut Criterion) {
+    let mut terminfo: TerminfoWrapper = Database::from_path("assets/test_kitty_database")
+        .unwrap()
+        .into();
+    let mut out = Vec::new();
+    let mut group = c.benchmark_group("emit");
+    group.throughput(Throughput::Elements(CELLS as u64));
+    group.bench_function("10k styled cells", |b| {
+        b.iter(|| {
+            out.clear();
+            styled_cells(&mut terminfo, &mut out);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, emit_cells);
+criterion_main!(benches);
@@ -0,0 +1,328 @@
+//! Color depth output is limited to, see [`ColorPolicy`].
+
+use crate::env::EnvSnapshot;
+
+/// How many colors the terminal shows, from none to 24-bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorLevel {
+    Off,
+    /// The 8 standard colors and their bright variants.
+    Basic16,
+    Indexed256,
+    Truecolor,
+}
+
+/// Color as written by the application, before it is fit to a [`ColorLevel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// Index into the palette of the terminal.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorPolicy {
+    pub level: ColorLevel,
+    /// At [`ColorLevel::Off`], also leave out attributes like bold or reverse.
+    pub strip_attributes: bool,
+}
+
+impl ColorPolicy {
+    pub fn new(level: ColorLevel) -> Self {
+        Self {
+            level,
+            strip_attributes: false,
+        }
+    }
+
+    /// Level the terminal supports, as far as `COLORTERM` and the `max_colors`
+    /// capability tell, then overridden by the user:
+    /// `NO_COLOR` and `CLICOLOR=0` turn colors off, `CLICOLOR_FORCE` turns at least 16 colors on.
+    /// `NO_COLOR` wins over `CLICOLOR_FORCE`, which wins over `CLICOLOR`.
+    pub fn detect(env: &EnvSnapshot, max_colors: Option<i32>) -> Self {
+        let set = |value: &Option<String>| value.as_deref().is_some_and(|v| !v.is_empty());
+        let mut level = match max_colors {
+            _ if env.truecolor() => ColorLevel::Truecolor,
+            Some(n) if n >= 256 => ColorLevel::Indexed256,
+            Some(n) if n >= 8 => ColorLevel::Basic16,
+            _ => ColorLevel::Off,
+        };
+        if set(&env.no_color) {
+            level = ColorLevel::Off;
+        } else if set(&env.clicolor_force) && env.clicolor_force.as_deref() != Some("0") {
+            level = level.max(ColorLevel::Basic16);
+        } else if env.clicolor.as_deref() == Some("0") {
+            level = ColorLevel::Off;
+        }
+        Self::new(level)
+    }
+
+    /// Closest color the level can show, `None` at [`ColorLevel::Off`].
+    pub fn downgrade(&self, color: Color) -> Option<Color> {
+        match (self.level, color) {
+            (ColorLevel::Off, _) => None,
+            (ColorLevel::Truecolor, color) => Some(color),
+            (ColorLevel::Indexed256, Color::Rgb(r, g, b)) => {
+                Some(Color::Indexed(rgb_to_indexed(r, g, b)))
+            }
+            (ColorLevel::Basic16, Color::Rgb(r, g, b)) => {
+                Some(Color::Indexed(nearest_basic((r, g, b))))
+            }
+            (ColorLevel::Basic16, Color::Indexed(index)) => {
+                Some(Color::Indexed(indexed_to_basic(index)))
+            }
+            (_, color) => Some(color),
+        }
+    }
+
+    /// Whether attributes other than colors are emitted.
+    pub fn attributes(&self) -> bool {
+        !(self.level == ColorLevel::Off && self.strip_attributes)
+    }
+}
+
+impl Default for ColorPolicy {
+    /// Everything is emitted as written.
+    fn default() -> Self {
+        Self::new(ColorLevel::Truecolor)
+    }
+}
+
+/// Colors the 16 basic palette entries are drawn with instead of the terminal palette,
+/// so parts of the screen can have their own theme while the application keeps writing
+/// `Indexed(0)` to `Indexed(15)`.
+///
+/// Mapped entries become [`Color::Rgb`], which [`ColorPolicy::downgrade`] fits to the
+/// nearest entry of the 256 color palette where truecolor is not available.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PaletteMap {
+    colors: [Option<(u8, u8, u8)>; 16],
+}
+
+impl PaletteMap {
+    /// Map leaving every color to the terminal palette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws palette entry `index` as `rgb`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is 16 or more.
+    pub fn color(mut self, index: u8, rgb: (u8, u8, u8)) -> Self {
+        assert!(
+            index < 16,
+            "only the first 16 palette entries can be mapped"
+        );
+        self.colors[usize::from(index)] = Some(rgb);
+        self
+    }
+
+    pub fn map(&self, color: Color) -> Color {
+        match color {
+            Color::Indexed(index) => match self.colors.get(usize::from(index)) {
+                Some(&Some((r, g, b))) => Color::Rgb(r, g, b),
+                _ => color,
+            },
+            color => color,
+        }
+    }
+}
+
+/// Values of the channels in the 6x6x6 cube of the 256 color palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Default xterm values of the first 16 colors.
+const BASIC: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_cube_step(value: u8) -> usize {
+    (0..CUBE.len())
+        .min_by_key(|&i| (i32::from(CUBE[i]) - i32::from(value)).abs())
+        .unwrap()
+}
+
+/// Closest entry of the 256 color palette, from the color cube or the gray ramp.
+pub fn rgb_to_indexed(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (
+        nearest_cube_step(r),
+        nearest_cube_step(g),
+        nearest_cube_step(b),
+    );
+    let cube = (CUBE[ri], CUBE[gi], CUBE[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    // The ramp goes from 8 to 238 in steps of 10
+    let step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + 10 * step;
+    if distance((gray, gray, gray), (r, g, b)) < distance(cube, (r, g, b)) {
+        232 + step
+    } else {
+        cube_index as u8
+    }
+}
+
+/// RGB value of an entry of the 256 color palette, assuming the xterm defaults.
+pub fn indexed_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => BASIC[usize::from(index)],
+        16..=231 => {
+            let i = usize::from(index - 16);
+            (CUBE[i / 36], CUBE[i / 6 % 6], CUBE[i % 6])
+        }
+        _ => {
+            let gray = 8 + 10 * (index - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn nearest_basic(rgb: (u8, u8, u8)) -> u8 {
+    (0..16u8)
+        .min_by_key(|&i| distance(BASIC[usize::from(i)], rgb))
+        .unwrap()
+}
+
+/// Closest of the first 16 entries of the palette.
+pub fn indexed_to_basic(index: u8) -> u8 {
+    if index < 16 {
+        index
+    } else {
+        nearest_basic(indexed_to_rgb(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrade_each_level() {
+        let orange = Color::Rgb(255, 135, 0);
+        let levels = [
+            (ColorLevel::Truecolor, Some(orange)),
+            (ColorLevel::Indexed256, Some(Color::Indexed(208))),
+            (ColorLevel::Basic16, Some(Color::Indexed(3))),
+            (ColorLevel::Off, None),
+        ];
+        for (level, expected) in levels {
+            assert_eq!(ColorPolicy::new(level).downgrade(orange), expected);
+        }
+        let basic = ColorPolicy::new(ColorLevel::Basic16);
+        assert_eq!(basic.downgrade(Color::Indexed(4)), Some(Color::Indexed(4)));
+        assert_eq!(
+            basic.downgrade(Color::Indexed(196)),
+            Some(Color::Indexed(9))
+        );
+        assert_eq!(
+            basic.downgrade(Color::Indexed(232)),
+            Some(Color::Indexed(0))
+        );
+    }
+
+    #[test]
+    fn palette() {
+        assert_eq!(rgb_to_indexed(0, 0, 0), 16);
+        assert_eq!(rgb_to_indexed(255, 255, 255), 231);
+        assert_eq!(rgb_to_indexed(128, 128, 128), 244);
+        assert_eq!(rgb_to_indexed(95, 135, 175), 67);
+        for index in 16..=255 {
+            let (r, g, b) = indexed_to_rgb(index);
+            assert_eq!(rgb_to_indexed(r, g, b), index, "{index}");
+        }
+    }
+
+    #[test]
+    fn quantization() {
+        // Close colors go to the same cube entry, the steps of the cube are uneven
+        assert_eq!(rgb_to_indexed(250, 0, 0), 196);
+        assert_eq!(rgb_to_indexed(255, 10, 5), 196);
+        assert_eq!(rgb_to_indexed(0, 47, 255), 21);
+        assert_eq!(rgb_to_indexed(0, 48, 255), 27);
+        // Grays prefer the finer ramp over the cube
+        assert_eq!(rgb_to_indexed(8, 8, 8), 232);
+        assert_eq!(rgb_to_indexed(238, 238, 238), 255);
+        assert_eq!(rgb_to_indexed(100, 101, 99), 241);
+        // Saturated colors never end up gray
+        assert!(rgb_to_indexed(200, 30, 30) < 232);
+    }
+
+    #[test]
+    fn palette_map() {
+        let map = PaletteMap::new()
+            .color(1, (200, 40, 40))
+            .color(15, (250, 250, 240));
+        assert_eq!(map.map(Color::Indexed(1)), Color::Rgb(200, 40, 40));
+        assert_eq!(map.map(Color::Indexed(15)), Color::Rgb(250, 250, 240));
+        assert_eq!(map.map(Color::Indexed(2)), Color::Indexed(2));
+        assert_eq!(map.map(Color::Indexed(100)), Color::Indexed(100));
+        assert_eq!(map.map(Color::Rgb(1, 2, 3)), Color::Rgb(1, 2, 3));
+
+        let indexed = ColorPolicy::new(ColorLevel::Indexed256);
+        assert_eq!(
+            indexed.downgrade(map.map(Color::Indexed(1))),
+            Some(Color::Indexed(160))
+        );
+    }
+
+    #[test]
+    fn detect() {
+        let env = EnvSnapshot::default().colorterm("truecolor");
+        let level = |env: &EnvSnapshot, colors| ColorPolicy::detect(env, colors).level;
+        assert_eq!(level(&env, Some(256)), ColorLevel::Truecolor);
+        assert_eq!(
+            level(&EnvSnapshot::default(), Some(256)),
+            ColorLevel::Indexed256
+        );
+        assert_eq!(level(&EnvSnapshot::default(), Some(8)), ColorLevel::Basic16);
+        assert_eq!(level(&EnvSnapshot::default(), None), ColorLevel::Off);
+        assert_eq!(
+            level(&env.clone().no_color("1"), Some(256)),
+            ColorLevel::Off
+        );
+        // An empty NO_COLOR is the same as not set
+        assert_eq!(
+            level(&env.clone().no_color(""), Some(256)),
+            ColorLevel::Truecolor
+        );
+        let forced = EnvSnapshot::default().clicolor_force("1");
+        assert_eq!(level(&forced, None), ColorLevel::Basic16);
+        assert_eq!(level(&forced.clone().no_color("1"), None), ColorLevel::Off);
+        let not_forced = EnvSnapshot::default().clicolor_force("0");
+        assert_eq!(level(&not_forced, None), ColorLevel::Off);
+
+        let off = EnvSnapshot::default().clicolor("0");
+        assert_eq!(level(&off, Some(256)), ColorLevel::Off);
+        assert_eq!(
+            level(&off.clone().clicolor_force("1"), None),
+            ColorLevel::Basic16
+        );
+        assert_eq!(
+            level(&EnvSnapshot::default().clicolor("1"), Some(256)),
+            ColorLevel::Indexed256
+        );
+    }
+}
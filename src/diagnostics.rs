@@ -0,0 +1,347 @@
+//! Summary of how the crate sees the terminal, meant to be attached to bug reports.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::os::fd::AsFd;
+use std::path::PathBuf;
+
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::termios::SetArg;
+use terminfo::{Database, Value};
+
+use crate::env::EnvSnapshot;
+use crate::input::InputParser;
+use crate::tty::UnixTerminal;
+
+/// Standard capabilities the crate emits.
+pub const USED_CAPABILITIES: &[&str] = &[
+    "cursor_address",
+    "clear_screen",
+    "clr_eol",
+    "clr_eos",
+    "clr_bol",
+    "cursor_invisible",
+    "cursor_normal",
+    "enter_ca_mode",
+    "exit_ca_mode",
+    "keypad_xmit",
+    "keypad_local",
+    "exit_attribute_mode",
+    "enter_bold_mode",
+    "enter_dim_mode",
+    "enter_italics_mode",
+    "enter_underline_mode",
+    "enter_blink_mode",
+    "enter_reverse_mode",
+    "enter_secure_mode",
+    "max_colors",
+    "set_a_foreground",
+    "set_a_background",
+    "orig_pair",
+    "change_scroll_region",
+];
+
+/// Widely used user-defined capabilities, only the ones present are reported.
+pub const EXTENDED_CAPABILITIES: &[&str] = &[
+    "AX", "BD", "BE", "E3", "Ms", "PE", "PS", "RGB", "Se", "Setulc", "Smulx", "Ss", "Su", "Sync",
+    "Tc", "XM", "XT", "fullkbd", "rmxx", "smxx",
+];
+
+/// How long [`probe_interactive`] waits for each reply.
+const PROBE_TIMEOUT_MS: u16 = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityValue {
+    pub name: String,
+    /// Escaped value, `None` when the capability is absent.
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyMapping {
+    /// Escaped control sequence.
+    pub sequence: String,
+    pub key_code: u32,
+}
+
+/// Reply of the terminal to a query sent by [`probe_interactive`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbeResult {
+    pub name: String,
+    /// Escaped reply, `None` when the terminal didn't answer in time.
+    pub reply: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiagnosticReport {
+    pub term: Option<String>,
+    pub colorterm: Option<String>,
+    /// Terminal emulator with its version, see [`EnvSnapshot::terminal_program`].
+    pub terminal_program: Option<String>,
+    pub entry_name: String,
+    pub entry_aliases: Vec<String>,
+    /// Where the entry for `term` is found, `None` if it isn't on disk.
+    pub entry_path: Option<PathBuf>,
+    pub capabilities: Vec<CapabilityValue>,
+    pub extended_capabilities: Vec<CapabilityValue>,
+    pub key_mappings: Vec<KeyMapping>,
+    pub probes: Vec<ProbeResult>,
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::True => "true".into(),
+        Value::Number(n) => n.to_string(),
+        Value::String(bytes) => bytes.escape_ascii().to_string(),
+    }
+}
+
+/// Collects everything that can be known without talking to the terminal.
+pub fn report(db: &Database, env: &EnvSnapshot) -> DiagnosticReport {
+    let capability = |name: &&str| CapabilityValue {
+        name: name.to_string(),
+        value: db.raw(name).map(format_value),
+    };
+    DiagnosticReport {
+        term: env.term.clone(),
+        colorterm: env.colorterm.clone(),
+        terminal_program: env.terminal_program().map(|program| {
+            match (&env.term_program, &env.term_program_version) {
+                (Some(_), Some(version)) => format!("{program} {version}"),
+                _ => program.into(),
+            }
+        }),
+        entry_name: db.name().into(),
+        entry_aliases: db.aliases().to_vec(),
+        entry_path: env.terminfo_path(),
+        capabilities: USED_CAPABILITIES.iter().map(capability).collect(),
+        extended_capabilities: EXTENDED_CAPABILITIES
+            .iter()
+            .map(capability)
+            .filter(|c| c.value.is_some())
+            .collect(),
+        key_mappings: InputParser::from_terminfo(db)
+            .mappings()
+            .into_iter()
+            .map(|(sequence, code)| KeyMapping {
+                sequence: sequence.escape_ascii().to_string(),
+                key_code: code.0,
+            })
+            .collect(),
+        probes: Vec::new(),
+    }
+}
+
+/// Sends a few queries to the terminal and appends the replies to `report`:
+/// primary device attributes (DA1), and the SGR state after setting a direct color,
+/// read back with DECRQSS, which shows whether truecolor is understood.
+///
+/// Switches to raw mode for the duration of the probe.
+pub fn probe_interactive<T>(tty: &mut T, report: &mut DiagnosticReport) -> std::io::Result<()>
+where
+    T: UnixTerminal + AsFd + Read + Write,
+{
+    let orig_termios = tty.get_termios()?;
+    tty.raw_mode()?;
+    let result = probe_raw(tty, report);
+    tty.set_termios(&orig_termios, SetArg::TCSADRAIN)?;
+    result
+}
+
+fn probe_raw<T>(tty: &mut T, report: &mut DiagnosticReport) -> std::io::Result<()>
+where
+    T: UnixTerminal + AsFd + Read + Write,
+{
+    let da1 = query(tty, b"\x1B[c", |reply| reply.ends_with(b"c"))?;
+    let truecolor = query(tty, b"\x1B[38;2;1;2;3m\x1BP$qm\x1B\\", |reply| {
+        reply.ends_with(b"\x1B\\")
+    })?;
+    tty.write_all(b"\x1B[m")?;
+    tty.flush()?;
+    for (name, reply) in [("DA1", da1), ("truecolor (DECRQSS)", truecolor)] {
+        report.probes.push(ProbeResult {
+            name: name.into(),
+            reply: reply.map(|r| r.escape_ascii().to_string()),
+        });
+    }
+    Ok(())
+}
+
+/// Writes `request` and reads until `complete` accepts the reply or the terminal goes quiet.
+fn query<T>(
+    tty: &mut T,
+    request: &[u8],
+    complete: impl Fn(&[u8]) -> bool,
+) -> std::io::Result<Option<Vec<u8>>>
+where
+    T: UnixTerminal + AsFd + Read + Write,
+{
+    tty.write_all(request)?;
+    tty.flush()?;
+    let mut reply = Vec::new();
+    let mut buf = [0; 256];
+    while !complete(&reply) {
+        let mut fds = [PollFd::new(tty.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, PROBE_TIMEOUT_MS) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+        }
+        let count = tty.read(&mut buf)?;
+        if count == 0 {
+            break;
+        }
+        reply.extend_from_slice(&buf[..count]);
+    }
+    Ok(complete(&reply).then_some(reply))
+}
+
+fn write_table(f: &mut fmt::Formatter<'_>, title: &str, rows: &[(&str, &str)]) -> fmt::Result {
+    writeln!(f)?;
+    writeln!(f, "{title}:")?;
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, value) in rows {
+        writeln!(f, "  {name:width$}  {value}")?;
+    }
+    if rows.is_empty() {
+        writeln!(f, "  (none)")?;
+    }
+    Ok(())
+}
+
+fn capability_rows(list: &[CapabilityValue]) -> Vec<(&str, &str)> {
+    list.iter()
+        .map(|c| (&*c.name, c.value.as_deref().unwrap_or("(missing)")))
+        .collect()
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unset = "(unset)";
+        writeln!(f, "TERM: {}", self.term.as_deref().unwrap_or(unset))?;
+        writeln!(
+            f,
+            "COLORTERM: {}",
+            self.colorterm.as_deref().unwrap_or(unset)
+        )?;
+        writeln!(
+            f,
+            "terminal program: {}",
+            self.terminal_program.as_deref().unwrap_or(unset)
+        )?;
+        writeln!(f, "terminfo entry: {}", self.entry_name)?;
+        if !self.entry_aliases.is_empty() {
+            writeln!(f, "terminfo aliases: {}", self.entry_aliases.join(", "))?;
+        }
+        match &self.entry_path {
+            Some(path) => writeln!(f, "terminfo path: {}", path.display())?,
+            None => writeln!(f, "terminfo path: (not found)")?,
+        }
+
+        write_table(f, "capabilities", &capability_rows(&self.capabilities))?;
+        write_table(
+            f,
+            "extended capabilities",
+            &capability_rows(&self.extended_capabilities),
+        )?;
+        let codes: Vec<_> = self
+            .key_mappings
+            .iter()
+            .map(|m| (&*m.sequence, m.key_code.to_string()))
+            .collect();
+        let rows: Vec<_> = codes.iter().map(|(seq, code)| (*seq, &**code)).collect();
+        write_table(f, "key mappings", &rows)?;
+        if !self.probes.is_empty() {
+            let rows: Vec<_> = self
+                .probes
+                .iter()
+                .map(|p| (&*p.name, p.reply.as_deref().unwrap_or("(no reply)")))
+                .collect();
+            write_table(f, "probes", &rows)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::KITTY_DATABASE;
+
+    #[test]
+    fn kitty_snapshot() {
+        let dir = std::env::temp_dir().join(format!("nixtui-diagnostics-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("x")).unwrap();
+        std::fs::write(dir.join("x/xterm-kitty"), KITTY_DATABASE).unwrap();
+
+        let db = Database::from_buffer(KITTY_DATABASE).unwrap();
+        let env = EnvSnapshot::default()
+            .term("xterm-kitty")
+            .colorterm("truecolor")
+            .kitty_window_id("1")
+            .terminfo(&dir);
+        let report = report(&db, &env);
+        assert_eq!(report.entry_path, Some(dir.join("x/xterm-kitty")));
+        let text = report
+            .to_string()
+            .replace(&dir.display().to_string(), "$TERMINFO");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(text, include_str!("../assets/diagnostics_kitty.txt"));
+    }
+
+    #[test]
+    fn probe_reads_replies() {
+        use std::fs::File;
+
+        let size = nix::pty::Winsize {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let pty = nix::pty::openpty(&size, None).unwrap();
+        let (mut master, mut slave) = (File::from(pty.master), File::from(pty.slave));
+        let terminal = std::thread::spawn(move || {
+            let mut seen = Vec::new();
+            let mut buf = [0; 256];
+            let mut answered = 0;
+            while answered < 2 {
+                let count = master.read(&mut buf).unwrap();
+                seen.extend_from_slice(&buf[..count]);
+                if answered == 0 && seen.windows(3).any(|w| w == b"\x1B[c") {
+                    master.write_all(b"\x1B[?62;22c").unwrap();
+                    answered += 1;
+                }
+                if answered == 1 && seen.windows(3).any(|w| w == b"$qm") {
+                    master.write_all(b"\x1BP1$r38:2::1:2:3m\x1B\\").unwrap();
+                    answered += 1;
+                }
+            }
+            master
+        });
+
+        let db = Database::from_buffer(KITTY_DATABASE).unwrap();
+        let mut report = report(&db, &EnvSnapshot::default());
+        probe_interactive(&mut slave, &mut report).unwrap();
+        drop(terminal.join().unwrap());
+        assert_eq!(
+            report.probes,
+            [
+                ProbeResult {
+                    name: "DA1".into(),
+                    reply: Some(r"\x1b[?62;22c".into())
+                },
+                ProbeResult {
+                    name: "truecolor (DECRQSS)".into(),
+                    reply: Some(r"\x1bP1$r38:2::1:2:3m\x1b\\".into())
+                },
+            ]
+        );
+        assert!(report.to_string().contains("DA1"));
+    }
+}
@@ -0,0 +1,307 @@
+//! Environment variables the crate looks at, captured once so they can be inspected or faked.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Copy of the environment variables relevant to terminal handling.
+///
+/// Every field can be overridden with the builder method of the same name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvSnapshot {
+    pub term: Option<String>,
+    pub colorterm: Option<String>,
+    pub terminfo: Option<PathBuf>,
+    /// Colon separated list of directories.
+    pub terminfo_dirs: Option<String>,
+    pub home: Option<PathBuf>,
+    /// Installation prefix on non-FHS systems like Termux.
+    pub prefix: Option<PathBuf>,
+    /// Socket and session of the tmux server, set inside tmux.
+    pub tmux: Option<String>,
+    /// Set to a non-empty value by users who don't want colored output.
+    pub no_color: Option<String>,
+    /// Set to anything but `0` to get colors even where they would be turned off.
+    pub clicolor_force: Option<String>,
+    /// Set to `0` by users who don't want colored output, unless forced.
+    pub clicolor: Option<String>,
+    pub lang: Option<String>,
+    pub lc_all: Option<String>,
+    pub lc_ctype: Option<String>,
+    /// Name of the terminal emulator, set by iTerm2, WezTerm, VS Code and others.
+    pub term_program: Option<String>,
+    pub term_program_version: Option<String>,
+    /// Set inside kitty, also when `TERM` was changed.
+    pub kitty_window_id: Option<String>,
+    /// Set inside WezTerm.
+    pub wezterm_pane: Option<String>,
+    /// Client and server address, set when logged in over SSH.
+    pub ssh_connection: Option<String>,
+}
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn path_var(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).map(PathBuf::from)
+}
+
+impl EnvSnapshot {
+    /// Reads the current process environment.
+    pub fn capture() -> Self {
+        Self {
+            term: var("TERM"),
+            colorterm: var("COLORTERM"),
+            terminfo: path_var("TERMINFO"),
+            terminfo_dirs: var("TERMINFO_DIRS"),
+            home: path_var("HOME"),
+            prefix: path_var("PREFIX"),
+            tmux: var("TMUX"),
+            no_color: var("NO_COLOR"),
+            clicolor_force: var("CLICOLOR_FORCE"),
+            clicolor: var("CLICOLOR"),
+            lang: var("LANG"),
+            lc_all: var("LC_ALL"),
+            lc_ctype: var("LC_CTYPE"),
+            term_program: var("TERM_PROGRAM"),
+            term_program_version: var("TERM_PROGRAM_VERSION"),
+            kitty_window_id: var("KITTY_WINDOW_ID"),
+            wezterm_pane: var("WEZTERM_PANE"),
+            ssh_connection: var("SSH_CONNECTION"),
+        }
+    }
+
+    pub fn term(mut self, term: impl Into<String>) -> Self {
+        self.term = Some(term.into());
+        self
+    }
+
+    pub fn colorterm(mut self, colorterm: impl Into<String>) -> Self {
+        self.colorterm = Some(colorterm.into());
+        self
+    }
+
+    pub fn terminfo(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.terminfo = Some(dir.into());
+        self
+    }
+
+    pub fn terminfo_dirs(mut self, dirs: impl Into<String>) -> Self {
+        self.terminfo_dirs = Some(dirs.into());
+        self
+    }
+
+    pub fn home(mut self, home: impl Into<PathBuf>) -> Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    pub fn prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn tmux(mut self, tmux: impl Into<String>) -> Self {
+        self.tmux = Some(tmux.into());
+        self
+    }
+
+    pub fn no_color(mut self, no_color: impl Into<String>) -> Self {
+        self.no_color = Some(no_color.into());
+        self
+    }
+
+    pub fn clicolor_force(mut self, clicolor_force: impl Into<String>) -> Self {
+        self.clicolor_force = Some(clicolor_force.into());
+        self
+    }
+
+    pub fn clicolor(mut self, clicolor: impl Into<String>) -> Self {
+        self.clicolor = Some(clicolor.into());
+        self
+    }
+
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    pub fn lc_all(mut self, lc_all: impl Into<String>) -> Self {
+        self.lc_all = Some(lc_all.into());
+        self
+    }
+
+    pub fn lc_ctype(mut self, lc_ctype: impl Into<String>) -> Self {
+        self.lc_ctype = Some(lc_ctype.into());
+        self
+    }
+
+    pub fn term_program(mut self, term_program: impl Into<String>) -> Self {
+        self.term_program = Some(term_program.into());
+        self
+    }
+
+    pub fn term_program_version(mut self, term_program_version: impl Into<String>) -> Self {
+        self.term_program_version = Some(term_program_version.into());
+        self
+    }
+
+    pub fn kitty_window_id(mut self, kitty_window_id: impl Into<String>) -> Self {
+        self.kitty_window_id = Some(kitty_window_id.into());
+        self
+    }
+
+    pub fn wezterm_pane(mut self, wezterm_pane: impl Into<String>) -> Self {
+        self.wezterm_pane = Some(wezterm_pane.into());
+        self
+    }
+
+    pub fn ssh_connection(mut self, ssh_connection: impl Into<String>) -> Self {
+        self.ssh_connection = Some(ssh_connection.into());
+        self
+    }
+
+    /// Locale of character handling, the first of `LC_ALL`, `LC_CTYPE` and `LANG`
+    /// that is set and not empty.
+    pub fn locale(&self) -> Option<&str> {
+        [&self.lc_all, &self.lc_ctype, &self.lang]
+            .into_iter()
+            .filter_map(|value| value.as_deref())
+            .find(|value| !value.is_empty())
+    }
+
+    /// Whether `COLORTERM` says the terminal shows 24-bit colors.
+    pub fn truecolor(&self) -> bool {
+        matches!(self.colorterm.as_deref(), Some("truecolor" | "24bit"))
+    }
+
+    /// Whether the process runs in a session over SSH.
+    pub fn is_remote(&self) -> bool {
+        self.ssh_connection.is_some()
+    }
+
+    /// Terminal emulator as told by the variables it sets, which survive
+    /// a `TERM` changed by the user, e. g. `kitty` or `WezTerm`.
+    pub fn terminal_program(&self) -> Option<&str> {
+        if let Some(program) = self.term_program.as_deref().filter(|p| !p.is_empty()) {
+            Some(program)
+        } else if self.kitty_window_id.is_some() {
+            Some("kitty")
+        } else if self.wezterm_pane.is_some() {
+            Some("WezTerm")
+        } else {
+            None
+        }
+    }
+
+    /// Directories searched for compiled terminfo entries, in the order ncurses uses.
+    pub fn terminfo_search_path(&self) -> Vec<PathBuf> {
+        let mut search = Vec::new();
+        if let Some(dir) = &self.terminfo {
+            search.push(dir.clone());
+        }
+        if let Some(home) = &self.home {
+            search.push(home.join(".terminfo"));
+        }
+        if let Some(dirs) = &self.terminfo_dirs {
+            search.extend(dirs.split(':').map(PathBuf::from));
+        }
+        if let Some(prefix) = &self.prefix {
+            for dir in ["etc/terminfo", "lib/terminfo", "share/terminfo"] {
+                search.push(prefix.join(dir));
+            }
+        }
+        for dir in [
+            "/etc/terminfo",
+            "/lib/terminfo",
+            "/usr/share/terminfo",
+            "/usr/local/share/terminfo",
+            "/usr/local/share/site-terminfo",
+            "/boot/system/data/terminfo",
+        ] {
+            search.push(dir.into());
+        }
+        search
+    }
+
+    /// File the entry for [`EnvSnapshot::term`] would be loaded from.
+    pub fn terminfo_path(&self) -> Option<PathBuf> {
+        let term = self.term.as_deref()?;
+        let first = term.chars().next()?;
+        self.terminfo_search_path().into_iter().find_map(|dir| {
+            [
+                OsString::from(first.to_string()),
+                format!("{:x}", first as usize).into(),
+            ]
+            .into_iter()
+            .map(|sub| dir.join(sub).join(term))
+            .find(|path| path.is_file())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_path_order() {
+        let env = EnvSnapshot::default()
+            .home("/home/me")
+            .terminfo_dirs("/a:/b")
+            .prefix("/data/usr");
+        let search = env.terminfo_search_path();
+        assert_eq!(
+            search[..6],
+            [
+                PathBuf::from("/home/me/.terminfo"),
+                "/a".into(),
+                "/b".into(),
+                "/data/usr/etc/terminfo".into(),
+                "/data/usr/lib/terminfo".into(),
+                "/data/usr/share/terminfo".into(),
+            ]
+        );
+        // TERMINFO comes first, ~/.terminfo is still searched after it
+        let search = env.terminfo("/ti").terminfo_search_path();
+        assert_eq!(
+            search[..3],
+            [
+                PathBuf::from("/ti"),
+                "/home/me/.terminfo".into(),
+                "/a".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn locale_precedence() {
+        let env = EnvSnapshot::default().lang("de_DE.UTF-8");
+        assert_eq!(env.locale(), Some("de_DE.UTF-8"));
+        let env = env.lc_ctype("ja_JP.eucJP");
+        assert_eq!(env.locale(), Some("ja_JP.eucJP"));
+        // An empty LC_ALL doesn't override anything
+        assert_eq!(env.clone().lc_all("").locale(), Some("ja_JP.eucJP"));
+        assert_eq!(env.lc_all("C").locale(), Some("C"));
+        assert_eq!(EnvSnapshot::default().locale(), None);
+    }
+
+    #[test]
+    fn terminal_program() {
+        let env = EnvSnapshot::default().term("xterm-256color");
+        assert_eq!(env.terminal_program(), None);
+        assert_eq!(
+            env.clone().kitty_window_id("1").terminal_program(),
+            Some("kitty")
+        );
+        assert_eq!(
+            env.clone().wezterm_pane("0").terminal_program(),
+            Some("WezTerm")
+        );
+        let env = env.term_program("vscode").kitty_window_id("1");
+        assert_eq!(env.terminal_program(), Some("vscode"));
+        assert!(!env.is_remote());
+        assert!(env.ssh_connection("10.0.0.2 50000 10.0.0.1 22").is_remote());
+    }
+}
@@ -1,8 +1,12 @@
 #![allow(dead_code)]
 
 pub mod constants;
+#[cfg(feature = "crossterm-compat")]
+pub mod crossterm_compat;
+pub mod keymap;
 
 use constants as c;
+#[cfg(feature = "terminfo")]
 use terminfo::Database;
 
 macro_rules! call_multiple {
@@ -24,6 +28,7 @@ macro_rules! call_multiple {
     };
 }
 
+#[cfg(feature = "terminfo")]
 macro_rules! push_from_db {
     ($db:ident, $to:expr, [$(($cap:path, $val:expr)),+$(,)?]) => {
         $(match $db.get::<$cap>() {
@@ -43,9 +48,21 @@ macro_rules! push_from_db {
     };
 }
 
+/// Longest incomplete sequence [`InputParser::advance`] keeps for the next read,
+/// longer ones are parsed as they are. Pastes have a limit of their own.
+const MAX_PENDING: usize = 256;
+/// Longest bracketed paste without its end [`InputParser::advance`] keeps,
+/// longer ones are returned in pieces.
+const MAX_PASTE: usize = 1 << 20;
+/// Markers around pasted text while bracketed paste is on.
+const PASTE_START: &[u8] = b"\x1B[200~";
+const PASTE_END: &[u8] = b"\x1B[201~";
+
 #[derive(Default, Debug)]
 pub struct InputParser {
     mappings: CSIList,
+    /// End of the last read that [`InputParser::advance`] couldn't decode yet.
+    pending: Vec<u8>,
 }
 
 impl InputParser {
@@ -53,16 +70,28 @@ impl InputParser {
         Self::default()
     }
 
+    #[cfg(feature = "terminfo")]
     pub fn from_env() -> Result<Self, terminfo::Error> {
         Ok(Self::from_terminfo(&Database::from_env()?))
     }
 
+    /// Without terminfo the mappings can't depend on the terminal,
+    /// this is the same as [`InputParser::push_default`] on an empty parser.
+    #[cfg(not(feature = "terminfo"))]
+    pub fn from_env() -> Result<Self, std::convert::Infallible> {
+        let mut ret = Self::new();
+        ret.push_default();
+        Ok(ret)
+    }
+
+    #[cfg(feature = "terminfo")]
     pub fn from_terminfo(db: &Database) -> Self {
         let mut ret = Self::new();
         ret.push_from_terminfo(db);
         ret
     }
 
+    #[cfg(feature = "terminfo")]
     pub fn push_from_terminfo(&mut self, db: &Database) {
         use c::*;
         use terminfo::capability as cap;
@@ -176,15 +205,142 @@ impl InputParser {
         );
     }
 
+    /// Control sequences the parser recognizes with the keys they map to, in matching order.
+    pub fn mappings(&self) -> Vec<(Vec<u8>, KeyCode)> {
+        self.mappings
+            .data
+            .iter()
+            .map(|(command, code)| {
+                let mut bytes = b"\x1B[".to_vec();
+                bytes.extend_from_slice(command.get_parameter());
+                bytes.extend_from_slice(command.get_intermediate());
+                bytes.push(command.get_final());
+                (bytes, KeyCode(*code))
+            })
+            .collect()
+    }
+
     /// Parsed all multybyte sequences in input, e. g. non-ascii UTF-8 characters,
     /// control sequences, representing keys that do not have UTF-8 representation,
     /// Alt-modified keys.
     pub fn parse(&self, input: &[u8]) -> KeyEventList {
-        let mut events = Vec::new();
-        let mut iter = input.iter().enumerate();
-        'outer: while let Some((i, byte)) = iter.next() {
+        let mut iter = self.parse_iter(input);
+        let list = iter.by_ref().collect();
+        KeyEventList {
+            list,
+            window_reports: iter.window_reports,
+            pastes: iter.pastes,
+        }
+    }
+
+    /// Parses `input` as the continuation of the previous reads.
+    ///
+    /// Unlike [`InputParser::parse`], a sequence cut at the end of `input`, like a partial CSI
+    /// or the first bytes of a UTF-8 character, is kept and completed by the next call.
+    /// So is a bracketed paste without its end, up to 1 MiB. Past that, what arrived of it
+    /// is returned as a [`Paste`] and the rest, up to the end marker, in further ones.
+    /// A lone ESC is kept as well, as it may start a sequence. Whether it is the Escape key
+    /// is up to the caller: call [`InputParser::flush_pending`] when no more input
+    /// arrived within some timeout.
+    pub fn advance(&mut self, input: &[u8]) -> KeyEventList {
+        let kept = self.pending.len();
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(input);
+        if bytes.starts_with(PASTE_START) {
+            // Only the new input can end the paste kept before
+            let from = kept.saturating_sub(PASTE_END.len() - 1);
+            if !bytes[from..]
+                .windows(PASTE_END.len())
+                .any(|w| w == PASTE_END)
+            {
+                if bytes.len() <= MAX_PASTE {
+                    self.pending = bytes;
+                    return KeyEventList::default();
+                }
+                // A lost end marker mustn't keep everything after it, but the rest stays a paste
+                let tail = match incomplete_tail(&bytes) {
+                    tail if tail > MAX_PENDING => 0,
+                    tail => tail,
+                };
+                let events = self.parse(&bytes[..bytes.len() - tail]);
+                self.pending = PASTE_START.to_vec();
+                self.pending.extend_from_slice(&bytes[bytes.len() - tail..]);
+                return events;
+            }
+        }
+        let complete = match open_paste(&bytes) {
+            Some(start) => start,
+            None => match incomplete_tail(&bytes) {
+                tail if tail > MAX_PENDING => bytes.len(),
+                tail => bytes.len() - tail,
+            },
+        };
+        let events = self.parse(&bytes[..complete]);
+        self.pending = bytes.split_off(complete);
+        events
+    }
+
+    /// Whether [`InputParser::advance`] kept an incomplete sequence.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Parses what [`InputParser::advance`] kept as it is, e. g. a lone ESC as the Escape key.
+    pub fn flush_pending(&mut self) -> KeyEventList {
+        let pending = std::mem::take(&mut self.pending);
+        self.parse(&pending)
+    }
+
+    /// Same as [`InputParser::parse`], but decodes one event at a time,
+    /// so a huge read can be handled a bounded number of events at a time.
+    pub fn parse_iter<'a>(&'a self, input: &'a [u8]) -> ParseIter<'a> {
+        ParseIter {
+            parser: self,
+            input,
+            iter: input.iter().enumerate(),
+            window_reports: Vec::new(),
+            pastes: Vec::new(),
+            returned: 0,
+        }
+    }
+}
+
+/// Lazily parsed events, see [`InputParser::parse_iter`].
+#[derive(Debug, Clone)]
+pub struct ParseIter<'a> {
+    parser: &'a InputParser,
+    input: &'a [u8],
+    iter: std::iter::Enumerate<std::slice::Iter<'a, u8>>,
+    window_reports: Vec<WindowReport>,
+    pastes: Vec<Paste>,
+    /// Events returned so far.
+    returned: usize,
+}
+
+impl ParseIter<'_> {
+    /// Bytes of the input decoded so far. When the rest is left for later,
+    /// it can be parsed from here with a new iterator.
+    pub fn consumed(&self) -> usize {
+        self.input.len() - self.iter.len()
+    }
+
+    /// Replies to window queries met so far, see [`KeyEventList::window_reports`].
+    pub fn window_reports(&self) -> &[WindowReport] {
+        &self.window_reports
+    }
+
+    /// Pastes met so far, see [`KeyEventList::pastes`].
+    pub fn pastes(&self) -> &[Paste] {
+        &self.pastes
+    }
+}
+
+impl ParseIter<'_> {
+    fn next_event(&mut self) -> Option<KeyEvent> {
+        let input = self.input;
+        'outer: while let Some((i, byte)) = self.iter.next() {
             let byte = *byte;
-            events.push(match byte {
+            return Some(match byte {
                 0x1B if {
                     let next = input.get(i + 1);
                     next == Some(&b'[') || next == Some(&b'O')
@@ -194,7 +350,24 @@ impl InputParser {
                     let next = *input.get(i).unwrap();
                     if let Some(slice) = input.get((i + 1)..) {
                         if let Some((command, len)) = CSICommand::parse(slice) {
-                            iter.nth(len);
+                            self.iter.nth(len);
+                            if command.get_final() == b'~' && command.get_parameter() == b"200" {
+                                // Taken as it is up to the end marker, or the end of the input
+                                let body = &input[i + 1 + len..];
+                                let end = body
+                                    .windows(PASTE_END.len())
+                                    .position(|w| w == PASTE_END)
+                                    .unwrap_or(body.len());
+                                let skip = (end + PASTE_END.len()).min(body.len());
+                                if skip > 0 {
+                                    self.iter.nth(skip - 1);
+                                }
+                                self.pastes.push(Paste {
+                                    position: self.returned,
+                                    text: String::from_utf8_lossy(&body[..end]).into_owned(),
+                                });
+                                continue 'outer;
+                            }
                             if command.final_byte == b'Z' {
                                 break 'ev KeyEvent {
                                     key_code: c::TAB.into(),
@@ -202,72 +375,75 @@ impl InputParser {
                                     ..Default::default()
                                 };
                             }
-                            if let Some(code) = self.mappings.match_csi(&command) {
-                                let mods = 'm: {
-                                    match command.get_final() {
-                                        b'A'..=b'Z' | b'~' => {
-                                            if let Some(bytes) =
-                                                command.get_parameter().split(|b| *b == b';').nth(1)
-                                            {
-                                                let mut num = 0;
-                                                if bytes.len() > 3 {
-                                                    break 'm Modifiers::NONE;
-                                                }
-                                                for (i, dig) in bytes.iter().rev().enumerate() {
-                                                    if !(48..58).contains(dig) {
-                                                        break 'm Modifiers::NONE;
-                                                    }
-                                                    num += (dig - 48) * 10_u8.pow(i as u32)
-                                                }
-                                                Modifiers::new(num - 1)
-                                            } else {
-                                                Modifiers::NONE
-                                            }
-                                        }
-                                        _ => Modifiers::NONE,
-                                    }
+                            if let Some(event) = parse_kitty_key(&command) {
+                                break 'ev event;
+                            }
+                            if let Some(code) = self.parser.mappings.match_csi(&command) {
+                                let mods = match command.get_final() {
+                                    // Sent as one plus the bits, zero and garbage are taken as none
+                                    b'A'..=b'Z' | b'~' => command
+                                        .get_parameter()
+                                        .split(|b| *b == b';')
+                                        .nth(1)
+                                        .filter(|bytes| bytes.iter().all(u8::is_ascii_digit))
+                                        .and_then(|bytes| {
+                                            std::str::from_utf8(bytes).ok()?.parse().ok()
+                                        })
+                                        .and_then(Modifiers::from_kitty_param)
+                                        .unwrap_or(Modifiers::NONE),
+                                    _ => Modifiers::NONE,
                                 };
                                 KeyEvent {
                                     key_code: code.into(),
                                     mods,
                                     ..Default::default()
                                 }
+                            } else if let Some(report) = WindowReport::from_csi(&command) {
+                                self.window_reports.push(report);
+                                continue 'outer;
                             } else {
+                                crate::logging::debug_event!(
+                                    sequence = %input[i - 1..(i + 1 + len).min(input.len())].escape_ascii(),
+                                    "unrecognized CSI sequence"
+                                );
                                 continue 'outer;
                             }
                         } else if next == b'[' {
-                            iter.next();
+                            self.iter.next();
                             KeyEvent {
                                 key_code: b'['.into(),
                                 mods: Modifiers::ALT,
                                 ..Default::default()
                             }
                         } else {
-                            iter.next();
+                            crate::logging::debug_event!(
+                                sequence = %input[i - 1..].escape_ascii(),
+                                "dropped unparsable escape sequence"
+                            );
+                            self.iter.next();
                             continue 'outer;
                         }
                     } else if next == b'[' {
-                        iter.next();
+                        self.iter.next();
                         KeyEvent {
                             key_code: b'['.into(),
                             mods: Modifiers::ALT,
                             ..Default::default()
                         }
                     } else {
+                        crate::logging::debug_event!("input ends inside an escape sequence");
+                        self.iter.by_ref().for_each(drop);
                         break 'outer;
                     }
                 }
                 0x1B if {
-                    let next = input.get(i + 1);
-                    if next.is_none() {
-                        false
-                    } else {
-                        let next = next.unwrap();
-                        (0x0..=0x40).contains(next) || (0x5B..=0x7E).contains(next)
+                    match input.get(i + 1) {
+                        Some(next) => (0x0..=0x40).contains(next) || (0x5B..=0x7E).contains(next),
+                        None => false,
                     }
                 } =>
                 {
-                    let next = *iter.next().unwrap().1;
+                    let next = *self.iter.next().unwrap().1;
                     KeyEvent {
                         key_code: next.into(),
                         mods: Modifiers::ALT,
@@ -285,12 +461,13 @@ impl InputParser {
                 },
                 // Continuation byte
                 0x80..=0xBF => {
+                    crate::logging::debug_event!(byte, "dropped stray UTF-8 continuation byte");
                     continue;
                 }
                 // First byte of 2-byte encoding
                 0xC2..=0xDF => {
                     let byte2 = (byte as u32 & !(0b111 << 5)) << 6;
-                    let byte1 = match iter.next().map(|x| x.1) {
+                    let byte1 = match self.iter.next().map(|x| x.1) {
                         Some(b) => *b,
                         None => continue,
                     } as u32
@@ -303,13 +480,13 @@ impl InputParser {
                 // First byte of 3-byte encoding
                 0xE0..=0xEF => {
                     let byte1 = (byte as u32 & !(0b1111 << 4)) << 12;
-                    let byte2 = (match iter.next().map(|x| x.1) {
+                    let byte2 = (match self.iter.next().map(|x| x.1) {
                         Some(b) => *b,
                         None => continue,
                     } as u32
                         & !(0b11 << 6))
                         << 6;
-                    let byte3 = (match iter.next().map(|x| x.1) {
+                    let byte3 = (match self.iter.next().map(|x| x.1) {
                         Some(b) => *b,
                         None => continue,
                     } as u32
@@ -323,19 +500,19 @@ impl InputParser {
                 // First byte of 4-byte encoding
                 0xF0..=0xF4 => {
                     let byte1 = (byte as u32 & !(0b11111 << 3)) << 20;
-                    let byte2 = (match iter.next().map(|x| x.1) {
+                    let byte2 = (match self.iter.next().map(|x| x.1) {
                         Some(b) => *b,
                         None => continue,
                     } as u32
                         & !(0b11 << 6))
                         << 12;
-                    let byte3 = (match iter.next().map(|x| x.1) {
+                    let byte3 = (match self.iter.next().map(|x| x.1) {
                         Some(b) => *b,
                         None => continue,
                     } as u32
                         & !(0b11 << 6))
                         << 6;
-                    let byte4 = (match iter.next().map(|x| x.1) {
+                    let byte4 = (match self.iter.next().map(|x| x.1) {
                         Some(b) => *b,
                         None => continue,
                     } as u32
@@ -351,16 +528,219 @@ impl InputParser {
                 }
             });
         }
-        KeyEventList { list: events }
+        None
     }
 }
 
+impl Iterator for ParseIter<'_> {
+    type Item = KeyEvent;
+
+    fn next(&mut self) -> Option<KeyEvent> {
+        let event = self.next_event();
+        self.returned += usize::from(event.is_some());
+        event
+    }
+}
+
+/// Start of the last paste in `input` that hasn't ended yet.
+fn open_paste(input: &[u8]) -> Option<usize> {
+    let start = input
+        .windows(PASTE_START.len())
+        .rposition(|w| w == PASTE_START)?;
+    let ended = input[start..]
+        .windows(PASTE_END.len())
+        .any(|w| w == PASTE_END);
+    (!ended).then_some(start)
+}
+
+/// Length of the sequence at the end of `input` that more input could still complete:
+/// a lone ESC, an escape sequence without its final byte or the start of a UTF-8 character.
+fn incomplete_tail(input: &[u8]) -> usize {
+    if let Some(esc) = input.iter().rposition(|byte| *byte == 0x1B) {
+        let open = match input[esc + 1..].split_first() {
+            None => true,
+            Some((b'[' | b'O', rest)) => rest.iter().all(|byte| (0x20..=0x3F).contains(byte)),
+            Some(_) => false,
+        };
+        if open {
+            return input.len() - esc;
+        }
+    }
+    // A lead byte among the last three bytes, followed by too few continuation bytes
+    for (back, byte) in input.iter().rev().take(3).enumerate() {
+        let len = match byte {
+            0x80..=0xBF => continue,
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => return 0,
+        };
+        return if back + 1 < len { back + 1 } else { 0 };
+    }
+    0
+}
+
+/// `CSI code[:shifted[:base]] [; mods[:event] [; text]] u` of the kitty keyboard protocol.
+///
+/// Functional keys are reported with their codes in the Private Use Area, which are
+/// the codes of [`constants`](c) already, e. g. [`c::SCROLL_LOCK`]. Esc, Enter, Tab and
+/// Backspace keep their legacy codes 27, 13, 9 and 127, the same key codes the bytes
+/// give without the protocol.
+fn parse_kitty_key(command: &CSICommand) -> Option<KeyEvent> {
+    if command.get_final() != b'u' || !command.get_intermediate().is_empty() {
+        return None;
+    }
+    let params = std::str::from_utf8(command.get_parameter()).ok()?;
+    let mut params = params.split(';');
+    let mut codes = params.next()?.split(':');
+    let key_code = codes.next()?.parse().ok()?;
+    let mut alternate = || match codes.next() {
+        Some("") | None => Some(None),
+        Some(code) => code.parse().ok().map(|code| Some(KeyCode(code))),
+    };
+    let shifted_key = alternate()?;
+    let base_layout_key = alternate()?;
+    let mut mods_field = params.next().unwrap_or_default().split(':');
+    let mods = match mods_field.next() {
+        Some("") | None => Modifiers::NONE,
+        Some(value) => Modifiers::from_kitty_param(value.parse().ok()?)?,
+    };
+    let event_type = match mods_field.next() {
+        None | Some("1") => EventType::Press,
+        Some("2") => EventType::Repeat,
+        Some("3") => EventType::Release,
+        Some(_) => return None,
+    };
+    let text = match params.next().and_then(|text| text.split(':').next()) {
+        Some(code) => Some(char::from_u32(code.parse().ok()?)?),
+        None => None,
+    };
+    Some(KeyEvent {
+        key_code: KeyCode(key_code),
+        mods,
+        event_type,
+        base_layout_key,
+        shifted_key,
+        text,
+    })
+}
+
+/// Enhancements of the kitty keyboard protocol, see
+/// [`TerminfoWrapper::push_keyboard_enhancement`](crate::tty::TerminfoWrapper::push_keyboard_enhancement).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct KeyboardFlags(u8);
+
+impl KeyboardFlags {
+    pub const NONE: Self = Self(0);
+    /// Keys that are ambiguous in legacy encoding, like Esc or alt+[, are sent as `CSI u`.
+    pub const DISAMBIGUATE_ESCAPE_CODES: Self = Self(1);
+    /// Repeats and releases are reported, see [`EventType`].
+    pub const REPORT_EVENT_TYPES: Self = Self(2);
+    /// See [`KeyEvent::shifted_key`] and [`KeyEvent::base_layout_key`].
+    pub const REPORT_ALTERNATE_KEYS: Self = Self(4);
+    /// Text producing keys are sent as `CSI u` too, including modifier keys.
+    pub const REPORT_ALL_KEYS_AS_ESCAPE_CODES: Self = Self(8);
+    /// See [`KeyEvent::text`].
+    pub const REPORT_ASSOCIATED_TEXT: Self = Self(16);
+
+    pub const fn all() -> Self {
+        Self(0b11111)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Same as `|`, usable in constants.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for KeyboardFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+/// Reply of the terminal to an XTWINOPS query, e. g.
+/// [`Capabilities::request_cell_pixels`](crate::tty::Capabilities::request_cell_pixels).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowReport {
+    TextAreaPixels { width: u32, height: u32 },
+    CellPixels { width: u32, height: u32 },
+    TextAreaChars { cols: u32, rows: u32 },
+}
+
+impl WindowReport {
+    /// `CSI 4 ; height ; width t`, `CSI 6 ; …` or `CSI 8 ; rows ; cols t`.
+    fn from_csi(command: &CSICommand) -> Option<Self> {
+        if command.get_final() != b't' || !command.get_intermediate().is_empty() {
+            return None;
+        }
+        let params = std::str::from_utf8(command.get_parameter()).ok()?;
+        let mut params = params.split(';').map(|param| param.parse::<u32>().ok());
+        let (kind, first, second) = (params.next()??, params.next()??, params.next()??);
+        if params.next().is_some() {
+            return None;
+        }
+        match kind {
+            4 => Some(Self::TextAreaPixels {
+                width: second,
+                height: first,
+            }),
+            6 => Some(Self::CellPixels {
+                width: second,
+                height: first,
+            }),
+            8 => Some(Self::TextAreaChars {
+                cols: second,
+                rows: first,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Text pasted while bracketed paste is on, see
+/// [`TerminfoWrapper::enable_bracketed_paste`](crate::tty::TerminfoWrapper::enable_bracketed_paste).
+///
+/// Pastes come apart from the keys, like window reports: [`KeyEvent`] is a plain key
+/// that keymaps and widgets match on, and most of them don't take text. Those that do
+/// put the paste between the keys at `position`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paste {
+    /// Number of keys before the paste in the same [`KeyEventList`].
+    pub position: usize,
+    /// The pasted bytes, decoded as UTF-8 with invalid sequences replaced.
+    /// Escape sequences in it are kept as they are, not taken as keys.
+    pub text: String,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct KeyEventList {
     list: Vec<KeyEvent>,
+    window_reports: Vec<WindowReport>,
+    pastes: Vec<Paste>,
 }
 
 impl KeyEventList {
+    /// Replies to window queries that arrived among the keys, in order.
+    pub fn window_reports(&self) -> &[WindowReport] {
+        &self.window_reports
+    }
+
+    /// Pasted texts that arrived among the keys, in order.
+    pub fn pastes(&self) -> &[Paste] {
+        &self.pastes
+    }
+
     pub fn c0_to_ctrl(&mut self) {
         for ev in self.list.iter_mut() {
             match ev.key_code.0 {
@@ -427,14 +807,10 @@ impl CSIList {
             .iter()
             .find(|item| match csi.get_final() {
                 b'A'..=b'Z' => csi.get_final() == item.0.get_final(),
-                b'~' => {
-                    if item.0.get_final() == b'~' {
-                        match csi.get_parameter().split(|x| *x == b';').next() {
-                            Some(x) => x == item.0.get_parameter(),
-                            None => false,
-                        }
-                    } else {
-                        false
+                b'~' if item.0.get_final() == b'~' => {
+                    match csi.get_parameter().split(|x| *x == b';').next() {
+                        Some(x) => x == item.0.get_parameter(),
+                        None => false,
                     }
                 }
                 _ => false,
@@ -465,10 +841,7 @@ impl CSICommand {
         let mut skipped = false;
         let bytes = if bytes.get(0..2) == Some(b"\x1B[") {
             skipped = true;
-            match bytes.get(2..) {
-                Some(v) => v,
-                None => return None,
-            }
+            bytes.get(2..)?
         } else {
             bytes
         };
@@ -525,6 +898,17 @@ pub struct KeyEvent {
     pub key_code: KeyCode,
     pub mods: Modifiers,
     pub event_type: EventType,
+    /// Key at the same position on the US layout, when the terminal reports
+    /// alternate keys with the kitty keyboard protocol. `ctrl+я` on a Russian
+    /// layout has `z` here.
+    pub base_layout_key: Option<KeyCode>,
+    /// Key with shift applied, when the terminal reports alternate keys with the
+    /// kitty keyboard protocol and shift was held, e. g. `A` for shift+a.
+    pub shifted_key: Option<KeyCode>,
+    /// Text the key produces, when the terminal reports associated text with the kitty
+    /// keyboard protocol. Only the first codepoint is kept so that the event stays `Copy`,
+    /// the rest of a text of several codepoints, e. g. from a compose key, is dropped.
+    pub text: Option<char>,
 }
 
 /// Used to represent any key as either
@@ -687,10 +1071,53 @@ impl Modifiers {
     pub const CAPS_LOCK: Self = Self(64);
     pub const NUM_LOCK: Self = Self(128);
 
-    pub fn new(mods: u8) -> Self {
+    pub const fn new(mods: u8) -> Self {
         Self(mods)
     }
 
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Same as `|`, usable in constants, e. g. `Modifiers::CTRL.union(Modifiers::SHIFT)`.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every modifier of `other` is set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any modifier of `other` is set.
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Set modifiers, in the order of their bits.
+    pub fn iter(self) -> impl Iterator<Item = Modifier> {
+        Modifier::ALL
+            .into_iter()
+            .filter(move |&modifier| self.contains(modifier.into()))
+    }
+
+    /// Modifiers of a kitty keyboard protocol event, sent as one plus their bits.
+    /// `None` for zero and values beyond every modifier.
+    pub const fn from_kitty_param(param: u16) -> Option<Self> {
+        match param {
+            1..=256 => Some(Self((param - 1) as u8)),
+            _ => None,
+        }
+    }
+
+    pub const fn to_kitty_param(self) -> u16 {
+        self.0 as u16 + 1
+    }
+
     #[inline]
     pub fn shift_pressed(&self) -> bool {
         check_bit_at(self.0, 0)
@@ -734,6 +1161,71 @@ impl Modifiers {
     }
 }
 
+/// Single modifier of [`Modifiers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Shift,
+    Alt,
+    Ctrl,
+    Super,
+    Hyper,
+    Meta,
+    CapsLock,
+    NumLock,
+}
+
+impl Modifier {
+    /// Every modifier, in the order of their bits.
+    pub const ALL: [Self; 8] = [
+        Self::Shift,
+        Self::Alt,
+        Self::Ctrl,
+        Self::Super,
+        Self::Hyper,
+        Self::Meta,
+        Self::CapsLock,
+        Self::NumLock,
+    ];
+}
+
+impl From<Modifier> for Modifiers {
+    fn from(modifier: Modifier) -> Self {
+        Self(1 << modifier as u8)
+    }
+}
+
+impl FromIterator<Modifier> for Modifiers {
+    fn from_iter<I: IntoIterator<Item = Modifier>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::NONE, |mods, modifier| mods | modifier.into())
+    }
+}
+
+/// Lets the [`bitflags`] crate format, parse and iterate [`Modifiers`].
+#[cfg(feature = "bitflags")]
+impl bitflags::Flags for Modifiers {
+    const FLAGS: &'static [bitflags::Flag<Self>] = &[
+        bitflags::Flag::new("SHIFT", Self::SHIFT),
+        bitflags::Flag::new("ALT", Self::ALT),
+        bitflags::Flag::new("CTRL", Self::CTRL),
+        bitflags::Flag::new("SUPER", Self::SUPER),
+        bitflags::Flag::new("HYPER", Self::HYPER),
+        bitflags::Flag::new("META", Self::META),
+        bitflags::Flag::new("CAPS_LOCK", Self::CAPS_LOCK),
+        bitflags::Flag::new("NUM_LOCK", Self::NUM_LOCK),
+    ];
+
+    type Bits = u8;
+
+    fn bits(&self) -> u8 {
+        self.0
+    }
+
+    fn from_bits_retain(bits: u8) -> Self {
+        Self(bits)
+    }
+}
+
 impl std::fmt::Debug for Modifiers {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut dbs = f.debug_list();
@@ -953,4 +1445,352 @@ mod tests {
         list.push(CSICommand::parse(b"2~").unwrap().0, 57349);
         assert_eq!(list.match_csi(&csi), Some(57349));
     }
+
+    #[test]
+    fn window_reports_among_keys() {
+        let mut parser = InputParser::new();
+        parser.push_default();
+        let events = parser.parse(b"a\x1B[6;18;9tb\x1B[9;1;1t\x1B[4;1t\x1B[8;24;80t");
+        assert_eq!(
+            events.iter().map(|event| event.key_code.0).collect::<Vec<_>>(),
+            [u32::from(b'a'), u32::from(b'b')]
+        );
+        assert_eq!(
+            events.window_reports(),
+            [
+                WindowReport::CellPixels {
+                    width: 9,
+                    height: 18
+                },
+                WindowReport::TextAreaChars { cols: 80, rows: 24 },
+            ]
+        );
+    }
+
+    #[test]
+    fn kitty_keys() {
+        let parser = InputParser::new();
+        let events = parser.parse(b"\x1B[97;5u\x1B[57359u\x1B[97:65;2;65u\x1B[13;1:3u");
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].key_code, KeyCode(u32::from(b'a')));
+        assert_eq!(events[0].mods, Modifiers::CTRL);
+        assert!(matches!(events[0].event_type, EventType::Press));
+        assert_eq!(events[1].key_code, KeyCode(c::SCROLL_LOCK));
+        assert_eq!(events[1].mods, Modifiers::NONE);
+        // shift+a with alternate keys and associated text
+        assert_eq!(events[2].shifted_key, Some(KeyCode(u32::from(b'A'))));
+        assert_eq!(events[2].base_layout_key, None);
+        assert_eq!(events[2].mods, Modifiers::SHIFT);
+        assert_eq!(events[2].text, Some('A'));
+        assert_eq!(events[3].key_code, KeyCode(u32::from(b'\r')));
+        assert!(matches!(events[3].event_type, EventType::Release));
+        assert_eq!(events[3].text, None);
+    }
+
+    #[test]
+    fn kitty_functional_key_codes() {
+        let parser = InputParser::new();
+        // Codes from the table of functional keys in the protocol's documentation
+        let events = parser.parse(
+            b"\x1B[57399u\x1B[57408u\x1B[57409u\x1B[57414u\x1B[57427u\x1B[57441u\x1B[57454u",
+        );
+        let codes: Vec<_> = events.iter().map(|e| e.key_code).collect();
+        assert_eq!(
+            codes,
+            [
+                KeyCode(c::KP_0),
+                KeyCode(c::KP_9),
+                KeyCode(c::KP_DECIMAL),
+                KeyCode(c::KP_ENTER),
+                KeyCode(c::KP_BEGIN),
+                KeyCode(c::LEFT_SHIFT),
+                KeyCode(c::ISO_LEVEL5_SHIFT),
+            ]
+        );
+    }
+
+    #[test]
+    fn kitty_legacy_keys_match_bytes() {
+        let parser = InputParser::new();
+        // Esc has to come on its own, anything after it would make it alt+
+        for (csi, legacy) in [
+            (&b"\x1B[27u"[..], &b"\x1B"[..]),
+            (b"\x1B[13u", b"\r"),
+            (b"\x1B[9u", b"\t"),
+            (b"\x1B[127u", b"\x7F"),
+        ] {
+            let (csi, legacy) = (parser.parse(csi), parser.parse(legacy));
+            assert_eq!(csi.len(), 1);
+            assert_eq!(csi[0].key_code, legacy[0].key_code);
+            assert_eq!(csi[0].mods, legacy[0].mods);
+        }
+    }
+
+    #[test]
+    fn kitty_alternate_keys() {
+        let parser = InputParser::new();
+        // ctrl+я, shift+ctrl+я and the release of ctrl+я on a Russian layout
+        let events =
+            parser.parse(b"\x1B[1103::122;5u\x1B[1103:1071:122;6u\x1B[1103::122;5:3u\x1B[97u");
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].key_code, KeyCode(1103));
+        assert_eq!(events[0].mods, Modifiers::CTRL);
+        assert_eq!(events[0].base_layout_key, Some(KeyCode(u32::from(b'z'))));
+        assert!(matches!(events[0].event_type, EventType::Press));
+        assert_eq!(events[1].mods, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(events[1].base_layout_key, Some(KeyCode(u32::from(b'z'))));
+        assert!(matches!(events[2].event_type, EventType::Release));
+        assert_eq!(events[3].key_code, KeyCode(u32::from(b'a')));
+        assert_eq!(events[3].base_layout_key, None);
+        // The reply to a flags query is not a key
+        assert!(parser.parse(b"\x1B[?1u").is_empty());
+    }
+
+    #[test]
+    fn split_across_reads() {
+        let mut parser = InputParser::new();
+        parser.push_default();
+        let emoji = "😀".as_bytes();
+        assert!(parser.advance(&emoji[..2]).is_empty());
+        assert!(parser.has_pending());
+        let events = parser.advance(&emoji[2..]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].key_code, KeyCode(0x1F600));
+
+        let events = parser.advance(b"a\x1B[1;5");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].key_code, KeyCode(u32::from(b'a')));
+        let events = parser.advance(b"C");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].key_code, KeyCode(c::RIGHT));
+        assert_eq!(events[0].mods, Modifiers::CTRL);
+        assert!(!parser.has_pending());
+
+        // A lone ESC waits for the caller to decide
+        assert!(parser.advance(b"\x1B").is_empty());
+        let events = parser.flush_pending();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].key_code, KeyCode(u32::from(0x1B_u8)));
+        assert!(parser.flush_pending().is_empty());
+
+        // Byte by byte gives what the whole input gives
+        let input = "x\x1B[Aé\x1B[1;5C漢\x1B[6;18;9t".as_bytes();
+        let all = parser.parse(input);
+        let mut events = Vec::new();
+        let mut reports = 0;
+        for byte in input {
+            let list = parser.advance(&[*byte]);
+            reports += list.window_reports().len();
+            events.extend(list.iter().copied());
+        }
+        assert_eq!(reports, 1);
+        assert_eq!(events.len(), all.len());
+        assert!(events
+            .iter()
+            .zip(all.iter())
+            .all(|(a, b)| a.key_code == b.key_code && a.mods == b.mods));
+    }
+
+    #[test]
+    fn bracketed_paste() {
+        let mut parser = InputParser::new();
+        parser.push_default();
+        let events = parser.parse(b"a\x1B[200~ls\x1B[A\r\x1B[201~b");
+        let keys: Vec<_> = events.iter().map(|e| e.key_code).collect();
+        assert_eq!(keys, [KeyCode(u32::from(b'a')), KeyCode(u32::from(b'b'))]);
+        assert_eq!(
+            events.pastes(),
+            [Paste {
+                position: 1,
+                text: "ls\x1B[A\r".into()
+            }]
+        );
+
+        // Split anywhere, even inside the end marker, it is still one paste
+        let input = "\x1B[200~é\x1B[1;5C\x1B[201~x".as_bytes();
+        for split in 1..input.len() {
+            let first = parser.advance(&input[..split]);
+            let second = parser.advance(&input[split..]);
+            let pastes: Vec<_> = first.pastes().iter().chain(second.pastes()).collect();
+            assert_eq!(pastes.len(), 1, "split at {split}");
+            assert_eq!(pastes[0].text, "é\x1B[1;5C");
+            assert_eq!(first.len() + second.len(), 1);
+            assert!(!parser.has_pending());
+        }
+
+        // Invalid UTF-8 is replaced, a paste without its end takes the rest
+        let events = parser.parse(b"\x1B[200~\xFFok");
+        assert!(events.is_empty());
+        assert_eq!(events.pastes()[0].text, "\u{FFFD}ok");
+    }
+
+    #[test]
+    fn long_paste_in_pieces() {
+        let mut parser = InputParser::new();
+        parser.push_default();
+        assert!(parser.advance(PASTE_START).pastes().is_empty());
+        let chunk = "é".repeat(1000);
+        let mut text = String::new();
+        let (mut pieces, mut keys) = (0, 0);
+        let mut input = Vec::new();
+        for _ in 0..MAX_PASTE / 1000 {
+            input.extend_from_slice(chunk.as_bytes());
+        }
+        input.extend_from_slice(b"\x1B[A");
+        input.extend_from_slice(PASTE_END);
+        input.push(b'x');
+        // Some chunks end inside a character
+        for chunk in input.chunks(4099) {
+            let events = parser.advance(chunk);
+            for paste in events.pastes() {
+                text.push_str(&paste.text);
+                pieces += 1;
+            }
+            for key in events.iter() {
+                assert_eq!(key.key_code, KeyCode(u32::from(b'x')));
+                keys += 1;
+            }
+            // Never much more than the limit is kept
+            assert!(parser.pending.len() <= MAX_PASTE + 4099);
+        }
+        assert!(pieces > 1);
+        assert_eq!(keys, 1);
+        assert!(!parser.has_pending());
+        assert_eq!(text.len(), input.len() - 1 - PASTE_END.len());
+        assert!(text.strip_suffix("\x1B[A").unwrap().chars().all(|ch| ch == 'é'));
+    }
+
+    #[test]
+    fn garbage_does_not_panic() {
+        let mut parser = InputParser::new();
+        parser.push_default();
+        let malformed: &[&[u8]] = &[
+            b"\x1B[1;0A",
+            b"\x1B[1;0~",
+            b"\x1B[;;~",
+            b"\x1B[;A",
+            b"\x1B[1;999A",
+            b"\x1B[1;65536A",
+            b"\x1B[99999999;1~",
+            b"\x1B[0u",
+            b"\x1B[97;0u",
+            b"\x1B[97:::;1:9:;0u",
+            b"\x1B[1114112;;1114112u",
+            b"\x1B[8;99999999999;99999999999t",
+            b"\x1B[200~",
+            b"\x1B[201~",
+            b"\x1B[",
+            b"\x1BO",
+            b"\x1B\x1B\x1B",
+        ];
+        for input in malformed {
+            parser.parse(input);
+        }
+        // Parameters beyond MAX_PENDING
+        let mut overlong = b"\x1B[".to_vec();
+        overlong.extend(b"12;".repeat(200));
+        overlong.push(b'A');
+        parser.parse(&overlong);
+        parser.advance(&overlong);
+        parser.flush_pending();
+        assert_eq!(parser.parse(b"\x1B[1;0A")[0].mods, Modifiers::NONE);
+
+        const ALPHABET: &[u8] = b"\x1B[O;:~0159ABZtu\x7F\xC3\xA9\xFF a";
+        // Small xorshift generator, so failures can be reproduced
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..5000 {
+            let len = next() % 40;
+            let input: Vec<u8> = (0..len)
+                .map(|_| match next() % 4 {
+                    0 => next() as u8,
+                    _ => ALPHABET[(next() % ALPHABET.len() as u64) as usize],
+                })
+                .collect();
+            parser.parse(&input);
+            parser.advance(&input);
+        }
+        parser.flush_pending();
+    }
+
+    #[test]
+    fn parse_in_bounded_steps() {
+        let mut parser = InputParser::new();
+        parser.push_default();
+        let unit = "ab\x1B[Aé\x1B[1;5C漢\x1B[6;18;9t".as_bytes();
+        let input = unit.repeat(2 * 1024 * 1024 / unit.len());
+        let all = parser.parse(&input);
+
+        // Every frame handles at most 1000 events and leaves the rest for the next one
+        let mut events = Vec::new();
+        let mut reports = 0;
+        let mut rest = &input[..];
+        while !rest.is_empty() {
+            let mut iter = parser.parse_iter(rest);
+            let frame: Vec<_> = iter.by_ref().take(1000).collect();
+            assert!(frame.len() <= 1000);
+            events.extend(frame);
+            reports += iter.window_reports().len();
+            rest = &rest[iter.consumed()..];
+        }
+        assert_eq!(events.len(), all.len());
+        assert_eq!(reports, all.window_reports().len());
+        assert!(events
+            .iter()
+            .zip(all.iter())
+            .all(|(a, b)| a.key_code == b.key_code && a.mods == b.mods));
+    }
+
+    #[test]
+    fn const_modifiers() {
+        const BINDINGS: [(char, Modifiers); 3] = [
+            ('s', Modifiers::CTRL),
+            ('z', Modifiers::CTRL.union(Modifiers::SHIFT)),
+            ('q', Modifiers::new(0).union(Modifiers::ALT)),
+        ];
+        const SAVE_AS: Modifiers = BINDINGS[1].1;
+        assert!(SAVE_AS.contains(Modifiers::CTRL));
+        assert!(!SAVE_AS.contains(Modifiers::CTRL | Modifiers::ALT));
+        assert!(SAVE_AS.intersects(Modifiers::CTRL | Modifiers::ALT));
+        assert!(!BINDINGS[2].1.intersects(SAVE_AS));
+        assert!(Modifiers::NONE.is_empty());
+        assert_eq!(
+            SAVE_AS.iter().collect::<Vec<_>>(),
+            [Modifier::Shift, Modifier::Ctrl]
+        );
+        assert_eq!(
+            [Modifier::Ctrl, Modifier::Shift].into_iter().collect::<Modifiers>(),
+            SAVE_AS
+        );
+        assert_eq!(Modifiers::from(Modifier::NumLock), Modifiers::NUM_LOCK);
+    }
+
+    #[test]
+    fn kitty_modifier_param() {
+        for bits in 0..=u8::MAX {
+            let mods = Modifiers::new(bits);
+            assert_eq!(Modifiers::from_kitty_param(mods.to_kitty_param()), Some(mods));
+        }
+        assert_eq!(Modifiers::from_kitty_param(5), Some(Modifiers::CTRL));
+        assert_eq!(Modifiers::from_kitty_param(0), None);
+        assert_eq!(Modifiers::from_kitty_param(257), None);
+    }
+
+    #[cfg(feature = "bitflags")]
+    #[test]
+    fn bitflags_format() {
+        let mods = Modifiers::CTRL | Modifiers::SHIFT;
+        let mut text = String::new();
+        bitflags::parser::to_writer(&mods, &mut text).unwrap();
+        assert_eq!(text, "SHIFT | CTRL");
+        assert_eq!(
+            bitflags::parser::from_str::<Modifiers>("ALT | CAPS_LOCK").unwrap(),
+            Modifiers::ALT | Modifiers::CAPS_LOCK
+        );
+    }
 }
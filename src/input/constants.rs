@@ -62,49 +62,50 @@ pub const KP_5: u32 =               57404;
 pub const KP_6: u32 =               57405;
 pub const KP_7: u32 =               57406;
 pub const KP_8: u32 =               57407;
-pub const KP_DECIMAL: u32 =         57408;
-pub const KP_DIVIDE: u32 =          57409;
-pub const KP_MULTIPLY: u32 =        57410;
-pub const KP_SUBTRACT: u32 =        57411;
-pub const KP_ADD: u32 =             57412;
-pub const KP_ENTER: u32 =           57413;
-pub const KP_EQUAL: u32 =           57414;
-pub const KP_SEPARATOR: u32 =       57415;
-pub const KP_LEFT: u32 =            57416;
-pub const KP_RIGHT: u32 =           57417;
-pub const KP_UP: u32 =              57418;
-pub const KP_DOWN: u32 =            57419;
-pub const KP_PAGE_UP: u32 =         57420;
-pub const KP_PAGE_DOWN: u32 =       57421;
-pub const KP_HOME: u32 =            57422;
-pub const KP_END: u32 =             57423;
-pub const KP_INSERT: u32 =          57424;
-pub const KP_DELETE: u32 =          57425;
-pub const KP_BEGIN: u32 =           57426;
-pub const MEDIA_PLAY: u32 =         57427;
-pub const MEDIA_PAUSE: u32 =        57428;
-pub const MEDIA_PLAY_PAUSE: u32 =   57429;
-pub const MEDIA_REVERSE: u32 =      57430;
-pub const MEDIA_STOP: u32 =         57431;
-pub const FEDIA_FAST_FORWARD: u32 = 57432;
-pub const MEDIA_REWIND: u32 =       57433;
-pub const MEDIA_TRACK_NEXT: u32 =   57434;
-pub const MEDIA_TRACK_PREVIOUS: u32 = 57435;
-pub const MEDIA_RECORD: u32 =       57436;
-pub const LOWER_VOLUME: u32 =       57437;
-pub const RAISE_VOLUME: u32 =       57438;
-pub const MUTE_VOLUME: u32 =        57439;
-pub const LEFT_SHIFT: u32 =         57440;
-pub const LEFT_CONTROL: u32 =       57441;
-pub const LEFT_ALT: u32 =           57442;
-pub const LEFT_SUPER: u32 =         57443;
-pub const LEFT_HYPER: u32 =         57444;
-pub const LEFT_META: u32 =          57445;
-pub const RIGHT_SHIFT: u32 =        57446;
-pub const RIGHT_CONTROL: u32 =      57447;
-pub const RIGHT_ALT: u32 =          57448;
-pub const RIGHT_SUPER: u32 =        57449;
-pub const RIGHT_HYPER: u32 =        57450;
-pub const RIGHT_META: u32 =         57451;
-pub const ISO_LEVEL3_SHIFT: u32 =   57452;
-pub const ISO_LEVEL5_SHIFT: u32 =   57453;
+pub const KP_9: u32 =               57408;
+pub const KP_DECIMAL: u32 =         57409;
+pub const KP_DIVIDE: u32 =          57410;
+pub const KP_MULTIPLY: u32 =        57411;
+pub const KP_SUBTRACT: u32 =        57412;
+pub const KP_ADD: u32 =             57413;
+pub const KP_ENTER: u32 =           57414;
+pub const KP_EQUAL: u32 =           57415;
+pub const KP_SEPARATOR: u32 =       57416;
+pub const KP_LEFT: u32 =            57417;
+pub const KP_RIGHT: u32 =           57418;
+pub const KP_UP: u32 =              57419;
+pub const KP_DOWN: u32 =            57420;
+pub const KP_PAGE_UP: u32 =         57421;
+pub const KP_PAGE_DOWN: u32 =       57422;
+pub const KP_HOME: u32 =            57423;
+pub const KP_END: u32 =             57424;
+pub const KP_INSERT: u32 =          57425;
+pub const KP_DELETE: u32 =          57426;
+pub const KP_BEGIN: u32 =           57427;
+pub const MEDIA_PLAY: u32 =         57428;
+pub const MEDIA_PAUSE: u32 =        57429;
+pub const MEDIA_PLAY_PAUSE: u32 =   57430;
+pub const MEDIA_REVERSE: u32 =      57431;
+pub const MEDIA_STOP: u32 =         57432;
+pub const FEDIA_FAST_FORWARD: u32 = 57433;
+pub const MEDIA_REWIND: u32 =       57434;
+pub const MEDIA_TRACK_NEXT: u32 =   57435;
+pub const MEDIA_TRACK_PREVIOUS: u32 = 57436;
+pub const MEDIA_RECORD: u32 =       57437;
+pub const LOWER_VOLUME: u32 =       57438;
+pub const RAISE_VOLUME: u32 =       57439;
+pub const MUTE_VOLUME: u32 =        57440;
+pub const LEFT_SHIFT: u32 =         57441;
+pub const LEFT_CONTROL: u32 =       57442;
+pub const LEFT_ALT: u32 =           57443;
+pub const LEFT_SUPER: u32 =         57444;
+pub const LEFT_HYPER: u32 =         57445;
+pub const LEFT_META: u32 =          57446;
+pub const RIGHT_SHIFT: u32 =        57447;
+pub const RIGHT_CONTROL: u32 =      57448;
+pub const RIGHT_ALT: u32 =          57449;
+pub const RIGHT_SUPER: u32 =        57450;
+pub const RIGHT_HYPER: u32 =        57451;
+pub const RIGHT_META: u32 =         57452;
+pub const ISO_LEVEL3_SHIFT: u32 =   57453;
+pub const ISO_LEVEL5_SHIFT: u32 =   57454;
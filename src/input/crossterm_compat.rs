@@ -0,0 +1,475 @@
+//! Conversions between this crate's key events and crossterm's, for code that uses both.
+//!
+//! The mapping is lossy in a few places:
+//! - keypad keys become crossterm keys with [`KeyEventState::KEYPAD`] set and come back
+//!   as the `KP_*` codes only when that flag is present;
+//! - plain `\r`, `\t`, ESC and DEL/BS bytes become [`CtKeyCode::Enter`], [`CtKeyCode::Tab`],
+//!   [`CtKeyCode::Esc`] and [`CtKeyCode::Backspace`], which convert back to the
+//!   [`c::ENTER`], [`c::TAB`], [`c::ESCAPE`] and [`c::BACKSPACE`] codes;
+//! - other C0 bytes become `Ctrl` + char, like [`KeyEventList::c0_to_ctrl`](super::KeyEventList::c0_to_ctrl) does;
+//! - codes crossterm has no variant for become [`CtKeyCode::Null`],
+//!   [`key_event_to_crossterm`] returns the original code next to the event so it can be restored
+//!   with [`key_event_from_crossterm`].
+//!
+//! Only key events are converted: this crate has no mouse or resize events yet.
+
+use crossterm::event::{
+    KeyCode as CtKeyCode, KeyEvent as CtKeyEvent, KeyEventKind, KeyEventState, KeyModifiers,
+    MediaKeyCode, ModifierKeyCode,
+};
+
+use super::{constants as c, EventType, KeyCode, KeyEvent, Modifiers};
+
+/// First and last codepoints of the Private Use Area kitty takes functional key codes from.
+const PUA: std::ops::RangeInclusive<u32> = 0xE000..=0xF8FF;
+
+const MODIFIER_TABLE: [(Modifiers, KeyModifiers); 6] = [
+    (Modifiers::SHIFT, KeyModifiers::SHIFT),
+    (Modifiers::ALT, KeyModifiers::ALT),
+    (Modifiers::CTRL, KeyModifiers::CONTROL),
+    (Modifiers::SUPER, KeyModifiers::SUPER),
+    (Modifiers::HYPER, KeyModifiers::HYPER),
+    (Modifiers::META, KeyModifiers::META),
+];
+
+const STATE_TABLE: [(Modifiers, KeyEventState); 2] = [
+    (Modifiers::CAPS_LOCK, KeyEventState::CAPS_LOCK),
+    (Modifiers::NUM_LOCK, KeyEventState::NUM_LOCK),
+];
+
+/// Functional keys that map to a crossterm key without the keypad flag.
+const KEY_TABLE: [(u32, CtKeyCode); 47] = [
+    (c::ESCAPE, CtKeyCode::Esc),
+    (c::ENTER, CtKeyCode::Enter),
+    (c::TAB, CtKeyCode::Tab),
+    (c::BACKSPACE, CtKeyCode::Backspace),
+    (c::INSERT, CtKeyCode::Insert),
+    (c::DELETE, CtKeyCode::Delete),
+    (c::LEFT, CtKeyCode::Left),
+    (c::RIGHT, CtKeyCode::Right),
+    (c::UP, CtKeyCode::Up),
+    (c::DOWN, CtKeyCode::Down),
+    (c::PAGE_UP, CtKeyCode::PageUp),
+    (c::PAGE_DOWN, CtKeyCode::PageDown),
+    (c::HOME, CtKeyCode::Home),
+    (c::END, CtKeyCode::End),
+    (c::CAPS_LOCK, CtKeyCode::CapsLock),
+    (c::SCROLL_LOCK, CtKeyCode::ScrollLock),
+    (c::NUM_LOCK, CtKeyCode::NumLock),
+    (c::PRINT_SCREEN, CtKeyCode::PrintScreen),
+    (c::PAUSE, CtKeyCode::Pause),
+    (c::MENU, CtKeyCode::Menu),
+    (c::MEDIA_PLAY, CtKeyCode::Media(MediaKeyCode::Play)),
+    (c::MEDIA_PAUSE, CtKeyCode::Media(MediaKeyCode::Pause)),
+    (
+        c::MEDIA_PLAY_PAUSE,
+        CtKeyCode::Media(MediaKeyCode::PlayPause),
+    ),
+    (c::MEDIA_REVERSE, CtKeyCode::Media(MediaKeyCode::Reverse)),
+    (c::MEDIA_STOP, CtKeyCode::Media(MediaKeyCode::Stop)),
+    (
+        c::FEDIA_FAST_FORWARD,
+        CtKeyCode::Media(MediaKeyCode::FastForward),
+    ),
+    (c::MEDIA_REWIND, CtKeyCode::Media(MediaKeyCode::Rewind)),
+    (
+        c::MEDIA_TRACK_NEXT,
+        CtKeyCode::Media(MediaKeyCode::TrackNext),
+    ),
+    (
+        c::MEDIA_TRACK_PREVIOUS,
+        CtKeyCode::Media(MediaKeyCode::TrackPrevious),
+    ),
+    (c::MEDIA_RECORD, CtKeyCode::Media(MediaKeyCode::Record)),
+    (c::LOWER_VOLUME, CtKeyCode::Media(MediaKeyCode::LowerVolume)),
+    (c::RAISE_VOLUME, CtKeyCode::Media(MediaKeyCode::RaiseVolume)),
+    (c::MUTE_VOLUME, CtKeyCode::Media(MediaKeyCode::MuteVolume)),
+    (
+        c::LEFT_SHIFT,
+        CtKeyCode::Modifier(ModifierKeyCode::LeftShift),
+    ),
+    (
+        c::LEFT_CONTROL,
+        CtKeyCode::Modifier(ModifierKeyCode::LeftControl),
+    ),
+    (c::LEFT_ALT, CtKeyCode::Modifier(ModifierKeyCode::LeftAlt)),
+    (
+        c::LEFT_SUPER,
+        CtKeyCode::Modifier(ModifierKeyCode::LeftSuper),
+    ),
+    (
+        c::LEFT_HYPER,
+        CtKeyCode::Modifier(ModifierKeyCode::LeftHyper),
+    ),
+    (c::LEFT_META, CtKeyCode::Modifier(ModifierKeyCode::LeftMeta)),
+    (
+        c::RIGHT_SHIFT,
+        CtKeyCode::Modifier(ModifierKeyCode::RightShift),
+    ),
+    (
+        c::RIGHT_CONTROL,
+        CtKeyCode::Modifier(ModifierKeyCode::RightControl),
+    ),
+    (c::RIGHT_ALT, CtKeyCode::Modifier(ModifierKeyCode::RightAlt)),
+    (
+        c::RIGHT_SUPER,
+        CtKeyCode::Modifier(ModifierKeyCode::RightSuper),
+    ),
+    (
+        c::RIGHT_HYPER,
+        CtKeyCode::Modifier(ModifierKeyCode::RightHyper),
+    ),
+    (
+        c::RIGHT_META,
+        CtKeyCode::Modifier(ModifierKeyCode::RightMeta),
+    ),
+    (
+        c::ISO_LEVEL3_SHIFT,
+        CtKeyCode::Modifier(ModifierKeyCode::IsoLevel3Shift),
+    ),
+    (
+        c::ISO_LEVEL5_SHIFT,
+        CtKeyCode::Modifier(ModifierKeyCode::IsoLevel5Shift),
+    ),
+];
+
+/// Functional keys that map to a crossterm key with [`KeyEventState::KEYPAD`] set.
+const KEYPAD_TABLE: [(u32, CtKeyCode); 29] = [
+    (c::KP_0, CtKeyCode::Char('0')),
+    (c::KP_1, CtKeyCode::Char('1')),
+    (c::KP_2, CtKeyCode::Char('2')),
+    (c::KP_3, CtKeyCode::Char('3')),
+    (c::KP_4, CtKeyCode::Char('4')),
+    (c::KP_5, CtKeyCode::Char('5')),
+    (c::KP_6, CtKeyCode::Char('6')),
+    (c::KP_7, CtKeyCode::Char('7')),
+    (c::KP_8, CtKeyCode::Char('8')),
+    (c::KP_9, CtKeyCode::Char('9')),
+    (c::KP_DECIMAL, CtKeyCode::Char('.')),
+    (c::KP_DIVIDE, CtKeyCode::Char('/')),
+    (c::KP_MULTIPLY, CtKeyCode::Char('*')),
+    (c::KP_SUBTRACT, CtKeyCode::Char('-')),
+    (c::KP_ADD, CtKeyCode::Char('+')),
+    (c::KP_ENTER, CtKeyCode::Enter),
+    (c::KP_EQUAL, CtKeyCode::Char('=')),
+    (c::KP_SEPARATOR, CtKeyCode::Char(',')),
+    (c::KP_LEFT, CtKeyCode::Left),
+    (c::KP_RIGHT, CtKeyCode::Right),
+    (c::KP_UP, CtKeyCode::Up),
+    (c::KP_DOWN, CtKeyCode::Down),
+    (c::KP_PAGE_UP, CtKeyCode::PageUp),
+    (c::KP_PAGE_DOWN, CtKeyCode::PageDown),
+    (c::KP_HOME, CtKeyCode::Home),
+    (c::KP_END, CtKeyCode::End),
+    (c::KP_INSERT, CtKeyCode::Insert),
+    (c::KP_DELETE, CtKeyCode::Delete),
+    (c::KP_BEGIN, CtKeyCode::KeypadBegin),
+];
+
+impl From<Modifiers> for KeyModifiers {
+    /// Caps lock and num lock are dropped, they live in [`KeyEventState`] on the crossterm side.
+    fn from(mods: Modifiers) -> Self {
+        MODIFIER_TABLE
+            .iter()
+            .filter(|(m, _)| mods.superset_of(*m))
+            .fold(KeyModifiers::NONE, |acc, (_, k)| acc | *k)
+    }
+}
+
+impl From<KeyModifiers> for Modifiers {
+    fn from(mods: KeyModifiers) -> Self {
+        MODIFIER_TABLE
+            .iter()
+            .filter(|(_, k)| mods.contains(*k))
+            .fold(Modifiers::NONE, |acc, (m, _)| acc | *m)
+    }
+}
+
+impl From<EventType> for KeyEventKind {
+    fn from(event_type: EventType) -> Self {
+        match event_type {
+            EventType::Press => KeyEventKind::Press,
+            EventType::Repeat => KeyEventKind::Repeat,
+            EventType::Release => KeyEventKind::Release,
+        }
+    }
+}
+
+impl From<KeyEventKind> for EventType {
+    fn from(kind: KeyEventKind) -> Self {
+        match kind {
+            KeyEventKind::Press => EventType::Press,
+            KeyEventKind::Repeat => EventType::Repeat,
+            KeyEventKind::Release => EventType::Release,
+        }
+    }
+}
+
+impl From<KeyEvent> for CtKeyEvent {
+    /// See [`key_event_to_crossterm`], this drops the unmapped key code.
+    fn from(event: KeyEvent) -> Self {
+        key_event_to_crossterm(event).0
+    }
+}
+
+impl From<CtKeyEvent> for KeyEvent {
+    /// See [`key_event_from_crossterm`].
+    fn from(event: CtKeyEvent) -> Self {
+        key_event_from_crossterm(event, None)
+    }
+}
+
+/// Converts `event` to a crossterm key event.
+///
+/// If the key code has no crossterm equivalent the event gets [`CtKeyCode::Null`]
+/// and the original code is returned as the second element.
+pub fn key_event_to_crossterm(event: KeyEvent) -> (CtKeyEvent, Option<KeyCode>) {
+    let mut modifiers = KeyModifiers::from(event.mods);
+    let mut state = STATE_TABLE
+        .iter()
+        .filter(|(m, _)| event.mods.superset_of(*m))
+        .fold(KeyEventState::NONE, |acc, (_, s)| acc | *s);
+    let raw = event.key_code.0;
+    let mut unmapped = None;
+
+    let code = if let Some((_, code)) = KEY_TABLE.iter().find(|(k, _)| *k == raw) {
+        *code
+    } else if let Some((_, code)) = KEYPAD_TABLE.iter().find(|(k, _)| *k == raw) {
+        state |= KeyEventState::KEYPAD;
+        *code
+    } else if (c::F1..=c::F35).contains(&raw) {
+        CtKeyCode::F((raw - c::F1 + 1) as u8)
+    } else {
+        match raw {
+            0x0D => CtKeyCode::Enter,
+            0x09 => CtKeyCode::Tab,
+            0x1B => CtKeyCode::Esc,
+            0x08 | 0x7F => CtKeyCode::Backspace,
+            0 => {
+                modifiers |= KeyModifiers::CONTROL;
+                CtKeyCode::Char(' ')
+            }
+            0x1..=0x1A => {
+                modifiers |= KeyModifiers::CONTROL;
+                CtKeyCode::Char((raw as u8 - 1 + b'a').into())
+            }
+            0x1C..=0x1F => {
+                modifiers |= KeyModifiers::CONTROL;
+                CtKeyCode::Char((raw as u8 - 28 + b'4').into())
+            }
+            _ if PUA.contains(&raw) => {
+                unmapped = Some(event.key_code);
+                CtKeyCode::Null
+            }
+            _ => match char::from_u32(raw) {
+                Some(ch) => CtKeyCode::Char(ch),
+                None => {
+                    unmapped = Some(event.key_code);
+                    CtKeyCode::Null
+                }
+            },
+        }
+    };
+    // crossterm reports Shift+Tab as its own key
+    let code = match code {
+        CtKeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => CtKeyCode::BackTab,
+        code => code,
+    };
+
+    let mut ct_event = CtKeyEvent::new_with_kind(code, modifiers, event.event_type.into());
+    ct_event.state = state;
+    (ct_event, unmapped)
+}
+
+/// Converts a crossterm key event, using `unmapped` as the key code if the event has
+/// [`CtKeyCode::Null`], which is what [`key_event_to_crossterm`] produces for codes it can't map.
+///
+/// [`CtKeyCode::BackTab`] becomes [`c::TAB`] with shift, [`CtKeyCode::F`] outside of `F1..=F35`
+/// and a `Null` without `unmapped` become `KeyCode(0)`.
+pub fn key_event_from_crossterm(event: CtKeyEvent, unmapped: Option<KeyCode>) -> KeyEvent {
+    let mut mods = Modifiers::from(event.modifiers);
+    for (m, s) in STATE_TABLE {
+        if event.state.contains(s) {
+            mods |= m;
+        }
+    }
+    let keypad = event.state.contains(KeyEventState::KEYPAD);
+
+    let key_code = match event.code {
+        CtKeyCode::BackTab => {
+            mods |= Modifiers::SHIFT;
+            KeyCode(c::TAB)
+        }
+        CtKeyCode::Null => unmapped.unwrap_or_default(),
+        CtKeyCode::F(n @ 1..=35) => KeyCode(c::F1 + n as u32 - 1),
+        CtKeyCode::F(_) => KeyCode(0),
+        code => {
+            let keypad_key = keypad
+                .then(|| KEYPAD_TABLE.iter().find(|(_, k)| *k == code))
+                .flatten();
+            match keypad_key.or_else(|| KEY_TABLE.iter().find(|(_, k)| *k == code)) {
+                Some((raw, _)) => KeyCode(*raw),
+                None => match code {
+                    CtKeyCode::Char(ch) => KeyCode(ch.into()),
+                    CtKeyCode::KeypadBegin => KeyCode(c::KP_BEGIN),
+                    _ => KeyCode(0),
+                },
+            }
+        }
+    };
+
+    KeyEvent {
+        key_code,
+        mods,
+        event_type: event.kind.into(),
+        base_layout_key: None,
+        shifted_key: None,
+        text: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(event: KeyEvent) -> KeyEvent {
+        let (ct_event, unmapped) = key_event_to_crossterm(event);
+        key_event_from_crossterm(ct_event, unmapped)
+    }
+
+    #[test]
+    fn modifiers_round_trip() {
+        for bits in 0..64 {
+            let mods = Modifiers::new(bits);
+            assert_eq!(Modifiers::from(KeyModifiers::from(mods)), mods);
+        }
+        for (m, k) in MODIFIER_TABLE {
+            assert_eq!(KeyModifiers::from(m), k);
+        }
+    }
+
+    #[test]
+    fn functional_keys_round_trip() {
+        let f_keys = (c::F1..=c::F35).collect::<Vec<_>>();
+        let tables = KEY_TABLE.iter().chain(&KEYPAD_TABLE).map(|(k, _)| *k);
+        let mut seen = Vec::new();
+        for raw in tables.chain(f_keys) {
+            assert!(!seen.contains(&raw), "{raw} is mapped twice");
+            seen.push(raw);
+            for bits in 0..=u8::MAX {
+                let event = KeyEvent {
+                    key_code: KeyCode(raw),
+                    mods: Modifiers::new(bits),
+                    event_type: EventType::Release,
+                    base_layout_key: None,
+                    shifted_key: None,
+                    text: None,
+                };
+                let (ct_event, unmapped) = key_event_to_crossterm(event);
+                assert_ne!(ct_event.code, CtKeyCode::Null, "{raw} is not mapped");
+                assert_eq!(unmapped, None);
+                let back = round_trip(event);
+                assert_eq!(back.key_code, event.key_code);
+                assert_eq!(back.mods, event.mods);
+                assert!(matches!(back.event_type, EventType::Release));
+            }
+        }
+        // Every functional key kitty defines is covered
+        assert_eq!(seen.len() as u32, c::ISO_LEVEL5_SHIFT - c::ESCAPE + 1);
+    }
+
+    #[test]
+    fn spec_codes() {
+        // Codes from the table of functional keys in the kitty keyboard protocol's documentation
+        let keypad = [
+            (57399, CtKeyCode::Char('0')),
+            (57408, CtKeyCode::Char('9')),
+            (57409, CtKeyCode::Char('.')),
+            (57414, CtKeyCode::Enter),
+            (57427, CtKeyCode::KeypadBegin),
+        ];
+        for (raw, code) in keypad {
+            let mut ct_event = CtKeyEvent::from(code);
+            ct_event.state = KeyEventState::KEYPAD;
+            assert_eq!(KeyEvent::from(ct_event).key_code, KeyCode(raw));
+            let (back, _) = key_event_to_crossterm(KeyEvent {
+                key_code: KeyCode(raw),
+                ..Default::default()
+            });
+            assert_eq!((back.code, back.state), (code, KeyEventState::KEYPAD));
+        }
+        let other = [
+            (57428, CtKeyCode::Media(MediaKeyCode::Play)),
+            (57440, CtKeyCode::Media(MediaKeyCode::MuteVolume)),
+            (57441, CtKeyCode::Modifier(ModifierKeyCode::LeftShift)),
+            (57454, CtKeyCode::Modifier(ModifierKeyCode::IsoLevel5Shift)),
+        ];
+        for (raw, code) in other {
+            assert_eq!(
+                KeyEvent::from(CtKeyEvent::from(code)).key_code,
+                KeyCode(raw)
+            );
+        }
+    }
+
+    #[test]
+    fn keypad_flag_selects_keypad_key() {
+        let (ct_event, _) = key_event_to_crossterm(KeyEvent {
+            key_code: KeyCode(c::KP_ENTER),
+            ..Default::default()
+        });
+        assert_eq!(ct_event.code, CtKeyCode::Enter);
+        assert!(ct_event.state.contains(KeyEventState::KEYPAD));
+
+        let plain = key_event_from_crossterm(CtKeyEvent::from(CtKeyCode::Char('5')), None);
+        assert_eq!(plain.key_code, KeyCode(b'5'.into()));
+    }
+
+    #[test]
+    fn unknown_codes_keep_raw_value() {
+        for raw in [c::ISO_LEVEL5_SHIFT + 1, 0xF8FF, 0xD800, 0x11_0000] {
+            let event = KeyEvent {
+                key_code: KeyCode(raw),
+                ..Default::default()
+            };
+            let (ct_event, unmapped) = key_event_to_crossterm(event);
+            assert_eq!(ct_event.code, CtKeyCode::Null);
+            assert_eq!(unmapped, Some(KeyCode(raw)));
+            assert_eq!(round_trip(event).key_code, KeyCode(raw));
+            assert_eq!(KeyEvent::from(ct_event).key_code, KeyCode(0));
+        }
+    }
+
+    #[test]
+    fn legacy_bytes() {
+        let convert = |raw: u8| {
+            CtKeyEvent::from(KeyEvent {
+                key_code: raw.into(),
+                ..Default::default()
+            })
+        };
+        assert_eq!(convert(b'\r').code, CtKeyCode::Enter);
+        assert_eq!(convert(0x7F).code, CtKeyCode::Backspace);
+        assert_eq!(convert(0x1B).code, CtKeyCode::Esc);
+        assert_eq!(convert(b'x').code, CtKeyCode::Char('x'));
+        let ctrl_c = convert(0x03);
+        assert_eq!(ctrl_c.code, CtKeyCode::Char('c'));
+        assert_eq!(ctrl_c.modifiers, KeyModifiers::CONTROL);
+    }
+
+    #[test]
+    fn back_tab() {
+        let event = KeyEvent {
+            key_code: KeyCode(c::TAB),
+            mods: Modifiers::SHIFT,
+            ..Default::default()
+        };
+        let ct_event = CtKeyEvent::from(event);
+        assert_eq!(ct_event.code, CtKeyCode::BackTab);
+        assert_eq!(ct_event.modifiers, KeyModifiers::SHIFT);
+
+        let back = KeyEvent::from(CtKeyEvent::from(CtKeyCode::BackTab));
+        assert_eq!(back.key_code, KeyCode(c::TAB));
+        assert_eq!(back.mods, Modifiers::SHIFT);
+    }
+}
@@ -0,0 +1,806 @@
+//! Mapping of key chords and chord sequences to application defined actions.
+//!
+//! ```
+//! use nixtui_core::input::keymap::{Keymap, LookupResult};
+//! use nixtui_core::input::KeyEvent;
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! enum Action {
+//!     Top,
+//!     Save,
+//! }
+//!
+//! let mut keymap = Keymap::new();
+//! keymap.bind_str("normal", "g g", Action::Top).unwrap();
+//! keymap.bind_str("normal", "ctrl+s", Action::Save).unwrap();
+//!
+//! let g = KeyEvent {
+//!     key_code: (b'g').into(),
+//!     ..Default::default()
+//! };
+//! assert_eq!(keymap.lookup(&g), LookupResult::Pending);
+//! assert_eq!(keymap.lookup(&g), LookupResult::Matched(Action::Top));
+//! ```
+
+use std::time::{Duration, Instant};
+
+use super::constants as c;
+use super::{EventType, KeyCode, KeyEvent, Modifiers};
+
+/// Single key press with the modifiers held.
+pub type Chord = (KeyCode, Modifiers);
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum KeymapError {
+    #[error("Binding `{0}` could not be parsed.")]
+    InvalidBinding(String),
+    #[error("Binding conflicts with an existing one in layer `{layer}`.")]
+    Conflict { layer: String },
+    #[error("Empty key sequence can't be bound.")]
+    EmptySequence,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupResult<A> {
+    /// A whole sequence was typed, pending keys are consumed.
+    Matched(A),
+    /// The keys typed so far are a prefix of at least one binding.
+    Pending,
+    NoMatch,
+}
+
+#[derive(Debug, Clone)]
+struct Layer<A> {
+    name: String,
+    enabled: bool,
+    bindings: Vec<(Vec<Chord>, A, Option<Help>)>,
+}
+
+/// What a binding does, in words for help screens, see [`Keymap::bind_with_help`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Help {
+    /// Heading the binding is listed under, e. g. "Navigation".
+    pub category: String,
+    pub description: String,
+}
+
+impl Help {
+    pub fn new(category: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            category: category.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// Which key of a [`KeyEvent`] bindings are matched against.
+///
+/// Only terminals that report alternate keys with the kitty keyboard protocol
+/// tell the physical key, otherwise every policy matches the logical key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchPolicy {
+    /// The character the key produces with the current layout.
+    #[default]
+    Logical,
+    /// The key at the same position on the US layout,
+    /// see [`KeyEvent::base_layout_key`].
+    Physical,
+    /// The logical key, or the physical one when no binding takes the logical key.
+    Either,
+}
+
+/// What typing a chord after a prefix leads to, see [`Keymap::continuations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Continuation<'a, A> {
+    Action(&'a A),
+    /// Start of longer sequences, with the number of bindings below it.
+    Prefix(usize),
+}
+
+enum LayerMatch<'a, A> {
+    Exact(&'a A),
+    Prefix,
+}
+
+impl<A> Layer<A> {
+    fn find(&self, keys: &[Chord]) -> Option<LayerMatch<'_, A>> {
+        let mut prefix = false;
+        for (sequence, action, _) in &self.bindings {
+            if sequence == keys {
+                return Some(LayerMatch::Exact(action));
+            }
+            prefix |= sequence.starts_with(keys);
+        }
+        prefix.then_some(LayerMatch::Prefix)
+    }
+}
+
+/// Ordered set of binding layers, e. g. an "insert" layer consulted before a "normal" one.
+///
+/// Layers are consulted in the order they were created, the first enabled layer
+/// that knows the typed keys decides the result.
+/// Within a layer a sequence can't be bound twice or be a prefix of another sequence.
+#[derive(Debug, Clone)]
+pub struct Keymap<A> {
+    layers: Vec<Layer<A>>,
+    pending: Vec<Chord>,
+    last_key: Option<Instant>,
+    timeout: Option<Duration>,
+    policy: MatchPolicy,
+}
+
+impl<A> Default for Keymap<A> {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            pending: Vec::new(),
+            last_key: None,
+            timeout: None,
+            policy: MatchPolicy::default(),
+        }
+    }
+}
+
+impl<A: Clone> Keymap<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pending keys older than `timeout` are dropped on the next lookup.
+    /// Without a timeout they are kept until the sequence completes or is interrupted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Matches bindings against the physical key as well, so that e. g. `ctrl+z`
+    /// works on any keyboard layout.
+    pub fn with_match_policy(mut self, policy: MatchPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Adds an empty enabled layer after the existing ones, if it doesn't exist yet.
+    pub fn add_layer(&mut self, name: &str) {
+        if self.layer_index(name).is_none() {
+            self.layers.push(Layer {
+                name: name.into(),
+                enabled: true,
+                bindings: Vec::new(),
+            });
+        }
+    }
+
+    /// Enables or disables a layer, e. g. when switching modes. Returns false if it doesn't exist.
+    pub fn set_enabled(&mut self, layer: &str, enabled: bool) -> bool {
+        let Some(i) = self.layer_index(layer) else {
+            return false;
+        };
+        self.layers[i].enabled = enabled;
+        self.pending.clear();
+        true
+    }
+
+    /// Binds `keys` to `action` in `layer`, creating the layer if needed.
+    pub fn bind(&mut self, layer: &str, keys: &[Chord], action: A) -> Result<(), KeymapError> {
+        self.insert(layer, keys, action, None)
+    }
+
+    /// Same as [`Keymap::bind`], with a description listed by [`Keymap::help`].
+    pub fn bind_with_help(
+        &mut self,
+        layer: &str,
+        keys: &[Chord],
+        action: A,
+        help: Help,
+    ) -> Result<(), KeymapError> {
+        self.insert(layer, keys, action, Some(help))
+    }
+
+    fn insert(
+        &mut self,
+        layer: &str,
+        keys: &[Chord],
+        action: A,
+        help: Option<Help>,
+    ) -> Result<(), KeymapError> {
+        if keys.is_empty() {
+            return Err(KeymapError::EmptySequence);
+        }
+        let keys: Vec<_> = keys.iter().map(|&chord| normalize(chord)).collect();
+        self.add_layer(layer);
+        let i = self.layer_index(layer).unwrap();
+        let layer = &mut self.layers[i];
+        if layer
+            .bindings
+            .iter()
+            .any(|(sequence, ..)| sequence.starts_with(&keys) || keys.starts_with(sequence))
+        {
+            return Err(KeymapError::Conflict {
+                layer: layer.name.clone(),
+            });
+        }
+        layer.bindings.push((keys, action, help));
+        Ok(())
+    }
+
+    /// Same as [`Keymap::bind`] with the keys given as text, see [`parse_sequence`].
+    pub fn bind_str(&mut self, layer: &str, keys: &str, action: A) -> Result<(), KeymapError> {
+        self.bind(layer, &parse_sequence(keys)?, action)
+    }
+
+    /// Same as [`Keymap::bind_with_help`] with the keys given as text.
+    pub fn bind_str_with_help(
+        &mut self,
+        layer: &str,
+        keys: &str,
+        action: A,
+        help: Help,
+    ) -> Result<(), KeymapError> {
+        self.bind_with_help(layer, &parse_sequence(keys)?, action, help)
+    }
+
+    /// Described bindings that can currently be typed, in the order the layers were
+    /// created and the bindings made. Bindings of disabled layers and bindings shadowed
+    /// by an earlier layer are left out.
+    pub fn help(&self) -> Vec<(&[Chord], &Help)> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.enabled)
+            .flat_map(|layer| &layer.bindings)
+            .filter_map(|(keys, action, help)| {
+                let reachable = matches!(
+                    self.find(keys),
+                    Some(LayerMatch::Exact(found)) if std::ptr::eq(found, action)
+                );
+                Some((keys.as_slice(), help.as_ref().filter(|_| reachable)?))
+            })
+            .collect()
+    }
+
+    /// Keys typed so far of an incomplete sequence.
+    pub fn pending(&self) -> &[Chord] {
+        &self.pending
+    }
+
+    /// Chords that continue `prefix`, each with what it leads to as [`Keymap::lookup`]
+    /// would decide it, in the order the bindings were made.
+    /// Pass [`Keymap::pending`] for the keys typed so far.
+    pub fn continuations(&self, prefix: &[Chord]) -> Vec<(Chord, Continuation<'_, A>)> {
+        let enabled = || self.layers.iter().filter(|layer| layer.enabled);
+        let mut chords: Vec<Chord> = Vec::new();
+        for layer in enabled() {
+            for (sequence, ..) in &layer.bindings {
+                if let Some(&next) = sequence.get(prefix.len()) {
+                    if sequence.starts_with(prefix) && !chords.contains(&next) {
+                        chords.push(next);
+                    }
+                }
+            }
+        }
+        chords
+            .into_iter()
+            .filter_map(|chord| {
+                let keys = [prefix, &[chord]].concat();
+                let (layer, found) =
+                    enabled().find_map(|layer| Some((layer, layer.find(&keys)?)))?;
+                let continuation = match found {
+                    LayerMatch::Exact(action) => Continuation::Action(action),
+                    LayerMatch::Prefix => Continuation::Prefix(
+                        layer
+                            .bindings
+                            .iter()
+                            .filter(|(sequence, ..)| sequence.starts_with(&keys))
+                            .count(),
+                    ),
+                };
+                Some((chord, continuation))
+            })
+            .collect()
+    }
+
+    /// Drops the pending keys, for callers that implement the timeout themselves.
+    pub fn cancel_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Feeds a key to the keymap. Release events are ignored.
+    ///
+    /// A key that doesn't continue the pending sequence drops it
+    /// and is then looked up on its own.
+    pub fn lookup(&mut self, event: &KeyEvent) -> LookupResult<A> {
+        if matches!(event.event_type, EventType::Release) {
+            return LookupResult::NoMatch;
+        }
+        let now = Instant::now();
+        if let (Some(timeout), Some(last)) = (self.timeout, self.last_key) {
+            if now.duration_since(last) > timeout {
+                self.pending.clear();
+            }
+        }
+        self.last_key = Some(now);
+
+        let logical = normalize((event.key_code, event.mods));
+        let physical = event
+            .base_layout_key
+            .map(|code| normalize((code, event.mods)));
+        let chords = match (self.policy, physical) {
+            (MatchPolicy::Physical, Some(physical)) => vec![physical],
+            (MatchPolicy::Either, Some(physical)) if physical != logical => vec![logical, physical],
+            _ => vec![logical],
+        };
+
+        let pending = std::mem::take(&mut self.pending);
+        let starts: &[&[Chord]] = if pending.is_empty() {
+            &[&[]]
+        } else {
+            &[&pending, &[]]
+        };
+        for start in starts {
+            for &chord in &chords {
+                let mut keys = start.to_vec();
+                keys.push(chord);
+                match self.find(&keys) {
+                    Some(LayerMatch::Exact(action)) => {
+                        return LookupResult::Matched(action.clone())
+                    }
+                    Some(LayerMatch::Prefix) => {
+                        self.pending = keys;
+                        return LookupResult::Pending;
+                    }
+                    None => {}
+                }
+            }
+        }
+        LookupResult::NoMatch
+    }
+
+    /// What `keys` lead to in the first enabled layer that knows them.
+    fn find(&self, keys: &[Chord]) -> Option<LayerMatch<'_, A>> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.enabled)
+            .find_map(|layer| layer.find(keys))
+    }
+
+    fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+}
+
+/// The chord as bindings see it, whether the terminal sent it as legacy bytes
+/// or with the kitty keyboard protocol.
+///
+/// ESC, CR, TAB and DEL become `esc`, `enter`, `tab` and `backspace`, the other C0 bytes
+/// become `ctrl+` a letter like [`c0_to_ctrl`](super::KeyEventList::c0_to_ctrl) does,
+/// e. g. `0x03` is `ctrl+c`. Lock keys don't change what a chord means and are dropped.
+pub fn normalize((code, mods): Chord) -> Chord {
+    let mods = Modifiers(mods.0 & !(Modifiers::CAPS_LOCK.0 | Modifiers::NUM_LOCK.0));
+    let (code, ctrl) = match code.0 {
+        0x1B => (c::ESCAPE, false),
+        0x0D => (c::ENTER, false),
+        0x09 => (c::TAB, false),
+        0x7F => (c::BACKSPACE, false),
+        0x00 => (u32::from(b' '), true),
+        n @ 0x01..=0x1A => (n - 1 + u32::from(b'a'), true),
+        n @ 0x1C..=0x1F => (n - 0x1C + u32::from(b'4'), true),
+        n => (n, false),
+    };
+    let mods = if ctrl { mods | Modifiers::CTRL } else { mods };
+    (KeyCode(code), mods)
+}
+
+/// Parses whitespace separated chords like `"ctrl+x ctrl+s"` or `"g g"`.
+///
+/// A chord is any number of `ctrl`, `alt`, `shift`, `super`, `hyper` or `meta` prefixes
+/// joined with `+`, followed by a single character or a key name:
+/// `esc`, `enter`, `tab`, `backspace`, `space`, `insert`, `delete`, `left`, `right`,
+/// `up`, `down`, `pageup`, `pagedown`, `home`, `end` or `f1` to `f35`.
+/// Modifier and key names are case insensitive.
+pub fn parse_sequence(text: &str) -> Result<Vec<Chord>, KeymapError> {
+    let sequence = text
+        .split_whitespace()
+        .map(parse_chord)
+        .collect::<Result<Vec<_>, _>>()?;
+    if sequence.is_empty() {
+        return Err(KeymapError::EmptySequence);
+    }
+    Ok(sequence)
+}
+
+fn parse_chord(text: &str) -> Result<Chord, KeymapError> {
+    let invalid = || KeymapError::InvalidBinding(text.into());
+    // `+` alone or as the last part, e. g. "ctrl++", is the plus key
+    let (mods, key) = match text.strip_suffix("++") {
+        Some(mods) => (mods, "+"),
+        None if text == "+" => ("", "+"),
+        None => match text.rsplit_once('+') {
+            Some((mods, key)) => (mods, key),
+            None => ("", text),
+        },
+    };
+    let mut modifiers = Modifiers::NONE;
+    for name in mods.split('+').filter(|_| !mods.is_empty()) {
+        let modifier = match name.to_ascii_lowercase().as_str() {
+            "ctrl" => Modifiers::CTRL,
+            "alt" => Modifiers::ALT,
+            "shift" => Modifiers::SHIFT,
+            "super" => Modifiers::SUPER,
+            "hyper" => Modifiers::HYPER,
+            "meta" => Modifiers::META,
+            _ => return Err(invalid()),
+        };
+        modifiers = Modifiers(modifiers.0 | modifier.0);
+    }
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) => ch as u32,
+        _ => key_by_name(&key.to_ascii_lowercase()).ok_or_else(invalid)?,
+    };
+    Ok((KeyCode(code), modifiers))
+}
+
+/// Text of a chord in the syntax of [`parse_sequence`], e. g. `ctrl+x` or `f5`.
+pub fn format_chord(&(code, mods): &Chord) -> String {
+    let mut text = String::new();
+    for (modifier, name) in [
+        (Modifiers::CTRL, "ctrl"),
+        (Modifiers::ALT, "alt"),
+        (Modifiers::SHIFT, "shift"),
+        (Modifiers::SUPER, "super"),
+        (Modifiers::HYPER, "hyper"),
+        (Modifiers::META, "meta"),
+    ] {
+        if mods.0 & modifier.0 != 0 {
+            text.push_str(name);
+            text.push('+');
+        }
+    }
+    match KEY_NAMES.iter().find(|&&(_, key)| key == code.0) {
+        Some((name, _)) => text.push_str(name),
+        None => match code.0 {
+            n @ c::F1..=c::F35 => text.push_str(&format!("f{}", n - c::F1 + 1)),
+            n => text.push(char::from_u32(n).unwrap_or(char::REPLACEMENT_CHARACTER)),
+        },
+    }
+    text
+}
+
+const KEY_NAMES: [(&str, u32); 15] = [
+    ("esc", c::ESCAPE),
+    ("enter", c::ENTER),
+    ("tab", c::TAB),
+    ("backspace", c::BACKSPACE),
+    ("space", b' ' as u32),
+    ("insert", c::INSERT),
+    ("delete", c::DELETE),
+    ("left", c::LEFT),
+    ("right", c::RIGHT),
+    ("up", c::UP),
+    ("down", c::DOWN),
+    ("pageup", c::PAGE_UP),
+    ("pagedown", c::PAGE_DOWN),
+    ("home", c::HOME),
+    ("end", c::END),
+];
+
+fn key_by_name(name: &str) -> Option<u32> {
+    if name == "escape" {
+        return Some(c::ESCAPE);
+    }
+    if let Some(&(_, code)) = KEY_NAMES.iter().find(|&&(key, _)| key == name) {
+        return Some(code);
+    }
+    let n: u32 = name.strip_prefix('f')?.parse().ok()?;
+    if !(1..=35).contains(&n) {
+        return None;
+    }
+    Some(c::F1 + n - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Action {
+        Top,
+        Delete,
+        Quit,
+        Insert(char),
+    }
+
+    fn key(text: &str) -> KeyEvent {
+        let (key_code, mods) = parse_chord(text).unwrap();
+        KeyEvent {
+            key_code,
+            mods,
+            event_type: EventType::Press,
+            base_layout_key: None,
+            shifted_key: None,
+            text: None,
+        }
+    }
+
+    fn feed(keymap: &mut Keymap<Action>, keys: &str) -> Vec<LookupResult<Action>> {
+        keys.split_whitespace()
+            .map(|k| keymap.lookup(&key(k)))
+            .collect()
+    }
+
+    #[test]
+    fn parse_bindings() {
+        assert_eq!(
+            parse_sequence("ctrl+x ctrl+S").unwrap(),
+            [
+                (KeyCode(b'x'.into()), Modifiers::CTRL),
+                (KeyCode(b'S'.into()), Modifiers::CTRL)
+            ]
+        );
+        assert_eq!(
+            parse_sequence("Alt+Shift+F5 esc ctrl++ +").unwrap(),
+            [
+                (
+                    KeyCode(c::F5),
+                    Modifiers(Modifiers::ALT.0 | Modifiers::SHIFT.0)
+                ),
+                (KeyCode(c::ESCAPE), Modifiers::NONE),
+                (KeyCode(b'+'.into()), Modifiers::CTRL),
+                (KeyCode(b'+'.into()), Modifiers::NONE),
+            ]
+        );
+        assert_eq!(
+            parse_sequence("cmd+x"),
+            Err(KeymapError::InvalidBinding("cmd+x".into()))
+        );
+        assert_eq!(
+            parse_sequence("f36"),
+            Err(KeymapError::InvalidBinding("f36".into()))
+        );
+        assert_eq!(parse_sequence("  "), Err(KeymapError::EmptySequence));
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut keymap = Keymap::new();
+        keymap.bind_str("normal", "d d", Action::Delete).unwrap();
+        let conflict = Err(KeymapError::Conflict {
+            layer: "normal".into(),
+        });
+        assert_eq!(keymap.bind_str("normal", "d d", Action::Quit), conflict);
+        assert_eq!(keymap.bind_str("normal", "d", Action::Quit), conflict);
+        assert_eq!(keymap.bind_str("normal", "d d w", Action::Quit), conflict);
+        // Shadowing in another layer is allowed
+        assert_eq!(keymap.bind_str("insert", "d", Action::Insert('d')), Ok(()));
+    }
+
+    #[test]
+    fn interrupted_sequence() {
+        use LookupResult::*;
+
+        let mut keymap = Keymap::new();
+        keymap.bind_str("normal", "g g", Action::Top).unwrap();
+        keymap.bind_str("normal", "d d", Action::Delete).unwrap();
+        keymap.bind_str("normal", "q", Action::Quit).unwrap();
+
+        assert_eq!(
+            feed(&mut keymap, "g g d d"),
+            [
+                Pending,
+                Matched(Action::Top),
+                Pending,
+                Matched(Action::Delete)
+            ]
+        );
+        // The interrupting key is looked up on its own
+        assert_eq!(feed(&mut keymap, "g q"), [Pending, Matched(Action::Quit)]);
+        assert_eq!(
+            feed(&mut keymap, "g d d"),
+            [Pending, Pending, Matched(Action::Delete)]
+        );
+        assert_eq!(feed(&mut keymap, "g x g"), [Pending, NoMatch, Pending]);
+        assert_eq!(keymap.pending(), [(KeyCode(b'g'.into()), Modifiers::NONE)]);
+        keymap.cancel_pending();
+        assert_eq!(feed(&mut keymap, "g"), [Pending]);
+
+        // Lock keys and releases don't interfere
+        let mut locked = key("g");
+        locked.mods = Modifiers::CAPS_LOCK;
+        let mut release = key("x");
+        release.event_type = EventType::Release;
+        assert_eq!(keymap.lookup(&release), NoMatch);
+        assert_eq!(keymap.lookup(&locked), Matched(Action::Top));
+    }
+
+    #[test]
+    fn timeout_drops_prefix() {
+        let mut keymap = Keymap::new().with_timeout(Duration::from_millis(10));
+        keymap.bind_str("normal", "g g", Action::Top).unwrap();
+        assert_eq!(keymap.lookup(&key("g")), LookupResult::Pending);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(keymap.lookup(&key("g")), LookupResult::Pending);
+        assert_eq!(keymap.lookup(&key("g")), LookupResult::Matched(Action::Top));
+    }
+
+    #[test]
+    fn layered_fallthrough() {
+        use LookupResult::*;
+
+        let mut keymap = Keymap::new();
+        keymap.add_layer("insert");
+        keymap.bind_str("insert", "d", Action::Insert('d')).unwrap();
+        keymap.bind_str("insert", "esc", Action::Quit).unwrap();
+        keymap.bind_str("normal", "d d", Action::Delete).unwrap();
+        keymap.bind_str("normal", "ctrl+g g", Action::Top).unwrap();
+
+        // Insert layer shadows normal
+        assert_eq!(
+            feed(&mut keymap, "d d"),
+            [Matched(Action::Insert('d')), Matched(Action::Insert('d'))]
+        );
+        // Keys unknown to insert fall through to normal
+        assert_eq!(
+            feed(&mut keymap, "ctrl+g g"),
+            [Pending, Matched(Action::Top)]
+        );
+
+        assert!(keymap.set_enabled("insert", false));
+        assert_eq!(feed(&mut keymap, "d d"), [Pending, Matched(Action::Delete)]);
+        assert_eq!(feed(&mut keymap, "esc"), [NoMatch]);
+        assert!(!keymap.set_enabled("visual", true));
+    }
+
+    #[test]
+    fn continuations() {
+        let mut keymap = Keymap::new();
+        keymap.add_layer("insert");
+        keymap
+            .bind_str("insert", "g x", Action::Insert('x'))
+            .unwrap();
+        keymap.bind_str("normal", "g g", Action::Top).unwrap();
+        keymap.bind_str("normal", "g x", Action::Quit).unwrap();
+        keymap.bind_str("normal", "g d d", Action::Delete).unwrap();
+        keymap.bind_str("normal", "g d w", Action::Delete).unwrap();
+        keymap.bind_str("normal", "q", Action::Quit).unwrap();
+
+        let chord = |text| parse_chord(text).unwrap();
+        assert_eq!(
+            keymap.continuations(&[chord("g")]),
+            [
+                (chord("x"), Continuation::Action(&Action::Insert('x'))),
+                (chord("g"), Continuation::Action(&Action::Top)),
+                (chord("d"), Continuation::Prefix(2)),
+            ]
+        );
+        assert_eq!(
+            keymap.continuations(&[]),
+            [
+                (chord("g"), Continuation::Prefix(1)),
+                (chord("q"), Continuation::Action(&Action::Quit)),
+            ]
+        );
+        keymap.set_enabled("insert", false);
+        assert_eq!(
+            keymap.continuations(&[chord("g")])[0],
+            (chord("g"), Continuation::Action(&Action::Top))
+        );
+        assert!(keymap.continuations(&[chord("q")]).is_empty());
+    }
+
+    #[test]
+    fn format_chords() {
+        for text in ["ctrl+x", "alt+shift+f5", "esc", "ctrl++", "space", "G", "é"] {
+            assert_eq!(format_chord(&parse_chord(text).unwrap()), text);
+        }
+        assert_eq!(format_chord(&parse_chord("Escape").unwrap()), "esc");
+    }
+
+    #[test]
+    fn legacy_bytes() {
+        let mut keymap = Keymap::new();
+        for (binding, action) in [
+            ("ctrl+c", Action::Quit),
+            ("esc", Action::Top),
+            ("enter", Action::Insert('\n')),
+            ("tab", Action::Insert('\t')),
+            ("backspace", Action::Delete),
+            ("alt+ctrl+x", Action::Insert('x')),
+            ("ctrl+space", Action::Insert(' ')),
+        ] {
+            keymap.bind_str("normal", binding, action).unwrap();
+        }
+        let parser = super::super::InputParser::new();
+        // One input each, ESC followed by anything would be alt+
+        for (input, action) in [
+            (&b"\x03"[..], Action::Quit),
+            (b"\x1B", Action::Top),
+            (b"\x1B[27u", Action::Top),
+            (b"\r", Action::Insert('\n')),
+            (b"\x1B[13u", Action::Insert('\n')),
+            (b"\t", Action::Insert('\t')),
+            (b"\x7F", Action::Delete),
+            (b"\x1B\x18", Action::Insert('x')),
+            (b"\x00", Action::Insert(' ')),
+        ] {
+            let events = parser.parse(input);
+            assert_eq!(events.len(), 1, "{input:?}");
+            assert_eq!(
+                keymap.lookup(&events[0]),
+                LookupResult::Matched(action),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn match_policy() {
+        // ctrl+я on a Russian layout, as reported with kitty alternate keys
+        let ctrl_ya = KeyEvent {
+            base_layout_key: Some(KeyCode(b'z'.into())),
+            ..key("ctrl+я")
+        };
+        let bind = |policy| {
+            let mut keymap = Keymap::new().with_match_policy(policy);
+            keymap.bind_str("normal", "ctrl+z", Action::Quit).unwrap();
+            keymap
+        };
+        assert_eq!(
+            bind(MatchPolicy::Logical).lookup(&ctrl_ya),
+            LookupResult::NoMatch
+        );
+        assert_eq!(
+            bind(MatchPolicy::Physical).lookup(&ctrl_ya),
+            LookupResult::Matched(Action::Quit)
+        );
+        let mut keymap = bind(MatchPolicy::Either);
+        assert_eq!(keymap.lookup(&ctrl_ya), LookupResult::Matched(Action::Quit));
+        // Without the protocol only the logical key is known
+        assert_eq!(keymap.lookup(&key("ctrl+я")), LookupResult::NoMatch);
+        assert_eq!(
+            keymap.lookup(&key("ctrl+z")),
+            LookupResult::Matched(Action::Quit)
+        );
+
+        // The logical key wins when both are bound, also after a pending prefix
+        keymap.bind_str("normal", "ctrl+я", Action::Delete).unwrap();
+        keymap.bind_str("normal", "g ctrl+z", Action::Top).unwrap();
+        assert_eq!(
+            keymap.lookup(&ctrl_ya),
+            LookupResult::Matched(Action::Delete)
+        );
+        assert_eq!(keymap.lookup(&key("g")), LookupResult::Pending);
+        assert_eq!(keymap.lookup(&ctrl_ya), LookupResult::Matched(Action::Top));
+    }
+
+    #[test]
+    fn help_of_reachable_bindings() {
+        let mut keymap = Keymap::new();
+        keymap.add_layer("insert");
+        let help = |description| Help::new("Editing", description);
+        keymap
+            .bind_str_with_help("insert", "esc", Action::Quit, help("leave insert mode"))
+            .unwrap();
+        keymap
+            .bind_str_with_help("normal", "esc", Action::Delete, help("clear"))
+            .unwrap();
+        keymap
+            .bind_str_with_help("normal", "g g", Action::Top, Help::new("Moving", "top"))
+            .unwrap();
+        keymap.bind_str("normal", "x", Action::Delete).unwrap();
+
+        let described = |keymap: &Keymap<Action>| {
+            keymap
+                .help()
+                .into_iter()
+                .map(|(keys, help)| (keys.len(), help.description.clone()))
+                .collect::<Vec<_>>()
+        };
+        // Esc of the normal layer is shadowed while inserting
+        assert_eq!(
+            described(&keymap),
+            [(1, "leave insert mode".into()), (2, "top".into())]
+        );
+        keymap.set_enabled("insert", false);
+        assert_eq!(described(&keymap), [(1, "clear".into()), (2, "top".into())]);
+    }
+}
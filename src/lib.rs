@@ -1,2 +1,18 @@
+#[cfg(not(any(feature = "terminfo", feature = "ansi")))]
+compile_error!("at least one of the `terminfo` and `ansi` features has to be enabled");
+
+pub mod color;
+#[cfg(feature = "terminfo")]
+pub mod diagnostics;
+pub mod env;
 pub mod input;
+pub mod logging;
+#[cfg(feature = "ratatui-backend")]
+pub mod ratatui_backend;
+pub mod sanitize;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod tty;
+pub mod ui;
+pub mod vt;
+pub mod width;
@@ -0,0 +1,119 @@
+//! Instrumentation through the `tracing` crate, enabled by the `tracing` feature.
+//!
+//! Without the feature the macros used inside the crate expand to nothing,
+//! their arguments are not even evaluated.
+
+#[cfg(feature = "tracing")]
+macro_rules! debug_event {
+    ($($arg:tt)*) => { ::tracing::debug!($($arg)*) };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug_event {
+    ($($arg:tt)*) => {};
+}
+
+// Only used by the terminfo backend
+#[allow(unused_macros)]
+#[cfg(feature = "tracing")]
+macro_rules! warn_event {
+    ($($arg:tt)*) => { ::tracing::warn!($($arg)*) };
+}
+#[allow(unused_macros)]
+#[cfg(not(feature = "tracing"))]
+macro_rules! warn_event {
+    ($($arg:tt)*) => {};
+}
+
+/// Enters a debug span until the end of the enclosing scope.
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($($arg:tt)*) => { ::tracing::debug_span!($($arg)*).entered() };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($($arg:tt)*) => {
+        ()
+    };
+}
+
+pub(crate) use debug_event;
+pub(crate) use enter_span;
+#[allow(unused_imports)]
+pub(crate) use warn_event;
+
+/// Sends all events of this crate to the file at `path`, appending to it.
+///
+/// Meant for debugging applications whose stdout is the UI, the log never touches the terminal.
+/// Fails if a global subscriber is already set.
+#[cfg(feature = "tracing")]
+pub fn install_file_logger(path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer(std::sync::Mutex::new(file))
+        .try_init()
+        .map_err(std::io::Error::other)
+}
+
+#[cfg(all(test, feature = "tracing", feature = "terminfo"))]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use terminfo::Database;
+
+    use crate::testing::KITTY_DATABASE;
+    use crate::tty::TerminfoWrapper;
+    use crate::ui::components::Select;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn scripted_render_emits_events() {
+        let recorder = Recorder::default();
+        let writer = recorder.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut terminfo =
+                TerminfoWrapper::from(Database::from_buffer(KITTY_DATABASE).unwrap());
+            let mut select = Select::new(["foo", "bar"]);
+            select.render(&mut terminfo, 0, 0, 10, 2).unwrap();
+            let mut out = Vec::new();
+            terminfo.flush_to(&mut out).unwrap();
+            // kitty has no double wide mode
+            assert!(terminfo.enter_doublewide_mode().is_err());
+            terminfo.get_parser().parse(b"\x1B[99;99X");
+        });
+
+        let log = String::from_utf8(recorder.0.lock().unwrap().clone()).unwrap();
+        assert!(log.contains("flush bytes="), "{log}");
+        assert!(
+            log.contains("capability not found cap=\"enter_doublewide_mode\""),
+            "{log}"
+        );
+        assert!(
+            log.contains(r"unrecognized CSI sequence sequence=\x1b[99;99X"),
+            "{log}"
+        );
+    }
+}
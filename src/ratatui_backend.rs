@@ -0,0 +1,604 @@
+//! [`ratatui_core::backend::Backend`] implementation, enabled by the `ratatui-backend` feature.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::os::fd::AsFd;
+
+use ratatui_core::backend::{Backend, ClearType, WindowSize};
+use ratatui_core::buffer::Cell;
+use ratatui_core::layout::{Position, Rect, Size};
+use ratatui_core::style::{Color, Modifier};
+
+use crate::color::{self, ColorPolicy, PaletteMap};
+use crate::tty::errors::CapabilityError;
+use crate::tty::{TerminfoWrapper, UnixTerminal, Winsize};
+use crate::width::str_width;
+
+/// Output side of a terminal [`TerminfoBackend`] can draw to.
+pub trait BackendTty: Write {
+    fn window_size(&self) -> std::io::Result<Winsize>;
+}
+
+impl<T: AsFd + Write> BackendTty for T {
+    fn window_size(&self) -> std::io::Result<Winsize> {
+        self.as_fd().get_size()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Pen {
+    fg: Color,
+    bg: Color,
+    modifier: Modifier,
+}
+
+impl Default for Pen {
+    fn default() -> Self {
+        Self {
+            fg: Color::Reset,
+            bg: Color::Reset,
+            modifier: Modifier::empty(),
+        }
+    }
+}
+
+/// Ratatui backend drawing through the capabilities of a [`TerminfoWrapper`].
+///
+/// Output is buffered in the wrapper until [`Backend::flush`].
+/// Raw mode and the alternate screen are left to the caller, like with other ratatui backends.
+pub struct TerminfoBackend<T: BackendTty> {
+    tty: T,
+    terminfo: TerminfoWrapper,
+    /// Where the cursor was left by the last write or move, `None` when unknown.
+    /// Right after a symbol in the last column it is one past the last column,
+    /// where the terminal waits to wrap.
+    cursor: Option<Position>,
+    pen: Pen,
+    /// Cells on screen by row and column, drawn again when the color policy changes.
+    screen: BTreeMap<(u16, u16), Cell>,
+    /// Areas with their own palette, later ones cover earlier ones.
+    palettes: Vec<(Rect, PaletteMap)>,
+}
+
+impl<T: BackendTty> TerminfoBackend<T> {
+    pub fn new(tty: T, terminfo: TerminfoWrapper) -> Self {
+        Self {
+            tty,
+            terminfo,
+            cursor: None,
+            pen: Pen::default(),
+            screen: BTreeMap::new(),
+            palettes: Vec::new(),
+        }
+    }
+
+    /// Policy of the wrapper, see [`TerminfoWrapper::set_color_policy`].
+    pub fn color_policy(&self) -> ColorPolicy {
+        self.terminfo.color_policy()
+    }
+
+    /// Sets `policy` on the wrapper and draws every cell on screen again with it,
+    /// ratatui would only send the cells that change. The cursor is put back afterwards.
+    pub fn set_color_policy(&mut self, policy: ColorPolicy) -> Result<(), CapabilityError> {
+        if policy == self.terminfo.color_policy() {
+            return Ok(());
+        }
+        self.terminfo.set_color_policy(policy);
+        let cursor = self.cursor;
+        let screen = std::mem::take(&mut self.screen);
+        self.draw(screen.iter().map(|(&(y, x), cell)| (x, y, cell)))?;
+        match cursor {
+            Some(cursor) if self.cursor != Some(cursor) => self.move_to(cursor),
+            _ => Ok(()),
+        }
+    }
+
+    /// Cells drawn inside `area` from now on take the first 16 colors from `map`,
+    /// e. g. for panes showing the output of programs with different themes.
+    /// Where areas overlap, the one set last applies.
+    pub fn set_palette(&mut self, area: Rect, map: PaletteMap) {
+        self.palettes.push((area, map));
+    }
+
+    pub fn clear_palettes(&mut self) {
+        self.palettes.clear();
+    }
+
+    /// `color` as drawn at `position`, after the palette of its area.
+    fn remap(&self, color: Color, position: Position) -> Color {
+        let Some((_, map)) = self
+            .palettes
+            .iter()
+            .rev()
+            .find(|(area, _)| area.contains(position))
+        else {
+            return color;
+        };
+        match to_color(color).map(|color| map.map(color)) {
+            Some(color::Color::Rgb(r, g, b)) => Color::Rgb(r, g, b),
+            _ => color,
+        }
+    }
+
+    pub fn tty(&self) -> &T {
+        &self.tty
+    }
+
+    pub fn tty_mut(&mut self) -> &mut T {
+        &mut self.tty
+    }
+
+    pub fn terminfo_mut(&mut self) -> &mut TerminfoWrapper {
+        &mut self.terminfo
+    }
+
+    pub fn into_inner(self) -> (T, TerminfoWrapper) {
+        (self.tty, self.terminfo)
+    }
+
+    fn move_to(&mut self, position: Position) -> Result<(), CapabilityError> {
+        self.terminfo
+            .move_cursor(position.y.into(), position.x.into())?;
+        self.cursor = Some(position);
+        Ok(())
+    }
+
+    /// Emits whatever is needed to go from the current pen to `pen`.
+    fn apply_style(&mut self, pen: Pen) -> Result<(), CapabilityError> {
+        let old = self.pen;
+        if old == pen {
+            return Ok(());
+        }
+        // Terminfo has no way to turn most attributes off one by one
+        let reset = !(old.modifier - pen.modifier).is_empty()
+            || (pen.fg == Color::Reset && old.fg != Color::Reset)
+            || (pen.bg == Color::Reset && old.bg != Color::Reset);
+        let (from, fg_changed, bg_changed) = if reset {
+            self.terminfo.exit_attribute_mode()?;
+            (
+                Modifier::empty(),
+                pen.fg != Color::Reset,
+                pen.bg != Color::Reset,
+            )
+        } else {
+            (old.modifier, pen.fg != old.fg, pen.bg != old.bg)
+        };
+
+        let modifiers = if self.terminfo.color_policy().attributes() {
+            pen.modifier - from
+        } else {
+            Modifier::empty()
+        };
+        for modifier in modifiers.iter() {
+            optional(self.enter_modifier(modifier))?;
+        }
+        if fg_changed {
+            self.set_color(pen.fg, true)?;
+        }
+        if bg_changed {
+            self.set_color(pen.bg, false)?;
+        }
+        self.pen = pen;
+        Ok(())
+    }
+
+    fn enter_modifier(&mut self, modifier: Modifier) -> Result<(), CapabilityError> {
+        let t = &mut self.terminfo;
+        match modifier {
+            Modifier::BOLD => t.enter_bold_mode(),
+            Modifier::DIM => t.enter_dim_mode(),
+            Modifier::ITALIC => t.enter_italics_mode(),
+            Modifier::UNDERLINED => t.enter_underline_mode(),
+            // Terminfo only knows a single kind of blinking
+            Modifier::SLOW_BLINK | Modifier::RAPID_BLINK => t.enter_blink_mode(),
+            Modifier::REVERSED => t.enter_reverse_mode(),
+            Modifier::HIDDEN => t.enter_secure_mode(),
+            Modifier::CROSSED_OUT => t.enter_crossed_out_mode(),
+            _ => Ok(()),
+        }
+    }
+
+    /// The wrapper fits the color to the color policy and to what the terminal shows.
+    fn set_color(&mut self, color: Color, foreground: bool) -> Result<(), CapabilityError> {
+        let t = &mut self.terminfo;
+        match (to_color(color), foreground) {
+            // Reset is only reached after exit_attribute_mode, which resets colors as well
+            (None, _) => Ok(()),
+            (Some(color::Color::Indexed(index)), true) => t.set_foreground_256(index),
+            (Some(color::Color::Indexed(index)), false) => t.set_background_256(index),
+            (Some(color::Color::Rgb(r, g, b)), true) => t.set_foreground_rgb(r, g, b),
+            (Some(color::Color::Rgb(r, g, b)), false) => t.set_background_rgb(r, g, b),
+        }
+    }
+}
+
+/// Ratatui color as a palette index or RGB value, `None` for [`Color::Reset`].
+fn to_color(color: Color) -> Option<color::Color> {
+    Some(match color {
+        Color::Reset => return None,
+        Color::Black => color::Color::Indexed(0),
+        Color::Red => color::Color::Indexed(1),
+        Color::Green => color::Color::Indexed(2),
+        Color::Yellow => color::Color::Indexed(3),
+        Color::Blue => color::Color::Indexed(4),
+        Color::Magenta => color::Color::Indexed(5),
+        Color::Cyan => color::Color::Indexed(6),
+        Color::Gray => color::Color::Indexed(7),
+        Color::DarkGray => color::Color::Indexed(8),
+        Color::LightRed => color::Color::Indexed(9),
+        Color::LightGreen => color::Color::Indexed(10),
+        Color::LightYellow => color::Color::Indexed(11),
+        Color::LightBlue => color::Color::Indexed(12),
+        Color::LightMagenta => color::Color::Indexed(13),
+        Color::LightCyan => color::Color::Indexed(14),
+        Color::White => color::Color::Indexed(15),
+        Color::Indexed(index) => color::Color::Indexed(index),
+        Color::Rgb(r, g, b) => color::Color::Rgb(r, g, b),
+    })
+}
+
+/// Treats a missing capability as success, the attribute just won't show up.
+fn optional(result: Result<(), CapabilityError>) -> Result<(), CapabilityError> {
+    match result {
+        Err(CapabilityError::CapabilityNotFound { .. }) => {
+            crate::logging::debug_event!("attribute skipped");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+impl<T: BackendTty> Backend for TerminfoBackend<T> {
+    type Error = CapabilityError;
+
+    fn draw<'a, I>(&mut self, content: I) -> Result<(), Self::Error>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        // Symbols the terminal can't show take the columns of their replacement
+        let policy = self.terminfo.output_encoding().width_policy();
+        for (x, y, cell) in content {
+            let position = Position { x, y };
+            if self.cursor != Some(position) {
+                self.move_to(position)?;
+            }
+            self.apply_style(Pen {
+                fg: self.remap(cell.fg, position),
+                bg: self.remap(cell.bg, position),
+                modifier: cell.modifier,
+            })?;
+            self.terminfo.write_all(cell.symbol().as_bytes())?;
+            self.screen.insert((y, x), cell.clone());
+            // The terminal cursor is now past the symbol
+            let width = str_width(cell.symbol(), &policy) as u16;
+            self.cursor = Some(Position {
+                x: x.saturating_add(width),
+                y,
+            });
+        }
+        self.apply_style(Pen::default())?;
+        Ok(())
+    }
+
+    fn append_lines(&mut self, n: u16) -> Result<(), Self::Error> {
+        for _ in 0..n {
+            self.terminfo.append(b"\n");
+        }
+        // Lines may have scrolled, where the cells went is unknown
+        self.screen.clear();
+        self.cursor = None;
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), Self::Error> {
+        self.terminfo.cursor_invisible()
+    }
+
+    fn show_cursor(&mut self) -> Result<(), Self::Error> {
+        self.terminfo.cursor_normal()
+    }
+
+    /// Where the last move or symbol drawn left the cursor, the terminal isn't queried.
+    /// After a symbol in the last column, the cursor is reported in that column.
+    /// The origin when unknown, e. g. after [`Backend::append_lines`].
+    fn get_cursor_position(&mut self) -> Result<Position, Self::Error> {
+        let Some(mut position) = self.cursor else {
+            return Ok(Position::ORIGIN);
+        };
+        position.x = position.x.min(self.size()?.width.saturating_sub(1));
+        Ok(position)
+    }
+
+    fn set_cursor_position<P: Into<Position>>(&mut self, position: P) -> Result<(), Self::Error> {
+        self.move_to(position.into())
+    }
+
+    fn clear(&mut self) -> Result<(), Self::Error> {
+        self.clear_region(ClearType::All)
+    }
+
+    fn clear_region(&mut self, clear_type: ClearType) -> Result<(), Self::Error> {
+        self.apply_style(Pen::default())?;
+        match clear_type {
+            ClearType::All => {
+                self.screen.clear();
+                // clear_screen homes the cursor, the trait wants it to stay
+                self.terminfo.clear_screen()?;
+                match self.cursor {
+                    Some(position) => self.move_to(position),
+                    None => {
+                        self.cursor = Some(Position::ORIGIN);
+                        Ok(())
+                    }
+                }
+            }
+            ClearType::AfterCursor => {
+                if let Some(Position { x, y }) = self.cursor {
+                    self.screen.retain(|&cell, _| cell < (y, x));
+                }
+                self.terminfo.clr_eos()
+            }
+            ClearType::CurrentLine => {
+                if let Some(Position { y, .. }) = self.cursor {
+                    self.screen.retain(|&(row, _), _| row != y);
+                }
+                self.terminfo.clr_bol()?;
+                self.terminfo.clr_eol()
+            }
+            ClearType::UntilNewLine => {
+                if let Some(Position { x, y }) = self.cursor {
+                    self.screen.retain(|&(row, col), _| row != y || col < x);
+                }
+                self.terminfo.clr_eol()
+            }
+            ClearType::BeforeCursor => {
+                // Terminfo can only clear to the start of a line, rows above are cleared
+                // one by one
+                let Some(position) = self.cursor else {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "clearing before the cursor needs a known cursor position",
+                    )
+                    .into());
+                };
+                let Position { x, y } = position;
+                self.screen.retain(|&cell, _| cell > (y, x));
+                for y in 0..position.y {
+                    self.terminfo.move_cursor(y.into(), 0)?;
+                    self.terminfo.clr_eol()?;
+                }
+                self.move_to(position)?;
+                self.terminfo.clr_bol()
+            }
+        }
+    }
+
+    fn size(&self) -> Result<Size, Self::Error> {
+        let size = self.tty.window_size()?;
+        Ok(Size::new(size.col, size.row))
+    }
+
+    /// Pixel size is always reported as zero.
+    fn window_size(&mut self) -> Result<WindowSize, Self::Error> {
+        Ok(WindowSize {
+            columns_rows: self.size()?,
+            pixels: Size::ZERO,
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.terminfo.flush_to(&mut self.tty)?;
+        self.tty.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui_core::buffer::Buffer;
+    use ratatui_core::layout::Rect;
+    use ratatui_core::style::Style;
+    use terminfo::Database;
+
+    use crate::testing::{TestTty, KITTY_DATABASE};
+
+    fn backend() -> TerminfoBackend<TestTty> {
+        let db = Database::from_buffer(KITTY_DATABASE).unwrap();
+        TerminfoBackend::new(TestTty::new(), db.into())
+    }
+
+    fn draw(backend: &mut TerminfoBackend<TestTty>, prev: &Buffer, next: &Buffer) -> Vec<u8> {
+        backend.tty_mut().clear_output();
+        backend.draw(prev.diff(next).into_iter()).unwrap();
+        backend.flush().unwrap();
+        backend.tty().output()
+    }
+
+    #[test]
+    fn size_comes_from_tty() {
+        let mut backend = backend();
+        backend.tty_mut().set_size(30, 7);
+        assert_eq!(backend.size().unwrap(), Size::new(30, 7));
+        assert_eq!(
+            backend.window_size().unwrap().columns_rows,
+            Size::new(30, 7)
+        );
+    }
+
+    #[test]
+    fn draw_snapshot() {
+        let mut backend = backend();
+        let area = Rect::new(0, 0, 10, 3);
+        let prev = Buffer::empty(area);
+        let mut next = Buffer::empty(area);
+        next.set_string(0, 0, "ab", Style::new().bold().red());
+        next.set_string(2, 0, "c", Style::new().red());
+        next.set_string(4, 2, "d", Style::new().on_blue().italic());
+        next.set_string(5, 2, "e", Style::new().fg(Color::Rgb(1, 2, 3)));
+
+        let output = draw(&mut backend, &prev, &next);
+        assert_eq!(
+            output.escape_ascii().to_string(),
+            concat!(
+                r"\x1b[1;1H\x1b[1m\x1b[31mab",
+                // Bold is dropped, so everything is reset and red applied again
+                r"\x1b(B\x1b[m\x1b[31mc",
+                r"\x1b[3;5H\x1b(B\x1b[m\x1b[3m\x1b[44md",
+                r"\x1b(B\x1b[m\x1b[38;2;1;2;3me",
+                r"\x1b(B\x1b[m",
+            )
+        );
+
+        // Nothing changed, nothing to draw
+        assert_eq!(draw(&mut backend, &next, &next), b"");
+    }
+
+    #[test]
+    fn crossed_out_to_normal() {
+        let mut backend = backend();
+        let area = Rect::new(0, 0, 3, 1);
+        let mut next = Buffer::empty(area);
+        next.set_string(0, 0, "ab", Style::new().crossed_out());
+        next.set_string(2, 0, "c", Style::new());
+        let output = draw(&mut backend, &Buffer::empty(area), &next);
+        assert_eq!(output, b"\x1b[1;1H\x1b[9mab\x1b(B\x1b[mc");
+
+        // The pen is back to normal after a frame, so only the changed cell is written
+        let mut after = next.clone();
+        after.set_string(0, 0, "a", Style::reset());
+        let output = draw(&mut backend, &next, &after);
+        assert_eq!(output, b"\x1b[1;1Ha");
+    }
+
+    #[test]
+    fn indexed_and_named_colors_use_capabilities() {
+        let mut backend = backend();
+        let area = Rect::new(0, 0, 2, 1);
+        let mut next = Buffer::empty(area);
+        next.set_string(0, 0, "x", Style::new().fg(Color::Indexed(200)).on_white());
+        let output = draw(&mut backend, &Buffer::empty(area), &next);
+        assert_eq!(output, b"\x1b[1;1H\x1b[38;5;200m\x1b[107mx\x1b(B\x1b[m");
+    }
+
+    #[test]
+    fn color_policy_levels() {
+        use crate::color::ColorLevel;
+
+        let area = Rect::new(0, 0, 1, 1);
+        let mut next = Buffer::empty(area);
+        next.set_string(0, 0, "x", Style::new().fg(Color::Rgb(255, 135, 0)).bold());
+        let levels: [(_, &[u8]); 4] = [
+            (ColorLevel::Truecolor, b"\x1b[1m\x1b[38;2;255;135;0m"),
+            (ColorLevel::Indexed256, b"\x1b[1m\x1b[38;5;208m"),
+            (ColorLevel::Basic16, b"\x1b[1m\x1b[33m"),
+            (ColorLevel::Off, b"\x1b[1m"),
+        ];
+        for (level, style) in levels {
+            let mut backend = backend();
+            backend.set_color_policy(ColorPolicy::new(level)).unwrap();
+            let output = draw(&mut backend, &Buffer::empty(area), &next);
+            let expected = [b"\x1b[1;1H", style, b"x\x1b(B\x1b[m"].concat();
+            assert_eq!(output, expected, "{level:?}");
+        }
+
+        let mut backend = backend();
+        backend
+            .set_color_policy(ColorPolicy {
+                level: ColorLevel::Off,
+                strip_attributes: true,
+            })
+            .unwrap();
+        let output = draw(&mut backend, &Buffer::empty(area), &next);
+        assert_eq!(output, b"\x1b[1;1Hx\x1b(B\x1b[m");
+    }
+
+    #[test]
+    fn palettes_per_pane() {
+        use crate::color::ColorLevel;
+
+        let area = Rect::new(0, 0, 4, 1);
+        let mut next = Buffer::empty(area);
+        next.set_string(0, 0, "abcd", Style::new().red());
+        let mut backend = backend();
+        backend.set_palette(Rect::new(0, 0, 2, 1), PaletteMap::new());
+        backend.set_palette(
+            Rect::new(2, 0, 2, 1),
+            PaletteMap::new().color(1, (200, 40, 40)),
+        );
+        let output = draw(&mut backend, &Buffer::empty(area), &next);
+        assert_eq!(
+            output,
+            b"\x1b[1;1H\x1b[31mab\x1b[38;2;200;40;40mcd\x1b(B\x1b[m"
+        );
+
+        // Without truecolor the nearest entry of the color cube is used,
+        // the cells on screen are drawn again
+        backend
+            .set_color_policy(ColorPolicy::new(ColorLevel::Indexed256))
+            .unwrap();
+        let output = draw(&mut backend, &next, &next);
+        assert_eq!(output, b"\x1b[1;1H\x1b[31mab\x1b[38;5;160mcd\x1b(B\x1b[m");
+        let output = draw(&mut backend, &Buffer::empty(area), &next);
+        assert_eq!(output, b"\x1b[1;1H\x1b[31mab\x1b[38;5;160mcd\x1b(B\x1b[m");
+
+        backend.clear_palettes();
+        let output = draw(&mut backend, &Buffer::empty(area), &next);
+        assert_eq!(output, b"\x1b[1;1H\x1b[31mabcd\x1b(B\x1b[m");
+
+        // Cleared cells aren't drawn again
+        backend.clear().unwrap();
+        backend.set_color_policy(ColorPolicy::default()).unwrap();
+        let output = draw(&mut backend, &next, &next);
+        assert_eq!(output, b"\x1b[H\x1b[2J\x1b[1;5H");
+    }
+
+    #[test]
+    fn cursor() {
+        let mut backend = backend();
+        backend.set_cursor_position((3, 4)).unwrap();
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(3, 4));
+        backend.hide_cursor().unwrap();
+        backend.clear().unwrap();
+        backend.flush().unwrap();
+        assert_eq!(
+            backend.tty().output(),
+            b"\x1b[5;4H\x1b[?25l\x1b[H\x1b[2J\x1b[5;4H"
+        );
+
+        backend.tty_mut().clear_output();
+        backend.set_cursor_position((1, 2)).unwrap();
+        backend.clear_region(ClearType::BeforeCursor).unwrap();
+        backend.flush().unwrap();
+        assert_eq!(
+            backend.tty().output(),
+            b"\x1b[3;2H\x1b[1;1H\x1b[K\x1b[2;1H\x1b[K\x1b[3;2H\x1b[1K"
+        );
+    }
+
+    #[test]
+    fn cursor_after_draw() {
+        let mut backend = backend();
+        backend.tty_mut().set_size(5, 2);
+        let area = Rect::new(0, 0, 5, 2);
+        let mut next = Buffer::empty(area);
+        next.set_string(0, 0, "ab", Style::new());
+        next.set_string(0, 1, "漢", Style::new());
+        draw(&mut backend, &Buffer::empty(area), &next);
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(2, 1));
+
+        // The next draw continues there without a move
+        let mut after = next.clone();
+        after.set_string(2, 1, "c", Style::new());
+        assert_eq!(draw(&mut backend, &next, &after), b"c");
+
+        // In the last column the cursor stays there, waiting to wrap
+        let mut last = after.clone();
+        last.set_string(4, 0, "z", Style::new());
+        assert_eq!(draw(&mut backend, &after, &last), b"\x1b[1;5Hz");
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(4, 0));
+        let mut again = last.clone();
+        again.set_string(4, 0, "y", Style::new());
+        assert_eq!(draw(&mut backend, &last, &again), b"\x1b[1;5Hy");
+    }
+}
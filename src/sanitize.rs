@@ -0,0 +1,210 @@
+//! Making untrusted text safe to show, see [`display_safe`].
+//!
+//! File names, messages and log lines written by other programs can contain escape
+//! sequences that retitle the window, move the cursor or write to the clipboard
+//! once they reach the terminal.
+
+use std::borrow::Cow;
+use std::iter::Peekable;
+use std::str::Chars;
+
+const ESC: char = '\x1B';
+const BEL: char = '\x07';
+/// String terminator, also written as `ESC \`.
+const ST: char = '\u{9C}';
+const FIRST_STRONG_ISOLATE: char = '\u{2068}';
+const POP_DIRECTIONAL_ISOLATE: char = '\u{2069}';
+
+/// What [`display_safe`] turns control characters outside of escape sequences into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ControlStyle {
+    /// Caret notation like `cat -v`: `^G` for BEL, `^?` for DEL and `M-^[` for U+009B.
+    #[default]
+    Caret,
+    /// Left out.
+    Strip,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SanitizePolicy {
+    pub controls: ControlStyle,
+    /// Keeps `\n` as it is, for text that is split into lines afterwards.
+    pub keep_newlines: bool,
+    /// Wraps the text in a first strong isolate, so right-to-left overrides in it
+    /// can't reorder what is shown after it.
+    pub isolate_bidi: bool,
+}
+
+/// Whether `ch` introduces a control string that ends with ST, or BEL for OSC.
+fn starts_string(ch: char) -> bool {
+    matches!(ch, ']' | 'P' | 'X' | '^' | '_')
+}
+
+/// Skips the rest of a control string. Unterminated strings take the rest of the text,
+/// so no part of the payload is shown.
+fn skip_string(chars: &mut Peekable<Chars>) {
+    while let Some(ch) = chars.next() {
+        match ch {
+            BEL | ST => return,
+            ESC if chars.next_if_eq(&'\\').is_some() => return,
+            _ => {}
+        }
+    }
+}
+
+/// Skips the parameters, intermediates and final character of a CSI sequence,
+/// up to the first character that can't be part of it.
+fn skip_csi(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|ch| ('\x20'..='\x3F').contains(ch)).is_some() {}
+    chars.next_if(|ch| ('\x40'..='\x7E').contains(ch));
+}
+
+/// Skips what follows an ESC.
+fn skip_escape(chars: &mut Peekable<Chars>) {
+    match chars.next_if(|ch| !ch.is_control()) {
+        Some('[') => skip_csi(chars),
+        Some(ch) if starts_string(ch) => skip_string(chars),
+        // nF sequences, like character set designations
+        Some('\x20'..='\x2F') => {
+            while chars.next_if(|ch| ('\x20'..='\x2F').contains(ch)).is_some() {}
+            chars.next_if(|ch| ('\x30'..='\x7E').contains(ch));
+        }
+        _ => {}
+    }
+}
+
+fn push_control(ch: char, style: ControlStyle, to: &mut String) {
+    if style == ControlStyle::Strip {
+        return;
+    }
+    let code = u32::from(ch);
+    if code >= 0x80 {
+        to.push_str("M-");
+    }
+    to.push('^');
+    to.push(match code & 0x7F {
+        0x7F => '?',
+        low => char::from_u32(low + 0x40).unwrap_or('?'),
+    });
+}
+
+/// `input` without anything the terminal would act upon.
+///
+/// Escape sequences, in their 7-bit and C1 forms, are removed as a whole, including the
+/// payload of OSC, DCS, SOS, PM and APC strings. Other control characters are handled as the
+/// policy says. The input is borrowed when nothing has to change.
+///
+/// ```
+/// # use nixtui_core::sanitize::{display_safe, SanitizePolicy};
+/// let title = "notes.txt\x1B]0;pwned\x07\x08!";
+/// assert_eq!(display_safe(title, &SanitizePolicy::default()), "notes.txt^H!");
+/// ```
+pub fn display_safe<'a>(input: &'a str, policy: &SanitizePolicy) -> Cow<'a, str> {
+    let unsafe_char = |ch: char| ch.is_control() && !(policy.keep_newlines && ch == '\n');
+    if !policy.isolate_bidi && !input.chars().any(unsafe_char) {
+        return Cow::Borrowed(input);
+    }
+
+    let mut output = String::with_capacity(input.len() + 6);
+    if policy.isolate_bidi {
+        output.push(FIRST_STRONG_ISOLATE);
+    }
+    let mut chars = input.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ESC => skip_escape(&mut chars),
+            '\u{9B}' => skip_csi(&mut chars),
+            '\u{90}' | '\u{98}' | '\u{9D}' | '\u{9E}' | '\u{9F}' => skip_string(&mut chars),
+            ch if unsafe_char(ch) => push_control(ch, policy.controls, &mut output),
+            ch => output.push(ch),
+        }
+    }
+    if policy.isolate_bidi {
+        output.push(POP_DIRECTIONAL_ISOLATE);
+    }
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn safe(input: &str) -> Cow<'_, str> {
+        display_safe(input, &SanitizePolicy::default())
+    }
+
+    #[test]
+    fn sequences_removed_whole() {
+        assert!(matches!(safe("plain text ✓"), Cow::Borrowed(_)));
+        assert_eq!(safe("a\x1B[1;31mred\x1B[0m b"), "ared b");
+        assert_eq!(safe("\x1B]0;title\x1B\\x"), "x");
+        assert_eq!(safe("\x1B]52;c;Zm9v\x07x"), "x");
+        assert_eq!(safe("\x1BP+q544e\x1B\\x"), "x");
+        assert_eq!(safe("\u{9D}8;;http://evil\u{9C}link"), "link");
+        assert_eq!(safe("\u{9B}2Jx"), "x");
+        assert_eq!(safe("\x1B(0x\x1B7y"), "xy");
+        // Unterminated strings hide their payload
+        assert_eq!(safe("x\x1B]0;never ends"), "x");
+        // A CSI cut by a control character ends there
+        assert_eq!(safe("\x1B[12\x08x"), "^Hx");
+        assert_eq!(safe("end\x1B"), "end");
+    }
+
+    #[test]
+    fn controls() {
+        assert_eq!(safe("a\tb\r\nc\x7F\u{85}"), "a^Ib^M^Jc^?M-^E");
+        let policy = SanitizePolicy {
+            controls: ControlStyle::Strip,
+            keep_newlines: true,
+            isolate_bidi: false,
+        };
+        assert_eq!(display_safe("a\tb\r\nc\x7F", &policy), "ab\nc");
+        let policy = SanitizePolicy {
+            isolate_bidi: true,
+            ..SanitizePolicy::default()
+        };
+        assert_eq!(
+            display_safe("\u{202E}txt.exe", &policy),
+            "\u{2068}\u{202E}txt.exe\u{2069}"
+        );
+    }
+
+    #[test]
+    fn no_controls_survive() {
+        const ALPHABET: &[char] = &[
+            '\x1B', '[', ']', 'P', '\\', '\x07', ';', '0', '9', 'm', 'a', ' ', '(', '\n', '\x00',
+            '\x7F', '\u{9B}', '\u{9C}', '\u{9D}', '\u{90}', '\u{85}', 'é',
+        ];
+        // Small xorshift generator, so failures can be reproduced
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let policies = [
+            SanitizePolicy::default(),
+            SanitizePolicy {
+                controls: ControlStyle::Strip,
+                keep_newlines: true,
+                isolate_bidi: true,
+            },
+        ];
+        for _ in 0..5000 {
+            let len = next() % 40;
+            let input: String = (0..len)
+                .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+                .collect();
+            for policy in &policies {
+                let output = display_safe(&input, policy);
+                assert!(
+                    output
+                        .chars()
+                        .all(|ch| !ch.is_control() || (policy.keep_newlines && ch == '\n')),
+                    "{input:?} became {output:?}"
+                );
+            }
+        }
+    }
+}
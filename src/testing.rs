@@ -0,0 +1,257 @@
+//! Helpers for testing code that talks to a terminal without needing a real one.
+
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::AsFd;
+use std::path::Path;
+
+use nix::poll::{poll, PollFd, PollFlags};
+
+use crate::tty::{Capabilities, Sequences};
+use crate::vt::Buffer;
+
+#[cfg(feature = "terminfo")]
+mod tty;
+
+#[cfg(feature = "terminfo")]
+pub use tty::{Chunk, Origin, TermiosCall, TestTty};
+
+/// Compiled kitty terminfo entry, the same one the crate's own tests use.
+pub const KITTY_DATABASE: &[u8] = include_bytes!("../assets/test_kitty_database");
+
+/// Sequences of [`KITTY_DATABASE`], or the built-in ANSI ones without the `terminfo` feature.
+#[cfg(feature = "terminfo")]
+pub fn sequences() -> Sequences {
+    terminfo::Database::from_buffer(KITTY_DATABASE)
+        .unwrap()
+        .into()
+}
+
+/// Sequences of [`KITTY_DATABASE`], or the built-in ANSI ones without the `terminfo` feature.
+#[cfg(not(feature = "terminfo"))]
+pub fn sequences() -> Sequences {
+    Sequences::new()
+}
+
+/// Reads from `from`, e. g. the master side of a pty, until `until` was seen,
+/// or until nothing arrives for 200ms if `None`.
+///
+/// # Panics
+/// If the output goes quiet before `until` was seen.
+pub fn read_output(from: &mut File, until: Option<&[u8]>) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    loop {
+        if until.is_some_and(|u| output.windows(u.len()).any(|w| w == u)) {
+            return output;
+        }
+        let mut fds = [PollFd::new(from.as_fd(), PollFlags::POLLIN)];
+        if poll(&mut fds, 200u16).unwrap() == 0 {
+            assert!(until.is_none(), "output so far: {output:?}");
+            return output;
+        }
+        let count = from.read(&mut buf).unwrap();
+        if count == 0 {
+            assert!(until.is_none(), "output so far: {output:?}");
+            return output;
+        }
+        output.extend_from_slice(&buf[..count]);
+    }
+}
+
+/// The screen after `render` drew on a fresh `width` x `height` one. `render` gets the
+/// [`sequences`] to draw with and the size, e. g. `|t, w, h| widget.render(t, 0, 0, w, h)`.
+pub fn render_screen<E: std::fmt::Debug>(
+    width: usize,
+    height: usize,
+    render: impl FnOnce(&mut Sequences, usize, usize) -> Result<(), E>,
+) -> Buffer {
+    let mut sequences = sequences();
+    render(&mut sequences, width, height).unwrap();
+    crate::vt::replay(Capabilities::buffer(&sequences), (width, height))
+}
+
+/// Compares `buffer` with the snapshot stored in `snapshots/<name>.snap` of the calling crate,
+/// see [`assert_snapshot`].
+///
+/// ```no_run
+/// # use nixtui_core::assert_buffer_snapshot;
+/// let screen = nixtui_core::vt::replay(b"hello", (8, 1));
+/// assert_buffer_snapshot!(screen, "hello");
+/// ```
+#[macro_export]
+macro_rules! assert_buffer_snapshot {
+    ($buffer:expr, $name:expr) => {
+        $crate::testing::assert_snapshot(
+            &$buffer,
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("snapshots")
+                .join(format!("{}.snap", $name)),
+        )
+    };
+}
+
+pub use crate::assert_buffer_snapshot;
+
+/// Rows shown around changed ones in the message of [`assert_snapshot`].
+const CONTEXT: usize = 1;
+
+/// Compares `buffer` with the snapshot in the file at `path`, in the format of
+/// [`Buffer::annotated`].
+///
+/// With the environment variable `UPDATE_SNAPSHOTS` set to anything but `0`,
+/// the file is written instead, creating it if needed.
+///
+/// # Panics
+///
+/// If the snapshot differs or doesn't exist. The message shows the rows that differ,
+/// with the changed columns marked and what changed about every cell.
+#[track_caller]
+pub fn assert_snapshot(buffer: &Buffer, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let annotated = buffer.annotated();
+    let update = std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| !v.is_empty() && v != "0");
+    if update {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(path, &annotated).unwrap();
+        return;
+    }
+    let stored = match std::fs::read_to_string(path) {
+        Ok(stored) => stored,
+        Err(err) => panic!(
+            "snapshot {} can't be read: {err}, run with UPDATE_SNAPSHOTS=1 to create it\n{annotated}",
+            path.display()
+        ),
+    };
+    if stored != annotated {
+        panic!(
+            "snapshot {} differs, run with UPDATE_SNAPSHOTS=1 to accept the change\n{}",
+            path.display(),
+            snapshot_diff(&stored, buffer)
+        );
+    }
+}
+
+/// Lines of `text` with `prefix` in front of each.
+fn prefixed(prefix: &str, text: &str) -> String {
+    text.lines()
+        .map(|line| format!("{prefix}{line}\n"))
+        .collect()
+}
+
+/// Differences between a stored snapshot and `buffer`, `-` for stored and `+` for new lines.
+fn snapshot_diff(stored: &str, buffer: &Buffer) -> String {
+    let old = match Buffer::from_annotated(stored) {
+        Some(old) if (old.width(), old.height()) == (buffer.width(), buffer.height()) => old,
+        old => {
+            let size = old.map_or("unreadable".into(), |old| {
+                format!("{}x{}", old.width(), old.height())
+            });
+            return format!(
+                "size {size} -> {}x{}\n{}{}",
+                buffer.width(),
+                buffer.height(),
+                prefixed("-", stored),
+                prefixed("+", &buffer.annotated())
+            );
+        }
+    };
+
+    let changed: Vec<Vec<usize>> = (0..buffer.height())
+        .map(|row| {
+            (0..buffer.width())
+                .filter(|&col| old.cell(row, col) != buffer.cell(row, col))
+                .collect()
+        })
+        .collect();
+    let near_change = |row: usize| {
+        (row.saturating_sub(CONTEXT)..=row + CONTEXT)
+            .any(|near| changed.get(near).is_some_and(|cols| !cols.is_empty()))
+    };
+    let mut diff = String::new();
+    let mut skipped = false;
+    for (row, cols) in changed.iter().enumerate() {
+        if !near_change(row) {
+            skipped = true;
+            continue;
+        }
+        if std::mem::take(&mut skipped) || diff.is_empty() {
+            diff.push_str(&format!("@@ row {row} @@\n"));
+        }
+        if cols.is_empty() {
+            diff.push_str(&prefixed(" ", &buffer.annotated_row(row)));
+            continue;
+        }
+        diff.push_str(&prefixed("-", &old.annotated_row(row)));
+        diff.push_str(&prefixed("+", &buffer.annotated_row(row)));
+        // Columns of the row line are the columns of the buffer, wide characters included
+        let mut marks = vec![' '; buffer.width()];
+        for &col in cols {
+            marks[col] = '^';
+        }
+        diff.push_str(&format!("  {}\n", String::from_iter(marks).trim_end()));
+        for &col in cols {
+            let (old, new) = (old.cell(row, col).unwrap(), buffer.cell(row, col).unwrap());
+            let mut changes = Vec::new();
+            if old.symbol != new.symbol {
+                changes.push(format!("{:?} -> {:?}", old.symbol, new.symbol));
+            }
+            if old.style != new.style {
+                let style = |style: crate::vt::Style| match style.to_string() {
+                    text if text.is_empty() => "default".to_owned(),
+                    text => text,
+                };
+                changes.push(format!(
+                    "style {} -> {}",
+                    style(old.style),
+                    style(new.style)
+                ));
+            }
+            diff.push_str(&format!("  ({row}, {col}): {}\n", changes.join(", ")));
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_diff_marks_cells() {
+        let old = crate::vt::replay("ab漢d\r\n2\r\n3\r\n4\r\n5".as_bytes(), (6, 5));
+        let new = crate::vt::replay("ab字d\r\n2\r\n3\r\n4\r\n\x1B[1m5".as_bytes(), (6, 5));
+        assert_eq!(
+            snapshot_diff(&old.annotated(), &new),
+            concat!(
+                "@@ row 0 @@\n",
+                "-|ab漢d |\n",
+                "+|ab字d |\n",
+                "    ^\n",
+                "  (0, 2): \"漢\" -> \"字\"\n",
+                " |2     |\n",
+                "@@ row 3 @@\n",
+                " |4     |\n",
+                "-|5     |\n",
+                "+|5     |\n",
+                "+  0..1 bold\n",
+                "  ^\n",
+                "  (4, 0): style default -> bold\n",
+            )
+        );
+        let small = crate::vt::replay(b"x", (2, 1));
+        assert!(snapshot_diff(&small.annotated(), &new).starts_with("size 2x1 -> 6x5\n-|x |\n"));
+    }
+
+    #[test]
+    fn unreadable_snapshot() {
+        let screen = crate::vt::replay(b"x", (2, 1));
+        // A directory can be neither read nor written, also with UPDATE_SNAPSHOTS set
+        let result = std::panic::catch_unwind(|| {
+            assert_snapshot(&screen, "src");
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+use nix::sys::termios::{SetArg, Termios};
+use terminfo::Database;
+
+use crate::tty::{TerminfoWrapper, UnixTerminal, Winsize};
+
+use super::KITTY_DATABASE;
+
+/// Where a [`Chunk`] of output came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// Expanded by the named capability method of [`TestTty::terminfo`].
+    Capability(&'static str),
+    /// Plain bytes, either written to the [`TestTty`] directly
+    /// or appended to [`TestTty::terminfo`] without a capability method.
+    Write,
+}
+
+/// Bytes that reached the [`TestTty`] as one unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub origin: Origin,
+    pub bytes: Vec<u8>,
+}
+
+/// Termios related call made on a [`TestTty`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermiosCall {
+    Get,
+    Set(SetArg),
+    RawMode,
+}
+
+/// In-memory terminal recording everything written to it.
+///
+/// Capability methods are called on the embedded [`TestTty::terminfo`] and show up in the
+/// output after [`Write::flush`], split into one [`Chunk`] per capability.
+/// Bytes written to the `TestTty` itself are recorded immediately.
+pub struct TestTty {
+    pub terminfo: TerminfoWrapper,
+    chunks: Vec<Chunk>,
+    input: VecDeque<u8>,
+    size: Winsize,
+    termios: Termios,
+    termios_calls: Vec<TermiosCall>,
+}
+
+impl TestTty {
+    /// 80x24 terminal described by [`KITTY_DATABASE`].
+    pub fn new() -> Self {
+        Self::with_database(Database::from_buffer(KITTY_DATABASE).unwrap())
+    }
+
+    pub fn with_database(db: Database) -> Self {
+        let mut terminfo = TerminfoWrapper::from(db);
+        terminfo.record_capabilities(true);
+        // SAFETY: termios is a plain C struct, all zeroes is a valid value
+        let termios = unsafe { std::mem::zeroed::<nix::libc::termios>() };
+        Self {
+            terminfo,
+            chunks: Vec::new(),
+            input: VecDeque::new(),
+            size: Winsize { col: 80, row: 24 },
+            termios: termios.into(),
+            termios_calls: Vec::new(),
+        }
+    }
+
+    pub fn set_size(&mut self, col: u16, row: u16) {
+        self.size = Winsize { col, row };
+    }
+
+    pub fn size(&self) -> Winsize {
+        Winsize {
+            col: self.size.col,
+            row: self.size.row,
+        }
+    }
+
+    pub fn termios_calls(&self) -> &[TermiosCall] {
+        &self.termios_calls
+    }
+
+    /// Queues bytes to be returned by [`Read::read`].
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+    }
+
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    /// Everything written so far.
+    pub fn output(&self) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .flat_map(|c| c.bytes.iter().copied())
+            .collect()
+    }
+
+    /// Capability methods in the order they were called, with the bytes they produced.
+    pub fn capability_log(&self) -> Vec<(&'static str, &[u8])> {
+        self.chunks
+            .iter()
+            .filter_map(|c| match c.origin {
+                Origin::Capability(name) => Some((name, &*c.bytes)),
+                Origin::Write => None,
+            })
+            .collect()
+    }
+
+    pub fn clear_output(&mut self) {
+        self.chunks.clear();
+    }
+
+    pub fn contains_sequence(&self, sequence: &[u8]) -> bool {
+        sequence.is_empty() || self.output().windows(sequence.len()).any(|w| w == sequence)
+    }
+
+    /// # Panics
+    /// If `sequence` was never written.
+    #[track_caller]
+    pub fn assert_contains_sequence(&self, sequence: &[u8]) {
+        assert!(
+            self.contains_sequence(sequence),
+            "sequence {:?} not found in output {:?}",
+            sequence.escape_ascii().to_string(),
+            self.output().escape_ascii().to_string(),
+        );
+    }
+
+    fn push_chunk(&mut self, origin: Origin, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        if origin == Origin::Write {
+            if let Some(last) = self.chunks.last_mut().filter(|c| c.origin == Origin::Write) {
+                last.bytes.extend_from_slice(bytes);
+                return;
+            }
+        }
+        self.chunks.push(Chunk {
+            origin,
+            bytes: bytes.to_vec(),
+        });
+    }
+}
+
+/// Termios calls are only recorded, the stored termios is left as is.
+impl UnixTerminal for TestTty {
+    fn get_termios(&mut self) -> std::io::Result<Termios> {
+        self.termios_calls.push(TermiosCall::Get);
+        Ok(self.termios.clone())
+    }
+
+    fn set_termios(&mut self, _termios: &Termios, mode: SetArg) -> std::io::Result<()> {
+        self.termios_calls.push(TermiosCall::Set(mode));
+        Ok(())
+    }
+
+    fn get_size(&mut self) -> std::io::Result<Winsize> {
+        Ok(self.size())
+    }
+
+    fn raw_mode(&mut self) -> std::io::Result<()> {
+        self.termios_calls.push(TermiosCall::RawMode);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ratatui-backend")]
+impl crate::ratatui_backend::BackendTty for TestTty {
+    fn window_size(&self) -> std::io::Result<Winsize> {
+        Ok(self.size())
+    }
+}
+
+impl Default for TestTty {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for TestTty {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.push_chunk(Origin::Write, buf);
+        Ok(buf.len())
+    }
+
+    /// Moves the contents of [`TestTty::terminfo`] into the recorded output.
+    fn flush(&mut self) -> std::io::Result<()> {
+        let bytes = self.terminfo.buffer().to_vec();
+        let records = self.terminfo.records().to_vec();
+        self.terminfo.flush_to(&mut std::io::sink())?;
+        let mut pos = 0;
+        for record in records {
+            self.push_chunk(Origin::Write, &bytes[pos..record.range.start]);
+            self.push_chunk(
+                Origin::Capability(record.name),
+                &bytes[record.range.clone()],
+            );
+            pos = record.range.end;
+        }
+        self.push_chunk(Origin::Write, &bytes[pos..]);
+        Ok(())
+    }
+}
+
+impl Read for TestTty {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::components::{Select, SelectEvent, Selection};
+
+    #[test]
+    fn splits_output_by_capability() {
+        let mut tty = TestTty::new();
+        tty.terminfo.move_cursor(1, 2).unwrap();
+        tty.terminfo.write_all(b"hi").unwrap();
+        tty.terminfo.enter_bold_mode().unwrap();
+        tty.flush().unwrap();
+        tty.write_all(b"!").unwrap();
+        assert_eq!(
+            tty.chunks(),
+            [
+                Chunk {
+                    origin: Origin::Capability("cursor_address"),
+                    bytes: b"\x1B[2;3H".to_vec()
+                },
+                Chunk {
+                    origin: Origin::Write,
+                    bytes: b"hi".to_vec()
+                },
+                Chunk {
+                    origin: Origin::Capability("enter_bold_mode"),
+                    bytes: b"\x1B[1m".to_vec()
+                },
+                Chunk {
+                    origin: Origin::Write,
+                    bytes: b"!".to_vec()
+                },
+            ]
+        );
+        tty.assert_contains_sequence(b"hi\x1B[1m!");
+        assert!(!tty.contains_sequence(b"\x1B[0m"));
+    }
+
+    #[test]
+    fn records_termios_calls() {
+        let mut tty = TestTty::new();
+        let termios = tty.get_termios().unwrap();
+        tty.raw_mode().unwrap();
+        tty.set_termios(&termios, SetArg::TCSADRAIN).unwrap();
+        assert_eq!(
+            tty.termios_calls(),
+            [
+                TermiosCall::Get,
+                TermiosCall::RawMode,
+                TermiosCall::Set(SetArg::TCSADRAIN)
+            ]
+        );
+    }
+
+    // The loop of examples/selector.rs, driven through the widget API.
+    #[test]
+    fn selector_example() {
+        let mut tty = TestTty::new();
+        tty.set_size(10, 3);
+        let mut keys = [&b"\x1B[B"[..], b"\x1B[B", b"\r"].into_iter();
+        let parser = tty.terminfo.get_parser();
+        let mut select = Select::new(["foo", "bar", "baz", "cow"]);
+
+        tty.terminfo.enter_ca_mode().unwrap();
+        let mut result = None;
+        let mut buf = [0; 64];
+        while result.is_none() {
+            let size = tty.get_size().unwrap();
+            select
+                .render(&mut tty.terminfo, 0, 0, size.col.into(), size.row.into())
+                .unwrap();
+            tty.flush().unwrap();
+            tty.push_input(keys.next().expect("ran out of input"));
+            let count = tty.read(&mut buf).unwrap();
+            for event in parser.parse(&buf[..count]).iter() {
+                if let SelectEvent::Confirmed(selection) = select.handle_key(event) {
+                    result = Some(selection);
+                }
+            }
+        }
+        tty.terminfo.exit_ca_mode().unwrap();
+        tty.flush().unwrap();
+
+        assert_eq!(result, Some(Selection::Single(2)));
+        assert_eq!(select.scroll_offset(), 0);
+        // The cursor row is drawn reversed and padded to the terminal width
+        tty.assert_contains_sequence(b"\x1B[3;1H\x1B[7mbaz       ");
+        let log = tty.capability_log();
+        assert_eq!(log.first().map(|c| c.0), Some("enter_ca_mode"));
+        assert_eq!(log.last().map(|c| c.0), Some("exit_ca_mode"));
+    }
+}
@@ -1,4 +1,37 @@
+#[cfg(feature = "ansi")]
+mod ansi;
+mod claim;
+mod custom;
+mod encoding;
 pub mod errors;
+mod external;
+mod guard;
+mod passthrough;
+mod profile;
+mod restore;
+mod secret;
+#[cfg(feature = "terminfo")]
+mod setup;
+mod terminal;
+
+#[cfg(feature = "ansi")]
+pub use ansi::AnsiWrapper;
+pub use claim::{claim_terminal, TerminalClaim};
+pub use custom::{Param, SequenceTemplate, TemplateError};
+pub use encoding::OutputEncoding;
+pub use external::run_external;
+pub use guard::{AltScreenGuard, RawModeGuard};
+pub use passthrough::{wrap_passthrough, Multiplexer};
+pub use profile::{ByteOrigin, FrameStats};
+pub use restore::{
+    install_panic_restore, install_signal_restore, OnSignal, PanicRestore, SignalRestore,
+    TerminalRestorer,
+};
+pub use secret::{read_secret, EchoGuard};
+#[cfg(feature = "terminfo")]
+pub use setup::{SetupReport, Tui, TuiBuilder};
+pub use terminal::Tty;
+pub(crate) use secret::read_line_uninterrupted;
 
 use errors::CapabilityError;
 use nix::libc::ioctl;
@@ -9,41 +42,81 @@ use nix::{
         tcgetattr, tcsetattr, ControlFlags, InputFlags, LocalFlags, OutputFlags, SetArg,
     },
 };
+use std::collections::HashMap;
+use std::ops::Range;
 use std::os::fd::{AsFd, AsRawFd};
+#[cfg(feature = "terminfo")]
 use terminfo::{capability as cap, Capability, Database};
 
+#[cfg(feature = "terminfo")]
+use crate::color::{indexed_to_basic, rgb_to_indexed};
+use crate::color::{Color, ColorPolicy};
 use crate::input::InputParser;
+#[cfg(feature = "terminfo")]
+use crate::input::KeyboardFlags;
+use profile::Profiler;
+
+#[cfg(feature = "terminfo")]
 macro_rules! tty_expand_cap {
     ($db:expr, $to:expr, $cap:ty) => {
         {
             let Some(cap) = $db.get::<$cap>() else {
+                crate::logging::warn_event!(cap = <$cap>::name(), "capability not found");
                 return Err(CapabilityError::CapabilityNotFound { cap_name: <$cap>::name().into() });
             };
+            let start = $to.len();
             ::terminfo::expand!($to, cap.as_ref()).map_err(|e| {
                 use ::terminfo::Error as E;
                 match e {
                     E::Io(io_err) => CapabilityError::IoError(io_err),
-                    _ => CapabilityError::CapabilityExpansionError,
+                    _ => {
+                        crate::logging::warn_event!(cap = <$cap>::name(), error = %e, "capability expansion failed");
+                        CapabilityError::CapabilityExpansionError
+                    }
                 }
-            })
+            }).map(|()| $to.record(<$cap>::name(), start))
         }
     };
     ($db:expr, $to:expr, $cap:ty; $first_param:expr $(,$params:expr)*$(,)?) => {
         {
             let Some(cap) = $db.get::<$cap>() else {
+                crate::logging::warn_event!(cap = <$cap>::name(), "capability not found");
                 return Err(CapabilityError::CapabilityNotFound { cap_name: <$cap>::name().into() });
             };
+            let start = $to.len();
             ::terminfo::expand!($to, cap.as_ref(); $first_param $(,$params)* ).map_err(|e| {
                 use ::terminfo::Error as E;
                 match e {
                     E::Io(io_err) => CapabilityError::IoError(io_err),
-                    _ => CapabilityError::CapabilityExpansionError,
+                    _ => {
+                        crate::logging::warn_event!(cap = <$cap>::name(), error = %e, "capability expansion failed");
+                        CapabilityError::CapabilityExpansionError
+                    }
                 }
-            })
+            }).map(|()| $to.record(<$cap>::name(), start))
         }
     };
 }
 
+/// Methods that expand a capability without parameters, named after the capability.
+/// The list is kept for the tests, which check every method against several databases.
+#[cfg(feature = "terminfo")]
+macro_rules! simple_capabilities {
+    ($($name:ident: $cap:ident;)+) => {
+        $(
+            pub fn $name(&mut self) -> Result<(), CapabilityError> {
+                tty_expand_cap!(self.db, &mut self.buffer, cap::$cap)
+            }
+        )+
+
+        #[cfg(test)]
+        const SIMPLE_CAPABILITIES: &'static [(
+            &'static str,
+            fn(&mut Self) -> Result<(), CapabilityError>,
+        )] = &[$((stringify!($name), Self::$name),)+];
+    };
+}
+
 pub struct Winsize {
     pub col: u16,
     pub row: u16,
@@ -58,13 +131,38 @@ impl From<nix::libc::winsize> for Winsize {
     }
 }
 
+/// Mouse events the terminal reports, see [`TerminfoWrapper::enable_mouse`].
+#[cfg(feature = "terminfo")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseMode {
+    /// Presses and releases of buttons, including the wheel. DEC mode 1000.
+    Clicks,
+    /// Also motion while a button is held. DEC mode 1002.
+    Drag,
+    /// Also motion without any button held. DEC mode 1003.
+    AnyMotion,
+}
+
+#[cfg(feature = "terminfo")]
+impl MouseMode {
+    fn dec_mode(self) -> &'static str {
+        match self {
+            Self::Clicks => "1000",
+            Self::Drag => "1002",
+            Self::AnyMotion => "1003",
+        }
+    }
+}
+
+/// Termios and size of a terminal, implemented for everything with a file descriptor.
+/// Fakes like [`TestTty`](crate::testing::TestTty) implement it without one.
 #[cfg(target_family = "unix")]
-pub trait UnixTerminal: AsFd {
+pub trait UnixTerminal {
     fn get_termios(&mut self) -> std::io::Result<Termios>;
     fn set_termios(&mut self, termios: &Termios, mode: SetArg) -> std::io::Result<()>;
+    fn get_size(&mut self) -> std::io::Result<Winsize>;
     fn raw_mode(&mut self) -> std::io::Result<()> {
         let mut termios = self.get_termios()?;
-        let ttyfd = self.as_fd();
         // According to https://www.man7.org/linux/man-pages/man3/termios.3.html `Raw mode` section
         {
             termios.input_flags &= !(InputFlags::IGNBRK
@@ -89,8 +187,23 @@ pub trait UnixTerminal: AsFd {
             termios.control_chars[VTIME] = 0;
             termios.control_chars[VMIN] = 1;
         }
-        tcsetattr(ttyfd, SetArg::TCSAFLUSH, &termios)?;
-        Ok(())
+        self.set_termios(&termios, SetArg::TCSAFLUSH)
+    }
+    /// Enters raw mode until the returned guard is dropped.
+    fn raw_mode_guard(&mut self) -> std::io::Result<RawModeGuard<'_, Self>>
+    where
+        Self: Sized,
+    {
+        RawModeGuard::new(self)
+    }
+}
+
+impl<T: AsFd> UnixTerminal for T {
+    fn get_termios(&mut self) -> std::io::Result<Termios> {
+        tcgetattr(self).map_err(|e| e.into())
+    }
+    fn set_termios(&mut self, termios: &Termios, mode: SetArg) -> std::io::Result<()> {
+        tcsetattr(self, mode, termios).map_err(|e| e.into())
     }
     fn get_size(&mut self) -> std::io::Result<Winsize> {
         let mut buf = nix::libc::winsize {
@@ -107,1214 +220,1085 @@ pub trait UnixTerminal: AsFd {
     }
 }
 
-impl<T: AsFd> UnixTerminal for T {
-    fn get_termios(&mut self) -> std::io::Result<Termios> {
-        tcgetattr(self).map_err(|e| e.into())
-    }
-    fn set_termios(&mut self, termios: &Termios, mode: SetArg) -> std::io::Result<()> {
-        tcsetattr(self, mode, termios).map_err(|e| e.into())
-    }
+/// Bytes expanded from a single capability, see [`TerminfoWrapper::record_capabilities`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityRecord {
+    pub name: &'static str,
+    /// Position of the expanded bytes in [`TerminfoWrapper::buffer`].
+    pub range: Range<usize>,
 }
 
-pub struct TerminfoWrapper {
-    pub db: Database,
-    buffer: Vec<u8>,
+#[derive(Default)]
+struct OutputBuffer {
+    bytes: Vec<u8>,
+    records: Option<Vec<CapabilityRecord>>,
+    multiplexer: Option<Multiplexer>,
+    profiler: Option<Profiler>,
+    encoding: OutputEncoding,
+    /// Start of a character cut at the end of the last write, kept for the next one.
+    cut_char: Vec<u8>,
+    custom: HashMap<String, SequenceTemplate>,
+    window_ops: bool,
+    /// See [`TerminfoWrapper::set_color_policy`].
+    color_policy: ColorPolicy,
+    /// Mouse mode turned on by [`TerminfoWrapper::enable_mouse`], and whether `XM` did it.
+    #[cfg(feature = "terminfo")]
+    mouse: Option<(MouseMode, bool)>,
+    /// Whether [`TerminfoWrapper::enable_bracketed_paste`] was the last of the two called.
+    #[cfg(feature = "terminfo")]
+    bracketed_paste: bool,
+    /// Mouse mode and bracketed paste turned off by [`TerminfoWrapper::suspend_input_modes`].
+    #[cfg(feature = "terminfo")]
+    suspended: Option<(Option<MouseMode>, bool)>,
+    /// See [`TerminfoWrapper::set_truecolor`].
+    #[cfg(feature = "terminfo")]
+    truecolor: Option<bool>,
 }
 
-impl<'a> TerminfoWrapper {
-    pub fn from_env() -> Result<Self, errors::TerminfoCreationError> {
-        Ok(Self {
-            db: Database::from_env()?,
-            buffer: Vec::new(),
-        })
+impl OutputBuffer {
+    fn len(&self) -> usize {
+        self.bytes.len()
     }
 
-    pub fn flush_to(&mut self, to: &mut impl std::io::Write) -> std::io::Result<()> {
-        to.write_all(&self.buffer)?;
-        self.clear();
-        Ok(())
+    fn append_host(&mut self, bytes: &[u8]) {
+        let start = self.bytes.len();
+        match self.multiplexer {
+            Some(multiplexer) => wrap_passthrough(multiplexer, bytes, &mut self.bytes),
+            None => self.bytes.extend_from_slice(bytes),
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.add(ByteOrigin::Host, self.bytes.len() - start);
+        }
     }
 
-    pub fn clear(&mut self) {
-        self.buffer.clear();
+    fn record(&mut self, name: &'static str, start: usize) {
+        if let Some(records) = &mut self.records {
+            records.push(CapabilityRecord {
+                name,
+                range: start..self.bytes.len(),
+            });
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.add(ByteOrigin::Capability(name), self.bytes.len() - start);
+        }
     }
 
-    pub fn append(&mut self, bytes: &[u8]) {
-        self.buffer.extend_from_slice(bytes);
+    /// Palette entry `color` as the color policy lets it through, `None` when colors are off.
+    fn fit_policy(&self, color: u8) -> Option<u8> {
+        match self.color_policy.downgrade(Color::Indexed(color)) {
+            Some(Color::Indexed(index)) => Some(index),
+            _ => None,
+        }
     }
 
-    pub fn move_cursor(&mut self, row: usize, col: usize) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorAddress; row as i32, col as i32)
-    }
-    pub fn back_tab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::BackTab)
-    }
-    pub fn bell(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Bell)
-    }
-    pub fn carriage_return(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CarriageReturn)
-    }
-    pub fn clear_all_tabs(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ClearAllTabs)
-    }
-    pub fn clear_screen(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ClearScreen)
-    }
-    pub fn clr_eol(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ClrEol)
-    }
-    pub fn clr_eos(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ClrEos)
-    }
-    pub fn command_character(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CommandCharacter)
-    }
-    pub fn cursor_down(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorDown)
-    }
-    pub fn cursor_home(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorHome)
-    }
-    pub fn cursor_invisible(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorInvisible)
-    }
-    pub fn cursor_left(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorLeft)
-    }
-    pub fn cursor_mem_address(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorMemAddress)
-    }
-    pub fn cursor_normal(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorNormal)
-    }
-    pub fn cursor_right(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorRight)
-    }
-    pub fn cursor_to_ll(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorToLl)
-    }
-    pub fn cursor_up(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorUp)
-    }
-    pub fn cursor_visible(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorVisible)
-    }
-    pub fn delete_character(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DeleteCharacter)
-    }
-    pub fn delete_line(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DeleteLine)
-    }
-    pub fn dis_status_line(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DisStatusLine)
-    }
-    pub fn down_half_line(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DownHalfLine)
-    }
-    pub fn enter_alt_charset_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterAltCharsetMode)
-    }
-    pub fn enter_blink_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterBlinkMode)
-    }
-    pub fn enter_bold_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterBoldMode)
-    }
-    pub fn enter_ca_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterCaMode)
-    }
-    pub fn enter_delete_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterDeleteMode)
-    }
-    pub fn enter_dim_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterDimMode)
-    }
-    pub fn enter_insert_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterInsertMode)
-    }
-    pub fn enter_secure_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterSecureMode)
-    }
-    pub fn enter_protected_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterProtectedMode)
-    }
-    pub fn enter_reverse_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterReverseMode)
-    }
-    pub fn enter_standout_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterStandoutMode)
-    }
-    pub fn enter_underline_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterUnderlineMode)
-    }
-    pub fn exit_alt_charset_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitAltCharsetMode)
-    }
-    pub fn exit_attribute_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitAttributeMode)
-    }
-    pub fn exit_ca_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitCaMode)
-    }
-    pub fn exit_delete_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitDeleteMode)
-    }
-    pub fn exit_insert_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitInsertMode)
-    }
-    pub fn exit_standout_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitStandoutMode)
-    }
-    pub fn exit_underline_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitUnderlineMode)
-    }
-    pub fn flash_screen(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::FlashScreen)
-    }
-    pub fn form_feed(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::FormFeed)
-    }
-    pub fn from_status_line(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::FromStatusLine)
-    }
-    pub fn init_1string(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Init1String)
-    }
-    pub fn init_2string(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Init2String)
-    }
-    pub fn init_3string(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Init3String)
-    }
-    pub fn init_file(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::InitFile)
-    }
-    pub fn insert_character(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::InsertCharacter)
-    }
-    pub fn insert_line(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::InsertLine)
-    }
-    pub fn insert_padding(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::InsertPadding)
-    }
-    pub fn key_backspace(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyBackspace)
-    }
-    pub fn key_catab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyCATab)
-    }
-    pub fn key_clear(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyClear)
-    }
-    pub fn key_ctab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyCTab)
-    }
-    pub fn key_dc(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyDc)
-    }
-    pub fn key_dl(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyDl)
-    }
-    pub fn key_down(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyDown)
-    }
-    pub fn key_eic(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyEic)
-    }
-    pub fn key_eol(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyEol)
-    }
-    pub fn key_eos(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyEos)
-    }
-    pub fn key_f0(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF0)
-    }
-    pub fn key_f1(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF1)
-    }
-    pub fn key_f10(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF10)
-    }
-    pub fn key_f2(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF2)
-    }
-    pub fn key_f3(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF3)
-    }
-    pub fn key_f4(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF4)
-    }
-    pub fn key_f5(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF5)
-    }
-    pub fn key_f6(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF6)
-    }
-    pub fn key_f7(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF7)
-    }
-    pub fn key_f8(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF8)
-    }
-    pub fn key_f9(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF9)
-    }
-    pub fn key_home(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyHome)
-    }
-    pub fn key_ic(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyIc)
-    }
-    pub fn key_il(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyIl)
-    }
-    pub fn key_left(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyLeft)
-    }
-    pub fn key_ll(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyLl)
-    }
-    pub fn key_npage(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyNPage)
-    }
-    pub fn key_ppage(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyPPage)
-    }
-    pub fn key_right(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyRight)
-    }
-    pub fn key_sf(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySf)
-    }
-    pub fn key_sr(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySr)
-    }
-    pub fn key_stab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySTab)
-    }
-    pub fn key_up(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyUp)
-    }
-    pub fn keypad_local(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeypadLocal)
-    }
-    pub fn keypad_xmit(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeypadXmit)
-    }
-    pub fn lab_f0(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF0)
-    }
-    pub fn lab_f1(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF1)
-    }
-    pub fn lab_f10(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF10)
-    }
-    pub fn lab_f2(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF2)
-    }
-    pub fn lab_f3(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF3)
-    }
-    pub fn lab_f4(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF4)
-    }
-    pub fn lab_f5(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF5)
-    }
-    pub fn lab_f6(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF6)
-    }
-    pub fn lab_f7(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF7)
-    }
-    pub fn lab_f8(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF8)
-    }
-    pub fn lab_f9(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabF9)
-    }
-    pub fn meta_off(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MetaOff)
-    }
-    pub fn meta_on(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MetaOn)
-    }
-    pub fn newline(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Newline)
-    }
-    pub fn pad_char(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PadChar)
-    }
-    pub fn pkey_key(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PKeyKey)
-    }
-    pub fn pkey_local(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PKeyLocal)
-    }
-    pub fn pkey_xmit(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PKeyXmit)
-    }
-    pub fn print_screen(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PrintScreen)
-    }
-    pub fn prtr_off(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PrtrOff)
-    }
-    pub fn prtr_on(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PrtrOn)
-    }
-    pub fn repeat_char(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::RepeatChar)
-    }
-    pub fn reset_1string(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Reset1String)
-    }
-    pub fn reset_2string(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Reset2String)
-    }
-    pub fn reset_3string(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Reset3String)
-    }
-    pub fn reset_file(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ResetFile)
-    }
-    pub fn restore_cursor(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::RestoreCursor)
-    }
-    pub fn save_cursor(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SaveCursor)
-    }
-    pub fn scroll_forward(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ScrollForward)
-    }
-    pub fn scroll_reverse(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ScrollReverse)
-    }
-    pub fn set_tab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetTab)
-    }
-    pub fn set_window(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetWindow)
-    }
-    pub fn tab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Tab)
-    }
-    pub fn to_status_line(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ToStatusLine)
-    }
-    pub fn underline_char(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::UnderlineChar)
-    }
-    pub fn up_half_line(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::UpHalfLine)
-    }
-    pub fn init_prog(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::InitProg)
-    }
-    pub fn key_a1(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyA1)
-    }
-    pub fn key_a3(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyA3)
-    }
-    pub fn key_b2(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyB2)
-    }
-    pub fn key_c1(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyC1)
-    }
-    pub fn key_c3(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyC3)
-    }
-    pub fn prtr_non(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PrtrNon)
-    }
-    pub fn char_padding(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CharPadding)
-    }
-    pub fn acs_chars(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsChars)
-    }
-    pub fn plab_norm(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PlabNorm)
-    }
-    pub fn key_btab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyBTab)
-    }
-    pub fn enter_xon_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterXonMode)
-    }
-    pub fn exit_xon_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitXonMode)
-    }
-    pub fn enter_am_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterAmMode)
-    }
-    pub fn exit_am_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitAmMode)
-    }
-    pub fn xon_character(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::XonCharacter)
-    }
-    pub fn xoff_character(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::XoffCharacter)
-    }
-    pub fn ena_acs(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnaAcs)
-    }
-    pub fn label_on(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabelOn)
-    }
-    pub fn label_off(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabelOff)
-    }
-    pub fn key_beg(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyBeg)
-    }
-    pub fn key_cancel(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyCancel)
-    }
-    pub fn key_close(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyClose)
-    }
-    pub fn key_command(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyCommand)
-    }
-    pub fn key_copy(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyCopy)
-    }
-    pub fn key_create(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyCreate)
-    }
-    pub fn key_end(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyEnd)
-    }
-    pub fn key_enter(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyEnter)
-    }
-    pub fn key_exit(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyExit)
-    }
-    pub fn key_find(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyFind)
-    }
-    pub fn key_help(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyHelp)
-    }
-    pub fn key_mark(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyMark)
-    }
-    pub fn key_message(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyMessage)
-    }
-    pub fn key_move(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyMove)
-    }
-    pub fn key_next(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyNext)
-    }
-    pub fn key_open(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyOpen)
-    }
-    pub fn key_options(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyOptions)
-    }
-    pub fn key_previous(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyPrevious)
-    }
-    pub fn key_print(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyPrint)
-    }
-    pub fn key_redo(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyRedo)
-    }
-    pub fn key_reference(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyReference)
-    }
-    pub fn key_refresh(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyRefresh)
-    }
-    pub fn key_replace(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyReplace)
-    }
-    pub fn key_restart(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyRestart)
-    }
-    pub fn key_resume(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyResume)
-    }
-    pub fn key_save(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySave)
-    }
-    pub fn key_suspend(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySuspend)
-    }
-    pub fn key_undo(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyUndo)
-    }
-    pub fn key_sbeg(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySBeg)
-    }
-    pub fn key_scancel(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySCancel)
-    }
-    pub fn key_scommand(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySCommand)
-    }
-    pub fn key_scopy(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySCopy)
-    }
-    pub fn key_screate(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySCreate)
-    }
-    pub fn key_sdc(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySDc)
-    }
-    pub fn key_sdl(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySDl)
-    }
-    pub fn key_select(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySelect)
-    }
-    pub fn key_send(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySEnd)
-    }
-    pub fn key_seol(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySEol)
-    }
-    pub fn key_sexit(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySExit)
-    }
-    pub fn key_sfind(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySFind)
-    }
-    pub fn key_shelp(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySHelp)
-    }
-    pub fn key_shome(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySHome)
-    }
-    pub fn key_sic(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySIc)
-    }
-    pub fn key_sleft(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySLeft)
-    }
-    pub fn key_smessage(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySMessage)
-    }
-    pub fn key_smove(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySMove)
-    }
-    pub fn key_snext(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySNext)
-    }
-    pub fn key_soptions(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySOptions)
-    }
-    pub fn key_sprevious(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySPrevious)
-    }
-    pub fn key_sprint(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySPrint)
-    }
-    pub fn key_sredo(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySRedo)
-    }
-    pub fn key_sreplace(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySReplace)
-    }
-    pub fn key_sright(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySRight)
-    }
-    pub fn key_srsume(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySRsume)
-    }
-    pub fn key_ssave(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySSave)
-    }
-    pub fn key_ssuspend(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySSuspend)
-    }
-    pub fn key_sundo(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeySUndo)
-    }
-    pub fn req_for_input(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ReqForInput)
-    }
-    pub fn key_f11(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF11)
-    }
-    pub fn key_f12(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF12)
-    }
-    pub fn key_f13(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF13)
-    }
-    pub fn key_f14(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF14)
-    }
-    pub fn key_f15(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF15)
-    }
-    pub fn key_f16(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF16)
-    }
-    pub fn key_f17(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF17)
-    }
-    pub fn key_f18(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF18)
-    }
-    pub fn key_f19(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF19)
-    }
-    pub fn key_f20(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF20)
-    }
-    pub fn key_f21(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF21)
-    }
-    pub fn key_f22(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF22)
-    }
-    pub fn key_f23(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF23)
-    }
-    pub fn key_f24(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF24)
-    }
-    pub fn key_f25(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF25)
-    }
-    pub fn key_f26(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF26)
-    }
-    pub fn key_f27(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF27)
-    }
-    pub fn key_f28(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF28)
-    }
-    pub fn key_f29(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF29)
-    }
-    pub fn key_f30(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF30)
-    }
-    pub fn key_f31(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF31)
-    }
-    pub fn key_f32(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF32)
-    }
-    pub fn key_f33(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF33)
-    }
-    pub fn key_f34(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF34)
-    }
-    pub fn key_f35(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF35)
-    }
-    pub fn key_f36(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF36)
-    }
-    pub fn key_f37(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF37)
-    }
-    pub fn key_f38(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF38)
-    }
-    pub fn key_f39(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF39)
-    }
-    pub fn key_f40(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF40)
-    }
-    pub fn key_f41(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF41)
-    }
-    pub fn key_f42(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF42)
-    }
-    pub fn key_f43(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF43)
-    }
-    pub fn key_f44(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF44)
-    }
-    pub fn key_f45(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF45)
-    }
-    pub fn key_f46(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF46)
-    }
-    pub fn key_f47(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF47)
-    }
-    pub fn key_f48(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF48)
-    }
-    pub fn key_f49(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF49)
-    }
-    pub fn key_f50(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF50)
-    }
-    pub fn key_f51(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF51)
-    }
-    pub fn key_f52(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF52)
-    }
-    pub fn key_f53(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF53)
-    }
-    pub fn key_f54(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF54)
-    }
-    pub fn key_f55(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF55)
-    }
-    pub fn key_f56(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF56)
-    }
-    pub fn key_f57(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF57)
-    }
-    pub fn key_f58(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF58)
-    }
-    pub fn key_f59(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF59)
-    }
-    pub fn key_f60(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF60)
-    }
-    pub fn key_f61(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF61)
-    }
-    pub fn key_f62(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF62)
-    }
-    pub fn key_f63(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyF63)
-    }
-    pub fn clr_bol(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ClrBol)
-    }
-    pub fn clear_margins(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ClearMargins)
-    }
-    pub fn set_left_margin(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetLeftMargin)
-    }
-    pub fn set_right_margin(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetRightMargin)
-    }
-    pub fn label_format(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LabelFormat)
-    }
-    pub fn set_clock(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetClock)
-    }
-    pub fn display_clock(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DisplayClock)
-    }
-    pub fn remove_clock(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::RemoveClock)
-    }
-    pub fn create_window(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CreateWindow)
-    }
-    pub fn goto_window(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::GotoWindow)
-    }
-    pub fn hangup(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Hangup)
-    }
-    pub fn dial_phone(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DialPhone)
-    }
-    pub fn quick_dial(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::QuickDial)
-    }
-    pub fn tone(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Tone)
-    }
-    pub fn pulse(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Pulse)
-    }
-    pub fn flash_hook(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::FlashHook)
-    }
-    pub fn fixed_pause(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::FixedPause)
-    }
-    pub fn wait_tone(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::WaitTone)
-    }
-    pub fn user0(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User0)
-    }
-    pub fn user1(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User1)
-    }
-    pub fn user2(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User2)
-    }
-    pub fn user3(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User3)
-    }
-    pub fn user4(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User4)
-    }
-    pub fn user5(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User5)
-    }
-    pub fn user6(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User6)
-    }
-    pub fn user7(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User7)
-    }
-    pub fn user8(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User8)
-    }
-    pub fn user9(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::User9)
-    }
-    pub fn orig_pair(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::OrigPair)
-    }
-    pub fn orig_colors(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::OrigColors)
-    }
-    pub fn initialize_color(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::InitializeColor)
-    }
-    pub fn initialize_pair(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::InitializePair)
-    }
-    pub fn set_color_pair(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetColorPair)
-    }
-    pub fn change_char_pitch(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ChangeCharPitch)
-    }
-    pub fn change_line_pitch(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ChangeLinePitch)
-    }
-    pub fn change_res_horz(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ChangeResHorz)
-    }
-    pub fn change_res_vert(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ChangeResVert)
-    }
-    pub fn define_char(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DefineChar)
-    }
-    pub fn enter_doublewide_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterDoublewideMode)
-    }
-    pub fn enter_draft_quality(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterDraftQuality)
-    }
-    pub fn enter_italics_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterItalicsMode)
-    }
-    pub fn enter_leftward_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterLeftwardMode)
-    }
-    pub fn enter_micro_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterMicroMode)
-    }
-    pub fn enter_near_letter_quality(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterNearLetterQuality)
-    }
-    pub fn enter_normal_quality(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterNormalQuality)
-    }
-    pub fn enter_shadow_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterShadowMode)
-    }
-    pub fn enter_subscript_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterSubscriptMode)
-    }
-    pub fn enter_superscript_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterSuperscriptMode)
-    }
-    pub fn enter_upward_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterUpwardMode)
-    }
-    pub fn exit_doublewide_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitDoublewideMode)
-    }
-    pub fn exit_italics_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitItalicsMode)
-    }
-    pub fn exit_leftward_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitLeftwardMode)
-    }
-    pub fn exit_micro_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitMicroMode)
-    }
-    pub fn exit_shadow_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitShadowMode)
-    }
-    pub fn exit_subscript_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitSubscriptMode)
-    }
-    pub fn exit_superscript_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitSuperscriptMode)
-    }
-    pub fn exit_upward_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitUpwardMode)
-    }
-    pub fn micro_column_address(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MicroColumnAddress)
-    }
-    pub fn micro_down(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MicroDown)
-    }
-    pub fn micro_left(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MicroLeft)
-    }
-    pub fn micro_right(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MicroRight)
-    }
-    pub fn micro_row_address(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MicroRowAddress)
-    }
-    pub fn micro_up(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MicroUp)
-    }
-    pub fn order_of_pins(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::OrderOfPins)
-    }
-    pub fn select_char_set(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SelectCharSet)
-    }
-    pub fn set_bottom_margin(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetBottomMargin)
-    }
-    pub fn set_bottom_margin_parm(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetBottomMarginParm)
-    }
-    pub fn set_left_margin_parm(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetLeftMarginParm)
-    }
-    pub fn set_right_margin_parm(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetRightMarginParm)
-    }
-    pub fn set_top_margin(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetTopMargin)
-    }
-    pub fn set_top_margin_parm(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetTopMarginParm)
-    }
-    pub fn start_bit_image(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::StartBitImage)
+    /// Text written through [`std::io::Write`] of the wrappers, converted to the encoding.
+    fn write_text(&mut self, text: &[u8]) -> std::io::Result<usize> {
+        if self.encoding == OutputEncoding::Utf8 {
+            self.bytes.extend_from_slice(text);
+            return Ok(text.len());
+        }
+        let mut joined = std::mem::take(&mut self.cut_char);
+        joined.extend_from_slice(text);
+        let whole = joined.len() - encoding::incomplete_char(&joined);
+        self.encoding.transcode(&joined[..whole], &mut self.bytes);
+        self.cut_char = joined.split_off(whole);
+        Ok(text.len())
     }
-    pub fn start_char_set_def(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::StartCharSetDef)
+
+    /// OSC 133 shell integration mark, `params` starting with the letter of the mark.
+    fn semantic_mark(&mut self, name: &'static str, params: &str) -> Result<(), CapabilityError> {
+        let start = self.len();
+        self.bytes.extend_from_slice(b"\x1B]133;");
+        self.bytes.extend_from_slice(params.as_bytes());
+        self.bytes.extend_from_slice(b"\x1B\\");
+        self.record(name, start);
+        Ok(())
     }
-    pub fn stop_bit_image(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::StopBitImage)
+
+    /// XTWINOPS, `CSI params t`. Operations that change the window are refused
+    /// unless they were allowed.
+    fn window_op(
+        &mut self,
+        name: &'static str,
+        params: &str,
+        changes_window: bool,
+    ) -> Result<(), CapabilityError> {
+        if changes_window && !self.window_ops {
+            return Err(CapabilityError::WindowOpsDisabled {
+                cap_name: name.into(),
+            });
+        }
+        let start = self.len();
+        self.bytes.extend_from_slice(b"\x1B[");
+        self.bytes.extend_from_slice(params.as_bytes());
+        self.bytes.push(b't');
+        self.record(name, start);
+        Ok(())
     }
-    pub fn stop_char_set_def(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::StopCharSetDef)
+
+    fn emit_custom(&mut self, name: &str, params: &[Param]) -> Result<(), CapabilityError> {
+        let Some(template) = self.custom.get(name) else {
+            return Err(CapabilityError::UnknownCustomSequence { name: name.into() });
+        };
+        if params.len() != template.arity() {
+            return Err(CapabilityError::CustomSequenceArity {
+                name: name.into(),
+                expected: template.arity(),
+                got: params.len(),
+            });
+        }
+        let start = self.len();
+        template
+            .expand(params, &mut self.bytes)
+            .map_err(|index| CapabilityError::CustomSequenceParam {
+                name: name.into(),
+                index,
+            })?;
+        self.record("custom_sequence", start);
+        Ok(())
     }
-    pub fn subscript_characters(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SubscriptCharacters)
+
+    /// Called right before the buffer is written out.
+    fn end_frame(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame(self.bytes.len());
+        }
     }
-    pub fn superscript_characters(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SuperscriptCharacters)
+
+    fn clear(&mut self) {
+        self.bytes.clear();
+        self.cut_char.clear();
+        if let Some(records) = &mut self.records {
+            records.clear();
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.discard();
+        }
     }
-    pub fn these_cause_cr(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::TheseCauseCr)
+
+    fn profile(&mut self, enable: bool) {
+        self.profiler = enable.then(Profiler::default);
     }
-    pub fn zero_motion(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ZeroMotion)
+
+    fn frame_stats(&self) -> Option<&FrameStats> {
+        self.profiler.as_ref().map(|profiler| &profiler.last)
     }
-    pub fn char_set_names(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CharSetNames)
+
+    fn cumulative_stats(&self) -> Option<&FrameStats> {
+        self.profiler.as_ref().map(|profiler| &profiler.cumulative)
     }
-    pub fn key_mouse(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::KeyMouse)
+}
+
+impl std::io::Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes.write(buf)
     }
-    pub fn mouse_info(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MouseInfo)
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
-    pub fn req_mouse_pos(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ReqMousePos)
+}
+
+/// Generates the [`Capabilities`] methods of [`TerminfoWrapper`] from its inherent ones.
+#[cfg(feature = "terminfo")]
+macro_rules! delegate_capabilities {
+    ($($name:ident($($arg:ident: $ty:ty),*);)+) => {
+        $(fn $name(&mut self, $($arg: $ty),*) -> Result<(), CapabilityError> {
+            TerminfoWrapper::$name(self, $($arg),*)
+        })+
+    };
+}
+
+/// Declares the capability methods shared by every sequence backend.
+macro_rules! capability_methods {
+    ($($name:ident($($arg:ident: $ty:ty),*);)+) => {
+        $(fn $name(&mut self, $($arg: $ty),*) -> Result<(), CapabilityError>;)+
+    };
+}
+
+/// The part of [`TerminfoWrapper`] that is also available without a terminfo database.
+///
+/// Code written against this trait works with any backend the crate was built with,
+/// see [`Sequences`].
+pub trait Capabilities: std::io::Write {
+    /// Bytes written since the last flush.
+    fn buffer(&self) -> &[u8];
+    fn append(&mut self, bytes: &[u8]);
+    /// Appends a sequence meant for the terminal itself rather than a multiplexer
+    /// in between, e. g. OSC 52 or kitty graphics. It is wrapped for passthrough
+    /// when a multiplexer was set.
+    fn append_host(&mut self, bytes: &[u8]);
+    fn clear(&mut self);
+    fn flush_to(&mut self, to: &mut impl std::io::Write) -> std::io::Result<()>;
+    fn get_parser(&self) -> InputParser;
+
+    /// Turns off mouse reporting and bracketed paste while another program has the
+    /// terminal, see [`run_external`]. Nothing for backends that don't turn them on.
+    fn suspend_input_modes(&mut self) -> Result<(), CapabilityError> {
+        Ok(())
     }
-    pub fn get_mouse(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::GetMouse)
+
+    /// Turns on again what [`Capabilities::suspend_input_modes`] turned off.
+    fn resume_input_modes(&mut self) -> Result<(), CapabilityError> {
+        Ok(())
     }
-    pub fn pkey_plab(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PkeyPlab)
+
+    capability_methods! {
+        move_cursor(row: usize, col: usize);
+        bell();
+        carriage_return();
+        clear_screen();
+        clr_eol();
+        clr_eos();
+        clr_bol();
+        cursor_home();
+        cursor_up();
+        cursor_down();
+        cursor_left();
+        cursor_right();
+        parm_up_cursor(count: u32);
+        parm_down_cursor(count: u32);
+        parm_left_cursor(count: u32);
+        parm_right_cursor(count: u32);
+        cursor_invisible();
+        cursor_normal();
+        cursor_visible();
+        scroll_forward();
+        scroll_reverse();
+        change_scroll_region(top: u32, bottom: u32);
+        enter_ca_mode();
+        exit_ca_mode();
+        keypad_xmit();
+        keypad_local();
+        exit_attribute_mode();
+        enter_bold_mode();
+        enter_dim_mode();
+        enter_italics_mode();
+        exit_italics_mode();
+        enter_underline_mode();
+        exit_underline_mode();
+        enter_blink_mode();
+        enter_reverse_mode();
+        enter_secure_mode();
+        enter_crossed_out_mode();
+        enter_overline_mode();
+        enter_standout_mode();
+        exit_standout_mode();
+        orig_pair();
+        set_a_foreground(color: u8);
+        set_a_background(color: u8);
+        mark_prompt_start();
+        mark_prompt_end();
+        mark_output_start();
+        mark_output_end(exit_code: Option<i32>);
+        request_text_area_pixels();
+        request_cell_pixels();
+        request_text_area_chars();
+        iconify_window(iconify: bool);
+        move_window(x: u32, y: u32);
+        resize_text_area(rows: u32, cols: u32);
+        single_width_line();
+        double_width_line();
+        double_height_line(top: bool);
     }
-    pub fn device_type(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DeviceType)
+}
+
+/// Sequence backend picked by the enabled features, terminfo when available.
+#[cfg(feature = "terminfo")]
+pub type Sequences = TerminfoWrapper;
+/// Sequence backend picked by the enabled features, terminfo when available.
+#[cfg(all(feature = "ansi", not(feature = "terminfo")))]
+pub type Sequences = AnsiWrapper;
+
+#[cfg(feature = "terminfo")]
+pub struct TerminfoWrapper {
+    pub db: Database,
+    buffer: OutputBuffer,
+}
+
+#[cfg(feature = "terminfo")]
+impl<'a> TerminfoWrapper {
+    pub fn from_env() -> Result<Self, errors::TerminfoCreationError> {
+        let mut wrapper = Self::from(Database::from_env()?);
+        wrapper.apply_env(&crate::env::EnvSnapshot::capture());
+        Ok(wrapper)
     }
-    pub fn code_set_init(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::CodeSetInit)
+
+    /// Loads the entry for the `TERM` of `env` from its terminfo search path,
+    /// see [`EnvSnapshot::terminfo_path`](crate::env::EnvSnapshot::terminfo_path).
+    pub fn from_snapshot(
+        env: &crate::env::EnvSnapshot,
+    ) -> Result<Self, errors::TerminfoCreationError> {
+        let path = env
+            .terminfo_path()
+            .ok_or(errors::TerminfoCreationError::TerminfoDbNotFound)?;
+        let mut wrapper = Self::from(Database::from_path(path)?);
+        wrapper.apply_env(env);
+        Ok(wrapper)
     }
-    pub fn set0_des_seq(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Set0DesSeq)
+
+    /// `COLORTERM` tells about truecolor where the database doesn't, the color policy
+    /// is detected, e. g. to honor `NO_COLOR`, and the output encoding from the locale.
+    fn apply_env(&mut self, env: &crate::env::EnvSnapshot) {
+        if env.truecolor() {
+            self.buffer.truecolor = Some(true);
+        }
+        self.buffer.encoding = OutputEncoding::detect_or_ascii(env);
+        let max_colors = self.db.get::<cap::MaxColors>().map(i32::from);
+        self.buffer.color_policy = ColorPolicy::detect(env, max_colors);
     }
-    pub fn set1_des_seq(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Set1DesSeq)
+
+    pub fn flush_to(&mut self, to: &mut impl std::io::Write) -> std::io::Result<()> {
+        crate::logging::debug_event!(bytes = self.buffer.len(), "flush");
+        self.buffer.end_frame();
+        to.write_all(&self.buffer.bytes)?;
+        self.clear();
+        Ok(())
     }
-    pub fn set2_des_seq(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Set2DesSeq)
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
     }
-    pub fn set3_des_seq(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::Set3DesSeq)
+
+    pub fn append(&mut self, bytes: &[u8]) {
+        self.buffer.bytes.extend_from_slice(bytes);
     }
-    pub fn set_lr_margin(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetLrMargin)
+
+    /// Enters the alternate screen on `out` until the returned guard is dropped,
+    /// see [`AltScreenGuard::new`].
+    pub fn alt_screen_guard<'o, W: std::io::Write>(
+        &mut self,
+        out: &'o mut W,
+    ) -> Result<AltScreenGuard<'o, W>, CapabilityError> {
+        AltScreenGuard::new(out, self)
     }
-    pub fn set_tb_margin(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetTbMargin)
+
+    /// See [`Capabilities::append_host`].
+    pub fn append_host(&mut self, bytes: &[u8]) {
+        self.buffer.append_host(bytes);
     }
-    pub fn bit_image_repeat(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::BitImageRepeat)
+
+    /// Multiplexer that [`TerminfoWrapper::append_host`] wraps sequences for,
+    /// see [`Multiplexer::detect`].
+    pub fn set_multiplexer(&mut self, multiplexer: Option<Multiplexer>) {
+        self.buffer.multiplexer = multiplexer;
     }
-    pub fn bit_image_newline(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::BitImageNewline)
+
+    pub fn multiplexer(&self) -> Option<Multiplexer> {
+        self.buffer.multiplexer
     }
-    pub fn bit_image_carriage_return(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::BitImageCarriageReturn)
+
+    /// Encoding text written through [`std::io::Write`] is converted to, which
+    /// [`TerminfoWrapper::from_env`] and [`TerminfoWrapper::from_snapshot`] take from the locale.
+    pub fn set_output_encoding(&mut self, encoding: OutputEncoding) {
+        self.buffer.encoding = encoding;
     }
-    pub fn color_names(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ColorNames)
+
+    pub fn output_encoding(&self) -> OutputEncoding {
+        self.buffer.encoding
     }
-    pub fn define_bit_image_region(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DefineBitImageRegion)
+
+    /// Whether [`TerminfoWrapper::set_foreground_rgb`] and [`TerminfoWrapper::set_background_rgb`]
+    /// send colors as they are. `None` goes by the `RGB` and `Tc` flags of the database,
+    /// which [`TerminfoWrapper::from_env`] and [`TerminfoWrapper::from_snapshot`] override
+    /// when `COLORTERM` is `truecolor` or `24bit`.
+    pub fn set_truecolor(&mut self, truecolor: Option<bool>) {
+        self.buffer.truecolor = truecolor;
     }
-    pub fn end_bit_image_region(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EndBitImageRegion)
+
+    pub fn truecolor(&self) -> bool {
+        self.buffer.truecolor.unwrap_or_else(|| {
+            ["RGB", "Tc"]
+                .iter()
+                .any(|flag| matches!(self.db.raw(flag), Some(terminfo::Value::True)))
+        })
     }
-    pub fn set_color_band(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetColorBand)
+
+    /// Colors every color method is fit to from now on, e. g. [`TerminfoWrapper::set_a_foreground`]
+    /// writes nothing at [`ColorLevel::Off`](crate::color::ColorLevel::Off). Colors the database
+    /// doesn't have are still left out. [`TerminfoWrapper::from_env`] and
+    /// [`TerminfoWrapper::from_snapshot`] detect the policy, otherwise colors are written as asked.
+    ///
+    /// Attributes are left to the caller, see [`ColorPolicy::attributes`].
+    pub fn set_color_policy(&mut self, policy: ColorPolicy) {
+        self.buffer.color_policy = policy;
     }
-    pub fn set_page_length(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetPageLength)
+
+    pub fn color_policy(&self) -> ColorPolicy {
+        self.buffer.color_policy
     }
-    pub fn display_pc_char(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::DisplayPcChar)
+
+    /// Lets [`TerminfoWrapper::iconify_window`], [`TerminfoWrapper::move_window`] and
+    /// [`TerminfoWrapper::resize_text_area`] through, they fail with
+    /// [`CapabilityError::WindowOpsDisabled`] otherwise. Many terminals ignore them anyway.
+    pub fn allow_window_ops(&mut self, allow: bool) {
+        self.buffer.window_ops = allow;
     }
-    pub fn enter_pc_charset_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterPcCharsetMode)
+
+    /// Turns on mouse reporting with SGR encoding (mode 1006), which isn't limited to 223 columns.
+    ///
+    /// Clicks are turned on with the `XM` extended capability when the database has it,
+    /// the other modes always with DEC private modes. A mode enabled before is turned off first.
+    pub fn enable_mouse(&mut self, mode: MouseMode) -> Result<(), CapabilityError> {
+        self.disable_mouse()?;
+        let start = self.buffer.len();
+        let xm = match self.db.raw("XM") {
+            Some(terminfo::Value::String(seq)) if mode == MouseMode::Clicks => {
+                terminfo::expand!(&mut self.buffer.bytes, &seq[..]; 1)
+                    .map_err(|_| CapabilityError::CapabilityExpansionError)?;
+                true
+            }
+            _ => {
+                let dec = format!("\x1B[?{};1006h", mode.dec_mode());
+                self.buffer.bytes.extend_from_slice(dec.as_bytes());
+                false
+            }
+        };
+        self.buffer.mouse = Some((mode, xm));
+        self.buffer.record("enable_mouse", start);
+        Ok(())
     }
-    pub fn exit_pc_charset_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitPcCharsetMode)
+
+    /// Turns off the modes [`TerminfoWrapper::enable_mouse`] turned on, nothing if none.
+    pub fn disable_mouse(&mut self) -> Result<(), CapabilityError> {
+        let Some((mode, xm)) = self.buffer.mouse else {
+            return Ok(());
+        };
+        let start = self.buffer.len();
+        match self.db.raw("XM") {
+            Some(terminfo::Value::String(seq)) if xm => {
+                terminfo::expand!(&mut self.buffer.bytes, &seq[..]; 0)
+                    .map_err(|_| CapabilityError::CapabilityExpansionError)?;
+            }
+            _ => {
+                let dec = format!("\x1B[?{};1006l", mode.dec_mode());
+                self.buffer.bytes.extend_from_slice(dec.as_bytes());
+            }
+        }
+        self.buffer.mouse = None;
+        self.buffer.record("disable_mouse", start);
+        Ok(())
     }
-    pub fn enter_scancode_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterScancodeMode)
+
+    /// Makes `template` available to [`TerminfoWrapper::emit_custom`] as `name`,
+    /// replacing a template registered with the same name before.
+    pub fn register_custom_sequence(&mut self, name: &str, template: SequenceTemplate) {
+        self.buffer.custom.insert(name.into(), template);
     }
-    pub fn exit_scancode_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ExitScancodeMode)
+
+    /// Appends the custom sequence `name` expanded with `params`.
+    /// Recorded as `custom_sequence`, see [`TerminfoWrapper::record_capabilities`].
+    pub fn emit_custom(&mut self, name: &str, params: &[Param]) -> Result<(), CapabilityError> {
+        self.buffer.emit_custom(name, params)
     }
-    pub fn pc_term_options(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::PcTermOptions)
+
+    /// Bytes written since the last flush.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer.bytes
     }
-    pub fn scancode_escape(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ScancodeEscape)
+
+    /// When enabled, every capability method remembers which bytes of the
+    /// buffer it produced until the next [`TerminfoWrapper::clear`] or flush.
+    pub fn record_capabilities(&mut self, enable: bool) {
+        self.buffer.records = enable.then(Vec::new);
     }
-    pub fn alt_scancode_esc(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AltScancodeEsc)
+
+    /// Capabilities expanded into the buffer since the last flush,
+    /// empty unless [`TerminfoWrapper::record_capabilities`] is enabled.
+    pub fn records(&self) -> &[CapabilityRecord] {
+        self.buffer.records.as_deref().unwrap_or_default()
     }
-    pub fn enter_horizontal_hl_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterHorizontalHlMode)
+
+    /// When enabled, bytes are counted by [`ByteOrigin`] and every flush ends a frame.
+    /// Turning it off or on again resets the counts.
+    pub fn profile_output(&mut self, enable: bool) {
+        self.buffer.profile(enable);
     }
-    pub fn enter_left_hl_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterLeftHlMode)
+
+    /// Counts of the last flushed frame, `None` unless [`TerminfoWrapper::profile_output`] is enabled.
+    pub fn frame_stats(&self) -> Option<&FrameStats> {
+        self.buffer.frame_stats()
     }
-    pub fn enter_low_hl_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterLowHlMode)
+
+    /// Counts of every frame flushed since profiling was enabled.
+    pub fn cumulative_stats(&self) -> Option<&FrameStats> {
+        self.buffer.cumulative_stats()
     }
-    pub fn enter_right_hl_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterRightHlMode)
+
+    pub fn move_cursor(&mut self, row: usize, col: usize) -> Result<(), CapabilityError> {
+        tty_expand_cap!(self.db, &mut self.buffer, cap::CursorAddress; row as i32, col as i32)
     }
-    pub fn enter_top_hl_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterTopHlMode)
+    simple_capabilities! {
+        back_tab: BackTab;
+        bell: Bell;
+        carriage_return: CarriageReturn;
+        clear_all_tabs: ClearAllTabs;
+        clear_screen: ClearScreen;
+        clr_eol: ClrEol;
+        clr_eos: ClrEos;
+        command_character: CommandCharacter;
+        cursor_down: CursorDown;
+        cursor_home: CursorHome;
+        cursor_invisible: CursorInvisible;
+        cursor_left: CursorLeft;
+        cursor_mem_address: CursorMemAddress;
+        cursor_normal: CursorNormal;
+        cursor_right: CursorRight;
+        cursor_to_ll: CursorToLl;
+        cursor_up: CursorUp;
+        cursor_visible: CursorVisible;
+        delete_character: DeleteCharacter;
+        delete_line: DeleteLine;
+        dis_status_line: DisStatusLine;
+        down_half_line: DownHalfLine;
+        enter_alt_charset_mode: EnterAltCharsetMode;
+        enter_blink_mode: EnterBlinkMode;
+        enter_bold_mode: EnterBoldMode;
+        enter_ca_mode: EnterCaMode;
+        enter_delete_mode: EnterDeleteMode;
+        enter_dim_mode: EnterDimMode;
+        enter_insert_mode: EnterInsertMode;
+        enter_secure_mode: EnterSecureMode;
+        enter_protected_mode: EnterProtectedMode;
+        enter_reverse_mode: EnterReverseMode;
+        enter_standout_mode: EnterStandoutMode;
+        enter_underline_mode: EnterUnderlineMode;
+        exit_alt_charset_mode: ExitAltCharsetMode;
+        exit_attribute_mode: ExitAttributeMode;
+        exit_ca_mode: ExitCaMode;
+        exit_delete_mode: ExitDeleteMode;
+        exit_insert_mode: ExitInsertMode;
+        exit_standout_mode: ExitStandoutMode;
+        exit_underline_mode: ExitUnderlineMode;
+        flash_screen: FlashScreen;
+        form_feed: FormFeed;
+        from_status_line: FromStatusLine;
+        init_1string: Init1String;
+        init_2string: Init2String;
+        init_3string: Init3String;
+        init_file: InitFile;
+        insert_character: InsertCharacter;
+        insert_line: InsertLine;
+        insert_padding: InsertPadding;
+        key_backspace: KeyBackspace;
+        key_catab: KeyCATab;
+        key_clear: KeyClear;
+        key_ctab: KeyCTab;
+        key_dc: KeyDc;
+        key_dl: KeyDl;
+        key_down: KeyDown;
+        key_eic: KeyEic;
+        key_eol: KeyEol;
+        key_eos: KeyEos;
+        key_f0: KeyF0;
+        key_f1: KeyF1;
+        key_f10: KeyF10;
+        key_f2: KeyF2;
+        key_f3: KeyF3;
+        key_f4: KeyF4;
+        key_f5: KeyF5;
+        key_f6: KeyF6;
+        key_f7: KeyF7;
+        key_f8: KeyF8;
+        key_f9: KeyF9;
+        key_home: KeyHome;
+        key_ic: KeyIc;
+        key_il: KeyIl;
+        key_left: KeyLeft;
+        key_ll: KeyLl;
+        key_npage: KeyNPage;
+        key_ppage: KeyPPage;
+        key_right: KeyRight;
+        key_sf: KeySf;
+        key_sr: KeySr;
+        key_stab: KeySTab;
+        key_up: KeyUp;
+        keypad_local: KeypadLocal;
+        keypad_xmit: KeypadXmit;
+        lab_f0: LabF0;
+        lab_f1: LabF1;
+        lab_f10: LabF10;
+        lab_f2: LabF2;
+        lab_f3: LabF3;
+        lab_f4: LabF4;
+        lab_f5: LabF5;
+        lab_f6: LabF6;
+        lab_f7: LabF7;
+        lab_f8: LabF8;
+        lab_f9: LabF9;
+        meta_off: MetaOff;
+        meta_on: MetaOn;
+        newline: Newline;
+        pad_char: PadChar;
+        pkey_key: PKeyKey;
+        pkey_local: PKeyLocal;
+        pkey_xmit: PKeyXmit;
+        print_screen: PrintScreen;
+        prtr_off: PrtrOff;
+        prtr_on: PrtrOn;
+        repeat_char: RepeatChar;
+        reset_1string: Reset1String;
+        reset_2string: Reset2String;
+        reset_3string: Reset3String;
+        reset_file: ResetFile;
+        restore_cursor: RestoreCursor;
+        save_cursor: SaveCursor;
+        scroll_forward: ScrollForward;
+        scroll_reverse: ScrollReverse;
+        set_tab: SetTab;
+        set_window: SetWindow;
+        tab: Tab;
+        to_status_line: ToStatusLine;
+        underline_char: UnderlineChar;
+        up_half_line: UpHalfLine;
+        init_prog: InitProg;
+        key_a1: KeyA1;
+        key_a3: KeyA3;
+        key_b2: KeyB2;
+        key_c1: KeyC1;
+        key_c3: KeyC3;
+        prtr_non: PrtrNon;
+        char_padding: CharPadding;
+        acs_chars: AcsChars;
+        plab_norm: PlabNorm;
+        key_btab: KeyBTab;
+        enter_xon_mode: EnterXonMode;
+        exit_xon_mode: ExitXonMode;
+        enter_am_mode: EnterAmMode;
+        exit_am_mode: ExitAmMode;
+        xon_character: XonCharacter;
+        xoff_character: XoffCharacter;
+        ena_acs: EnaAcs;
+        label_on: LabelOn;
+        label_off: LabelOff;
+        key_beg: KeyBeg;
+        key_cancel: KeyCancel;
+        key_close: KeyClose;
+        key_command: KeyCommand;
+        key_copy: KeyCopy;
+        key_create: KeyCreate;
+        key_end: KeyEnd;
+        key_enter: KeyEnter;
+        key_exit: KeyExit;
+        key_find: KeyFind;
+        key_help: KeyHelp;
+        key_mark: KeyMark;
+        key_message: KeyMessage;
+        key_move: KeyMove;
+        key_next: KeyNext;
+        key_open: KeyOpen;
+        key_options: KeyOptions;
+        key_previous: KeyPrevious;
+        key_print: KeyPrint;
+        key_redo: KeyRedo;
+        key_reference: KeyReference;
+        key_refresh: KeyRefresh;
+        key_replace: KeyReplace;
+        key_restart: KeyRestart;
+        key_resume: KeyResume;
+        key_save: KeySave;
+        key_suspend: KeySuspend;
+        key_undo: KeyUndo;
+        key_sbeg: KeySBeg;
+        key_scancel: KeySCancel;
+        key_scommand: KeySCommand;
+        key_scopy: KeySCopy;
+        key_screate: KeySCreate;
+        key_sdc: KeySDc;
+        key_sdl: KeySDl;
+        key_select: KeySelect;
+        key_send: KeySEnd;
+        key_seol: KeySEol;
+        key_sexit: KeySExit;
+        key_sfind: KeySFind;
+        key_shelp: KeySHelp;
+        key_shome: KeySHome;
+        key_sic: KeySIc;
+        key_sleft: KeySLeft;
+        key_smessage: KeySMessage;
+        key_smove: KeySMove;
+        key_snext: KeySNext;
+        key_soptions: KeySOptions;
+        key_sprevious: KeySPrevious;
+        key_sprint: KeySPrint;
+        key_sredo: KeySRedo;
+        key_sreplace: KeySReplace;
+        key_sright: KeySRight;
+        key_srsume: KeySRsume;
+        key_ssave: KeySSave;
+        key_ssuspend: KeySSuspend;
+        key_sundo: KeySUndo;
+        req_for_input: ReqForInput;
+        key_f11: KeyF11;
+        key_f12: KeyF12;
+        key_f13: KeyF13;
+        key_f14: KeyF14;
+        key_f15: KeyF15;
+        key_f16: KeyF16;
+        key_f17: KeyF17;
+        key_f18: KeyF18;
+        key_f19: KeyF19;
+        key_f20: KeyF20;
+        key_f21: KeyF21;
+        key_f22: KeyF22;
+        key_f23: KeyF23;
+        key_f24: KeyF24;
+        key_f25: KeyF25;
+        key_f26: KeyF26;
+        key_f27: KeyF27;
+        key_f28: KeyF28;
+        key_f29: KeyF29;
+        key_f30: KeyF30;
+        key_f31: KeyF31;
+        key_f32: KeyF32;
+        key_f33: KeyF33;
+        key_f34: KeyF34;
+        key_f35: KeyF35;
+        key_f36: KeyF36;
+        key_f37: KeyF37;
+        key_f38: KeyF38;
+        key_f39: KeyF39;
+        key_f40: KeyF40;
+        key_f41: KeyF41;
+        key_f42: KeyF42;
+        key_f43: KeyF43;
+        key_f44: KeyF44;
+        key_f45: KeyF45;
+        key_f46: KeyF46;
+        key_f47: KeyF47;
+        key_f48: KeyF48;
+        key_f49: KeyF49;
+        key_f50: KeyF50;
+        key_f51: KeyF51;
+        key_f52: KeyF52;
+        key_f53: KeyF53;
+        key_f54: KeyF54;
+        key_f55: KeyF55;
+        key_f56: KeyF56;
+        key_f57: KeyF57;
+        key_f58: KeyF58;
+        key_f59: KeyF59;
+        key_f60: KeyF60;
+        key_f61: KeyF61;
+        key_f62: KeyF62;
+        key_f63: KeyF63;
+        clr_bol: ClrBol;
+        clear_margins: ClearMargins;
+        set_left_margin: SetLeftMargin;
+        set_right_margin: SetRightMargin;
+        label_format: LabelFormat;
+        set_clock: SetClock;
+        display_clock: DisplayClock;
+        remove_clock: RemoveClock;
+        create_window: CreateWindow;
+        goto_window: GotoWindow;
+        hangup: Hangup;
+        dial_phone: DialPhone;
+        quick_dial: QuickDial;
+        tone: Tone;
+        pulse: Pulse;
+        flash_hook: FlashHook;
+        fixed_pause: FixedPause;
+        wait_tone: WaitTone;
+        user0: User0;
+        user1: User1;
+        user2: User2;
+        user3: User3;
+        user4: User4;
+        user5: User5;
+        user6: User6;
+        user7: User7;
+        user8: User8;
+        user9: User9;
+        orig_pair: OrigPair;
+        orig_colors: OrigColors;
+        initialize_color: InitializeColor;
+        initialize_pair: InitializePair;
+        set_color_pair: SetColorPair;
+        change_char_pitch: ChangeCharPitch;
+        change_line_pitch: ChangeLinePitch;
+        change_res_horz: ChangeResHorz;
+        change_res_vert: ChangeResVert;
+        define_char: DefineChar;
+        enter_doublewide_mode: EnterDoublewideMode;
+        enter_draft_quality: EnterDraftQuality;
+        enter_italics_mode: EnterItalicsMode;
+        enter_leftward_mode: EnterLeftwardMode;
+        enter_micro_mode: EnterMicroMode;
+        enter_near_letter_quality: EnterNearLetterQuality;
+        enter_normal_quality: EnterNormalQuality;
+        enter_shadow_mode: EnterShadowMode;
+        enter_subscript_mode: EnterSubscriptMode;
+        enter_superscript_mode: EnterSuperscriptMode;
+        enter_upward_mode: EnterUpwardMode;
+        exit_doublewide_mode: ExitDoublewideMode;
+        exit_italics_mode: ExitItalicsMode;
+        exit_leftward_mode: ExitLeftwardMode;
+        exit_micro_mode: ExitMicroMode;
+        exit_shadow_mode: ExitShadowMode;
+        exit_subscript_mode: ExitSubscriptMode;
+        exit_superscript_mode: ExitSuperscriptMode;
+        exit_upward_mode: ExitUpwardMode;
+        micro_column_address: MicroColumnAddress;
+        micro_down: MicroDown;
+        micro_left: MicroLeft;
+        micro_right: MicroRight;
+        micro_row_address: MicroRowAddress;
+        micro_up: MicroUp;
+        order_of_pins: OrderOfPins;
+        select_char_set: SelectCharSet;
+        set_bottom_margin: SetBottomMargin;
+        set_bottom_margin_parm: SetBottomMarginParm;
+        set_left_margin_parm: SetLeftMarginParm;
+        set_right_margin_parm: SetRightMarginParm;
+        set_top_margin: SetTopMargin;
+        set_top_margin_parm: SetTopMarginParm;
+        start_bit_image: StartBitImage;
+        start_char_set_def: StartCharSetDef;
+        stop_bit_image: StopBitImage;
+        stop_char_set_def: StopCharSetDef;
+        subscript_characters: SubscriptCharacters;
+        superscript_characters: SuperscriptCharacters;
+        these_cause_cr: TheseCauseCr;
+        zero_motion: ZeroMotion;
+        char_set_names: CharSetNames;
+        key_mouse: KeyMouse;
+        mouse_info: MouseInfo;
+        req_mouse_pos: ReqMousePos;
+        get_mouse: GetMouse;
+        pkey_plab: PkeyPlab;
+        device_type: DeviceType;
+        code_set_init: CodeSetInit;
+        set0_des_seq: Set0DesSeq;
+        set1_des_seq: Set1DesSeq;
+        set2_des_seq: Set2DesSeq;
+        set3_des_seq: Set3DesSeq;
+        set_lr_margin: SetLrMargin;
+        set_tb_margin: SetTbMargin;
+        bit_image_repeat: BitImageRepeat;
+        bit_image_newline: BitImageNewline;
+        bit_image_carriage_return: BitImageCarriageReturn;
+        color_names: ColorNames;
+        define_bit_image_region: DefineBitImageRegion;
+        end_bit_image_region: EndBitImageRegion;
+        set_color_band: SetColorBand;
+        set_page_length: SetPageLength;
+        display_pc_char: DisplayPcChar;
+        enter_pc_charset_mode: EnterPcCharsetMode;
+        exit_pc_charset_mode: ExitPcCharsetMode;
+        enter_scancode_mode: EnterScancodeMode;
+        exit_scancode_mode: ExitScancodeMode;
+        pc_term_options: PcTermOptions;
+        scancode_escape: ScancodeEscape;
+        alt_scancode_esc: AltScancodeEsc;
+        enter_horizontal_hl_mode: EnterHorizontalHlMode;
+        enter_left_hl_mode: EnterLeftHlMode;
+        enter_low_hl_mode: EnterLowHlMode;
+        enter_right_hl_mode: EnterRightHlMode;
+        enter_top_hl_mode: EnterTopHlMode;
+        enter_vertical_hl_mode: EnterVerticalHlMode;
+        set_a_attributes: SetAAttributes;
+        set_pglen_inch: SetPglenInch;
+        termcap_init2: TermcapInit2;
+        termcap_reset: TermcapReset;
+        linefeed_if_not_lf: LinefeedIfNotLf;
+        backspace_if_not_bs: BackspaceIfNotBs;
+        other_non_function_keys: OtherNonFunctionKeys;
+        arrow_key_map: ArrowKeyMap;
+        acs_ulcorner: AcsULcorner;
+        acs_llcorner: AcsLLcorner;
+        acs_urcorner: AcsURcorner;
+        acs_lrcorner: AcsLRcorner;
+        acs_ltee: AcsLTee;
+        acs_rtee: AcsRTee;
+        acs_btee: AcsBTee;
+        acs_ttee: AcsTTee;
+        acs_hline: AcsHLine;
+        acs_vline: AcsVLine;
+        acs_plus: AcsPlus;
+        memory_lock: MemoryLock;
+        memory_unlock: MemoryUnlock;
+        box_chars_1: BoxChars1;
     }
-    pub fn enter_vertical_hl_mode(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::EnterVerticalHlMode)
+
+    /// Strikethrough, from the `smxx` extended capability or SGR 9 when the database lacks it.
+    pub fn enter_crossed_out_mode(&mut self) -> Result<(), CapabilityError> {
+        let start = self.buffer.len();
+        match self.db.raw("smxx") {
+            Some(terminfo::Value::String(seq)) => {
+                terminfo::expand!(&mut self.buffer.bytes, &seq[..])
+                    .map_err(|_| CapabilityError::CapabilityExpansionError)?;
+            }
+            _ => self.buffer.bytes.extend_from_slice(b"\x1B[9m"),
+        }
+        self.buffer.record("enter_crossed_out_mode", start);
+        Ok(())
     }
-    pub fn set_a_attributes(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetAAttributes)
+
+    /// Marks where a shell prompt starts with OSC 133, so terminals can jump between prompts.
+    /// Like the marks below it has no terminfo capability and is sent unconditionally.
+    pub fn mark_prompt_start(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.semantic_mark("mark_prompt_start", "A")
     }
-    pub fn set_pglen_inch(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::SetPglenInch)
+
+    /// Marks the end of the prompt, where the typed command starts.
+    pub fn mark_prompt_end(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.semantic_mark("mark_prompt_end", "B")
     }
-    pub fn termcap_init2(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::TermcapInit2)
+
+    /// Marks where the output of the command starts.
+    pub fn mark_output_start(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.semantic_mark("mark_output_start", "C")
     }
-    pub fn termcap_reset(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::TermcapReset)
+
+    /// Marks the end of the command output, with the exit code of the command if known.
+    pub fn mark_output_end(&mut self, exit_code: Option<i32>) -> Result<(), CapabilityError> {
+        match exit_code {
+            Some(code) => self
+                .buffer
+                .semantic_mark("mark_output_end", &format!("D;{code}")),
+            None => self.buffer.semantic_mark("mark_output_end", "D"),
+        }
     }
-    pub fn linefeed_if_not_lf(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::LinefeedIfNotLf)
+
+    /// Asks for the size of the text area in pixels, answered with
+    /// [`WindowReport::TextAreaPixels`](crate::input::WindowReport::TextAreaPixels).
+    /// Like the other XTWINOPS methods it has no terminfo capability and is sent unconditionally.
+    pub fn request_text_area_pixels(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.window_op("request_text_area_pixels", "14", false)
     }
-    pub fn backspace_if_not_bs(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::BackspaceIfNotBs)
+
+    /// Asks for the size of a cell in pixels, answered with
+    /// [`WindowReport::CellPixels`](crate::input::WindowReport::CellPixels).
+    pub fn request_cell_pixels(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.window_op("request_cell_pixels", "16", false)
     }
-    pub fn other_non_function_keys(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::OtherNonFunctionKeys)
+
+    /// Asks for the size of the text area in cells, answered with
+    /// [`WindowReport::TextAreaChars`](crate::input::WindowReport::TextAreaChars).
+    pub fn request_text_area_chars(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.window_op("request_text_area_chars", "18", false)
     }
-    pub fn arrow_key_map(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::ArrowKeyMap)
+
+    /// Iconifies or restores the window, see [`TerminfoWrapper::allow_window_ops`].
+    pub fn iconify_window(&mut self, iconify: bool) -> Result<(), CapabilityError> {
+        let params = if iconify { "2" } else { "1" };
+        self.buffer.window_op("iconify_window", params, true)
     }
-    pub fn acs_ulcorner(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsULcorner)
+
+    /// Moves the window to `x`, `y` pixels on the screen, see [`TerminfoWrapper::allow_window_ops`].
+    pub fn move_window(&mut self, x: u32, y: u32) -> Result<(), CapabilityError> {
+        self.buffer.window_op("move_window", &format!("3;{x};{y}"), true)
     }
-    pub fn acs_llcorner(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsLLcorner)
+
+    /// Resizes the text area to `rows` x `cols` cells, see [`TerminfoWrapper::allow_window_ops`].
+    pub fn resize_text_area(&mut self, rows: u32, cols: u32) -> Result<(), CapabilityError> {
+        self.buffer.window_op("resize_text_area", &format!("8;{rows};{cols}"), true)
     }
-    pub fn acs_urcorner(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsURcorner)
+
+    /// DECSWL, back to normal size for the row of the cursor.
+    /// Terminfo has no capabilities for line sizes, this and the two below are sent unconditionally.
+    pub fn single_width_line(&mut self) -> Result<(), CapabilityError> {
+        self.line_size("single_width_line", b'5')
     }
-    pub fn acs_lrcorner(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsLRcorner)
+
+    /// DECDWL, every character of the row of the cursor takes two columns.
+    pub fn double_width_line(&mut self) -> Result<(), CapabilityError> {
+        self.line_size("double_width_line", b'6')
     }
-    pub fn acs_ltee(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsLTee)
+
+    /// DECDHL, the row of the cursor shows the `top` or bottom half of double height text.
+    pub fn double_height_line(&mut self, top: bool) -> Result<(), CapabilityError> {
+        self.line_size("double_height_line", if top { b'3' } else { b'4' })
     }
-    pub fn acs_rtee(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsRTee)
+
+    /// Turns on the kitty keyboard protocol with `flags`, remembering the flags set before.
+    /// Terminals without the protocol ignore it, like the pop below.
+    pub fn push_keyboard_enhancement(
+        &mut self,
+        flags: KeyboardFlags,
+    ) -> Result<(), CapabilityError> {
+        let start = self.buffer.len();
+        let sequence = format!("\x1B[>{}u", flags.bits());
+        self.buffer.bytes.extend_from_slice(sequence.as_bytes());
+        self.buffer.record("push_keyboard_enhancement", start);
+        Ok(())
     }
-    pub fn acs_btee(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsBTee)
+
+    /// Goes back to the flags set before the last [`TerminfoWrapper::push_keyboard_enhancement`].
+    pub fn pop_keyboard_enhancement(&mut self) -> Result<(), CapabilityError> {
+        let start = self.buffer.len();
+        self.buffer.bytes.extend_from_slice(b"\x1B[<u");
+        self.buffer.record("pop_keyboard_enhancement", start);
+        Ok(())
     }
-    pub fn acs_ttee(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsTTee)
+
+    /// Has pasted text arrive between markers, reported as
+    /// [`Paste`](crate::input::Paste) instead of keys. From the `BE` extended capability,
+    /// or `CSI ?2004h` when the database lacks it.
+    pub fn enable_bracketed_paste(&mut self) -> Result<(), CapabilityError> {
+        self.extended_or("enable_bracketed_paste", "BE", b"\x1B[?2004h");
+        self.buffer.bracketed_paste = true;
+        Ok(())
     }
-    pub fn acs_hline(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsHLine)
+
+    /// Pasted text arrives as typed again, from `BD` or `CSI ?2004l`.
+    pub fn disable_bracketed_paste(&mut self) -> Result<(), CapabilityError> {
+        self.extended_or("disable_bracketed_paste", "BD", b"\x1B[?2004l");
+        self.buffer.bracketed_paste = false;
+        Ok(())
     }
-    pub fn acs_vline(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsVLine)
+
+    /// Turns off the mouse mode and bracketed paste turned on through the wrapper,
+    /// e. g. while another program has the terminal, see [`run_external`].
+    pub fn suspend_input_modes(&mut self) -> Result<(), CapabilityError> {
+        let mouse = self.buffer.mouse.map(|(mode, _)| mode);
+        let paste = self.buffer.bracketed_paste;
+        self.disable_mouse()?;
+        if paste {
+            self.disable_bracketed_paste()?;
+        }
+        self.buffer.suspended = Some((mouse, paste));
+        Ok(())
     }
-    pub fn acs_plus(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::AcsPlus)
+
+    /// Turns on again what [`TerminfoWrapper::suspend_input_modes`] turned off.
+    pub fn resume_input_modes(&mut self) -> Result<(), CapabilityError> {
+        let Some((mouse, paste)) = self.buffer.suspended.take() else {
+            return Ok(());
+        };
+        if paste {
+            self.enable_bracketed_paste()?;
+        }
+        if let Some(mode) = mouse {
+            self.enable_mouse(mode)?;
+        }
+        Ok(())
     }
-    pub fn memory_lock(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MemoryLock)
+
+    /// The extended capability `cap`, or `fallback` when the database lacks it.
+    fn extended_or(&mut self, name: &'static str, cap: &str, fallback: &[u8]) {
+        let start = self.buffer.len();
+        match self.db.raw(cap) {
+            Some(terminfo::Value::String(seq)) => self.buffer.bytes.extend_from_slice(seq),
+            _ => self.buffer.bytes.extend_from_slice(fallback),
+        }
+        self.buffer.record(name, start);
     }
-    pub fn memory_unlock(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::MemoryUnlock)
+
+    fn line_size(&mut self, name: &'static str, size: u8) -> Result<(), CapabilityError> {
+        let start = self.buffer.len();
+        self.buffer.bytes.extend_from_slice(&[0x1B, b'#', size]);
+        self.buffer.record(name, start);
+        Ok(())
     }
-    pub fn box_chars_1(&mut self) -> Result<(), CapabilityError> {
-        tty_expand_cap!(self.db, &mut self.buffer, cap::BoxChars1)
+
+    /// SGR 53, terminfo has no capability for overline.
+    pub fn enter_overline_mode(&mut self) -> Result<(), CapabilityError> {
+        let start = self.buffer.len();
+        self.buffer.bytes.extend_from_slice(b"\x1B[53m");
+        self.buffer.record("enter_overline_mode", start);
+        Ok(())
     }
 
     pub fn expand_write<C>(&'a mut self) -> Result<(), CapabilityError>
@@ -1419,22 +1403,106 @@ impl<'a> TerminfoWrapper {
         tty_expand_cap!(self.db, &mut self.buffer, cap::SetAttributes; standout, underline, reverse, blink, dim, bold, invisible, protected, alt_charset)
     }
 
+    /// Fit to the color policy like the other color methods, see
+    /// [`TerminfoWrapper::set_color_policy`].
     pub fn set_a_foreground(&mut self, color: u8) -> Result<(), CapabilityError> {
+        let Some(color) = self.buffer.fit_policy(color) else {
+            return Ok(());
+        };
         tty_expand_cap!(self.db, &mut self.buffer, cap::SetAForeground; color)
     }
 
     pub fn set_a_background(&mut self, color: u8) -> Result<(), CapabilityError> {
+        let Some(color) = self.buffer.fit_policy(color) else {
+            return Ok(());
+        };
         tty_expand_cap!(self.db, &mut self.buffer, cap::SetABackground; color)
     }
 
     pub fn set_foreground(&mut self, color: u8) -> Result<(), CapabilityError> {
+        let Some(color) = self.buffer.fit_policy(color) else {
+            return Ok(());
+        };
         tty_expand_cap!(self.db, &mut self.buffer, cap::SetForeground; color)
     }
 
     pub fn set_background(&mut self, color: u8) -> Result<(), CapabilityError> {
+        let Some(color) = self.buffer.fit_policy(color) else {
+            return Ok(());
+        };
         tty_expand_cap!(self.db, &mut self.buffer, cap::SetBackground; color)
     }
 
+    /// Entry `color` of the 256 color palette, through `setaf`. The closest color is used
+    /// where the color policy or the terminal has fewer colors, none when colors are off.
+    pub fn set_foreground_256(&mut self, color: u8) -> Result<(), CapabilityError> {
+        let Some(color) = self.buffer.fit_policy(color) else {
+            return Ok(());
+        };
+        let color = self.fit_indexed(color);
+        self.set_a_foreground(color)
+    }
+
+    /// Like [`TerminfoWrapper::set_foreground_256`], through `setab`.
+    pub fn set_background_256(&mut self, color: u8) -> Result<(), CapabilityError> {
+        let Some(color) = self.buffer.fit_policy(color) else {
+            return Ok(());
+        };
+        let color = self.fit_indexed(color);
+        self.set_a_background(color)
+    }
+
+    /// SGR 38;2 where the color policy allows truecolor and the terminal shows it, see
+    /// [`TerminfoWrapper::truecolor`], the closest palette entry otherwise and nothing
+    /// when colors are off.
+    pub fn set_foreground_rgb(&mut self, r: u8, g: u8, b: u8) -> Result<(), CapabilityError> {
+        self.rgb_color(true, (r, g, b))
+    }
+
+    /// Like [`TerminfoWrapper::set_foreground_rgb`], with SGR 48;2.
+    pub fn set_background_rgb(&mut self, r: u8, g: u8, b: u8) -> Result<(), CapabilityError> {
+        self.rgb_color(false, (r, g, b))
+    }
+
+    fn rgb_color(
+        &mut self,
+        foreground: bool,
+        (r, g, b): (u8, u8, u8),
+    ) -> Result<(), CapabilityError> {
+        let index = match self.buffer.color_policy.downgrade(Color::Rgb(r, g, b)) {
+            None => return Ok(()),
+            Some(Color::Rgb(r, g, b)) if self.truecolor() => {
+                let (name, sgr) = if foreground {
+                    ("set_foreground_rgb", 38)
+                } else {
+                    ("set_background_rgb", 48)
+                };
+                let start = self.buffer.len();
+                let sequence = format!("\x1B[{sgr};2;{r};{g};{b}m");
+                self.buffer.bytes.extend_from_slice(sequence.as_bytes());
+                self.buffer.record(name, start);
+                return Ok(());
+            }
+            Some(Color::Rgb(r, g, b)) => rgb_to_indexed(r, g, b),
+            Some(Color::Indexed(index)) => index,
+        };
+        if foreground {
+            self.set_foreground_256(index)
+        } else {
+            self.set_background_256(index)
+        }
+    }
+
+    /// Closest palette entry within `max_colors`.
+    fn fit_indexed(&self, color: u8) -> u8 {
+        match self.db.get::<cap::MaxColors>().map(i32::from) {
+            Some(n) if n >= 256 => color,
+            Some(n) if n >= 16 => indexed_to_basic(color),
+            // Only the normal variants of the basic colors
+            _ => indexed_to_basic(color) % 8,
+        }
+    }
+
     // Some caps are still missing
 
     pub fn expand<C>(&'a mut self) -> Result<terminfo::Value, CapabilityError>
@@ -1449,9 +1517,104 @@ impl<'a> TerminfoWrapper {
     }
 }
 
+#[cfg(feature = "terminfo")]
+impl Capabilities for TerminfoWrapper {
+    fn buffer(&self) -> &[u8] {
+        TerminfoWrapper::buffer(self)
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        TerminfoWrapper::append(self, bytes)
+    }
+
+    fn append_host(&mut self, bytes: &[u8]) {
+        TerminfoWrapper::append_host(self, bytes)
+    }
+
+    fn clear(&mut self) {
+        TerminfoWrapper::clear(self)
+    }
+
+    fn flush_to(&mut self, to: &mut impl std::io::Write) -> std::io::Result<()> {
+        TerminfoWrapper::flush_to(self, to)
+    }
+
+    fn get_parser(&self) -> InputParser {
+        TerminfoWrapper::get_parser(self)
+    }
+
+    fn suspend_input_modes(&mut self) -> Result<(), CapabilityError> {
+        TerminfoWrapper::suspend_input_modes(self)
+    }
+
+    fn resume_input_modes(&mut self) -> Result<(), CapabilityError> {
+        TerminfoWrapper::resume_input_modes(self)
+    }
+
+    delegate_capabilities! {
+        move_cursor(row: usize, col: usize);
+        bell();
+        carriage_return();
+        clear_screen();
+        clr_eol();
+        clr_eos();
+        clr_bol();
+        cursor_home();
+        cursor_up();
+        cursor_down();
+        cursor_left();
+        cursor_right();
+        parm_up_cursor(count: u32);
+        parm_down_cursor(count: u32);
+        parm_left_cursor(count: u32);
+        parm_right_cursor(count: u32);
+        cursor_invisible();
+        cursor_normal();
+        cursor_visible();
+        scroll_forward();
+        scroll_reverse();
+        change_scroll_region(top: u32, bottom: u32);
+        enter_ca_mode();
+        exit_ca_mode();
+        keypad_xmit();
+        keypad_local();
+        exit_attribute_mode();
+        enter_bold_mode();
+        enter_dim_mode();
+        enter_italics_mode();
+        exit_italics_mode();
+        enter_underline_mode();
+        exit_underline_mode();
+        enter_blink_mode();
+        enter_reverse_mode();
+        enter_secure_mode();
+        enter_crossed_out_mode();
+        enter_overline_mode();
+        enter_standout_mode();
+        exit_standout_mode();
+        orig_pair();
+        set_a_foreground(color: u8);
+        set_a_background(color: u8);
+        mark_prompt_start();
+        mark_prompt_end();
+        mark_output_start();
+        mark_output_end(exit_code: Option<i32>);
+        request_text_area_pixels();
+        request_cell_pixels();
+        request_text_area_chars();
+        iconify_window(iconify: bool);
+        move_window(x: u32, y: u32);
+        resize_text_area(rows: u32, cols: u32);
+        single_width_line();
+        double_width_line();
+        double_height_line(top: bool);
+    }
+}
+
+#[cfg(feature = "terminfo")]
 impl std::io::Write for TerminfoWrapper {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.buffer.write(buf)
+        self.buffer.write_text(buf)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -1459,20 +1622,161 @@ impl std::io::Write for TerminfoWrapper {
     }
 }
 
+#[cfg(feature = "terminfo")]
 impl From<terminfo::Database> for TerminfoWrapper {
     fn from(value: terminfo::Database) -> Self {
         Self {
             db: value,
-            buffer: Vec::new(),
+            buffer: OutputBuffer::default(),
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "terminfo"))]
 mod tests {
     use super::*;
     use terminfo::Database;
 
+    #[test]
+    fn from_snapshot() {
+        use crate::env::EnvSnapshot;
+
+        let dir = std::env::temp_dir().join(format!("nixtui-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("x")).unwrap();
+        std::fs::copy("assets/test_kitty_database", dir.join("x/xterm-kitty")).unwrap();
+        let env = EnvSnapshot::default().terminfo(&dir);
+        let env = env.term("xterm-kitty");
+        let found = TerminfoWrapper::from_snapshot(&env);
+        let no_color = TerminfoWrapper::from_snapshot(&env.clone().no_color("1"));
+        let latin1 = TerminfoWrapper::from_snapshot(&env.clone().lang("de_DE.ISO-8859-1"));
+        let missing = TerminfoWrapper::from_snapshot(&env.term("no-such-terminal"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut found = found.unwrap();
+        assert_eq!(found.db.name(), "xterm-kitty");
+        found.set_a_foreground(1).unwrap();
+        assert_eq!(found.buffer(), b"\x1B[31m");
+        // NO_COLOR reaches every color method
+        assert_eq!(found.output_encoding(), OutputEncoding::Utf8);
+        assert_eq!(latin1.unwrap().output_encoding(), OutputEncoding::Latin1);
+        let mut no_color = no_color.unwrap();
+        assert_eq!(no_color.color_policy().level, crate::color::ColorLevel::Off);
+        no_color.set_a_foreground(1).unwrap();
+        no_color.set_background(2).unwrap();
+        assert!(no_color.buffer().is_empty());
+        assert!(matches!(
+            missing,
+            Err(errors::TerminfoCreationError::TerminfoDbNotFound)
+        ));
+    }
+
+    const FIXTURES: &[&str] = &[
+        "assets/test_kitty_database",
+        "assets/test_xterm_256color_database",
+        "assets/test_tmux_256color_database",
+        "assets/test_linux_database",
+        "assets/test_vt100_database",
+    ];
+
+    /// Checks what `method` did against the capability `name` of the database at `path`:
+    /// the bytes `expand` makes of it when it's there, the precise error otherwise.
+    fn check_capability(
+        path: &str,
+        name: &str,
+        method: fn(&mut TerminfoWrapper) -> Result<(), CapabilityError>,
+        expand: fn(&[u8]) -> terminfo::Result<Vec<u8>>,
+    ) {
+        let db = Database::from_path(path).unwrap();
+        let expected = match db.raw(name) {
+            // Describes the reply to a query, the terminfo crate doesn't finish expanding `%[`
+            Some(terminfo::Value::String(raw)) if raw.windows(2).any(|w| w == b"%[") => return,
+            Some(terminfo::Value::String(raw)) => Some(expand(raw)),
+            _ => None,
+        };
+        let mut wrapper = TerminfoWrapper::from(db);
+        let result = method(&mut wrapper);
+        match expected {
+            Some(Ok(bytes)) => {
+                assert!(result.is_ok(), "{path}: {name} failed with {result:?}");
+                assert_eq!(
+                    wrapper.buffer().escape_ascii().to_string(),
+                    bytes.escape_ascii().to_string(),
+                    "{path}: {name}"
+                );
+            }
+            Some(Err(_)) => assert!(
+                matches!(result, Err(CapabilityError::CapabilityExpansionError)),
+                "{path}: {name} gave {result:?}"
+            ),
+            None => assert!(
+                matches!(
+                    &result,
+                    Err(CapabilityError::CapabilityNotFound { cap_name }) if cap_name == name
+                ),
+                "{path}: {name} gave {result:?}"
+            ),
+        }
+    }
+
+    #[test]
+    fn simple_capabilities_match_fixtures() {
+        for path in FIXTURES {
+            for &(name, method) in TerminfoWrapper::SIMPLE_CAPABILITIES {
+                check_capability(path, name, method, |raw| terminfo::expand!(raw));
+            }
+        }
+    }
+
+    #[test]
+    fn parameterized_capabilities_match_fixtures() {
+        // Capability, then the method and representative arguments, which are
+        // also the parameters the capability is expanded with
+        macro_rules! cases {
+            ($($name:literal: $method:ident($($arg:expr),*);)+) => {
+                [$((
+                    $name,
+                    (|wrapper: &mut TerminfoWrapper| wrapper.$method($($arg),*))
+                        as fn(&mut TerminfoWrapper) -> Result<(), CapabilityError>,
+                    (|raw: &[u8]| terminfo::expand!(raw; $($arg as i32),*))
+                        as fn(&[u8]) -> terminfo::Result<Vec<u8>>,
+                ),)+]
+            };
+        }
+        let cases = cases! {
+            "cursor_address": move_cursor(3, 7);
+            "cursor_address": cursor_address(12, 0);
+            "change_scroll_region": change_scroll_region(1, 10);
+            "column_address": column_address(5);
+            "row_address": row_address(5);
+            "erase_chars": erase_chars(4);
+            "parm_dch": parm_dch(4);
+            "parm_delete_line": parm_delete_line(4);
+            "parm_down_cursor": parm_down_cursor(4);
+            "parm_ich": parm_ich(4);
+            "parm_index": parm_index(4);
+            "parm_insert_line": parm_insert_line(4);
+            "parm_left_cursor": parm_left_cursor(4);
+            "parm_right_cursor": parm_right_cursor(4);
+            "parm_rindex": parm_rindex(4);
+            "parm_up_cursor": parm_up_cursor(4);
+            "parm_down_micro": parm_down_micro(4);
+            "parm_left_micro": parm_left_micro(4);
+            "parm_right_micro": parm_right_micro(4);
+            "parm_up_micro": parm_up_micro(4);
+            "set_attributes":
+                set_attributes(true, false, true, false, false, true, false, false, true);
+            "set_a_foreground": set_a_foreground(5);
+            "set_a_background": set_a_background(200);
+            "set_foreground": set_foreground(5);
+            "set_background": set_background(3);
+        };
+        for path in FIXTURES {
+            for (name, method, expand) in cases {
+                check_capability(path, name, method, expand);
+            }
+        }
+    }
+
     #[test]
     fn test() {
         let mut db =
@@ -1492,4 +1796,306 @@ mod tests {
             &*bytes
         );
     }
+
+    #[test]
+    fn extra_attributes() {
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.record_capabilities(true);
+        db.enter_crossed_out_mode().unwrap();
+        db.enter_overline_mode().unwrap();
+        let names: Vec<_> = db.records().iter().map(|r| r.name).collect();
+        assert_eq!(names, ["enter_crossed_out_mode", "enter_overline_mode"]);
+        let mut bytes = Vec::new();
+        db.flush_to(&mut bytes).unwrap();
+        assert_eq!(b"\x1B[9m\x1B[53m", &*bytes);
+
+        // Expanded like the other capabilities
+        let mut builder = Database::new();
+        builder.name("strike").raw("smxx", "\x1B[9m%%");
+        let mut db = TerminfoWrapper::from(builder.build().unwrap());
+        db.enter_crossed_out_mode().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[9m%");
+    }
+
+    #[test]
+    fn semantic_marks() {
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.mark_prompt_start().unwrap();
+        db.append(b"$ ");
+        db.mark_prompt_end().unwrap();
+        db.append(b"ls\r\n");
+        db.mark_output_start().unwrap();
+        db.mark_output_end(Some(2)).unwrap();
+        db.mark_output_end(None).unwrap();
+        let mut bytes = Vec::new();
+        db.flush_to(&mut bytes).unwrap();
+        assert_eq!(
+            b"\x1B]133;A\x1B\\$ \x1B]133;B\x1B\\ls\r\n\
+            \x1B]133;C\x1B\\\x1B]133;D;2\x1B\\\x1B]133;D\x1B\\",
+            &*bytes
+        );
+    }
+
+    #[test]
+    fn keyboard_enhancement() {
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        let flags = KeyboardFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardFlags::REPORT_EVENT_TYPES;
+        db.push_keyboard_enhancement(flags).unwrap();
+        db.push_keyboard_enhancement(KeyboardFlags::all()).unwrap();
+        db.pop_keyboard_enhancement().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[>3u\x1B[>31u\x1B[<u");
+    }
+
+    #[test]
+    fn rgb_colors() {
+        // kitty's database has the Tc flag
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        assert!(db.truecolor());
+        db.set_foreground_rgb(255, 135, 0).unwrap();
+        db.set_background_rgb(1, 2, 3).unwrap();
+        db.set_foreground_256(208).unwrap();
+        assert_eq!(db.buffer(), b"\x1B[38;2;255;135;0m\x1B[48;2;1;2;3m\x1B[38;5;208m");
+
+        // Downconverted without truecolor
+        let mut db = TerminfoWrapper::from(
+            Database::from_path("assets/test_xterm_256color_database").unwrap(),
+        );
+        assert!(!db.truecolor());
+        db.set_foreground_rgb(255, 135, 0).unwrap();
+        db.set_background_rgb(128, 128, 128).unwrap();
+        assert_eq!(db.buffer(), b"\x1B[38;5;208m\x1B[48;5;244m");
+        db.clear();
+        db.set_truecolor(Some(true));
+        db.set_foreground_rgb(255, 135, 0).unwrap();
+        assert_eq!(db.buffer(), b"\x1B[38;2;255;135;0m");
+
+        // The linux console has 8 colors
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_linux_database").unwrap());
+        db.set_foreground_rgb(255, 0, 0).unwrap();
+        db.set_background_256(12).unwrap();
+        assert_eq!(db.buffer(), b"\x1B[31m\x1B[44m");
+    }
+
+    #[test]
+    fn rgb_and_256_colors_follow_policy() {
+        use crate::color::{ColorLevel, ColorPolicy};
+
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        let mut expand = |level| {
+            db.clear();
+            db.set_color_policy(ColorPolicy::new(level));
+            db.set_foreground_rgb(255, 135, 0).unwrap();
+            db.set_background_256(196).unwrap();
+            db.buffer().to_vec()
+        };
+        assert_eq!(expand(ColorLevel::Truecolor), b"\x1B[38;2;255;135;0m\x1B[48;5;196m");
+        assert_eq!(expand(ColorLevel::Indexed256), b"\x1B[38;5;208m\x1B[48;5;196m");
+        assert_eq!(expand(ColorLevel::Basic16), b"\x1B[33m\x1B[101m");
+        // NO_COLOR
+        assert_eq!(expand(ColorLevel::Off), b"");
+    }
+
+    #[test]
+    fn bracketed_paste() {
+        // kitty's database lacks BE and BD
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.enable_bracketed_paste().unwrap();
+        db.disable_bracketed_paste().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[?2004h\x1B[?2004l");
+        let mut db = TerminfoWrapper::from(
+            Database::from_path("assets/test_xterm_256color_database").unwrap(),
+        );
+        db.enable_bracketed_paste().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[?2004h");
+    }
+
+    #[test]
+    fn mouse_modes() {
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.disable_mouse().unwrap();
+        db.enable_mouse(MouseMode::Drag).unwrap();
+        assert_eq!(db.buffer(), b"\x1B[?1002;1006h");
+        db.clear();
+        // Switching modes turns the old one off
+        db.enable_mouse(MouseMode::AnyMotion).unwrap();
+        assert_eq!(db.buffer(), b"\x1B[?1002;1006l\x1B[?1003;1006h");
+        db.clear();
+        db.disable_mouse().unwrap();
+        db.disable_mouse().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[?1003;1006l");
+
+        // xterm describes clicks with XM
+        let mut db = TerminfoWrapper::from(
+            Database::from_path("assets/test_xterm_256color_database").unwrap(),
+        );
+        db.enable_mouse(MouseMode::Clicks).unwrap();
+        db.disable_mouse().unwrap();
+        db.enable_mouse(MouseMode::Drag).unwrap();
+        db.disable_mouse().unwrap();
+        assert_eq!(
+            db.buffer(),
+            b"\x1B[?1006;1000h\x1B[?1006;1000l\x1B[?1002;1006h\x1B[?1002;1006l"
+        );
+    }
+
+    #[test]
+    fn suspend_input_modes() {
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.resume_input_modes().unwrap();
+        db.suspend_input_modes().unwrap();
+        assert!(db.buffer().is_empty());
+        db.enable_bracketed_paste().unwrap();
+        db.enable_mouse(MouseMode::Drag).unwrap();
+        db.clear();
+        db.suspend_input_modes().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[?1002;1006l\x1B[?2004l");
+        db.clear();
+        db.resume_input_modes().unwrap();
+        db.resume_input_modes().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[?2004h\x1B[?1002;1006h");
+    }
+
+    #[test]
+    fn window_ops() {
+        use crate::input::WindowReport;
+
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        let parser = db.get_parser();
+        let queries: [(fn(&mut TerminfoWrapper) -> _, _, _); 3] = [
+            (
+                TerminfoWrapper::request_text_area_pixels,
+                &b"\x1B[14t"[..],
+                (
+                    &b"\x1B[4;600;800t"[..],
+                    WindowReport::TextAreaPixels {
+                        width: 800,
+                        height: 600,
+                    },
+                ),
+            ),
+            (
+                TerminfoWrapper::request_cell_pixels,
+                b"\x1B[16t",
+                (
+                    b"\x1B[6;20;10t",
+                    WindowReport::CellPixels {
+                        width: 10,
+                        height: 20,
+                    },
+                ),
+            ),
+            (
+                TerminfoWrapper::request_text_area_chars,
+                b"\x1B[18t",
+                (
+                    b"\x1B[8;30;80t",
+                    WindowReport::TextAreaChars { cols: 80, rows: 30 },
+                ),
+            ),
+        ];
+        for (query, sequence, (reply, report)) in queries {
+            query(&mut db).unwrap();
+            assert_eq!(db.buffer(), sequence);
+            db.clear();
+            assert_eq!(parser.parse(reply).window_reports(), [report]);
+        }
+
+        let error = db.resize_text_area(24, 80).unwrap_err();
+        assert!(matches!(error, CapabilityError::WindowOpsDisabled { .. }));
+        assert!(db.buffer().is_empty());
+        db.allow_window_ops(true);
+        db.resize_text_area(24, 80).unwrap();
+        db.move_window(5, 10).unwrap();
+        db.iconify_window(true).unwrap();
+        db.iconify_window(false).unwrap();
+        assert_eq!(db.buffer(), b"\x1B[8;24;80t\x1B[3;5;10t\x1B[2t\x1B[1t");
+    }
+
+    #[test]
+    fn custom_sequences() {
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.record_capabilities(true);
+        let template = r"\e_Ga=d,i=%d\e\\".parse().unwrap();
+        db.register_custom_sequence("delete_image", template);
+        db.move_cursor(0, 0).unwrap();
+        db.emit_custom("delete_image", &[7.into()]).unwrap();
+        db.bell().unwrap();
+        assert_eq!(db.buffer(), b"\x1B[1;1H\x1B_Ga=d,i=7\x1B\\\x07");
+        assert_eq!(db.records()[1].name, "custom_sequence");
+        assert_eq!(db.records()[1].range, 6..18);
+
+        let error = db.emit_custom("delete_images", &[]).unwrap_err();
+        assert!(matches!(
+            error,
+            CapabilityError::UnknownCustomSequence { name } if name == "delete_images"
+        ));
+        let error = db.emit_custom("delete_image", &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Custom sequence `delete_image` takes 1 parameters, got 0."
+        );
+        let error = db.emit_custom("delete_image", &["7".into()]).unwrap_err();
+        assert!(matches!(
+            error,
+            CapabilityError::CustomSequenceParam { index: 1, .. }
+        ));
+        assert_eq!(db.buffer().len(), 19);
+    }
+
+    #[test]
+    fn text_is_transcoded() {
+        use std::io::Write;
+
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.set_output_encoding(OutputEncoding::Latin1);
+        db.move_cursor(0, 0).unwrap();
+        write!(db, "é ├─ ▾").unwrap();
+        // Sequences are left alone, even when they carry UTF-8
+        db.append_host("\x1B]2;é\x07".as_bytes());
+        let mut bytes = Vec::new();
+        db.flush_to(&mut bytes).unwrap();
+        assert_eq!(
+            [b"\x1B[1;1H\xE9 +- v", "\x1B]2;é\x07".as_bytes()].concat(),
+            bytes
+        );
+
+        // A character cut between two writes is completed by the second
+        let text = "ü─".as_bytes();
+        for split in 0..text.len() {
+            db.write_all(&text[..split]).unwrap();
+            db.write_all(&text[split..]).unwrap();
+            assert_eq!(db.buffer(), b"\xFC-", "split at {split}");
+            db.clear();
+        }
+    }
+
+    #[test]
+    fn host_sequences_are_wrapped() {
+        let mut db =
+            TerminfoWrapper::from(Database::from_path("assets/test_kitty_database").unwrap());
+        db.append_host(b"\x1B]52;c;aGk=\x07");
+        db.set_multiplexer(Some(Multiplexer::Tmux));
+        db.bell().unwrap();
+        db.append_host(b"\x1B]52;c;aGk=\x07");
+        let mut bytes = Vec::new();
+        db.flush_to(&mut bytes).unwrap();
+        assert_eq!(
+            b"\x1B]52;c;aGk=\x07\
+            \x07\
+            \x1BPtmux;\x1B\x1B]52;c;aGk=\x07\x1B\\",
+            &*bytes
+        );
+    }
 }
@@ -0,0 +1,463 @@
+use std::io::Write;
+
+use super::errors::{CapabilityError, TerminfoCreationError};
+use super::{
+    Capabilities, CapabilityRecord, FrameStats, Multiplexer, OutputBuffer, OutputEncoding, Param,
+    SequenceTemplate,
+};
+use crate::color::ColorPolicy;
+use crate::env::EnvSnapshot;
+use crate::input::InputParser;
+
+/// Writes a fixed xterm compatible sequence, recording it like a terminfo capability.
+macro_rules! ansi_cap {
+    ($to:expr, $name:literal, $($arg:tt)+) => {{
+        let start = $to.len();
+        ::std::write!($to, $($arg)+)?;
+        $to.record($name, start);
+        Ok(())
+    }};
+}
+
+/// Same capability methods as [`TerminfoWrapper`](super::TerminfoWrapper),
+/// but with hardcoded ECMA-48/xterm sequences instead of a terminfo lookup.
+///
+/// Meant for builds that only target modern terminals, enabled by the `ansi` feature.
+#[derive(Default)]
+pub struct AnsiWrapper {
+    buffer: OutputBuffer,
+}
+
+impl AnsiWrapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never fails, exists so the same code works with [`Sequences`](super::Sequences)
+    /// regardless of the backend. The color policy is detected from the environment
+    /// for a terminal with 256 colors, the output encoding from the locale.
+    pub fn from_env() -> Result<Self, TerminfoCreationError> {
+        Self::from_snapshot(&EnvSnapshot::capture())
+    }
+
+    /// Never fails, like [`AnsiWrapper::from_env`].
+    pub fn from_snapshot(env: &EnvSnapshot) -> Result<Self, TerminfoCreationError> {
+        let mut wrapper = Self::new();
+        wrapper.set_color_policy(ColorPolicy::detect(env, Some(256)));
+        wrapper.set_output_encoding(OutputEncoding::detect_or_ascii(env));
+        Ok(wrapper)
+    }
+
+    /// See [`TerminfoWrapper::record_capabilities`](super::TerminfoWrapper::record_capabilities).
+    pub fn record_capabilities(&mut self, enable: bool) {
+        self.buffer.records = enable.then(Vec::new);
+    }
+
+    pub fn records(&self) -> &[CapabilityRecord] {
+        self.buffer.records.as_deref().unwrap_or_default()
+    }
+
+    /// See [`TerminfoWrapper::profile_output`](super::TerminfoWrapper::profile_output).
+    pub fn profile_output(&mut self, enable: bool) {
+        self.buffer.profile(enable);
+    }
+
+    pub fn frame_stats(&self) -> Option<&FrameStats> {
+        self.buffer.frame_stats()
+    }
+
+    pub fn cumulative_stats(&self) -> Option<&FrameStats> {
+        self.buffer.cumulative_stats()
+    }
+
+    /// See [`TerminfoWrapper::set_multiplexer`](super::TerminfoWrapper::set_multiplexer).
+    pub fn set_multiplexer(&mut self, multiplexer: Option<Multiplexer>) {
+        self.buffer.multiplexer = multiplexer;
+    }
+
+    pub fn multiplexer(&self) -> Option<Multiplexer> {
+        self.buffer.multiplexer
+    }
+
+    /// See [`TerminfoWrapper::set_output_encoding`](super::TerminfoWrapper::set_output_encoding).
+    pub fn set_output_encoding(&mut self, encoding: OutputEncoding) {
+        self.buffer.encoding = encoding;
+    }
+
+    pub fn output_encoding(&self) -> OutputEncoding {
+        self.buffer.encoding
+    }
+
+    /// See [`TerminfoWrapper::set_color_policy`](super::TerminfoWrapper::set_color_policy).
+    pub fn set_color_policy(&mut self, policy: ColorPolicy) {
+        self.buffer.color_policy = policy;
+    }
+
+    pub fn color_policy(&self) -> ColorPolicy {
+        self.buffer.color_policy
+    }
+
+    /// See [`TerminfoWrapper::allow_window_ops`](super::TerminfoWrapper::allow_window_ops).
+    pub fn allow_window_ops(&mut self, allow: bool) {
+        self.buffer.window_ops = allow;
+    }
+
+    /// See [`TerminfoWrapper::register_custom_sequence`](super::TerminfoWrapper::register_custom_sequence).
+    pub fn register_custom_sequence(&mut self, name: &str, template: SequenceTemplate) {
+        self.buffer.custom.insert(name.into(), template);
+    }
+
+    /// See [`TerminfoWrapper::emit_custom`](super::TerminfoWrapper::emit_custom).
+    pub fn emit_custom(&mut self, name: &str, params: &[Param]) -> Result<(), CapabilityError> {
+        self.buffer.emit_custom(name, params)
+    }
+}
+
+fn set_color(
+    to: &mut OutputBuffer,
+    name: &'static str,
+    base: u8,
+    color: u8,
+) -> std::io::Result<()> {
+    let Some(color) = to.fit_policy(color) else {
+        return Ok(());
+    };
+    let start = to.len();
+    match color {
+        0..8 => write!(to, "\x1B[{}m", base + color)?,
+        8..16 => write!(to, "\x1B[{}m", base + 60 + color - 8)?,
+        _ => write!(to, "\x1B[{};5;{color}m", base + 8)?,
+    }
+    to.record(name, start);
+    Ok(())
+}
+
+impl Capabilities for AnsiWrapper {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer.bytes
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        self.buffer.bytes.extend_from_slice(bytes);
+    }
+
+    fn append_host(&mut self, bytes: &[u8]) {
+        self.buffer.append_host(bytes);
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    fn flush_to(&mut self, to: &mut impl std::io::Write) -> std::io::Result<()> {
+        crate::logging::debug_event!(bytes = self.buffer.len(), "flush");
+        self.buffer.end_frame();
+        to.write_all(&self.buffer.bytes)?;
+        self.clear();
+        Ok(())
+    }
+
+    fn get_parser(&self) -> InputParser {
+        let mut parser = InputParser::new();
+        parser.push_default();
+        parser
+    }
+
+    fn move_cursor(&mut self, row: usize, col: usize) -> Result<(), CapabilityError> {
+        ansi_cap!(
+            self.buffer,
+            "cursor_address",
+            "\x1B[{};{}H",
+            row + 1,
+            col + 1
+        )
+    }
+    fn bell(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "bell", "\x07")
+    }
+    fn carriage_return(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "carriage_return", "\r")
+    }
+    fn clear_screen(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "clear_screen", "\x1B[H\x1B[2J")
+    }
+    fn clr_eol(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "clr_eol", "\x1B[K")
+    }
+    fn clr_eos(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "clr_eos", "\x1B[J")
+    }
+    fn clr_bol(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "clr_bol", "\x1B[1K")
+    }
+    fn cursor_home(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_home", "\x1B[H")
+    }
+    fn cursor_up(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_up", "\x1B[A")
+    }
+    fn cursor_down(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_down", "\n")
+    }
+    fn cursor_left(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_left", "\x08")
+    }
+    fn cursor_right(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_right", "\x1B[C")
+    }
+    fn parm_up_cursor(&mut self, count: u32) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "parm_up_cursor", "\x1B[{count}A")
+    }
+    fn parm_down_cursor(&mut self, count: u32) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "parm_down_cursor", "\x1B[{count}B")
+    }
+    fn parm_left_cursor(&mut self, count: u32) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "parm_left_cursor", "\x1B[{count}D")
+    }
+    fn parm_right_cursor(&mut self, count: u32) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "parm_right_cursor", "\x1B[{count}C")
+    }
+    fn cursor_invisible(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_invisible", "\x1B[?25l")
+    }
+    fn cursor_normal(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_normal", "\x1B[?12l\x1B[?25h")
+    }
+    fn cursor_visible(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "cursor_visible", "\x1B[?12;25h")
+    }
+    fn scroll_forward(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "scroll_forward", "\n")
+    }
+    fn scroll_reverse(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "scroll_reverse", "\x1BM")
+    }
+    fn change_scroll_region(&mut self, top: u32, bottom: u32) -> Result<(), CapabilityError> {
+        ansi_cap!(
+            self.buffer,
+            "change_scroll_region",
+            "\x1B[{};{}r",
+            top + 1,
+            bottom + 1
+        )
+    }
+    fn enter_ca_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_ca_mode", "\x1B[?1049h")
+    }
+    fn exit_ca_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "exit_ca_mode", "\x1B[?1049l")
+    }
+    fn keypad_xmit(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "keypad_xmit", "\x1B[?1h\x1B=")
+    }
+    fn keypad_local(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "keypad_local", "\x1B[?1l\x1B>")
+    }
+    fn exit_attribute_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "exit_attribute_mode", "\x1B(B\x1B[m")
+    }
+    fn enter_bold_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_bold_mode", "\x1B[1m")
+    }
+    fn enter_dim_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_dim_mode", "\x1B[2m")
+    }
+    fn enter_italics_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_italics_mode", "\x1B[3m")
+    }
+    fn exit_italics_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "exit_italics_mode", "\x1B[23m")
+    }
+    fn enter_underline_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_underline_mode", "\x1B[4m")
+    }
+    fn exit_underline_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "exit_underline_mode", "\x1B[24m")
+    }
+    fn enter_blink_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_blink_mode", "\x1B[5m")
+    }
+    fn enter_reverse_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_reverse_mode", "\x1B[7m")
+    }
+    fn enter_secure_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_secure_mode", "\x1B[8m")
+    }
+
+    fn enter_crossed_out_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_crossed_out_mode", "\x1B[9m")
+    }
+
+    fn enter_overline_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_overline_mode", "\x1B[53m")
+    }
+
+    fn mark_prompt_start(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.semantic_mark("mark_prompt_start", "A")
+    }
+
+    fn mark_prompt_end(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.semantic_mark("mark_prompt_end", "B")
+    }
+
+    fn mark_output_start(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.semantic_mark("mark_output_start", "C")
+    }
+
+    fn mark_output_end(&mut self, exit_code: Option<i32>) -> Result<(), CapabilityError> {
+        match exit_code {
+            Some(code) => self
+                .buffer
+                .semantic_mark("mark_output_end", &format!("D;{code}")),
+            None => self.buffer.semantic_mark("mark_output_end", "D"),
+        }
+    }
+
+    fn request_text_area_pixels(&mut self) -> Result<(), CapabilityError> {
+        self.buffer
+            .window_op("request_text_area_pixels", "14", false)
+    }
+
+    fn request_cell_pixels(&mut self) -> Result<(), CapabilityError> {
+        self.buffer.window_op("request_cell_pixels", "16", false)
+    }
+
+    fn request_text_area_chars(&mut self) -> Result<(), CapabilityError> {
+        self.buffer
+            .window_op("request_text_area_chars", "18", false)
+    }
+
+    fn iconify_window(&mut self, iconify: bool) -> Result<(), CapabilityError> {
+        let params = if iconify { "2" } else { "1" };
+        self.buffer.window_op("iconify_window", params, true)
+    }
+
+    fn move_window(&mut self, x: u32, y: u32) -> Result<(), CapabilityError> {
+        self.buffer
+            .window_op("move_window", &format!("3;{x};{y}"), true)
+    }
+
+    fn resize_text_area(&mut self, rows: u32, cols: u32) -> Result<(), CapabilityError> {
+        self.buffer
+            .window_op("resize_text_area", &format!("8;{rows};{cols}"), true)
+    }
+
+    fn single_width_line(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "single_width_line", "\x1B#5")
+    }
+
+    fn double_width_line(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "double_width_line", "\x1B#6")
+    }
+
+    fn double_height_line(&mut self, top: bool) -> Result<(), CapabilityError> {
+        if top {
+            ansi_cap!(self.buffer, "double_height_line", "\x1B#3")
+        } else {
+            ansi_cap!(self.buffer, "double_height_line", "\x1B#4")
+        }
+    }
+    fn enter_standout_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "enter_standout_mode", "\x1B[7m")
+    }
+    fn exit_standout_mode(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "exit_standout_mode", "\x1B[27m")
+    }
+    fn orig_pair(&mut self) -> Result<(), CapabilityError> {
+        ansi_cap!(self.buffer, "orig_pair", "\x1B[39;49m")
+    }
+    fn set_a_foreground(&mut self, color: u8) -> Result<(), CapabilityError> {
+        Ok(set_color(&mut self.buffer, "set_a_foreground", 30, color)?)
+    }
+    fn set_a_background(&mut self, color: u8) -> Result<(), CapabilityError> {
+        Ok(set_color(&mut self.buffer, "set_a_background", 40, color)?)
+    }
+}
+
+impl std::io::Write for AnsiWrapper {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.write_text(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.buffer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        let mut ansi = AnsiWrapper::new();
+        ansi.set_a_foreground(1).unwrap();
+        ansi.set_a_foreground(9).unwrap();
+        ansi.set_a_foreground(200).unwrap();
+        ansi.set_a_background(4).unwrap();
+        ansi.set_a_background(12).unwrap();
+        ansi.set_a_background(16).unwrap();
+        assert_eq!(
+            ansi.buffer(),
+            b"\x1B[31m\x1B[91m\x1B[38;5;200m\x1B[44m\x1B[104m\x1B[48;5;16m"
+        );
+    }
+
+    #[test]
+    fn records_like_terminfo() {
+        let mut ansi = AnsiWrapper::new();
+        ansi.record_capabilities(true);
+        ansi.move_cursor(1, 2).unwrap();
+        ansi.set_a_foreground(3).unwrap();
+        assert_eq!(
+            ansi.records(),
+            [
+                CapabilityRecord {
+                    name: "cursor_address",
+                    range: 0..6
+                },
+                CapabilityRecord {
+                    name: "set_a_foreground",
+                    range: 6..11
+                },
+            ]
+        );
+    }
+
+    // Sequences that have to match the xterm-kitty entry byte for byte
+    #[cfg(feature = "terminfo")]
+    #[test]
+    fn matches_kitty_terminfo() {
+        use crate::testing::KITTY_DATABASE;
+        use crate::tty::TerminfoWrapper;
+
+        let mut ansi = AnsiWrapper::new();
+        let mut terminfo =
+            TerminfoWrapper::from(terminfo::Database::from_buffer(KITTY_DATABASE).unwrap());
+        fn script(caps: &mut impl Capabilities) {
+            caps.move_cursor(4, 7).unwrap();
+            caps.clear_screen().unwrap();
+            caps.clr_eol().unwrap();
+            caps.clr_eos().unwrap();
+            caps.clr_bol().unwrap();
+            caps.cursor_invisible().unwrap();
+            caps.enter_ca_mode().unwrap();
+            caps.exit_ca_mode().unwrap();
+            caps.exit_attribute_mode().unwrap();
+            caps.enter_bold_mode().unwrap();
+            caps.enter_reverse_mode().unwrap();
+            caps.enter_italics_mode().unwrap();
+            caps.set_a_foreground(5).unwrap();
+            caps.set_a_foreground(13).unwrap();
+            caps.set_a_background(100).unwrap();
+            caps.parm_right_cursor(3).unwrap();
+            caps.change_scroll_region(0, 10).unwrap();
+        }
+        script(&mut ansi);
+        script(&mut terminfo);
+        assert_eq!(
+            ansi.buffer().escape_ascii().to_string(),
+            terminfo.buffer().escape_ascii().to_string()
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::os::fd::{AsFd, AsRawFd};
+use std::sync::Mutex;
+
+use nix::libc;
+
+use super::errors::TtyError;
+
+/// Device number of every terminal claimed in the process.
+static CLAIMED: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+fn claimed() -> std::sync::MutexGuard<'static, Vec<u64>> {
+    // The list stays consistent even if a thread panicked while holding the lock
+    CLAIMED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Ownership of a terminal device taken with [`claim_terminal`], released on drop.
+#[derive(Debug)]
+pub struct TerminalClaim {
+    id: u64,
+}
+
+impl Drop for TerminalClaim {
+    fn drop(&mut self) {
+        claimed().retain(|&id| id != self.id);
+    }
+}
+
+/// Claims the terminal `tty` refers to for the rest of the process, until the claim is dropped.
+///
+/// Two parts of a program that both set termios and restore it on exit undo each other,
+/// e. g. when a library sets up the terminal the application already set up.
+/// Claiming it before changing termios catches that: the same device claimed a second time,
+/// through any file descriptor or as `/dev/tty`, fails with [`TtyError::AlreadyAcquired`].
+/// Code that shares a terminal on purpose simply doesn't claim it, e. g. with
+/// [`Tty::new_unchecked`](super::Tty::new_unchecked).
+///
+/// The claim can be moved to another thread along with the terminal.
+pub fn claim_terminal(tty: &impl AsFd) -> Result<TerminalClaim, TtyError> {
+    let id = device(tty)?;
+    let mut claimed = claimed();
+    if claimed.contains(&id) {
+        return Err(TtyError::AlreadyAcquired);
+    }
+    claimed.push(id);
+    Ok(TerminalClaim { id })
+}
+
+/// Device number of the terminal `tty` refers to. `/dev/tty` is a device of its own,
+/// on Linux `TIOCGDEV` gives the one of the controlling terminal behind it.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn device(tty: &impl AsFd) -> std::io::Result<u64> {
+    let mut dev: libc::c_uint = 0;
+    let ret = unsafe { libc::ioctl(tty.as_fd().as_raw_fd(), libc::TIOCGDEV, &mut dev) };
+    nix::errno::Errno::result(ret)?;
+    // The kernel encodes it differently from st_rdev, only equality matters here
+    Ok(dev.into())
+}
+
+/// Device number of the terminal `tty` refers to.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn device(tty: &impl AsFd) -> std::io::Result<u64> {
+    let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+    let ret = unsafe { libc::fstat(tty.as_fd().as_raw_fd(), stat.as_mut_ptr()) };
+    nix::errno::Errno::result(ret)?;
+    let stat = unsafe { stat.assume_init() };
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.st_rdev as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn pty() -> (File, File) {
+        let pty = nix::pty::openpty(None, None).unwrap();
+        (File::from(pty.master), File::from(pty.slave))
+    }
+
+    #[test]
+    fn one_claim_per_device() {
+        let (_master, tty) = pty();
+        let claim = claim_terminal(&tty).unwrap();
+        // Another descriptor of the same device
+        let other = tty.try_clone().unwrap();
+        let err = claim_terminal(&other).unwrap_err();
+        assert!(matches!(err, TtyError::AlreadyAcquired));
+
+        // Other terminals are independent
+        let (_master2, tty2) = pty();
+        let claim2 = claim_terminal(&tty2).unwrap();
+
+        drop(claim);
+        let claim = claim_terminal(&other).unwrap();
+        drop((claim, claim2));
+        // One after the other is fine
+        drop(claim_terminal(&tty).unwrap());
+        drop(claim_terminal(&tty).unwrap());
+    }
+
+    #[test]
+    fn released_on_panic() {
+        let (_master, tty) = pty();
+        let tty = std::sync::Arc::new(tty);
+        let moved = tty.clone();
+        let result = std::thread::spawn(move || {
+            let _claim = claim_terminal(&*moved).unwrap();
+            panic!("render thread died");
+        })
+        .join();
+        assert!(result.is_err());
+        claim_terminal(&*tty).unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("Unknown escape `\\{0}` in sequence template.")]
+    UnknownEscape(char),
+    #[error("Invalid placeholder at byte {0} of sequence template.")]
+    InvalidPlaceholder(usize),
+    #[error("Parameter {0} of sequence template is used as both number and string.")]
+    ConflictingKinds(usize),
+    #[error("Parameter {0} of sequence template is never used.")]
+    UnusedParameter(usize),
+}
+
+/// Value substituted into a [`SequenceTemplate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param<'a> {
+    Number(i64),
+    Str(&'a str),
+}
+
+impl From<i64> for Param<'_> {
+    fn from(value: i64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<i32> for Param<'_> {
+    fn from(value: i32) -> Self {
+        Self::Number(value.into())
+    }
+}
+
+impl From<u32> for Param<'_> {
+    fn from(value: u32) -> Self {
+        Self::Number(value.into())
+    }
+}
+
+impl<'a> From<&'a str> for Param<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Str(value)
+    }
+}
+
+/// Most parameters a template takes, like terminfo.
+const MAX_PARAMS: usize = 9;
+/// Widest padding of a placeholder.
+const MAX_WIDTH: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Number,
+    Str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Placeholder {
+    /// Index into the parameters, from 0.
+    index: usize,
+    width: usize,
+    left: bool,
+    zero: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(Vec<u8>),
+    Placeholder(Placeholder),
+}
+
+/// Sequence with parameters, for terminal specific sequences that neither terminfo
+/// nor the capability methods know about.
+///
+/// Templates are parsed from strings, so they can be kept in user configuration:
+///
+/// - `\e` or `\E` is ESC, `\a`, `\n`, `\r`, `\t` and `\\` are what they are in Rust,
+///   `\xHH` is any byte.
+/// - `%d` is a number and `%s` a string parameter, `%%` is a percent sign.
+/// - `%2$d` takes the second parameter instead of the one after the previous placeholder.
+/// - Like printf, a width between `%` and the kind pads to that many characters,
+///   `-` aligns to the left and `0` pads numbers with zeroes, as in `%-8s` or `%1$03d`.
+/// - Templates take up to 9 parameters and widths go up to 1024.
+///
+/// ```
+/// # use nixtui_core::tty::SequenceTemplate;
+/// let template: SequenceTemplate = r"\e_Gi=%d,q=%1$03d\e\\".parse().unwrap();
+/// assert_eq!(template.arity(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceTemplate {
+    parts: Vec<Part>,
+    kinds: Vec<Kind>,
+}
+
+impl SequenceTemplate {
+    /// Number of parameters the template takes.
+    pub fn arity(&self) -> usize {
+        self.kinds.len()
+    }
+
+    /// Expands the template with `params`, `Err` with the position of the first
+    /// parameter of the wrong kind. The number of parameters has to be checked before.
+    pub(super) fn expand(&self, params: &[Param], to: &mut Vec<u8>) -> Result<(), usize> {
+        for (index, (param, kind)) in params.iter().zip(&self.kinds).enumerate() {
+            match (param, kind) {
+                (Param::Number(_), Kind::Number) | (Param::Str(_), Kind::Str) => {}
+                _ => return Err(index + 1),
+            }
+        }
+        let mut text = String::new();
+        for part in &self.parts {
+            let placeholder = match part {
+                Part::Literal(bytes) => {
+                    to.extend_from_slice(bytes);
+                    continue;
+                }
+                Part::Placeholder(placeholder) => placeholder,
+            };
+            let width = placeholder.width;
+            text.clear();
+            // Writing to a String can't fail
+            let _ = match (params[placeholder.index], placeholder.left) {
+                (Param::Number(n), true) => write!(text, "{n:<width$}"),
+                (Param::Number(n), false) if placeholder.zero => write!(text, "{n:0width$}"),
+                (Param::Number(n), false) => write!(text, "{n:>width$}"),
+                (Param::Str(s), true) => write!(text, "{s:<width$}"),
+                (Param::Str(s), false) => write!(text, "{s:>width$}"),
+            };
+            to.extend_from_slice(text.as_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SequenceTemplate {
+    type Err = TemplateError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = Vec::new();
+        let mut kinds: Vec<Option<Kind>> = Vec::new();
+        let mut next = 0;
+        let mut chars = template.char_indices().peekable();
+
+        while let Some((pos, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    let byte = match chars.next().map(|(_, ch)| ch) {
+                        Some('e' | 'E') => 0x1B,
+                        Some('a') => 0x07,
+                        Some('n') => b'\n',
+                        Some('r') => b'\r',
+                        Some('t') => b'\t',
+                        Some('\\') => b'\\',
+                        Some('x') => {
+                            let hex: String = chars.by_ref().take(2).map(|(_, ch)| ch).collect();
+                            u8::from_str_radix(&hex, 16)
+                                .ok()
+                                .filter(|_| hex.len() == 2)
+                                .ok_or(TemplateError::UnknownEscape('x'))?
+                        }
+                        Some(ch) => return Err(TemplateError::UnknownEscape(ch)),
+                        None => return Err(TemplateError::UnknownEscape('\\')),
+                    };
+                    literal.push(byte);
+                }
+                '%' if chars.next_if(|&(_, ch)| ch == '%').is_some() => literal.push(b'%'),
+                '%' => {
+                    let mut spec = String::new();
+                    let kind = loop {
+                        match chars.next() {
+                            Some((_, 'd')) => break Kind::Number,
+                            Some((_, 's')) => break Kind::Str,
+                            Some((_, ch)) if ch.is_ascii_digit() || ch == '$' || ch == '-' => {
+                                spec.push(ch)
+                            }
+                            _ => return Err(TemplateError::InvalidPlaceholder(pos)),
+                        }
+                    };
+                    let invalid = || TemplateError::InvalidPlaceholder(pos);
+                    let (index, flags) = match spec.split_once('$') {
+                        Some((index, flags)) => {
+                            let index: usize = index.parse().map_err(|_| invalid())?;
+                            (index.checked_sub(1).ok_or_else(invalid)?, flags)
+                        }
+                        None => (next, spec.as_str()),
+                    };
+                    next = index + 1;
+                    let left = flags.starts_with('-');
+                    let flags = flags.trim_start_matches('-');
+                    let zero = flags.starts_with('0');
+                    let width = match flags {
+                        "" => 0,
+                        width => width.parse().map_err(|_| invalid())?,
+                    };
+                    if zero && kind == Kind::Str || index >= MAX_PARAMS || width > MAX_WIDTH {
+                        return Err(invalid());
+                    }
+
+                    if kinds.len() <= index {
+                        kinds.resize(index + 1, None);
+                    }
+                    match kinds[index] {
+                        Some(used) if used != kind => {
+                            return Err(TemplateError::ConflictingKinds(index + 1))
+                        }
+                        _ => kinds[index] = Some(kind),
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(Placeholder {
+                        index,
+                        width,
+                        left,
+                        zero,
+                    }));
+                }
+                ch => {
+                    let mut utf8 = [0; 4];
+                    literal.extend_from_slice(ch.encode_utf8(&mut utf8).as_bytes());
+                }
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        let kinds = kinds
+            .into_iter()
+            .enumerate()
+            .map(|(index, kind)| kind.ok_or(TemplateError::UnusedParameter(index + 1)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { parts, kinds })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(template: &str, params: &[Param]) -> String {
+        let template: SequenceTemplate = template.parse().unwrap();
+        let mut bytes = Vec::new();
+        template.expand(params, &mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(expand(r"\e]1337;%s\a", &["x".into()]), "\x1B]1337;x\x07");
+        assert_eq!(
+            expand("[%4d|%-4d|%04d]", &[7.into(), 8.into(), 9.into()]),
+            "[   7|8   |0009]"
+        );
+        assert_eq!(expand("%03d", &[(-5).into()]), "-05");
+        assert_eq!(
+            expand("<%5s><%-5s>", &["ab".into(), "cd".into()]),
+            "<   ab><cd   >"
+        );
+        assert_eq!(
+            expand("%2$s=%1$02d %s %1$d%%", &[3.into(), "k".into()]),
+            "k=03 k 3%"
+        );
+        assert_eq!(expand(r"\x1b\\ é", &[]), "\x1B\\ é");
+    }
+
+    #[test]
+    fn invalid_templates() {
+        let parse = |template: &str| template.parse::<SequenceTemplate>().unwrap_err();
+        assert_eq!(parse(r"\q"), TemplateError::UnknownEscape('q'));
+        assert_eq!(parse(r"\x1"), TemplateError::UnknownEscape('x'));
+        assert_eq!(parse("ab%x"), TemplateError::InvalidPlaceholder(2));
+        assert_eq!(parse("%0$d"), TemplateError::InvalidPlaceholder(0));
+        assert_eq!(parse("%05s"), TemplateError::InvalidPlaceholder(0));
+        // Bounded, they come from user configuration
+        assert_eq!(parse("%10$d"), TemplateError::InvalidPlaceholder(0));
+        assert_eq!(parse("%4000000000$d"), TemplateError::InvalidPlaceholder(0));
+        assert_eq!(
+            parse(&"%d".repeat(10)),
+            TemplateError::InvalidPlaceholder(18)
+        );
+        assert_eq!(parse("%1025d"), TemplateError::InvalidPlaceholder(0));
+        assert_eq!(parse("%-4000000000s"), TemplateError::InvalidPlaceholder(0));
+        let template = "%d".repeat(8) + "%1024d";
+        assert_eq!(template.parse::<SequenceTemplate>().unwrap().arity(), 9);
+        assert_eq!(parse("%d %1$s"), TemplateError::ConflictingKinds(1));
+        assert_eq!(parse("%2$d"), TemplateError::UnusedParameter(1));
+    }
+
+    #[test]
+    fn wrong_kind() {
+        let template: SequenceTemplate = "%d;%s".parse().unwrap();
+        assert_eq!(template.arity(), 2);
+        let mut bytes = Vec::new();
+        assert_eq!(
+            template.expand(&["a".into(), "b".into()], &mut bytes),
+            Err(1)
+        );
+        assert_eq!(template.expand(&[1.into(), 2.into()], &mut bytes), Err(2));
+        assert!(bytes.is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::env::EnvSnapshot;
+use crate::width::{char_width, Policy};
+
+/// Character set the terminal decodes output with, taken from the locale.
+///
+/// Text is always produced as UTF-8, anything else is converted when it is
+/// written through [`std::io::Write`]. Capabilities, [`append`](super::Capabilities::append)
+/// and [`append_host`](super::Capabilities::append_host) are never converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    /// ISO 8859-1.
+    Latin1,
+    /// The `C` and `POSIX` locales.
+    Ascii,
+}
+
+impl OutputEncoding {
+    /// Encoding of a codeset name like `UTF-8`, `ISO-8859-1` or `ANSI_X3.4-1968`,
+    /// `None` for codesets that can't be written.
+    pub fn from_codeset(codeset: &str) -> Option<Self> {
+        let name: String = codeset
+            .chars()
+            .filter(|ch| ch.is_ascii_alphanumeric())
+            .map(|ch| ch.to_ascii_lowercase())
+            .collect();
+        match name.as_str() {
+            "utf8" => Some(Self::Utf8),
+            "iso88591" | "latin1" | "l1" => Some(Self::Latin1),
+            "ansix341968" | "ascii" | "usascii" => Some(Self::Ascii),
+            _ => None,
+        }
+    }
+
+    /// Encoding of a locale like `de_DE.ISO-8859-1@euro`. Locales without a codeset
+    /// are assumed to be UTF-8, except `C` and `POSIX`.
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let locale = locale.split('@').next().unwrap_or_default();
+        match locale.split_once('.') {
+            Some((_, codeset)) => Self::from_codeset(codeset),
+            None if locale == "C" || locale == "POSIX" => Some(Self::Ascii),
+            None => Some(Self::Utf8),
+        }
+    }
+
+    /// [`OutputEncoding::from_locale`] for `LC_ALL`, `LC_CTYPE` or `LANG`, UTF-8 when none is set.
+    ///
+    /// `nl_langinfo` is not asked, it only knows the locale after `setlocale`,
+    /// which Rust programs don't call.
+    pub fn from_env() -> Option<Self> {
+        Self::detect(&EnvSnapshot::capture())
+    }
+
+    /// [`OutputEncoding::from_locale`] for [`EnvSnapshot::locale`], UTF-8 when none is set.
+    pub fn detect(env: &EnvSnapshot) -> Option<Self> {
+        match env.locale() {
+            Some(locale) => Self::from_locale(locale),
+            None => Some(Self::Utf8),
+        }
+    }
+
+    /// [`OutputEncoding::detect`], ASCII for codesets that can't be written,
+    /// as nearly all of them include it.
+    pub(crate) fn detect_or_ascii(env: &EnvSnapshot) -> Self {
+        Self::detect(env).unwrap_or(Self::Ascii)
+    }
+
+    /// How text is measured for a terminal with the encoding: the default [`Policy`]
+    /// for UTF-8, [`Policy::single_byte`] otherwise.
+    pub fn width_policy(&self) -> Policy {
+        match self {
+            Self::Utf8 => Policy::default(),
+            Self::Latin1 | Self::Ascii => Policy::single_byte(),
+        }
+    }
+
+    fn encode(&self, ch: char) -> Option<u8> {
+        let max = match self {
+            Self::Utf8 => return None,
+            Self::Latin1 => 0xFF,
+            Self::Ascii => 0x7F,
+        };
+        u8::try_from(u32::from(ch)).ok().filter(|&byte| byte <= max)
+    }
+
+    /// Appends `text` converted to the encoding. Box drawing and a few symbols fall back to
+    /// lookalike ASCII, everything else that can't be encoded becomes a `?` per column,
+    /// so the text keeps its width.
+    ///
+    /// Text has to be split between whole characters, invalid UTF-8 is substituted as well.
+    /// The wrappers keep a character cut at the end of a write for the next one.
+    pub fn transcode(&self, text: &[u8], to: &mut Vec<u8>) {
+        if *self == Self::Utf8 {
+            to.extend_from_slice(text);
+            return;
+        }
+        let policy = self.width_policy();
+        for ch in String::from_utf8_lossy(text).chars() {
+            if let Some(byte) = self.encode(ch).or_else(|| fallback(ch)) {
+                to.push(byte);
+            } else {
+                let columns = char_width(ch, &policy).unwrap_or(1);
+                to.extend(std::iter::repeat_n(b'?', columns));
+            }
+        }
+    }
+}
+
+/// Length of the UTF-8 character cut at the end of `text`, 0 when it ends with a whole one.
+pub(super) fn incomplete_char(text: &[u8]) -> usize {
+    for len in 1..=text.len().min(3) {
+        let byte = text[text.len() - len];
+        let needed = match byte {
+            0x80..=0xBF => continue,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return 0,
+        };
+        return if needed > len { len } else { 0 };
+    }
+    0
+}
+
+/// ASCII lookalike of box drawing characters and the markers used by components.
+fn fallback(ch: char) -> Option<u8> {
+    let byte = match ch {
+        '─' | '━' | '═' | '╌' | '┄' => b'-',
+        '│' | '┃' | '║' | '╎' | '┆' => b'|',
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' | '╭' | '╮' | '╯' | '╰' | '┏' | '┓'
+        | '┗' | '┛' | '┣' | '┫' | '┳' | '┻' | '╋' | '╔' | '╗' | '╚' | '╝' | '╠' | '╣' | '╦'
+        | '╩' | '╬' => b'+',
+        '▾' | '▼' => b'v',
+        '▸' | '▶' | '→' => b'>',
+        '◂' | '◀' | '←' => b'<',
+        '▴' | '▲' | '↑' => b'^',
+        '↓' => b'v',
+        '•' | '●' => b'*',
+        '…' => b'.',
+        '█' | '▓' | '▒' | '░' => b'#',
+        _ => return None,
+    };
+    Some(byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcode(encoding: OutputEncoding, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        encoding.transcode(text.as_bytes(), &mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn latin1() {
+        let latin1 = OutputEncoding::Latin1;
+        assert_eq!(transcode(latin1, "Grüße, çà"), b"Gr\xFC\xDFe, \xE7\xE0");
+        assert_eq!(transcode(latin1, "├─ a\n│  └─ b"), b"+- a\n|  +- b");
+        // The euro sign isn't in ISO 8859-1, the wide character keeps its two columns
+        assert_eq!(transcode(latin1, "5€ 漢"), b"5? ??");
+        assert_eq!(transcode(OutputEncoding::Ascii, "é▾"), b"?v");
+        assert_eq!(transcode(OutputEncoding::Utf8, "é─"), "é─".as_bytes());
+    }
+
+    #[test]
+    fn cut_characters() {
+        assert_eq!(incomplete_char(b"ab"), 0);
+        assert_eq!(incomplete_char("é".as_bytes()), 0);
+        assert_eq!(incomplete_char(&"é".as_bytes()[..1]), 1);
+        assert_eq!(incomplete_char(&"a─".as_bytes()[..3]), 2);
+        assert_eq!(incomplete_char(&"😀".as_bytes()[..3]), 3);
+        assert_eq!(incomplete_char(b"\x80\x80"), 0);
+    }
+
+    #[test]
+    fn from_locale() {
+        let from = OutputEncoding::from_locale;
+        assert_eq!(from("de_DE.ISO-8859-1"), Some(OutputEncoding::Latin1));
+        assert_eq!(from("fr_FR.iso88591@euro"), Some(OutputEncoding::Latin1));
+        assert_eq!(from("en_US.UTF-8"), Some(OutputEncoding::Utf8));
+        assert_eq!(from("en_US.utf8"), Some(OutputEncoding::Utf8));
+        assert_eq!(from("C"), Some(OutputEncoding::Ascii));
+        assert_eq!(from("C.UTF-8"), Some(OutputEncoding::Utf8));
+        assert_eq!(from("POSIX"), Some(OutputEncoding::Ascii));
+        assert_eq!(from("ru_RU.KOI8-R"), None);
+
+        let env = EnvSnapshot::default().lang("de_DE.ISO-8859-1");
+        assert_eq!(OutputEncoding::detect(&env), Some(OutputEncoding::Latin1));
+        let env = env.lc_ctype("C");
+        assert_eq!(OutputEncoding::detect(&env), Some(OutputEncoding::Ascii));
+        assert_eq!(
+            OutputEncoding::detect(&EnvSnapshot::default()),
+            Some(OutputEncoding::Utf8)
+        );
+    }
+}
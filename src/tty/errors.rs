@@ -14,6 +14,7 @@ impl From<nix::errno::Errno> for TerminfoCreationError {
     }
 }
 
+#[cfg(feature = "terminfo")]
 impl From<terminfo::Error> for TerminfoCreationError {
     fn from(value: terminfo::Error) -> Self {
         use terminfo::Error as Te;
@@ -25,6 +26,33 @@ impl From<terminfo::Error> for TerminfoCreationError {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum TtyError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    TerminfoCreationError(#[from] TerminfoCreationError),
+    #[error("Terminal claimed already, see `Tty::new_unchecked` for sharing it.")]
+    AlreadyAcquired,
+}
+
+impl From<nix::errno::Errno> for TtyError {
+    fn from(value: nix::errno::Errno) -> Self {
+        Self::IoError(value.into())
+    }
+}
+
+impl From<TtyError> for std::io::Error {
+    fn from(value: TtyError) -> Self {
+        match value {
+            TtyError::IoError(e)
+            | TtyError::TerminfoCreationError(TerminfoCreationError::IoError(e)) => e,
+            TtyError::AlreadyAcquired => Self::new(std::io::ErrorKind::AlreadyExists, value),
+            TtyError::TerminfoCreationError(e) => Self::other(e),
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum CapabilityError {
     #[error(transparent)]
@@ -35,6 +63,18 @@ pub enum CapabilityError {
     },
     #[error("Failed to expand capability from terminfo database.")]
     CapabilityExpansionError,
+    #[error("Window operation `{cap_name}` used without allowing window operations.")]
+    WindowOpsDisabled { cap_name: String },
+    #[error("No custom sequence named `{name}` is registered.")]
+    UnknownCustomSequence { name: String },
+    #[error("Custom sequence `{name}` takes {expected} parameters, got {got}.")]
+    CustomSequenceArity {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Parameter {index} of custom sequence `{name}` has the wrong kind.")]
+    CustomSequenceParam { name: String, index: usize },
 }
 
 impl From<nix::errno::Errno> for CapabilityError {
@@ -0,0 +1,156 @@
+use std::io::Write;
+
+use nix::sys::termios::{SetArg, Termios};
+
+use super::errors::CapabilityError;
+use super::restore::HandOver;
+use super::{Capabilities, UnixTerminal, Winsize};
+
+/// Hands the terminal to another program, e. g. `$EDITOR` or a pager, and takes it back.
+///
+/// Meant for full screen applications in raw mode on the alternate screen. Bytes buffered
+/// in `caps` are written first, then attributes are reset, the cursor is shown, mouse
+/// reporting and bracketed paste are turned off, keypad transmit mode and the alternate
+/// screen are left and `cooked` is set, usually the termios read before entering raw mode.
+/// `program` runs next, typically [`std::process::Command::status`]. Afterwards the termios
+/// the terminal had before is set again and the rest is turned on again.
+///
+/// Returns what `program` returned and the size of the terminal, which may have changed
+/// meanwhile. The alternate screen is empty again, so everything has to be redrawn.
+///
+/// While `program` runs, the handlers of [`install_signal_restore`](super::install_signal_restore)
+/// don't restore the terminal, and SIGINT and SIGQUIT are ignored like system(3) does,
+/// Ctrl+C and `Ctrl+\` are meant for the other program. Programs spawned by `program`
+/// get the default disposition.
+pub fn run_external<T, R>(
+    tty: &mut T,
+    caps: &mut impl Capabilities,
+    cooked: &Termios,
+    program: impl FnOnce() -> R,
+) -> Result<(R, Winsize), CapabilityError>
+where
+    T: UnixTerminal + Write,
+{
+    let raw = tty.get_termios()?;
+    caps.flush_to(tty)?;
+    let _ = caps.exit_attribute_mode();
+    let _ = caps.cursor_normal();
+    caps.suspend_input_modes()?;
+    let _ = caps.keypad_local();
+    let _ = caps.exit_ca_mode();
+    caps.flush_to(tty)?;
+    tty.flush()?;
+    tty.set_termios(cooked, SetArg::TCSADRAIN)?;
+    crate::logging::debug_event!("terminal handed over");
+
+    let hand_over = HandOver::start()?;
+    let value = program();
+    drop(hand_over);
+
+    tty.set_termios(&raw, SetArg::TCSADRAIN)?;
+    let _ = caps.enter_ca_mode();
+    let _ = caps.keypad_xmit();
+    caps.resume_input_modes()?;
+    caps.flush_to(tty)?;
+    tty.flush()?;
+    crate::logging::debug_event!("terminal taken back");
+    Ok((value, tty.get_size()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_output, sequences};
+    use nix::libc;
+    use nix::pty::openpty;
+    use nix::sys::termios::LocalFlags;
+    use std::fs::File;
+    use std::os::fd::AsRawFd;
+    use std::process::Command;
+
+    fn find(output: &[u8], bytes: &[u8]) -> usize {
+        output
+            .windows(bytes.len())
+            .position(|w| w == bytes)
+            .unwrap_or_else(|| panic!("{bytes:?} not in {:?}", output.escape_ascii()))
+    }
+
+    #[test]
+    fn editor_gets_cooked_terminal() {
+        let pty = openpty(None, None).unwrap();
+        let (mut master, mut tty) = (File::from(pty.master), File::from(pty.slave));
+        let cooked = tty.get_termios().unwrap();
+        tty.raw_mode().unwrap();
+        let mut caps = sequences();
+        caps.enter_ca_mode().unwrap();
+        caps.write_all(b"app").unwrap();
+
+        let mut editor_tty = tty.try_clone().unwrap();
+        let ((during, edited), size) = run_external(&mut tty, &mut caps, &cooked, || {
+            let during = editor_tty.get_termios().unwrap();
+            assert!(Command::new("true").status().unwrap().success());
+            let edited = Command::new("sh")
+                .args(["-c", "printf edited"])
+                .stdout(editor_tty.try_clone().unwrap())
+                .status()
+                .unwrap();
+            // The window was resized meanwhile
+            let size = libc::winsize {
+                ws_row: 30,
+                ws_col: 100,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) };
+            (during, edited)
+        })
+        .unwrap();
+
+        assert!(edited.success());
+        assert!(during
+            .local_flags
+            .contains(LocalFlags::ICANON | LocalFlags::ECHO));
+        assert!(!tty
+            .get_termios()
+            .unwrap()
+            .local_flags
+            .intersects(LocalFlags::ICANON | LocalFlags::ECHO));
+        assert_eq!((size.row, size.col), (30, 100));
+        assert!(caps.buffer().is_empty());
+
+        let output = read_output(&mut master, None);
+        let mut expected = sequences();
+        expected.exit_ca_mode().unwrap();
+        let leave = expected.buffer().to_vec();
+        expected.clear();
+        expected.enter_ca_mode().unwrap();
+        let enter = expected.buffer().to_vec();
+        let app = find(&output, b"app");
+        let left = app + find(&output[app..], &leave);
+        let edited = left + find(&output[left..], b"edited");
+        find(&output[edited..], &enter);
+    }
+
+    #[cfg(feature = "terminfo")]
+    #[test]
+    fn input_modes_off_while_handed_over() {
+        use crate::testing::TestTty;
+        use crate::tty::MouseMode;
+
+        let mut tty = TestTty::new();
+        let cooked = tty.get_termios().unwrap();
+        let mut caps = sequences();
+        caps.enable_bracketed_paste().unwrap();
+        caps.enable_mouse(MouseMode::Clicks).unwrap();
+        caps.flush_to(&mut tty).unwrap();
+        tty.clear_output();
+
+        run_external(&mut tty, &mut caps, &cooked, || ()).unwrap();
+        let output = tty.output();
+        let mouse_off = find(&output, b"\x1B[?1000;1006l");
+        let paste_off = find(&output, b"\x1B[?2004l");
+        let paste_on = find(&output, b"\x1B[?2004h");
+        let mouse_on = find(&output, b"\x1B[?1000;1006h");
+        assert!(mouse_off.max(paste_off) < paste_on.min(mouse_on));
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::Write;
+use std::ops::{Deref, DerefMut};
+
+use nix::sys::termios::{SetArg, Termios};
+
+use super::errors::CapabilityError;
+use super::{Capabilities, UnixTerminal};
+
+/// Keeps the terminal in raw mode until dropped, see [`UnixTerminal::raw_mode_guard`].
+///
+/// The terminal is reachable through the guard. The termios read before entering raw mode
+/// is set again when the guard is dropped, also while unwinding from a panic.
+pub struct RawModeGuard<'a, T: UnixTerminal> {
+    tty: &'a mut T,
+    orig_termios: Termios,
+}
+
+impl<'a, T: UnixTerminal> RawModeGuard<'a, T> {
+    pub fn new(tty: &'a mut T) -> std::io::Result<Self> {
+        let orig_termios = tty.get_termios()?;
+        tty.raw_mode()?;
+        Ok(Self { tty, orig_termios })
+    }
+
+    /// Termios the guard restores.
+    pub fn orig_termios(&self) -> &Termios {
+        &self.orig_termios
+    }
+}
+
+impl<T: UnixTerminal> Deref for RawModeGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.tty
+    }
+}
+
+impl<T: UnixTerminal> DerefMut for RawModeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.tty
+    }
+}
+
+impl<T: UnixTerminal> Drop for RawModeGuard<'_, T> {
+    fn drop(&mut self) {
+        let _ = self.tty.set_termios(&self.orig_termios, SetArg::TCSADRAIN);
+    }
+}
+
+/// Keeps the terminal on the alternate screen until dropped.
+///
+/// The output is reachable through the guard. The bytes leaving the alternate screen
+/// and turning attributes off are expanded up front and only written when the guard
+/// is dropped, also while unwinding from a panic. Guards nest, e. g. an alternate screen
+/// guard on the terminal of a [`RawModeGuard`] is dropped first.
+pub struct AltScreenGuard<'a, W: Write> {
+    out: &'a mut W,
+    exit: Vec<u8>,
+}
+
+impl<'a, W: Write> AltScreenGuard<'a, W> {
+    /// Writes the bytes buffered in `caps` and then the ones entering the alternate screen
+    /// to `out`. Fails when the terminal has no alternate screen.
+    pub fn new(out: &'a mut W, caps: &mut impl Capabilities) -> Result<Self, CapabilityError> {
+        let exit = exit_sequences(caps)?;
+        caps.enter_ca_mode()?;
+        caps.flush_to(out)?;
+        out.flush()?;
+        Ok(Self { out, exit })
+    }
+
+    /// Guard for `out` entered already, leaving with `exit`.
+    pub(super) fn entered(out: &'a mut W, exit: Vec<u8>) -> Self {
+        Self { out, exit }
+    }
+}
+
+/// Bytes turning attributes off and leaving the alternate screen, keeping what is
+/// buffered in `caps`. Terminals without attributes are fine, ones without an alternate
+/// screen are not.
+pub(super) fn exit_sequences(caps: &mut impl Capabilities) -> Result<Vec<u8>, CapabilityError> {
+    let pending = caps.buffer().to_vec();
+    caps.clear();
+    let _ = caps.exit_attribute_mode();
+    let result = caps.exit_ca_mode();
+    let exit = caps.buffer().to_vec();
+    caps.clear();
+    caps.append(&pending);
+    result.map(|()| exit)
+}
+
+impl<W: Write> Deref for AltScreenGuard<'_, W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        self.out
+    }
+}
+
+impl<W: Write> DerefMut for AltScreenGuard<'_, W> {
+    fn deref_mut(&mut self) -> &mut W {
+        self.out
+    }
+}
+
+impl<W: Write> Drop for AltScreenGuard<'_, W> {
+    fn drop(&mut self) {
+        let _ = self.out.write_all(&self.exit);
+        let _ = self.out.flush();
+    }
+}
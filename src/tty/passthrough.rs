@@ -0,0 +1,116 @@
+use crate::env::EnvSnapshot;
+
+/// tmux reads passthrough sequences into a bounded buffer, larger payloads are split.
+const TMUX_CHUNK: usize = 4096;
+/// GNU screen drops DCS strings longer than this.
+const SCREEN_CHUNK: usize = 768;
+
+/// Terminal multiplexer between the application and the terminal,
+/// which swallows sequences it doesn't know unless they are wrapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// Guesses the multiplexer from `TMUX` and `TERM`.
+    pub fn detect(env: &EnvSnapshot) -> Option<Self> {
+        let term = env.term.as_deref().unwrap_or_default();
+        if env.tmux.is_some() || term.starts_with("tmux") {
+            Some(Self::Tmux)
+        } else if term.starts_with("screen") {
+            Some(Self::Screen)
+        } else {
+            None
+        }
+    }
+}
+
+/// Appends `payload` to `out` wrapped so that `multiplexer` forwards it to the outer terminal.
+///
+/// tmux gets `ESC P tmux; … ESC \` with every ESC of the payload doubled.
+/// screen gets plain `ESC P … ESC \` strings, split so that no string terminator
+/// of the payload ends up inside one of them.
+/// Payloads over the length limit of the multiplexer are sent as several strings.
+pub fn wrap_passthrough(multiplexer: Multiplexer, payload: &[u8], out: &mut Vec<u8>) {
+    match multiplexer {
+        Multiplexer::Tmux => {
+            for chunk in payload.chunks(TMUX_CHUNK) {
+                out.extend_from_slice(b"\x1BPtmux;");
+                for &byte in chunk {
+                    if byte == 0x1B {
+                        out.push(0x1B);
+                    }
+                    out.push(byte);
+                }
+                out.extend_from_slice(b"\x1B\\");
+            }
+        }
+        Multiplexer::Screen => {
+            let mut rest = payload;
+            while !rest.is_empty() {
+                let mut len = rest.len().min(SCREEN_CHUNK);
+                // End the string right after the ESC of an embedded terminator
+                if let Some(i) = rest[..len].windows(2).position(|w| w == b"\x1B\\") {
+                    len = i + 1;
+                }
+                out.extend_from_slice(b"\x1BP");
+                out.extend_from_slice(&rest[..len]);
+                out.extend_from_slice(b"\x1B\\");
+                rest = &rest[len..];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // OSC 52 terminated with ST, so the payload contains `ESC \`
+    const CLIPBOARD: &[u8] = b"\x1B]52;c;aGk=\x1B\\";
+
+    fn wrap(multiplexer: Multiplexer, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        wrap_passthrough(multiplexer, payload, &mut out);
+        out
+    }
+
+    #[test]
+    fn tmux_doubles_escapes() {
+        assert_eq!(
+            wrap(Multiplexer::Tmux, CLIPBOARD),
+            b"\x1BPtmux;\x1B\x1B]52;c;aGk=\x1B\x1B\\\x1B\\"
+        );
+        let long = vec![b'a'; TMUX_CHUNK + 1];
+        let wrapped = wrap(Multiplexer::Tmux, &long);
+        assert_eq!(wrapped.len(), long.len() + 2 * b"\x1BPtmux;\x1B\\".len());
+        assert!(wrapped.ends_with(b"\x1B\\\x1BPtmux;a\x1B\\"));
+    }
+
+    #[test]
+    fn screen_splits_at_terminators() {
+        assert_eq!(
+            wrap(Multiplexer::Screen, CLIPBOARD),
+            b"\x1BP\x1B]52;c;aGk=\x1B\x1B\\\x1BP\\\x1B\\"
+        );
+        let long = vec![b'a'; SCREEN_CHUNK + 1];
+        let wrapped = wrap(Multiplexer::Screen, &long);
+        assert_eq!(wrapped.len(), long.len() + 8);
+        assert!(wrapped.ends_with(b"\x1B\\\x1BPa\x1B\\"));
+    }
+
+    #[test]
+    fn detect() {
+        let env = EnvSnapshot::default().term("screen-256color");
+        assert_eq!(Multiplexer::detect(&env), Some(Multiplexer::Screen));
+        // tmux often sets TERM to screen
+        let env = env.tmux("/tmp/tmux-1000/default,1234,0");
+        assert_eq!(Multiplexer::detect(&env), Some(Multiplexer::Tmux));
+        let env = EnvSnapshot::default().term("tmux-256color");
+        assert_eq!(Multiplexer::detect(&env), Some(Multiplexer::Tmux));
+        let env = EnvSnapshot::default().term("xterm-kitty");
+        assert_eq!(Multiplexer::detect(&env), None);
+    }
+}
@@ -0,0 +1,123 @@
+/// What a chunk of output was appended by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrigin {
+    /// Expanded by the named capability method.
+    Capability(&'static str),
+    /// Appended with `append_host`, including the passthrough wrapping.
+    Host,
+    /// Appended or written without a capability method, usually text.
+    Text,
+}
+
+/// Capabilities that only change attributes or colors.
+const STYLE_CAPABILITIES: &[&str] = &[
+    "exit_attribute_mode",
+    "enter_bold_mode",
+    "enter_dim_mode",
+    "enter_italics_mode",
+    "exit_italics_mode",
+    "enter_underline_mode",
+    "exit_underline_mode",
+    "enter_blink_mode",
+    "enter_reverse_mode",
+    "enter_secure_mode",
+    "enter_crossed_out_mode",
+    "enter_overline_mode",
+    "enter_standout_mode",
+    "exit_standout_mode",
+    "orig_pair",
+    "set_a_foreground",
+    "set_a_background",
+    "set_foreground",
+    "set_background",
+    "set_foreground_rgb",
+    "set_background_rgb",
+];
+
+impl ByteOrigin {
+    /// Whether the bytes only changed attributes or colors.
+    pub fn is_style(&self) -> bool {
+        matches!(self, Self::Capability(name) if STYLE_CAPABILITIES.contains(name))
+    }
+}
+
+/// Bytes of output by where they came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Frames counted, 1 for the stats of a single frame.
+    pub frames: usize,
+    pub total: usize,
+    /// Byte count of every origin seen, in the order they first appeared.
+    pub by_origin: Vec<(ByteOrigin, usize)>,
+}
+
+impl FrameStats {
+    pub fn get(&self, origin: ByteOrigin) -> usize {
+        self.by_origin
+            .iter()
+            .find(|(o, _)| *o == origin)
+            .map_or(0, |&(_, bytes)| bytes)
+    }
+
+    /// Bytes spent on attributes and colors.
+    pub fn style(&self) -> usize {
+        self.by_origin
+            .iter()
+            .filter(|(origin, _)| origin.is_style())
+            .map(|&(_, bytes)| bytes)
+            .sum()
+    }
+
+    /// The `count` origins with the most bytes, largest first.
+    pub fn top(&self, count: usize) -> Vec<(ByteOrigin, usize)> {
+        let mut top = self.by_origin.clone();
+        top.sort_by_key(|&(_, bytes)| std::cmp::Reverse(bytes));
+        top.truncate(count);
+        top
+    }
+
+    fn add(&mut self, origin: ByteOrigin, bytes: usize) {
+        self.total += bytes;
+        match self.by_origin.iter_mut().find(|(o, _)| *o == origin) {
+            Some((_, count)) => *count += bytes,
+            None => self.by_origin.push((origin, bytes)),
+        }
+    }
+
+    fn merge(&mut self, other: &FrameStats) {
+        self.frames += other.frames;
+        for &(origin, bytes) in &other.by_origin {
+            self.add(origin, bytes);
+        }
+    }
+}
+
+/// Counts kept by an output buffer while profiling is on.
+#[derive(Debug, Default)]
+pub(super) struct Profiler {
+    current: FrameStats,
+    pub(super) last: FrameStats,
+    pub(super) cumulative: FrameStats,
+}
+
+impl Profiler {
+    pub(super) fn add(&mut self, origin: ByteOrigin, bytes: usize) {
+        self.current.add(origin, bytes);
+    }
+
+    /// Ends the frame of the `len` bytes in the buffer, bytes not attributed to anything are text.
+    pub(super) fn end_frame(&mut self, len: usize) {
+        let text = len - self.current.total;
+        if text > 0 {
+            self.current.add(ByteOrigin::Text, text);
+        }
+        self.current.frames = 1;
+        self.last = std::mem::take(&mut self.current);
+        self.cumulative.merge(&self.last);
+    }
+
+    /// Forgets the bytes of the current frame, they were dropped without being written.
+    pub(super) fn discard(&mut self) {
+        self.current = FrameStats::default();
+    }
+}
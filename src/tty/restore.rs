@@ -0,0 +1,342 @@
+use std::os::fd::{AsFd, AsRawFd, RawFd};
+use std::panic::PanicHookInfo;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use nix::libc::{self, c_int, c_void};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::termios::Termios;
+
+use super::Capabilities;
+
+/// Bytes and termios that bring the terminal back to the state the application found it in.
+///
+/// Everything is prepared up front, [`TerminalRestorer::restore`] only does `write`
+/// and `tcsetattr`, so it can run inside a signal handler or a panic hook.
+/// The terminal must stay open for as long as the restorer is installed.
+#[derive(Debug, Clone)]
+pub struct TerminalRestorer {
+    fd: RawFd,
+    termios: Option<libc::termios>,
+    bytes: Vec<u8>,
+}
+
+impl TerminalRestorer {
+    pub fn new(tty: &impl AsFd) -> Self {
+        Self {
+            fd: tty.as_fd().as_raw_fd(),
+            termios: None,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Termios to set, usually the one read before entering raw mode.
+    pub fn termios(mut self, termios: &Termios) -> Self {
+        self.termios = Some(termios.clone().into());
+        self
+    }
+
+    /// Appends raw bytes to write.
+    pub fn bytes(mut self, bytes: &[u8]) -> Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    /// Appends the sequences that undo what full screen applications usually turn on:
+    /// attributes, hidden cursor, keypad transmit mode and the alternate screen.
+    /// Capabilities the terminal lacks are skipped.
+    ///
+    /// Bytes already buffered in `caps` are kept there.
+    pub fn sequences(self, caps: &mut impl Capabilities) -> Self {
+        self.expand(caps, true)
+    }
+
+    /// Like [`TerminalRestorer::sequences`] for applications that stay on the main screen,
+    /// leaving the alternate screen would move the cursor on some terminals.
+    pub fn inline_sequences(self, caps: &mut impl Capabilities) -> Self {
+        self.expand(caps, false)
+    }
+
+    fn expand(mut self, caps: &mut impl Capabilities, alt_screen: bool) -> Self {
+        let pending = caps.buffer().to_vec();
+        caps.clear();
+        let _ = caps.exit_attribute_mode();
+        let _ = caps.cursor_normal();
+        let _ = caps.keypad_local();
+        if alt_screen {
+            let _ = caps.exit_ca_mode();
+        }
+        self.bytes.extend_from_slice(caps.buffer());
+        caps.clear();
+        caps.append(&pending);
+        self
+    }
+
+    /// Bytes [`TerminalRestorer::restore`] writes.
+    pub fn output(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Writes the bytes and sets the termios, ignoring errors. Async-signal-safe.
+    pub fn restore(&self) {
+        let mut bytes = &self.bytes[..];
+        while !bytes.is_empty() {
+            let count = unsafe { libc::write(self.fd, bytes.as_ptr().cast(), bytes.len()) };
+            if count < 0 && nix::errno::Errno::last() == nix::errno::Errno::EINTR {
+                continue;
+            }
+            if count <= 0 {
+                break;
+            }
+            bytes = &bytes[count as usize..];
+        }
+        if let Some(termios) = &self.termios {
+            unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, termios) };
+        }
+    }
+}
+
+/// What happens after the terminal was restored in a signal handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnSignal {
+    /// The signal is raised again with the default disposition,
+    /// so the process ends with the exit status the signal would have given it.
+    Terminate,
+    /// The handler installed before is called, if there was one, otherwise same as `Terminate`.
+    Chain,
+}
+
+const SIGNALS: [Signal; 3] = [Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP];
+
+static RESTORER: AtomicPtr<TerminalRestorer> = AtomicPtr::new(null_mut());
+static CHAIN: AtomicBool = AtomicBool::new(false);
+/// Set while another program has the terminal, see [`HandOver`].
+static HANDED_OVER: AtomicBool = AtomicBool::new(false);
+/// Handlers between reading [`RESTORER`] or [`PREVIOUS`] and being done with them.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static PREVIOUS: [AtomicPtr<SigAction>; 3] = [
+    AtomicPtr::new(null_mut()),
+    AtomicPtr::new(null_mut()),
+    AtomicPtr::new(null_mut()),
+];
+
+extern "C" fn on_signal(signum: c_int, info: *mut libc::siginfo_t, context: *mut c_void) {
+    // Keeps SignalRestore::drop from freeing the boxes while they are used here
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    let handed_over = HANDED_OVER.load(Ordering::Acquire);
+    // The terminal was already restored before handing it over
+    let restorer = RESTORER.load(Ordering::SeqCst);
+    if !restorer.is_null() && !handed_over {
+        unsafe { (*restorer).restore() };
+    }
+    let previous = SIGNALS
+        .iter()
+        .position(|&sig| sig as c_int == signum)
+        .map(|i| PREVIOUS[i].load(Ordering::SeqCst))
+        .filter(|previous| !previous.is_null())
+        .map(|previous| unsafe { (*previous).handler() });
+    RUNNING.fetch_sub(1, Ordering::SeqCst);
+    if CHAIN.load(Ordering::Acquire) {
+        match previous {
+            Some(SigHandler::Handler(handler)) => return handler(signum),
+            Some(SigHandler::SigAction(handler)) => return handler(signum, info, context),
+            _ => {}
+        }
+    }
+    // The signal is blocked until the handler returns, then delivered with the default action
+    unsafe {
+        libc::signal(signum, libc::SIG_DFL);
+        libc::raise(signum);
+    }
+}
+
+/// Does nothing, see [`HandOver::start`].
+extern "C" fn ignore_signal(_: c_int) {}
+
+/// Marks the terminal as handed to another program until dropped, see [`super::run_external`].
+///
+/// The signal handlers leave the terminal alone meanwhile. SIGINT and SIGQUIT are ignored,
+/// like system(3) does, they are meant for the other program.
+pub(super) struct HandOver {
+    previous: Vec<(Signal, SigAction)>,
+}
+
+impl HandOver {
+    pub(super) fn start() -> std::io::Result<Self> {
+        let mut hand_over = Self {
+            previous: Vec::new(),
+        };
+        // Caught rather than ignored, programs spawned meanwhile would inherit SIG_IGN
+        let action = SigAction::new(
+            SigHandler::Handler(ignore_signal),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        for signal in [Signal::SIGINT, Signal::SIGQUIT] {
+            let previous = unsafe { sigaction(signal, &action) }?;
+            if previous.handler() == SigHandler::SigIgn {
+                unsafe { sigaction(signal, &previous) }?;
+                continue;
+            }
+            hand_over.previous.push((signal, previous));
+        }
+        HANDED_OVER.store(true, Ordering::Release);
+        Ok(hand_over)
+    }
+}
+
+impl Drop for HandOver {
+    fn drop(&mut self) {
+        HANDED_OVER.store(false, Ordering::Release);
+        // Ignoring first discards a Ctrl+C not delivered yet, it would reach the handler put back
+        let ignore = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
+        for (signal, previous) in &self.previous {
+            let _ = unsafe { sigaction(*signal, &ignore) };
+            let _ = unsafe { sigaction(*signal, previous) };
+        }
+    }
+}
+
+/// Handlers for SIGINT, SIGTERM and SIGHUP installed by [`install_signal_restore`].
+/// Dropping it puts the previous handlers back.
+#[derive(Debug)]
+pub struct SignalRestore {
+    installed: Vec<usize>,
+}
+
+impl Drop for SignalRestore {
+    fn drop(&mut self) {
+        let mut previous = Vec::new();
+        for &i in &self.installed {
+            let action = PREVIOUS[i].swap(null_mut(), Ordering::SeqCst);
+            if !action.is_null() {
+                let _ = unsafe { sigaction(SIGNALS[i], &*action) };
+                previous.push(action);
+            }
+        }
+        let restorer = RESTORER.swap(null_mut(), Ordering::SeqCst);
+        // A handler running on another thread may still use them
+        while RUNNING.load(Ordering::SeqCst) > 0 {
+            std::thread::yield_now();
+        }
+        for action in previous {
+            drop(unsafe { Box::from_raw(action) });
+        }
+        if !restorer.is_null() {
+            drop(unsafe { Box::from_raw(restorer) });
+        }
+    }
+}
+
+/// Restores the terminal when the process receives SIGINT, SIGTERM or SIGHUP.
+///
+/// Signals that were ignored stay ignored. Only one set of handlers can be installed
+/// at a time, a second call fails with [`std::io::ErrorKind::AlreadyExists`].
+pub fn install_signal_restore(
+    restorer: TerminalRestorer,
+    behavior: OnSignal,
+) -> std::io::Result<SignalRestore> {
+    let restorer = Box::into_raw(Box::new(restorer));
+    if RESTORER
+        .compare_exchange(null_mut(), restorer, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        drop(unsafe { Box::from_raw(restorer) });
+        return Err(std::io::ErrorKind::AlreadyExists.into());
+    }
+    CHAIN.store(behavior == OnSignal::Chain, Ordering::Release);
+
+    let mut guard = SignalRestore {
+        installed: Vec::new(),
+    };
+    let action = SigAction::new(
+        SigHandler::SigAction(on_signal),
+        SaFlags::SA_SIGINFO | SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    for (i, &signal) in SIGNALS.iter().enumerate() {
+        let previous = unsafe { sigaction(signal, &action) }?;
+        if previous.handler() == SigHandler::SigIgn {
+            unsafe { sigaction(signal, &previous) }?;
+            continue;
+        }
+        PREVIOUS[i].store(Box::into_raw(Box::new(previous)), Ordering::Release);
+        guard.installed.push(i);
+    }
+    Ok(guard)
+}
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+#[derive(Default)]
+struct PanicState {
+    restorer: Option<TerminalRestorer>,
+    previous: Option<PanicHook>,
+}
+
+fn lock(state: &Mutex<PanicState>) -> MutexGuard<'_, PanicState> {
+    // A panic while holding the lock leaves nothing half done
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Address of the closure behind a panic hook, to recognize one.
+fn hook_address(hook: &PanicHook) -> usize {
+    &**hook as *const _ as *const () as usize
+}
+
+/// Panic hook installed by [`install_panic_restore`], removed when dropped.
+#[must_use = "the hook is removed when the guard is dropped"]
+pub struct PanicRestore {
+    state: Arc<Mutex<PanicState>>,
+    hook: usize,
+}
+
+impl Drop for PanicRestore {
+    fn drop(&mut self) {
+        // The terminal may be closed and its descriptor reused from now on
+        lock(&self.state).restorer = None;
+        // The hook can't be changed while unwinding, it stays and only calls the previous one
+        if std::thread::panicking() {
+            return;
+        }
+        let current = std::panic::take_hook();
+        if hook_address(&current) == self.hook {
+            if let Some(previous) = lock(&self.state).previous.take() {
+                std::panic::set_hook(previous);
+            }
+        } else {
+            // Another hook was installed on top of this one
+            std::panic::set_hook(current);
+        }
+    }
+}
+
+/// Restores the terminal before the panic message is printed, then runs the previous hook.
+///
+/// Dropping the returned guard puts the previous hook back. If another hook was installed
+/// on top meanwhile, this one stays in the chain but no longer touches the terminal.
+pub fn install_panic_restore(restorer: TerminalRestorer) -> PanicRestore {
+    let state = Arc::new(Mutex::new(PanicState {
+        restorer: Some(restorer),
+        previous: Some(std::panic::take_hook()),
+    }));
+    let shared = state.clone();
+    let hook: PanicHook = Box::new(move |info| {
+        let state = lock(&shared);
+        if let Some(restorer) = &state.restorer {
+            restorer.restore();
+        }
+        if let Some(previous) = &state.previous {
+            previous(info);
+        }
+    });
+    let address = hook_address(&hook);
+    std::panic::set_hook(hook);
+    PanicRestore {
+        state,
+        hook: address,
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+
+use nix::libc::{VEOL, VINTR};
+use nix::sys::termios::{LocalFlags, SetArg, Termios};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::UnixTerminal;
+
+/// Room reserved for a line up front, the longest one Linux returns in canonical mode.
+const LINE_CAPACITY: usize = 4096;
+
+/// Turns off echo of typed characters until dropped, keeping line editing.
+///
+/// The terminal is reachable through the guard, so the caller can draw
+/// its own prompt and read the input.
+pub struct EchoGuard<'a, T: UnixTerminal> {
+    tty: &'a mut T,
+    orig_termios: Termios,
+}
+
+impl<'a, T: UnixTerminal> EchoGuard<'a, T> {
+    /// Input typed before the call is discarded, so it can't end up in the secret.
+    pub fn new(tty: &'a mut T) -> std::io::Result<Self> {
+        let orig_termios = tty.get_termios()?;
+        let mut termios = orig_termios.clone();
+        termios.local_flags &=
+            !(LocalFlags::ECHO | LocalFlags::ECHOE | LocalFlags::ECHOK | LocalFlags::ECHONL);
+        tty.set_termios(&termios, SetArg::TCSAFLUSH)?;
+        Ok(Self { tty, orig_termios })
+    }
+
+    /// Termios the guard restores.
+    pub fn orig_termios(&self) -> &Termios {
+        &self.orig_termios
+    }
+}
+
+impl<T: UnixTerminal> Deref for EchoGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.tty
+    }
+}
+
+impl<T: UnixTerminal> DerefMut for EchoGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.tty
+    }
+}
+
+impl<T: UnixTerminal> Drop for EchoGuard<'_, T> {
+    fn drop(&mut self) {
+        let _ = self.tty.set_termios(&self.orig_termios, SetArg::TCSADRAIN);
+    }
+}
+
+/// Prints `prompt` and reads a line without echoing it, e. g. a passphrase.
+///
+/// The interrupt character (usually Ctrl+C) doesn't raise a signal while reading,
+/// it ends the input with an [`std::io::ErrorKind::Interrupted`] error instead,
+/// so the terminal is always restored.
+/// End of file before anything was typed is [`std::io::ErrorKind::UnexpectedEof`].
+pub fn read_secret<T>(tty: &mut T, prompt: &str) -> std::io::Result<Zeroizing<String>>
+where
+    T: UnixTerminal + Read + Write,
+{
+    let mut guard = EchoGuard::new(tty)?;
+    let line = read_line_uninterrupted(&mut *guard, prompt);
+    drop(guard);
+    tty.write_all(b"\n")?;
+    tty.flush()?;
+
+    let mut line = line?;
+    String::from_utf8(std::mem::take(&mut *line))
+        .map(Zeroizing::new)
+        .map_err(|e| {
+            e.into_bytes().zeroize();
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "secret is not valid UTF-8")
+        })
+}
+
+/// Prints `prompt` and reads a line with the line editing of the terminal,
+/// the rest of the termios is kept.
+///
+/// The interrupt character doesn't raise a signal, it ends the input with an
+/// [`std::io::ErrorKind::Interrupted`] error. End of file before anything was typed
+/// is [`std::io::ErrorKind::UnexpectedEof`]. The termios is restored before returning.
+pub(crate) fn read_line_uninterrupted<T>(
+    tty: &mut T,
+    prompt: &str,
+) -> std::io::Result<Zeroizing<Vec<u8>>>
+where
+    T: UnixTerminal + Read + Write,
+{
+    let orig_termios = tty.get_termios()?;
+    let mut termios = orig_termios.clone();
+    termios.local_flags &= !LocalFlags::ISIG;
+    termios.local_flags |= LocalFlags::ICANON;
+    // The interrupt character ends the line so that it is noticed right away
+    let intr = termios.control_chars[VINTR];
+    termios.control_chars[VEOL] = intr;
+    tty.set_termios(&termios, SetArg::TCSADRAIN)?;
+    // Only prompt once typed characters can't raise signals anymore
+    let line = tty
+        .write_all(prompt.as_bytes())
+        .and_then(|()| tty.flush())
+        .and_then(|()| read_line(tty, intr));
+    tty.set_termios(&orig_termios, SetArg::TCSADRAIN)?;
+    line
+}
+
+fn read_line(tty: &mut impl Read, intr: u8) -> std::io::Result<Zeroizing<Vec<u8>>> {
+    let mut line = Zeroizing::new(Vec::with_capacity(LINE_CAPACITY));
+    let mut byte = Zeroizing::new([0]);
+    loop {
+        match tty.read(&mut *byte) {
+            Ok(0) if line.is_empty() => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) if byte[0] == intr => return Err(std::io::ErrorKind::Interrupted.into()),
+            Ok(_) => push_zeroizing(&mut line, byte[0]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(line)
+}
+
+/// Pushes `byte`, moving to a bigger buffer by hand when full so that the old one
+/// is zeroed rather than freed with the line still in it.
+fn push_zeroizing(line: &mut Zeroizing<Vec<u8>>, byte: u8) {
+    if line.len() == line.capacity() {
+        let mut grown = Zeroizing::new(Vec::with_capacity(line.capacity() * 2 + 1));
+        grown.extend_from_slice(line);
+        *line = grown;
+    }
+    line.push(byte);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::thread;
+
+    use nix::pty::{openpty, Winsize};
+
+    use super::*;
+    use crate::testing::read_output;
+
+    fn open_pty() -> (File, File) {
+        let size = Winsize {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let pty = openpty(&size, None).unwrap();
+        (File::from(pty.master), File::from(pty.slave))
+    }
+
+    fn run(input: &[u8]) -> (std::io::Result<Zeroizing<String>>, Vec<u8>, bool) {
+        let (mut master, mut slave) = open_pty();
+        let before = slave.get_termios().unwrap();
+        let reader = thread::spawn(move || {
+            let secret = read_secret(&mut slave, "Passphrase: ");
+            let after = slave.get_termios().unwrap();
+            (secret, after, slave)
+        });
+        let mut output = read_output(&mut master, Some(b"Passphrase: "));
+        master.write_all(input).unwrap();
+        let (secret, after, _slave) = reader.join().unwrap();
+        output.extend(read_output(&mut master, None));
+        let restored =
+            after.local_flags == before.local_flags && after.control_chars == before.control_chars;
+        (secret, output, restored)
+    }
+
+    #[test]
+    fn secret_is_not_echoed() {
+        let (secret, output, restored) = run(b"hunter2\n");
+        assert_eq!(secret.unwrap().as_str(), "hunter2");
+        assert_eq!(output, b"Passphrase: \r\n");
+        assert!(restored);
+    }
+
+    #[test]
+    fn interrupt_restores_termios() {
+        let (secret, output, restored) = run(b"hun\x03");
+        assert_eq!(secret.unwrap_err().kind(), std::io::ErrorKind::Interrupted);
+        assert_eq!(output, b"Passphrase: \r\n");
+        assert!(restored);
+    }
+
+    #[test]
+    fn growing_keeps_the_line() {
+        let mut line = Zeroizing::new(Vec::with_capacity(2));
+        for &byte in b"hunter2" {
+            push_zeroizing(&mut line, byte);
+        }
+        assert_eq!(line.as_slice(), b"hunter2");
+    }
+
+    #[test]
+    fn eof() {
+        let (secret, _, restored) = run(b"\x04");
+        assert_eq!(
+            secret.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+        assert!(restored);
+        let (secret, _, _) = run(b"abc\x04\x04");
+        assert_eq!(secret.unwrap().as_str(), "abc");
+    }
+}
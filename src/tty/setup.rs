@@ -0,0 +1,562 @@
+use std::any::Any;
+use std::io::{Read, Write};
+use std::os::fd::AsFd;
+use std::time::Duration;
+
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::termios::{SetArg, Termios};
+use terminfo::capability as cap;
+
+use crate::color::ColorPolicy;
+use crate::env::EnvSnapshot;
+use crate::input::{InputParser, KeyEventList, KeyboardFlags};
+
+use super::errors::CapabilityError;
+use super::{
+    claim_terminal, install_panic_restore, install_signal_restore, MouseMode, OnSignal,
+    OutputEncoding, PanicRestore, SignalRestore, TerminalClaim, TerminalRestorer, TerminfoWrapper, Tty,
+    UnixTerminal,
+};
+
+/// What [`TuiBuilder::build`] turned on and what the terminal couldn't do,
+/// by the name of the builder option, plus `keypad` for keypad transmit mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetupReport {
+    pub applied: Vec<&'static str>,
+    pub skipped: Vec<&'static str>,
+}
+
+/// Sets up the terminal for a full screen application, see [`TuiBuilder::build`].
+///
+/// Defaults to the alternate screen and nothing else. Without a color policy, it is
+/// detected from the environment and the `max_colors` capability, without an output
+/// encoding from the locale.
+#[derive(Debug, Clone)]
+pub struct TuiBuilder {
+    alt_screen: bool,
+    mouse: Option<MouseMode>,
+    kitty_keyboard: Option<KeyboardFlags>,
+    bracketed_paste: bool,
+    color_policy: Option<ColorPolicy>,
+    output_encoding: Option<OutputEncoding>,
+    on_signal: Option<OnSignal>,
+    on_panic: bool,
+    tick: Duration,
+}
+
+impl Default for TuiBuilder {
+    fn default() -> Self {
+        Self {
+            alt_screen: true,
+            mouse: None,
+            kitty_keyboard: None,
+            bracketed_paste: false,
+            color_policy: None,
+            output_encoding: None,
+            on_signal: None,
+            on_panic: false,
+            tick: Duration::from_millis(100),
+        }
+    }
+}
+
+impl TuiBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn alt_screen(mut self, enable: bool) -> Self {
+        self.alt_screen = enable;
+        self
+    }
+
+    pub fn mouse(mut self, mode: MouseMode) -> Self {
+        self.mouse = Some(mode);
+        self
+    }
+
+    /// Keys are reported with the kitty keyboard protocol, see
+    /// [`TerminfoWrapper::push_keyboard_enhancement`]. Terminals without it ignore this.
+    pub fn kitty_keyboard(mut self, flags: KeyboardFlags) -> Self {
+        self.kitty_keyboard = Some(flags);
+        self
+    }
+
+    /// Pasted text arrives between `CSI 200 ~` and `CSI 201 ~`, so it can be told
+    /// apart from typed keys.
+    pub fn bracketed_paste(mut self, enable: bool) -> Self {
+        self.bracketed_paste = enable;
+        self
+    }
+
+    /// Set on the [`TerminfoWrapper`] of the [`Tui`], see [`TerminfoWrapper::set_color_policy`].
+    pub fn color_policy(mut self, policy: ColorPolicy) -> Self {
+        self.color_policy = Some(policy);
+        self
+    }
+
+    /// Set on the [`TerminfoWrapper`] of the [`Tui`], see [`TerminfoWrapper::set_output_encoding`].
+    pub fn output_encoding(mut self, encoding: OutputEncoding) -> Self {
+        self.output_encoding = Some(encoding);
+        self
+    }
+
+    /// Restores the terminal on SIGINT, SIGTERM and SIGHUP, see [`install_signal_restore`].
+    /// Skipped when other handlers are installed already.
+    pub fn restore_on_signal(mut self, behavior: OnSignal) -> Self {
+        self.on_signal = Some(behavior);
+        self
+    }
+
+    /// Restores the terminal before the panic message, see [`install_panic_restore`].
+    /// The hook is removed again when the [`Tui`] is dropped.
+    pub fn restore_on_panic(mut self, enable: bool) -> Self {
+        self.on_panic = enable;
+        self
+    }
+
+    /// How long [`Tui::read_events`] waits for input.
+    pub fn tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Sets up `tty`, restored when the returned [`Tui`] is dropped.
+    ///
+    /// The sequences that undo the setup are prepared first, then the signal and panic
+    /// hooks are installed, and only then raw mode is entered and the sequences written,
+    /// so the terminal is restored whenever the setup got so far. Features the database
+    /// lacks are left out and listed in [`Tui::report`].
+    ///
+    /// The terminal is claimed for as long as the [`Tui`] lives, building on a terminal
+    /// claimed already fails with [`std::io::ErrorKind::AlreadyExists`], see [`claim_terminal`].
+    /// A [`Tty`] is not claimed again, it holds a claim of its own.
+    pub fn build<T>(
+        self,
+        mut tty: T,
+        mut terminfo: TerminfoWrapper,
+    ) -> Result<Tui<T>, CapabilityError>
+    where
+        T: UnixTerminal + AsFd + Read + Write + 'static,
+    {
+        let claim = if (&tty as &dyn Any).is::<Tty>() {
+            None
+        } else {
+            Some(claim_terminal(&tty).map_err(std::io::Error::from)?)
+        };
+        let orig_termios = tty.get_termios()?;
+        let mut report = SetupReport::default();
+        let mut setup = Vec::new();
+        // Undone in reverse order
+        let mut teardown: Vec<Vec<u8>> = Vec::new();
+        let pending = terminfo.buffer().to_vec();
+        terminfo.clear();
+
+        let mut bracket =
+            |name,
+             terminfo: &mut TerminfoWrapper,
+             on: fn(&mut TerminfoWrapper) -> Result<(), CapabilityError>,
+             off: fn(&mut TerminfoWrapper) -> Result<(), CapabilityError>| {
+                let start = setup.len();
+                let applied = on(terminfo).is_ok() && {
+                    setup.extend_from_slice(terminfo.buffer());
+                    terminfo.clear();
+                    off(terminfo).is_ok()
+                };
+                if applied {
+                    teardown.push(terminfo.buffer().to_vec());
+                    report.applied.push(name);
+                } else {
+                    setup.truncate(start);
+                    report.skipped.push(name);
+                }
+                terminfo.clear();
+            };
+        if self.alt_screen {
+            bracket(
+                "alt_screen",
+                &mut terminfo,
+                TerminfoWrapper::enter_ca_mode,
+                TerminfoWrapper::exit_ca_mode,
+            );
+        }
+        bracket(
+            "keypad",
+            &mut terminfo,
+            TerminfoWrapper::keypad_xmit,
+            TerminfoWrapper::keypad_local,
+        );
+        if self.bracketed_paste {
+            bracket(
+                "bracketed_paste",
+                &mut terminfo,
+                TerminfoWrapper::enable_bracketed_paste,
+                TerminfoWrapper::disable_bracketed_paste,
+            );
+            // Stays enabled in the wrapper like the mouse below, for suspend_input_modes
+            terminfo.enable_bracketed_paste()?;
+            terminfo.clear();
+        }
+        // Undone by TerminalRestorer::sequences below, along with attributes and the cursor
+        let alt_screen = report.applied.contains(&"alt_screen");
+        let covered = report
+            .applied
+            .iter()
+            .filter(|name| ["alt_screen", "keypad"].contains(name))
+            .count();
+        teardown.drain(..covered);
+        if let Some(flags) = self.kitty_keyboard {
+            terminfo.push_keyboard_enhancement(flags)?;
+            setup.extend_from_slice(terminfo.buffer());
+            terminfo.clear();
+            terminfo.pop_keyboard_enhancement()?;
+            teardown.push(terminfo.buffer().to_vec());
+            terminfo.clear();
+            report.applied.push("kitty_keyboard");
+        }
+        if let Some(mode) = self.mouse {
+            // The mode stays enabled in the wrapper, the bytes turning it off are kept aside
+            terminfo.enable_mouse(mode)?;
+            setup.extend_from_slice(terminfo.buffer());
+            terminfo.clear();
+            terminfo.disable_mouse()?;
+            teardown.push(terminfo.buffer().to_vec());
+            terminfo.clear();
+            terminfo.enable_mouse(mode)?;
+            terminfo.clear();
+            report.applied.push("mouse");
+        }
+        terminfo.append(&pending);
+        let modes: Vec<u8> = teardown.into_iter().rev().flatten().collect();
+
+        let restorer = TerminalRestorer::new(&tty)
+            .termios(&orig_termios)
+            .bytes(&modes);
+        let restorer = if alt_screen {
+            restorer.sequences(&mut terminfo)
+        } else {
+            restorer.inline_sequences(&mut terminfo)
+        };
+        let teardown = restorer.output().to_vec();
+        let signal_restore = match self.on_signal {
+            Some(behavior) => match install_signal_restore(restorer.clone(), behavior) {
+                Ok(guard) => {
+                    report.applied.push("restore_on_signal");
+                    Some(guard)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    report.skipped.push("restore_on_signal");
+                    None
+                }
+                Err(e) => return Err(e.into()),
+            },
+            None => None,
+        };
+        let panic_restore = self.on_panic.then(|| {
+            report.applied.push("restore_on_panic");
+            install_panic_restore(restorer)
+        });
+
+        let env = EnvSnapshot::capture();
+        let color_policy = self.color_policy.unwrap_or_else(|| {
+            let max_colors = terminfo.db.get::<cap::MaxColors>().map(i32::from);
+            ColorPolicy::detect(&env, max_colors)
+        });
+        terminfo.set_color_policy(color_policy);
+        let encoding = self
+            .output_encoding
+            .unwrap_or_else(|| OutputEncoding::detect_or_ascii(&env));
+        terminfo.set_output_encoding(encoding);
+        tty.raw_mode()?;
+        crate::logging::debug_event!("raw mode applied");
+        let mut tui = Tui {
+            parser: terminfo.get_parser(),
+            tty,
+            terminfo,
+            orig_termios,
+            teardown,
+            report,
+            tick: self.tick,
+            signal_restore,
+            panic_restore,
+            _claim: claim,
+        };
+        tui.tty.write_all(&setup)?;
+        tui.tty.flush()?;
+        Ok(tui)
+    }
+}
+
+/// Terminal set up by [`TuiBuilder::build`], put back the way it was found when dropped.
+pub struct Tui<T: UnixTerminal + Write> {
+    tty: T,
+    terminfo: TerminfoWrapper,
+    parser: InputParser,
+    orig_termios: Termios,
+    teardown: Vec<u8>,
+    report: SetupReport,
+    tick: Duration,
+    signal_restore: Option<SignalRestore>,
+    panic_restore: Option<PanicRestore>,
+    _claim: Option<TerminalClaim>,
+}
+
+impl<T: UnixTerminal + AsFd + Read + Write> Tui<T> {
+    pub fn tty_mut(&mut self) -> &mut T {
+        &mut self.tty
+    }
+
+    pub fn terminfo_mut(&mut self) -> &mut TerminfoWrapper {
+        &mut self.terminfo
+    }
+
+    pub fn report(&self) -> &SetupReport {
+        &self.report
+    }
+
+    pub fn color_policy(&self) -> ColorPolicy {
+        self.terminfo.color_policy()
+    }
+
+    pub fn tick(&self) -> Duration {
+        self.tick
+    }
+
+    /// Writes what was drawn into [`Tui::terminfo_mut`] to the terminal.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.terminfo.flush_to(&mut self.tty)?;
+        self.tty.flush()
+    }
+
+    /// Waits up to a tick for input and returns the keys in it, nothing when the tick passed.
+    ///
+    /// A sequence cut at the end of a read is completed by the next one,
+    /// see [`InputParser::advance`]. An ESC still alone after a tick is the Escape key.
+    ///
+    /// Waiting goes on through signals, e. g. SIGWINCH on a resize. Fails with
+    /// [`std::io::ErrorKind::UnexpectedEof`] once the terminal is gone.
+    pub fn read_events(&mut self) -> std::io::Result<KeyEventList> {
+        let timeout = u16::try_from(self.tick.as_millis()).unwrap_or(u16::MAX);
+        let ready = loop {
+            let mut fds = [PollFd::new(self.tty.as_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, timeout) {
+                Err(nix::errno::Errno::EINTR) => continue,
+                result => break result?,
+            }
+        };
+        if ready == 0 {
+            return Ok(self.parser.flush_pending());
+        }
+        let mut buf = [0; 1024];
+        let count = loop {
+            match self.tty.read(&mut buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        if count == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(self.parser.advance(&buf[..count]))
+    }
+}
+
+impl<T: UnixTerminal + Write> Drop for Tui<T> {
+    fn drop(&mut self) {
+        self.terminfo.clear();
+        let _ = self.tty.write_all(&self.teardown);
+        let _ = self.tty.flush();
+        let _ = self.tty.set_termios(&self.orig_termios, SetArg::TCSADRAIN);
+        // Before the terminal is closed, its descriptor could be reused afterwards
+        self.signal_restore.take();
+        self.panic_restore.take();
+        crate::logging::debug_event!("terminal restored");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::pty::openpty;
+    use nix::sys::termios::LocalFlags;
+    use std::fs::File;
+    use std::os::fd::AsFd;
+    use terminfo::Database;
+
+    fn terminfo(name: &str) -> TerminfoWrapper {
+        Database::from_path(format!("assets/test_{name}_database"))
+            .unwrap()
+            .into()
+    }
+
+    /// Output of a setup on a pseudo terminal, with `frame` drawn in between,
+    /// and whether the termios was restored.
+    fn session(builder: TuiBuilder, database: &str, frame: &[u8]) -> (Vec<u8>, SetupReport, bool) {
+        let pty = openpty(None, None).unwrap();
+        let (mut master, tty) = (File::from(pty.master), File::from(pty.slave));
+        let mut check = tty.try_clone().unwrap();
+        let before = check.get_termios().unwrap();
+
+        let mut tui = builder.build(tty, terminfo(database)).unwrap();
+        assert!(!check
+            .get_termios()
+            .unwrap()
+            .local_flags
+            .contains(LocalFlags::ICANON));
+        tui.terminfo_mut().write_all(frame).unwrap();
+        tui.flush().unwrap();
+        let report = tui.report().clone();
+        drop(tui);
+
+        let after = check.get_termios().unwrap();
+        let mut output = Vec::new();
+        let mut buf = [0; 1024];
+        while poll(
+            &mut [PollFd::new(master.as_fd(), PollFlags::POLLIN)],
+            200u16,
+        )
+        .unwrap()
+            > 0
+        {
+            let count = master.read(&mut buf).unwrap();
+            output.extend_from_slice(&buf[..count]);
+        }
+        (output, report, after.local_flags == before.local_flags)
+    }
+
+    #[test]
+    fn color_policy_reaches_terminfo() {
+        use crate::color::ColorLevel;
+
+        let pty = openpty(None, None).unwrap();
+        let builder = TuiBuilder::new()
+            .alt_screen(false)
+            .color_policy(ColorPolicy::new(ColorLevel::Off));
+        let mut tui = builder
+            .build(File::from(pty.slave), terminfo("xterm_256color"))
+            .unwrap();
+        assert_eq!(tui.color_policy().level, ColorLevel::Off);
+        tui.terminfo_mut().set_a_foreground(1).unwrap();
+        assert!(tui.terminfo_mut().buffer().is_empty());
+    }
+
+    #[test]
+    fn output_encoding_reaches_terminfo() {
+        let pty = openpty(None, None).unwrap();
+        let builder = TuiBuilder::new()
+            .alt_screen(false)
+            .output_encoding(OutputEncoding::Latin1);
+        let mut tui = builder
+            .build(File::from(pty.slave), terminfo("xterm_256color"))
+            .unwrap();
+        write!(tui.terminfo_mut(), "ü─").unwrap();
+        assert_eq!(tui.terminfo_mut().buffer(), b"\xFC-");
+    }
+
+    #[test]
+    fn maximal() {
+        let builder = TuiBuilder::new()
+            .mouse(MouseMode::AnyMotion)
+            .kitty_keyboard(KeyboardFlags::all())
+            .bracketed_paste(true)
+            .restore_on_signal(OnSignal::Terminate)
+            .color_policy(ColorPolicy::default())
+            .tick(Duration::from_millis(33));
+        let (output, report, restored) = session(builder, "xterm_256color", b"frame");
+        assert!(restored);
+        assert_eq!(
+            report.applied,
+            [
+                "alt_screen",
+                "keypad",
+                "bracketed_paste",
+                "kitty_keyboard",
+                "mouse",
+                "restore_on_signal"
+            ]
+        );
+        assert!(report.skipped.is_empty());
+        assert_eq!(
+            output.escape_ascii().to_string(),
+            concat!(
+                "\\x1b[?1049h\\x1b[22;0;0t",
+                "\\x1b[?1h\\x1b=",
+                "\\x1b[?2004h",
+                "\\x1b[>31u",
+                "\\x1b[?1003;1006h",
+                "frame",
+                "\\x1b[?1003;1006l",
+                "\\x1b[<u",
+                "\\x1b[?2004l",
+                "\\x1b(B\\x1b[m\\x1b[?12l\\x1b[?25h",
+                "\\x1b[?1l\\x1b>",
+                "\\x1b[?1049l\\x1b[23;0;0t",
+            )
+        );
+    }
+
+    #[test]
+    fn claims_terminal() {
+        let pty = openpty(None, None).unwrap();
+        let tty = File::from(pty.slave);
+        let other = tty.try_clone().unwrap();
+        let tui = TuiBuilder::new()
+            .build(tty, terminfo("xterm_256color"))
+            .unwrap();
+        let err = TuiBuilder::new()
+            .build(other.try_clone().unwrap(), terminfo("xterm_256color"))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            CapabilityError::IoError(e) if e.kind() == std::io::ErrorKind::AlreadyExists
+        ));
+        drop(tui);
+        TuiBuilder::new()
+            .build(other, terminfo("xterm_256color"))
+            .unwrap();
+    }
+
+    #[test]
+    fn builds_on_tty() {
+        let pty = openpty(None, None).unwrap();
+        let mut master = File::from(pty.master);
+        let slave = File::from(pty.slave);
+        let tty = Tty::from_fd(slave.try_clone().unwrap().into(), crate::testing::sequences());
+        let builder = TuiBuilder::new().alt_screen(false);
+        let mut tui = builder
+            .build(tty.unwrap(), terminfo("xterm_256color"))
+            .unwrap();
+        // Drawn without flushing the Tty itself
+        let mut frame = terminfo("xterm_256color");
+        write!(frame, "frame").unwrap();
+        frame.flush_to(tui.tty_mut()).unwrap();
+        let output = crate::testing::read_output(&mut master, Some(b"frame"));
+        assert!(output.ends_with(b"frame"));
+        // The claim of the Tty is still the one that counts
+        let err = TuiBuilder::new()
+            .build(slave.try_clone().unwrap(), terminfo("xterm_256color"))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            CapabilityError::IoError(e) if e.kind() == std::io::ErrorKind::AlreadyExists
+        ));
+        drop(tui);
+        Tty::from_fd(slave.into(), crate::testing::sequences()).unwrap();
+    }
+
+    #[test]
+    fn minimal() {
+        // vt100 has neither the alternate screen nor bracketed paste capabilities
+        let builder = TuiBuilder::new().alt_screen(true);
+        let (output, report, restored) = session(builder, "vt100", b"frame");
+        assert!(restored);
+        assert_eq!(report.applied, ["keypad"]);
+        assert_eq!(report.skipped, ["alt_screen"]);
+        assert_eq!(
+            output.escape_ascii().to_string(),
+            "\\x1b[?1h\\x1b=frame\\x1b[m\\x0f$<2>\\x1b[?1l\\x1b>"
+        );
+    }
+}
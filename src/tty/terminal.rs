@@ -0,0 +1,328 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+
+use nix::sys::termios::{SetArg, Termios};
+use zeroize::Zeroizing;
+
+use crate::input::InputParser;
+
+use super::errors::{CapabilityError, TtyError};
+use super::guard::{exit_sequences, AltScreenGuard};
+#[cfg(not(feature = "terminfo"))]
+use super::Capabilities;
+use super::{claim_terminal, Sequences, TerminalClaim, UnixTerminal, Winsize};
+
+/// The controlling terminal together with its sequences and the termios it was found with.
+///
+/// Text written to it goes out right away, after whatever is buffered in the sequences,
+/// so code that only calls [`Capabilities::flush_to`](super::Capabilities::flush_to)
+/// on it draws without a [`Write::flush`]. The capability methods below expand and write
+/// in one call. Reading gives the raw input, see [`Tty::get_parser`] to make keys of it.
+/// Raw mode comes from [`UnixTerminal`].
+///
+/// The terminal is claimed for as long as the `Tty` lives, see [`claim_terminal`],
+/// unless it was made with one of the `_unchecked` constructors.
+/// When dropped, buffered bytes are written, the alternate screen entered with
+/// [`Tty::enter_ca_mode`] is left and the termios found at construction is set again.
+pub struct Tty {
+    file: File,
+    sequences: Sequences,
+    orig_termios: Termios,
+    ca_mode: bool,
+    /// Set by [`Tty::run_external`], taken by [`Tty::take_full_redraw`].
+    full_redraw: bool,
+    _claim: Option<TerminalClaim>,
+}
+
+impl Tty {
+    /// Opens `/dev/tty` with the sequences of `$TERM`, see [`Tty::from_fd`] for the claim.
+    pub fn new() -> Result<Self, TtyError> {
+        let file = File::options().read(true).write(true).open("/dev/tty")?;
+        Self::from_fd(file.into(), Sequences::from_env()?)
+    }
+
+    /// Like [`Tty::new`] without claiming the terminal, for the rare case of sharing it
+    /// on purpose, e. g. with a library that sets it up and restores it on its own.
+    pub fn new_unchecked() -> Result<Self, TtyError> {
+        let file = File::options().read(true).write(true).open("/dev/tty")?;
+        Ok(Self::from_fd_unchecked(file.into(), Sequences::from_env()?)?)
+    }
+
+    /// Terminal on `fd`, e. g. the slave side of a pty.
+    ///
+    /// Fails with [`TtyError::AlreadyAcquired`] when the terminal is claimed already,
+    /// e. g. by another `Tty`.
+    pub fn from_fd(fd: OwnedFd, sequences: Sequences) -> Result<Self, TtyError> {
+        let claim = claim_terminal(&fd)?;
+        let mut tty = Self::from_fd_unchecked(fd, sequences)?;
+        tty._claim = Some(claim);
+        Ok(tty)
+    }
+
+    /// Like [`Tty::from_fd`] without claiming the terminal, see [`Tty::new_unchecked`].
+    pub fn from_fd_unchecked(fd: OwnedFd, sequences: Sequences) -> std::io::Result<Self> {
+        let mut file = File::from(fd);
+        let orig_termios = file.get_termios()?;
+        Ok(Self {
+            file,
+            sequences,
+            orig_termios,
+            ca_mode: false,
+            full_redraw: false,
+            _claim: None,
+        })
+    }
+
+    /// Sequences for everything the methods below don't cover, written out with the next
+    /// write or flush.
+    pub fn sequences_mut(&mut self) -> &mut Sequences {
+        &mut self.sequences
+    }
+
+    /// Termios the terminal had when it was opened.
+    pub fn orig_termios(&self) -> &Termios {
+        &self.orig_termios
+    }
+
+    pub fn get_parser(&self) -> InputParser {
+        self.sequences.get_parser()
+    }
+
+    pub fn size(&mut self) -> std::io::Result<Winsize> {
+        self.file.get_size()
+    }
+
+    /// Hands the terminal to another program with the termios found at construction,
+    /// see [`run_external`](super::run_external). The alternate screen is entered again
+    /// afterwards, and left when the `Tty` is dropped.
+    ///
+    /// Everything has to be drawn again after it, [`Tty::take_full_redraw`] tells so
+    /// even when taking the terminal back failed halfway.
+    pub fn run_external<R>(
+        &mut self,
+        program: impl FnOnce() -> R,
+    ) -> Result<(R, Winsize), CapabilityError> {
+        self.full_redraw = true;
+        let result = super::run_external(
+            &mut self.file,
+            &mut self.sequences,
+            &self.orig_termios,
+            program,
+        );
+        self.ca_mode = true;
+        result
+    }
+
+    /// Prints `prompt` and reads a line without echoing it, see [`read_secret`](super::read_secret).
+    pub fn read_secret(&mut self, prompt: &str) -> std::io::Result<Zeroizing<String>> {
+        super::read_secret(self, prompt)
+    }
+
+    /// Whether the screen has to be drawn again in full since the last call,
+    /// e. g. because of [`Tty::run_external`].
+    pub fn take_full_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.full_redraw)
+    }
+
+    /// Expands a capability and writes it out together with everything buffered before it.
+    fn emit(
+        &mut self,
+        expand: impl FnOnce(&mut Sequences) -> Result<(), CapabilityError>,
+    ) -> Result<(), CapabilityError> {
+        expand(&mut self.sequences)?;
+        self.flush()?;
+        Ok(())
+    }
+
+    pub fn enter_ca_mode(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::enter_ca_mode)?;
+        self.ca_mode = true;
+        Ok(())
+    }
+
+    pub fn exit_ca_mode(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::exit_ca_mode)?;
+        self.ca_mode = false;
+        Ok(())
+    }
+
+    /// Enters the alternate screen until the returned guard is dropped,
+    /// unlike [`Tty::enter_ca_mode`] which lasts until the `Tty` is dropped.
+    pub fn alt_screen_guard(&mut self) -> Result<AltScreenGuard<'_, Self>, CapabilityError> {
+        let exit = exit_sequences(&mut self.sequences)?;
+        self.emit(Sequences::enter_ca_mode)?;
+        Ok(AltScreenGuard::entered(self, exit))
+    }
+
+    pub fn move_cursor(&mut self, row: usize, col: usize) -> Result<(), CapabilityError> {
+        self.emit(|sequences| sequences.move_cursor(row, col))
+    }
+
+    pub fn bold(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::enter_bold_mode)
+    }
+
+    pub fn italics(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::enter_italics_mode)
+    }
+
+    /// Turns every attribute off.
+    pub fn reset_attributes(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::exit_attribute_mode)
+    }
+
+    /// Clears the screen and moves the cursor home.
+    pub fn clean(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::clear_screen)
+    }
+
+    pub fn hide_cursor(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::cursor_invisible)
+    }
+
+    pub fn show_cursor(&mut self) -> Result<(), CapabilityError> {
+        self.emit(Sequences::cursor_normal)
+    }
+}
+
+impl AsFd for Tty {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl Read for Tty {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for Tty {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let count = self.sequences.write(buf)?;
+        self.flush()?;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.sequences.flush_to(&mut self.file)?;
+        self.file.flush()
+    }
+}
+
+impl Drop for Tty {
+    fn drop(&mut self) {
+        if self.ca_mode {
+            let _ = self.sequences.exit_ca_mode();
+        }
+        let _ = self.flush();
+        let _ = self.file.set_termios(&self.orig_termios, SetArg::TCSADRAIN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_output, sequences};
+    use nix::pty::openpty;
+    use nix::sys::termios::LocalFlags;
+
+    #[test]
+    fn restores_on_drop() {
+        let pty = openpty(None, None).unwrap();
+        let mut master = File::from(pty.master);
+        let mut slave = File::from(pty.slave);
+        let mut tty = Tty::from_fd(slave.try_clone().unwrap().into(), sequences()).unwrap();
+        tty.raw_mode().unwrap();
+        tty.enter_ca_mode().unwrap();
+        tty.move_cursor(1, 2).unwrap();
+        tty.bold().unwrap();
+
+        let mut expected = sequences();
+        expected.enter_ca_mode().unwrap();
+        expected.move_cursor(1, 2).unwrap();
+        expected.enter_bold_mode().unwrap();
+        // Written out without an explicit flush
+        assert_eq!(read_output(&mut master, None), expected.buffer());
+
+        // Text doesn't wait for a flush, nor does what was buffered before it
+        tty.sequences_mut().enter_italics_mode().unwrap();
+        tty.write_all(b"hi").unwrap();
+        let mut expected = sequences();
+        expected.enter_italics_mode().unwrap();
+        expected.write_all(b"hi").unwrap();
+        assert_eq!(read_output(&mut master, None), expected.buffer());
+        drop(tty);
+        let mut expected = sequences();
+        expected.exit_ca_mode().unwrap();
+        assert_eq!(read_output(&mut master, None), expected.buffer());
+        assert!(slave
+            .get_termios()
+            .unwrap()
+            .local_flags
+            .contains(LocalFlags::ICANON | LocalFlags::ECHO));
+    }
+
+    #[test]
+    fn one_tty_per_terminal() {
+        let pty = openpty(None, None).unwrap();
+        let slave = File::from(pty.slave);
+        let tty = Tty::from_fd(slave.try_clone().unwrap().into(), sequences()).unwrap();
+        let err = Tty::from_fd(slave.try_clone().unwrap().into(), sequences())
+            .err()
+            .unwrap();
+        assert!(matches!(err, TtyError::AlreadyAcquired));
+        // Shared on purpose
+        let shared = Tty::from_fd_unchecked(slave.try_clone().unwrap().into(), sequences());
+        drop((tty, shared.unwrap()));
+        let tty = Tty::from_fd(slave.try_clone().unwrap().into(), sequences()).unwrap();
+        // Unchecked ones don't hold a claim either
+        drop(tty);
+        let _shared = Tty::from_fd_unchecked(slave.try_clone().unwrap().into(), sequences());
+        Tty::from_fd(slave.into(), sequences()).unwrap();
+    }
+
+    #[test]
+    fn external_program_forces_redraw() {
+        let pty = openpty(None, None).unwrap();
+        let mut master = File::from(pty.master);
+        let slave = File::from(pty.slave);
+        let mut tty = Tty::from_fd(slave.try_clone().unwrap().into(), sequences()).unwrap();
+        tty.raw_mode().unwrap();
+        assert!(!tty.take_full_redraw());
+
+        let mut editor_tty = slave.try_clone().unwrap();
+        let (during, _) = tty
+            .run_external(|| editor_tty.get_termios().unwrap())
+            .unwrap();
+        assert!(during.local_flags.contains(LocalFlags::ICANON));
+        assert!(!tty
+            .get_termios()
+            .unwrap()
+            .local_flags
+            .contains(LocalFlags::ICANON));
+        assert!(tty.take_full_redraw());
+        assert!(!tty.take_full_redraw());
+
+        // Back on the alternate screen until dropped
+        read_output(&mut master, None);
+        drop(tty);
+        let mut expected = sequences();
+        expected.exit_ca_mode().unwrap();
+        assert_eq!(read_output(&mut master, None), expected.buffer());
+    }
+
+    #[test]
+    fn secret_through_tty() {
+        let pty = openpty(None, None).unwrap();
+        let mut master = File::from(pty.master);
+        let mut tty = Tty::from_fd(pty.slave, sequences()).unwrap();
+        let reader = std::thread::spawn(move || (tty.read_secret("Pin: ").unwrap(), tty));
+        read_output(&mut master, Some(b"Pin: "));
+        master.write_all(b"1234\n").unwrap();
+        let (secret, _tty) = reader.join().unwrap();
+        assert_eq!(secret.as_str(), "1234");
+        assert_eq!(read_output(&mut master, None), b"\r\n");
+    }
+}
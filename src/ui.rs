@@ -1 +1,2 @@
-pub mod anchors;
+pub mod components;
+pub mod prompts;
@@ -0,0 +1,43 @@
+mod big_text;
+mod canvas;
+mod cast;
+mod help;
+mod hints;
+mod log;
+mod select;
+mod stats;
+mod tree;
+
+use std::io::Write;
+
+use crate::sanitize::{display_safe, SanitizePolicy};
+use crate::width::{truncate_to_width, Policy};
+
+pub use big_text::BigText;
+pub use canvas::{Canvas, Marker, Sparkline};
+pub use cast::{Cast, CastError, CastPlayer, Fit};
+pub use help::{HelpOverlay, HelpScreen};
+pub use hints::{HintDelay, KeyHints};
+pub use log::{Level, LogBuffer, LogHandle, LogLine, LogPane};
+pub use select::{Select, SelectAction, SelectEvent, Selection};
+pub use stats::StatsOverlay;
+pub use tree::{Tree, TreeAction, TreeEvent, TreeSource, TreeState};
+
+/// Writes `text` cut or padded with spaces to exactly `width` columns.
+///
+/// Components show text they don't control, so control characters are made visible
+/// and escape sequences removed, see [`display_safe`].
+fn write_clipped(
+    terminfo: &mut impl Write,
+    text: &str,
+    width: usize,
+    policy: &Policy,
+) -> std::io::Result<()> {
+    let text = display_safe(text, &SanitizePolicy::default());
+    let (text, used) = truncate_to_width(&text, width, policy);
+    terminfo.write_all(text.as_bytes())?;
+    for _ in used..width {
+        terminfo.write_all(b" ")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use crate::tty::errors::CapabilityError;
+use crate::tty::Capabilities;
+use crate::width::Policy;
+
+use super::write_clipped;
+
+/// Glyphs of the block letter font, 3 x 5 pixels with the top row first
+/// and the leftmost pixel in the highest bit.
+const FONT: &[(char, [u8; 5])] = &[
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b110]),
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+];
+
+/// Rows the block letters take, two pixels per row.
+const BLOCK_ROWS: usize = 3;
+
+fn glyph(ch: char) -> [u8; 5] {
+    let ch = ch.to_ascii_uppercase();
+    let find = |ch| FONT.iter().find(|(glyph, _)| *glyph == ch);
+    find(ch).or_else(|| find('?')).unwrap().1
+}
+
+/// Banner text, e. g. the headline of a dashboard.
+///
+/// Terminals that know the DEC line sizes show the text at twice its height and width.
+/// As line sizes apply to whole rows, the rows are taken over entirely and `col` is
+/// rounded down to an even column. Other terminals get block letters built from half blocks,
+/// only letters, digits and a few symbols are known to the font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigText {
+    text: String,
+    line_sizes: bool,
+}
+
+impl BigText {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            line_sizes: false,
+        }
+    }
+
+    /// Whether the terminal supports DEC line sizes, block letters are drawn otherwise.
+    pub fn line_sizes(mut self, supported: bool) -> Self {
+        self.line_sizes = supported;
+        self
+    }
+
+    /// Rows needed to show the whole text.
+    pub fn height(&self) -> usize {
+        if self.line_sizes {
+            2
+        } else {
+            BLOCK_ROWS
+        }
+    }
+
+    /// Row `index` of the block letters.
+    fn block_row(&self, index: usize) -> String {
+        let mut row = String::new();
+        for (i, ch) in self.text.chars().enumerate() {
+            if i > 0 {
+                row.push(' ');
+            }
+            let pixels = glyph(ch);
+            let top = pixels[index * 2];
+            let bottom = pixels.get(index * 2 + 1).copied().unwrap_or(0);
+            for bit in (0..3).rev() {
+                row.push(match (top >> bit & 1, bottom >> bit & 1) {
+                    (1, 1) => '█',
+                    (1, 0) => '▀',
+                    (0, 1) => '▄',
+                    _ => ' ',
+                });
+            }
+        }
+        row
+    }
+
+    /// Draws the text into the `width` x `height` area with its upper left corner
+    /// at `row`, `col`. Every row of the area is overwritten, with line sizes the rows
+    /// are overwritten entirely.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        let policy = Policy::default();
+        if !self.line_sizes {
+            for r in 0..height {
+                terminfo.move_cursor(row + r, col)?;
+                let text = if r < BLOCK_ROWS {
+                    self.block_row(r)
+                } else {
+                    String::new()
+                };
+                write_clipped(terminfo, &text, width, &policy)?;
+            }
+            return Ok(());
+        }
+
+        for r in 0..height {
+            terminfo.move_cursor(row + r, 0)?;
+            match (r, height) {
+                (0, 1) => terminfo.double_width_line()?,
+                (0 | 1, _) => terminfo.double_height_line(r == 0)?,
+                _ => terminfo.single_width_line()?,
+            }
+            // Columns of double size rows are counted in double width cells
+            terminfo.move_cursor(row + r, col / 2)?;
+            let text = if r < 2 { self.text.as_str() } else { "" };
+            let width = if r < 2 { width / 2 } else { width };
+            write_clipped(terminfo, text, width, &policy)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::sequences;
+    use crate::vt::{LineSize, Model};
+
+    #[test]
+    fn block_letters() {
+        let mut terminfo = sequences();
+        BigText::new("Hi!?")
+            .render(&mut terminfo, 0, 1, 15, 4)
+            .unwrap();
+        assert_eq!(
+            crate::vt::replay(terminfo.buffer(), (16, 4)).text(),
+            concat!(
+                " █ █ ▀█▀  █  ▀▀▄\n",
+                " █▀█  █   ▀   ▀\n",
+                " ▀ ▀ ▀▀▀  ▀   ▀\n",
+                "\n"
+            )
+        );
+        // Unknown characters look like `?`
+        assert_eq!(
+            BigText::new("~").block_row(0),
+            BigText::new("?").block_row(0)
+        );
+    }
+
+    #[cfg(feature = "terminfo")]
+    #[test]
+    fn block_letters_snapshot() {
+        let mut terminfo = sequences();
+        BigText::new("Ok 42")
+            .render(&mut terminfo, 0, 0, 20, 3)
+            .unwrap();
+        let screen = crate::vt::replay(terminfo.buffer(), (20, 3));
+        crate::assert_buffer_snapshot!(screen, "big_text_block_letters");
+    }
+
+    #[test]
+    fn line_sizes() {
+        let big = BigText::new("Hi").line_sizes(true);
+        assert_eq!(big.height(), 2);
+        let mut terminfo = sequences();
+        big.render(&mut terminfo, 0, 4, 8, 3).unwrap();
+        assert!(terminfo
+            .buffer()
+            .starts_with(b"\x1B[1;1H\x1B#3\x1B[1;3HHi  "));
+
+        let mut model = Model::new(12, 3);
+        model.feed(terminfo.buffer());
+        let screen = model.screen();
+        assert_eq!(screen.line_size(0), LineSize::DoubleHeightTop);
+        assert_eq!(screen.line_size(1), LineSize::DoubleHeightBottom);
+        assert_eq!(screen.line_size(2), LineSize::Single);
+        assert_eq!(screen.text(), "  Hi\n  Hi\n\n");
+
+        // A single row is only made wider
+        let mut terminfo = sequences();
+        big.render(&mut terminfo, 0, 0, 8, 1).unwrap();
+        assert_eq!(terminfo.buffer(), b"\x1B[1;1H\x1B#6\x1B[1;1HHi  ");
+    }
+}
@@ -0,0 +1,431 @@
+use crate::tty::errors::CapabilityError;
+use crate::tty::Capabilities;
+
+/// How the pixels of a [`Canvas`] are packed into cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Marker {
+    /// 2x4 pixels per cell, one braille dot each.
+    #[default]
+    Braille,
+    /// 1x2 pixels per cell with `▀`, `▄` and `█`.
+    HalfBlock,
+    /// 2x2 pixels per cell with the quadrant block characters.
+    Quadrant,
+    /// 2x4 pixels per cell, shown by how many are set with a ramp of ASCII characters
+    /// for terminals or fonts without the other characters.
+    Ascii,
+}
+
+impl Marker {
+    /// Pixels per cell horizontally and vertically.
+    pub fn resolution(self) -> (usize, usize) {
+        match self {
+            Self::Braille | Self::Ascii => (2, 4),
+            Self::HalfBlock => (1, 2),
+            Self::Quadrant => (2, 2),
+        }
+    }
+
+    /// Symbol of a cell from the bits of its set pixels, row by row from the top left.
+    fn symbol(self, bits: u8) -> char {
+        match self {
+            Self::Braille => {
+                // Braille numbers dots column by column, the bottom row came later
+                const DOTS: [u8; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+                let dots = (0..8)
+                    .filter(|i| bits & (1 << i) != 0)
+                    .fold(0, |dots, i| dots | DOTS[i]);
+                match dots {
+                    0 => ' ',
+                    _ => char::from_u32(0x2800 + u32::from(dots)).unwrap(),
+                }
+            }
+            Self::HalfBlock => [' ', '▀', '▄', '█'][usize::from(bits)],
+            Self::Quadrant => [
+                ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+            ][usize::from(bits)],
+            Self::Ascii => {
+                [' ', '.', ',', ':', ';', '+', '*', '#', '@'][bits.count_ones() as usize]
+            }
+        }
+    }
+}
+
+/// Grid of pixels drawn at sub-cell resolution, for plots and other graphics.
+///
+/// Pixels have a color, a palette index, or none for the default foreground.
+/// A cell takes the color most of its set pixels have. On a tie the smaller index
+/// wins, and pixels without a color only count when no pixel of the cell has one.
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    cols: usize,
+    rows: usize,
+    marker: Marker,
+    pixels: Vec<bool>,
+    colors: Vec<Option<u8>>,
+    color: Option<u8>,
+}
+
+impl Canvas {
+    /// Canvas covering `cols` x `rows` cells.
+    pub fn new(cols: usize, rows: usize, marker: Marker) -> Self {
+        let (x, y) = marker.resolution();
+        let count = cols * x * rows * y;
+        Self {
+            cols,
+            rows,
+            marker,
+            pixels: vec![false; count],
+            colors: vec![None; count],
+            color: None,
+        }
+    }
+
+    /// Width and height in pixels. The origin is the upper left corner.
+    pub fn size(&self) -> (usize, usize) {
+        let (x, y) = self.marker.resolution();
+        (self.cols * x, self.rows * y)
+    }
+
+    /// Color of the pixels set from now on.
+    pub fn set_color(&mut self, color: Option<u8>) {
+        self.color = color;
+    }
+
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        let (width, height) = self.size();
+        x < width && y < height && self.pixels[y * width + x]
+    }
+
+    /// Sets a pixel, points outside of the canvas are ignored.
+    pub fn set_pixel(&mut self, x: isize, y: isize) {
+        let (width, height) = self.size();
+        let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) else {
+            return;
+        };
+        if x < width && y < height {
+            self.pixels[y * width + x] = true;
+            self.colors[y * width + x] = self.color;
+        }
+    }
+
+    /// Sets every pixel on the line between both points, ends included.
+    pub fn line(&mut self, (x0, y0): (isize, isize), (x1, y1): (isize, isize)) {
+        // Bresenham
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            self.set_pixel(x, y);
+            if x == x1 && y == y1 {
+                return;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    pub fn points(&mut self, points: impl IntoIterator<Item = (isize, isize)>) {
+        for (x, y) in points {
+            self.set_pixel(x, y);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(false);
+        self.colors.fill(None);
+    }
+
+    /// Symbol and color of a cell.
+    fn cell(&self, row: usize, col: usize) -> (char, Option<u8>) {
+        let (rx, ry) = self.marker.resolution();
+        let width = self.cols * rx;
+        let mut bits = 0u8;
+        let mut counts: Vec<(u8, usize)> = Vec::new();
+        for dy in 0..ry {
+            for dx in 0..rx {
+                let i = (row * ry + dy) * width + col * rx + dx;
+                if !self.pixels[i] {
+                    continue;
+                }
+                bits |= 1 << (dy * rx + dx);
+                if let Some(color) = self.colors[i] {
+                    match counts.iter_mut().find(|(c, _)| *c == color) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((color, 1)),
+                    }
+                }
+            }
+        }
+        let color = counts
+            .iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map(|&(color, _)| color);
+        (self.marker.symbol(bits), color)
+    }
+
+    /// Draws the canvas with its upper left corner at `row`, `col`,
+    /// clipped to `width` x `height` cells. Every cell of the clipped area is overwritten.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        let mut pen = None;
+        let mut symbol = [0; 4];
+        for r in 0..height.min(self.rows) {
+            terminfo.move_cursor(row + r, col)?;
+            for c in 0..width.min(self.cols) {
+                let (ch, color) = self.cell(r, c);
+                // Spaces don't show their color
+                let color = if ch == ' ' { pen } else { color };
+                if color != pen {
+                    match color {
+                        Some(color) => terminfo.set_a_foreground(color)?,
+                        None => terminfo.exit_attribute_mode()?,
+                    }
+                    pen = color;
+                }
+                terminfo.write_all(ch.encode_utf8(&mut symbol).as_bytes())?;
+            }
+        }
+        if pen.is_some() {
+            terminfo.exit_attribute_mode()?;
+        }
+        Ok(())
+    }
+}
+
+/// Bars of a series of values at sub-cell resolution, scaled to the area.
+///
+/// Every pixel column shows one value, when there are more values than
+/// columns only the last ones are shown. NaN and infinite values show no bar.
+#[derive(Debug, Clone)]
+pub struct Sparkline {
+    data: Vec<f64>,
+    max: Option<f64>,
+    marker: Marker,
+    color: Option<u8>,
+}
+
+impl Sparkline {
+    pub fn new(data: impl Into<Vec<f64>>) -> Self {
+        Self {
+            data: data.into(),
+            max: None,
+            marker: Marker::default(),
+            color: None,
+        }
+    }
+
+    /// Value of a full bar, by default the largest of the data.
+    /// Nothing is drawn when it isn't a positive finite number.
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn marker(mut self, marker: Marker) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    pub fn color(mut self, color: Option<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn set_data(&mut self, data: impl Into<Vec<f64>>) {
+        self.data = data.into();
+    }
+
+    /// Canvas of the bars for a `width` x `height` area.
+    pub fn canvas(&self, width: usize, height: usize) -> Canvas {
+        let mut canvas = Canvas::new(width, height, self.marker);
+        canvas.set_color(self.color);
+        let (columns, pixels) = canvas.size();
+        let data = &self.data[self.data.len().saturating_sub(columns)..];
+        let max = self.max.unwrap_or_else(|| {
+            data.iter()
+                .copied()
+                .filter(|value| value.is_finite())
+                .fold(0.0, f64::max)
+        });
+        if !(max.is_finite() && max > 0.0) || pixels == 0 {
+            return canvas;
+        }
+        for (x, &value) in data.iter().enumerate() {
+            if !value.is_finite() {
+                continue;
+            }
+            let bar = (value.clamp(0.0, max) / max * pixels as f64).round() as isize;
+            if bar > 0 {
+                let bottom = pixels as isize - 1;
+                canvas.line((x as isize, bottom), (x as isize, bottom + 1 - bar));
+            }
+        }
+        canvas
+    }
+
+    /// Draws the bars into the `width` x `height` area with its upper left corner at `row`, `col`.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        self.canvas(width, height)
+            .render(terminfo, row, col, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::render_screen;
+
+    fn sine(canvas: &mut Canvas) {
+        let (width, height) = canvas.size();
+        let amplitude = (height - 1) as f64 / 2.0;
+        let y = |x: usize| {
+            let phase = x as f64 / width as f64 * std::f64::consts::TAU;
+            (amplitude - phase.sin() * amplitude).round() as isize
+        };
+        for x in 1..width {
+            canvas.line(((x - 1) as isize, y(x - 1)), (x as isize, y(x)));
+        }
+    }
+
+    #[test]
+    fn sine_wave() {
+        let mut canvas = Canvas::new(8, 2, Marker::Braille);
+        sine(&mut canvas);
+        assert_eq!(
+            render_screen(canvas.cols, canvas.rows, |t, w, h| canvas
+                .render(t, 0, 0, w, h))
+            .text(),
+            "⢠⠊⠉⠢⡀\n⠁   ⠘⢄⣀⠔\n"
+        );
+
+        let mut canvas = Canvas::new(12, 3, Marker::HalfBlock);
+        sine(&mut canvas);
+        assert_eq!(
+            render_screen(canvas.cols, canvas.rows, |t, w, h| canvas
+                .render(t, 0, 0, w, h))
+            .text(),
+            " ▄▀▀▀▄\n▄▀    ▀▄\n       ▀▄▄▄▀\n"
+        );
+    }
+
+    #[test]
+    fn markers() {
+        let mut canvas = Canvas::new(2, 1, Marker::Quadrant);
+        canvas.points([(0, 0), (1, 1), (2, 1), (3, 1)]);
+        assert_eq!(
+            render_screen(canvas.cols, canvas.rows, |t, w, h| canvas
+                .render(t, 0, 0, w, h))
+            .text(),
+            "▚▄\n"
+        );
+
+        let mut canvas = Canvas::new(3, 1, Marker::Ascii);
+        canvas.line((0, 0), (0, 3));
+        canvas.points([(2, 0), (3, 3), (4, 0)]);
+        assert_eq!(
+            render_screen(canvas.cols, canvas.rows, |t, w, h| canvas
+                .render(t, 0, 0, w, h))
+            .text(),
+            ";,.\n"
+        );
+
+        // Points outside are clipped
+        let mut canvas = Canvas::new(1, 1, Marker::HalfBlock);
+        canvas.line((-5, 1), (5, 1));
+        assert_eq!(
+            render_screen(canvas.cols, canvas.rows, |t, w, h| canvas
+                .render(t, 0, 0, w, h))
+            .text(),
+            "▄\n"
+        );
+    }
+
+    #[test]
+    fn dominant_color() {
+        let mut canvas = Canvas::new(2, 1, Marker::Braille);
+        canvas.set_color(Some(1));
+        canvas.points([(0, 0), (0, 1)]);
+        canvas.set_color(Some(2));
+        canvas.points([(1, 0), (1, 1), (2, 0)]);
+        canvas.set_color(None);
+        canvas.points([(3, 0), (3, 1), (3, 2)]);
+        let screen = render_screen(canvas.cols, canvas.rows, |t, w, h| {
+            canvas.render(t, 0, 0, w, h)
+        });
+        // Two pixels each, the smaller index wins
+        assert_eq!(
+            screen.cell(0, 0).unwrap().style.fg,
+            crate::vt::Color::Indexed(1)
+        );
+        // Uncolored pixels lose against a single colored one
+        assert_eq!(
+            screen.cell(0, 1).unwrap().style.fg,
+            crate::vt::Color::Indexed(2)
+        );
+
+        let mut canvas = Canvas::new(1, 1, Marker::Braille);
+        canvas.set_color(Some(5));
+        canvas.set_pixel(0, 0);
+        canvas.set_color(Some(3));
+        canvas.set_pixel(1, 0);
+        let screen = render_screen(canvas.cols, canvas.rows, |t, w, h| {
+            canvas.render(t, 0, 0, w, h)
+        });
+        assert_eq!(
+            screen.cell(0, 0).unwrap().style.fg,
+            crate::vt::Color::Indexed(3)
+        );
+    }
+
+    #[test]
+    fn sparkline_scales_to_area() {
+        let data = [0.0, 1.0, 2.0, 3.0, 4.0, 3.0, 2.0, 1.0];
+        let sparkline = Sparkline::new(data).marker(Marker::HalfBlock);
+        let canvas = sparkline.canvas(8, 2);
+        assert_eq!(
+            render_screen(8, 2, |t, w, h| sparkline.render(t, 0, 0, w, h)).text(),
+            "   ▄█▄\n ▄█████▄\n"
+        );
+        assert_eq!(canvas.size(), (8, 4));
+
+        // Only the latest values fit
+        let canvas = Sparkline::new(data).max(4.0).canvas(1, 1);
+        assert!(canvas.is_set(0, 2) && !canvas.is_set(0, 1));
+        assert!(canvas.is_set(1, 3) && !canvas.is_set(1, 2));
+    }
+
+    #[test]
+    fn sparkline_ignores_non_finite() {
+        let data = [f64::NAN, 2.0, f64::INFINITY, 4.0, f64::NEG_INFINITY];
+        let canvas = Sparkline::new(data).canvas(5, 1);
+        let bars: Vec<_> = (0..5)
+            .map(|x| (0..4).filter(|&y| canvas.is_set(x, y)).count())
+            .collect();
+        assert_eq!(bars, [0, 2, 0, 4, 0]);
+        for max in [f64::NAN, f64::INFINITY, -1.0] {
+            let canvas = Sparkline::new(data).max(max).canvas(5, 1);
+            assert!((0..5).all(|x| (0..4).all(|y| !canvas.is_set(x, y))));
+        }
+    }
+}
@@ -0,0 +1,709 @@
+use std::time::{Duration, Instant};
+
+use crate::color::rgb_to_indexed;
+use crate::tty::errors::CapabilityError;
+use crate::tty::Capabilities;
+use crate::vt::{Alert, Buffer, Color, Model, Style};
+use crate::width::{str_width, Policy};
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum CastError {
+    #[error("Not an asciicast version 2 header.")]
+    InvalidHeader,
+    #[error("Line {0} is not a valid event.")]
+    InvalidEvent(usize),
+}
+
+/// Recording in the asciicast v2 format, only its output events are kept.
+#[derive(Debug, Clone)]
+pub struct Cast {
+    width: usize,
+    height: usize,
+    events: Vec<(Duration, Vec<u8>)>,
+}
+
+impl Cast {
+    /// Parses a whole cast file. Input, marker and resize events are skipped,
+    /// the recording is played at the size of its header, which can't be empty.
+    pub fn parse(text: &str) -> Result<Self, CastError> {
+        let mut lines = text.lines();
+        let header = lines.next().ok_or(CastError::InvalidHeader)?;
+        let field = |name| header_field(header, name).ok_or(CastError::InvalidHeader);
+        if field("version")? != 2 {
+            return Err(CastError::InvalidHeader);
+        }
+        let (width, height) = (field("width")?, field("height")?);
+        if width == 0 || height == 0 {
+            return Err(CastError::InvalidHeader);
+        }
+
+        let mut events = Vec::new();
+        for (i, line) in lines.enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (time, kind, data) = parse_event(line).ok_or(CastError::InvalidEvent(i + 2))?;
+            if kind == "o" {
+                events.push((time, data.into_bytes()));
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            events,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Time of the last output event.
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |&(time, _)| time)
+    }
+}
+
+/// Unsigned number of a top level field of the header object. Keys are only looked
+/// for at the top level, not in nested objects or in strings like the title.
+fn header_field(header: &str, name: &str) -> Option<usize> {
+    let mut json = Json(header.trim());
+    json.expect('{')?;
+    loop {
+        let key = json.string()?;
+        json.expect(':')?;
+        if key == name {
+            return json.unsigned();
+        }
+        json.skip_value()?;
+        json.expect(',')?;
+    }
+}
+
+/// `[time, "kind", "data"]`
+fn parse_event(line: &str) -> Option<(Duration, String, String)> {
+    let mut json = Json(line.trim());
+    json.expect('[')?;
+    let time = json.number()?;
+    json.expect(',')?;
+    let kind = json.string()?;
+    json.expect(',')?;
+    let data = json.string()?;
+    json.expect(']')?;
+    json.0.is_empty().then_some(())?;
+    Some((Duration::try_from_secs_f64(time).ok()?, kind, data))
+}
+
+/// Just enough of JSON for the events of a cast.
+struct Json<'a>(&'a str);
+
+impl Json<'_> {
+    fn expect(&mut self, ch: char) -> Option<()> {
+        self.0 = self.0.trim_start().strip_prefix(ch)?.trim_start();
+        Some(())
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        let end = self
+            .0
+            .find(|ch: char| !(ch.is_ascii_digit() || matches!(ch, '.' | 'e' | 'E' | '+' | '-')))
+            .unwrap_or(self.0.len());
+        let (number, rest) = self.0.split_at(end);
+        self.0 = rest;
+        number.parse().ok()
+    }
+
+    fn unsigned(&mut self) -> Option<usize> {
+        let end = self
+            .0
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(self.0.len());
+        let (number, rest) = self.0.split_at(end);
+        self.0 = rest;
+        number.parse().ok()
+    }
+
+    /// Skips a value of any kind with what is nested in it.
+    fn skip_value(&mut self) -> Option<()> {
+        let mut depth = 0usize;
+        loop {
+            match self.0.chars().next()? {
+                '"' => {
+                    self.string()?;
+                }
+                '[' | '{' => {
+                    depth += 1;
+                    self.0 = &self.0[1..];
+                }
+                ']' | '}' | ',' | ':' if depth > 0 => {
+                    if !self.0.starts_with([',', ':']) {
+                        depth -= 1;
+                    }
+                    self.0 = &self.0[1..];
+                }
+                // Numbers, true, false and null
+                _ => {
+                    let end = self
+                        .0
+                        .find(|ch: char| "[]{},:\"".contains(ch) || ch.is_whitespace())
+                        .unwrap_or(self.0.len());
+                    if end == 0 {
+                        return None;
+                    }
+                    self.0 = &self.0[end..];
+                }
+            }
+            self.0 = self.0.trim_start();
+            if depth == 0 {
+                return Some(());
+            }
+        }
+    }
+
+    fn hex(&mut self) -> Option<u32> {
+        let digits = self.0.get(..4)?;
+        self.0 = &self.0[4..];
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.0 = self.0.strip_prefix('"')?;
+        let mut string = String::new();
+        loop {
+            let mut chars = self.0.chars();
+            let ch = chars.next()?;
+            self.0 = chars.as_str();
+            match ch {
+                '"' => return Some(string),
+                '\\' => {
+                    let mut chars = self.0.chars();
+                    let escape = chars.next()?;
+                    self.0 = chars.as_str();
+                    string.push(match escape {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => {
+                            let unit = self.hex()?;
+                            let code = if (0xD800..0xDC00).contains(&unit) {
+                                // High surrogate, the low one follows as another escape
+                                self.0 = self.0.strip_prefix("\\u")?;
+                                let low = self.hex()?;
+                                0x10000 + ((unit - 0xD800) << 10) + low.checked_sub(0xDC00)?
+                            } else {
+                                unit
+                            };
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        other => other,
+                    });
+                }
+                ch => string.push(ch),
+            }
+        }
+    }
+}
+
+/// How a recording is placed into an area of a different size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// Upper left corner at the corner of the area, what doesn't fit is cut off.
+    #[default]
+    Crop,
+    /// Centered, with blank bars around it in a larger area and cut evenly
+    /// on both sides in a smaller one.
+    Letterbox,
+}
+
+impl Fit {
+    /// Columns or rows of the recording skipped and of the area left blank before it.
+    fn offsets(self, area: usize, size: usize) -> (usize, usize) {
+        match self {
+            Self::Crop => (0, 0),
+            Self::Letterbox if area < size => ((size - area) / 2, 0),
+            Self::Letterbox => (0, (area - size) / 2),
+        }
+    }
+}
+
+/// Plays a [`Cast`] through the [`vt`](crate::vt) model, e. g. for demo panes.
+///
+/// The player doesn't keep time itself, [`CastPlayer::update`] is called on every tick
+/// of the application, or [`CastPlayer::advance`] with the time that passed.
+#[derive(Debug, Clone)]
+pub struct CastPlayer {
+    cast: Cast,
+    model: Model,
+    /// Events fed to the model so far.
+    played: usize,
+    position: Duration,
+    /// Time of the last [`CastPlayer::update`].
+    last_update: Option<Instant>,
+    speed: f64,
+    paused: bool,
+    fit: Fit,
+    forward_notifications: bool,
+}
+
+impl CastPlayer {
+    /// Slowest playback speed other than stopped, see [`CastPlayer::set_speed`].
+    pub const MIN_SPEED: f64 = 1.0 / 1024.0;
+    /// Fastest playback speed, see [`CastPlayer::set_speed`].
+    pub const MAX_SPEED: f64 = 1024.0;
+
+    pub fn new(cast: Cast) -> Self {
+        Self {
+            model: Model::new(cast.width, cast.height),
+            cast,
+            played: 0,
+            position: Duration::ZERO,
+            last_update: None,
+            speed: 1.0,
+            paused: false,
+            fit: Fit::default(),
+            forward_notifications: false,
+        }
+    }
+
+    /// Factor the time passed to [`CastPlayer::advance`] is scaled by.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.set_speed(speed);
+        self
+    }
+
+    pub fn fit(mut self, fit: Fit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// Whether [`CastPlayer::forward_alerts`] passes desktop notifications of the
+    /// recording on to the terminal. Off by default, they reach outside the application.
+    pub fn forward_notifications(mut self, forward: bool) -> Self {
+        self.forward_notifications = forward;
+        self
+    }
+
+    /// Clamped to [`CastPlayer::MIN_SPEED`] and [`CastPlayer::MAX_SPEED`],
+    /// 0 and below or NaN stop the playback.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = if speed > 0.0 {
+            speed.clamp(Self::MIN_SPEED, Self::MAX_SPEED)
+        } else {
+            0.0
+        };
+    }
+
+    pub fn cast(&self) -> &Cast {
+        &self.cast
+    }
+
+    /// Time into the recording.
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Whether every event has been played.
+    pub fn is_finished(&self) -> bool {
+        self.played == self.cast.events.len()
+    }
+
+    /// Called on every tick, moves the playback on by the time since the last call,
+    /// see [`CastPlayer::advance`]. Returns whether the screen changed.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let played = self.played;
+        if let Some(last) = self.last_update {
+            self.advance(now.saturating_duration_since(last));
+        }
+        self.last_update = Some(now);
+        self.played != played
+    }
+
+    /// Time until the next event at the current speed, e. g. to wait no longer than
+    /// that for input. `None` when paused, stopped or finished.
+    pub fn until_next_event(&self) -> Option<Duration> {
+        let (time, _) = self.cast.events.get(self.played)?;
+        if self.paused || self.speed == 0.0 {
+            return None;
+        }
+        let wait = (*time - self.position).as_secs_f64() / self.speed;
+        Some(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+    }
+
+    /// Moves the playback on by `elapsed` times the speed, unless paused.
+    pub fn advance(&mut self, elapsed: Duration) {
+        if !self.paused {
+            let elapsed = Duration::try_from_secs_f64(elapsed.as_secs_f64() * self.speed);
+            self.seek(self.position.saturating_add(elapsed.unwrap_or(Duration::MAX)));
+        }
+    }
+
+    /// Jumps to `position`. Going back replays the recording from the start,
+    /// without raising the alerts of the replayed part again.
+    pub fn seek(&mut self, position: Duration) {
+        let rewind = position < self.position;
+        let urgent = self.model.is_urgent();
+        if rewind {
+            self.model = Model::new(self.cast.width, self.cast.height);
+            self.played = 0;
+        }
+        self.position = position;
+        while let Some((time, data)) = self.cast.events.get(self.played) {
+            if *time > position {
+                break;
+            }
+            self.model.feed(data);
+            self.played += 1;
+        }
+        if rewind {
+            self.model.take_alerts();
+            if !urgent {
+                self.model.clear_urgent();
+            }
+        }
+    }
+
+    /// Whether the recording rang the bell or sent a notification since
+    /// [`CastPlayer::clear_urgent`], e. g. to highlight the border of the pane.
+    pub fn is_urgent(&self) -> bool {
+        self.model.is_urgent()
+    }
+
+    pub fn clear_urgent(&mut self) {
+        self.model.clear_urgent();
+    }
+
+    /// Rings the bell of the terminal once if the recording rang it since the last call.
+    /// Notifications are passed on as well when enabled with
+    /// [`CastPlayer::forward_notifications`].
+    pub fn forward_alerts(
+        &mut self,
+        terminfo: &mut impl Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let mut bell = false;
+        for alert in self.model.take_alerts() {
+            match alert {
+                Alert::Bell | Alert::VisualBell => bell = true,
+                Alert::Notification(sequence) if self.forward_notifications => {
+                    terminfo.append_host(&sequence);
+                }
+                Alert::Notification(_) => {}
+            }
+        }
+        if bell {
+            terminfo.bell()?;
+        }
+        Ok(())
+    }
+
+    /// Terminal model at the current position, e. g. for its [`Model::marks`].
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Screen of the recording at the current position.
+    pub fn screen(&self) -> &Buffer {
+        self.model.screen()
+    }
+
+    /// Draws the screen into the `width` x `height` area with its upper left corner
+    /// at `row`, `col`, placed by the [`Fit`]. Every cell of the area is overwritten.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        let screen = self.screen();
+        let policy = Policy::default();
+        let (skip_rows, pad_rows) = self.fit.offsets(height, screen.height());
+        let (skip_cols, pad_cols) = self.fit.offsets(width, screen.width());
+        let mut pen = Style::default();
+        for r in 0..height {
+            terminfo.move_cursor(row + r, col)?;
+            let source = (r + skip_rows).checked_sub(pad_rows);
+            let mut c = 0;
+            while c < width {
+                let cell = source.zip((c + skip_cols).checked_sub(pad_cols));
+                let cell = cell.and_then(|(r, c)| screen.cell(r, c));
+                let style = cell.map_or_else(Style::default, |cell| cell.style);
+                if style != pen {
+                    apply_style(terminfo, &style)?;
+                    pen = style;
+                }
+                let symbol = cell.map_or("", |cell| cell.symbol.as_str());
+                let columns = str_width(symbol, &policy);
+                // Halves of wide characters cut off by the edges are left blank
+                if columns == 0 || c + columns > width {
+                    terminfo.write_all(b" ")?;
+                    c += 1;
+                } else {
+                    terminfo.write_all(symbol.as_bytes())?;
+                    c += columns;
+                }
+            }
+        }
+        if pen != Style::default() {
+            terminfo.exit_attribute_mode()?;
+        }
+        Ok(())
+    }
+}
+
+/// Resets the attributes and sets those of `style`, the ones the terminal lacks are left out.
+fn apply_style(terminfo: &mut impl Capabilities, style: &Style) -> Result<(), CapabilityError> {
+    let optional = |result| match result {
+        Err(CapabilityError::CapabilityNotFound { .. }) => Ok(()),
+        result => result,
+    };
+    terminfo.exit_attribute_mode()?;
+    if style.bold {
+        optional(terminfo.enter_bold_mode())?;
+    }
+    if style.dim {
+        optional(terminfo.enter_dim_mode())?;
+    }
+    if style.italic {
+        optional(terminfo.enter_italics_mode())?;
+    }
+    if style.underline {
+        optional(terminfo.enter_underline_mode())?;
+    }
+    if style.blink {
+        optional(terminfo.enter_blink_mode())?;
+    }
+    if style.reverse {
+        optional(terminfo.enter_reverse_mode())?;
+    }
+    if style.invisible {
+        optional(terminfo.enter_secure_mode())?;
+    }
+    if style.strikethrough {
+        optional(terminfo.enter_crossed_out_mode())?;
+    }
+    if style.overline {
+        optional(terminfo.enter_overline_mode())?;
+    }
+    let index = |color| match color {
+        Color::Default => None,
+        Color::Indexed(index) => Some(index),
+        Color::Rgb(r, g, b) => Some(rgb_to_indexed(r, g, b)),
+    };
+    if let Some(color) = index(style.fg) {
+        terminfo.set_a_foreground(color)?;
+    }
+    if let Some(color) = index(style.bg) {
+        terminfo.set_a_background(color)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{render_screen, sequences};
+
+    fn player() -> CastPlayer {
+        let text = std::fs::read_to_string("assets/test.cast").unwrap();
+        CastPlayer::new(Cast::parse(&text).unwrap())
+    }
+
+    #[test]
+    fn parse() {
+        let cast = player().cast;
+        assert_eq!((cast.width(), cast.height()), (10, 3));
+        assert_eq!(cast.events.len(), 4);
+        assert_eq!(cast.duration(), Duration::from_millis(1500));
+        assert_eq!(cast.events[3].1, b"\x1B[H\x1B[2J\"done\"");
+
+        assert_eq!(
+            Cast::parse("{\"version\": 1}").unwrap_err(),
+            CastError::InvalidHeader
+        );
+        let header = "{\"version\": 2, \"width\": 4, \"height\": 1}\n";
+        assert_eq!(
+            Cast::parse(&format!("{header}[1, \"o\"]")).unwrap_err(),
+            CastError::InvalidEvent(2)
+        );
+        let cast = Cast::parse(&format!("{header}[0.5, \"o\", \"\\ud83d\\ude00\\t\"]")).unwrap();
+        assert_eq!(cast.events[0].1, "😀\t".as_bytes());
+        for header in [
+            "{\"version\": 2, \"width\": 0, \"height\": 1}",
+            "{\"version\": 2, \"width\": 4, \"height\": 0}",
+            "{\"version\": 2, \"width\": -4, \"height\": 1}",
+        ] {
+            assert_eq!(
+                Cast::parse(&format!("{header}\n[0.5, \"o\", \"x\"]")).unwrap_err(),
+                CastError::InvalidHeader
+            );
+        }
+    }
+
+    #[test]
+    fn speed_bounds() {
+        let mut player = player();
+        for speed in [f64::NAN, -1.0, f64::NEG_INFINITY] {
+            player.set_speed(speed);
+            assert_eq!(player.until_next_event(), None);
+            player.advance(Duration::from_secs(1));
+            assert_eq!(player.position(), Duration::ZERO);
+        }
+        player.set_speed(f64::MIN_POSITIVE);
+        assert_eq!(
+            player.until_next_event(),
+            Some(Duration::from_millis(100 * 1024))
+        );
+        player.advance(Duration::from_secs(1024));
+        assert_eq!(player.position(), Duration::from_secs(1));
+
+        player.set_speed(f64::INFINITY);
+        player.advance(Duration::from_millis(1));
+        assert_eq!(player.position(), Duration::from_millis(2024));
+        player.advance(Duration::MAX);
+        assert_eq!(player.position(), Duration::MAX);
+        assert_eq!(player.until_next_event(), None);
+    }
+
+    #[test]
+    fn header_keys_at_top_level() {
+        let header = concat!(
+            r#"{"title": "\"width\": 1", "env": {"width": 2, "x": [3, {"height": 4}]}, "#,
+            r#""idle_time_limit": -1.5e2, "tags": null, "version": 2, "width": 80, "height": 24}"#,
+        );
+        assert_eq!(header_field(header, "width"), Some(80));
+        assert_eq!(header_field(header, "height"), Some(24));
+        assert_eq!(header_field(header, "timestamp"), None);
+        // Only in nested objects
+        let header = r#"{"version": 2, "env": {"width": 2, "height": 1}}"#;
+        assert_eq!(Cast::parse(header).unwrap_err(), CastError::InvalidHeader);
+        assert_eq!(header_field(r#"{"version": 2 "width": 1}"#, "width"), None);
+        assert_eq!(header_field(r#"{"a": ], "width": 1}"#, "width"), None);
+    }
+
+    #[test]
+    fn update_on_tick() {
+        let mut player = player();
+        let start = Instant::now();
+        assert!(!player.update(start));
+        assert_eq!(player.until_next_event(), Some(Duration::from_millis(100)));
+        assert!(!player.update(start + Duration::from_millis(50)));
+        assert!(player.update(start + Duration::from_millis(120)));
+        assert_eq!(
+            render_screen(10, 3, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "$ ls\n\n\n"
+        );
+        assert_eq!(player.until_next_event(), Some(Duration::from_millis(380)));
+
+        // Time paused doesn't count later
+        player.set_paused(true);
+        assert!(!player.update(start + Duration::from_secs(5)));
+        assert_eq!(player.until_next_event(), None);
+        player.set_paused(false);
+        player.set_speed(2.0);
+        assert_eq!(player.until_next_event(), Some(Duration::from_millis(190)));
+        assert!(player.update(start + Duration::from_millis(5190)));
+        assert_eq!(player.position(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn frames_at_timestamps() {
+        let mut player = player();
+        player.advance(Duration::from_millis(50));
+        assert_eq!(
+            render_screen(10, 3, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "\n\n\n"
+        );
+
+        player.advance(Duration::from_millis(950));
+        assert_eq!(
+            render_screen(10, 3, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "$ ls\na.txt bé\n$ clear\n"
+        );
+        assert!(player.screen().cell(1, 6).unwrap().style.bold);
+        let screen = render_screen(10, 3, |t, w, h| player.render(t, 0, 0, w, h));
+        assert!(screen.cell(1, 6).unwrap().style.bold);
+        assert!(!screen.cell(1, 8).unwrap().style.bold);
+
+        player.set_paused(true);
+        player.advance(Duration::from_secs(1));
+        assert!(!player.is_finished());
+        player.set_paused(false);
+        player.set_speed(2.0);
+        player.advance(Duration::from_millis(250));
+        assert_eq!(player.position(), Duration::from_millis(1500));
+        assert!(player.is_finished());
+        assert_eq!(
+            render_screen(10, 3, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "\"done\"\n\n\n"
+        );
+
+        player.seek(Duration::from_millis(100));
+        assert_eq!(
+            render_screen(10, 3, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "$ ls\n\n\n"
+        );
+    }
+
+    #[test]
+    fn fit() {
+        let mut player = player();
+        player.seek(Duration::from_secs(1));
+        assert_eq!(
+            render_screen(4, 2, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "$ ls\na.tx\n"
+        );
+        let player = player.fit(Fit::Letterbox);
+        assert_eq!(
+            render_screen(4, 2, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "s\nxt b\n"
+        );
+        assert_eq!(
+            render_screen(12, 5, |t, w, h| player.render(t, 0, 0, w, h)).text(),
+            "\n $ ls\n a.txt bé\n $ clear\n\n"
+        );
+    }
+
+    #[test]
+    fn alerts() {
+        let text = concat!(
+            "{\"version\": 2, \"width\": 4, \"height\": 1}\n",
+            "[0.1, \"o\", \"a\\u0007\\u0007\"]\n",
+            "[0.2, \"o\", \"\\u001b]9;built\\u0007\"]\n",
+        );
+        let mut player = CastPlayer::new(Cast::parse(text).unwrap());
+        let mut terminfo = sequences();
+        player.advance(Duration::from_millis(100));
+        assert!(player.is_urgent());
+        player.forward_alerts(&mut terminfo).unwrap();
+        player.advance(Duration::from_millis(100));
+        player.forward_alerts(&mut terminfo).unwrap();
+        // Bells are rung once per call, notifications are kept in by default
+        assert_eq!(terminfo.buffer(), b"\x07");
+
+        // Replaying after a rewind doesn't alert again
+        player.clear_urgent();
+        player.seek(Duration::from_millis(150));
+        assert!(!player.is_urgent());
+        let mut player = player.forward_notifications(true);
+        player.advance(Duration::from_millis(50));
+        assert!(player.is_urgent());
+        let mut terminfo = sequences();
+        player.forward_alerts(&mut terminfo).unwrap();
+        assert_eq!(terminfo.buffer(), b"\x1B]9;built\x07");
+    }
+}
@@ -0,0 +1,339 @@
+use crate::input::keymap::{format_chord, normalize, Chord, Keymap};
+use crate::input::{constants as c, EventType, KeyCode, KeyEvent, Modifiers};
+use crate::tty::errors::CapabilityError;
+use crate::tty::Capabilities;
+use crate::width::{str_width, Policy};
+
+use super::write_clipped;
+
+/// Columns between two columns of sections.
+const GAP: usize = 3;
+
+/// Bindings of one category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    category: String,
+    /// Keys and description of every binding.
+    bindings: Vec<(String, String)>,
+}
+
+/// Lists the described bindings of a keymap, see [`Keymap::bind_with_help`].
+///
+/// Bindings are grouped by category, in the order the categories first appear.
+/// Sections are laid out in as many columns as the area fits, filled top to bottom,
+/// a section that doesn't fit below another starts the next column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpScreen {
+    sections: Vec<Section>,
+}
+
+impl HelpScreen {
+    /// Bindings currently reachable in `keymap`, see [`Keymap::help`].
+    pub fn new<A: Clone>(keymap: &Keymap<A>) -> Self {
+        let mut sections: Vec<Section> = Vec::new();
+        for (keys, help) in keymap.help() {
+            let keys = keys.iter().map(format_chord).collect::<Vec<_>>().join(" ");
+            let binding = (keys, help.description.clone());
+            match sections.iter_mut().find(|s| s.category == help.category) {
+                Some(section) => section.bindings.push(binding),
+                None => sections.push(Section {
+                    category: help.category.clone(),
+                    bindings: vec![binding],
+                }),
+            }
+        }
+        Self { sections }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Draws the bindings into the `width` x `height` area with its upper left corner
+    /// at `row`, `col`. Every row of the area is overwritten, sections that don't fit
+    /// are left out and the end of a section taller than the area is cut.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let policy = Policy::default();
+        let key_width = self
+            .sections
+            .iter()
+            .flat_map(|section| &section.bindings)
+            .map(|(keys, _)| str_width(keys, &policy))
+            .max()
+            .unwrap_or(0);
+        // Headings are `None`, separated from the section above by an empty line
+        let sections: Vec<Vec<Option<String>>> = self
+            .sections
+            .iter()
+            .map(|section| {
+                let mut lines = vec![None];
+                lines.extend(section.bindings.iter().map(|(keys, description)| {
+                    let pad = key_width - str_width(keys, &policy);
+                    Some(format!("{}{keys}  {description}", " ".repeat(pad)))
+                }));
+                lines
+            })
+            .collect();
+        let column_width = self
+            .sections
+            .iter()
+            .map(|section| str_width(&section.category, &policy))
+            .chain(
+                sections
+                    .iter()
+                    .flatten()
+                    .flatten()
+                    .map(|line| str_width(line, &policy)),
+            )
+            .max()
+            .unwrap_or(0)
+            .min(width);
+        let columns = ((width + GAP) / (column_width + GAP)).max(1);
+
+        // Section and line of it shown in every row of every column
+        let mut layout: Vec<Vec<Option<(usize, usize)>>> = vec![Vec::new()];
+        for (index, lines) in sections.iter().enumerate() {
+            let column = layout.last_mut().unwrap();
+            let gap = usize::from(!column.is_empty());
+            if !column.is_empty() && column.len() + gap + lines.len() > height {
+                if layout.len() == columns {
+                    break;
+                }
+                layout.push(Vec::new());
+            } else if gap > 0 {
+                column.push(None);
+            }
+            let column = layout.last_mut().unwrap();
+            column.extend((0..lines.len()).map(|line| Some((index, line))));
+        }
+
+        for r in 0..height {
+            terminfo.move_cursor(row + r, col)?;
+            let mut used = 0;
+            for (c, column) in layout.iter().enumerate() {
+                if c > 0 {
+                    write_clipped(terminfo, "", GAP, &policy)?;
+                    used += GAP;
+                }
+                match column.get(r).copied().flatten() {
+                    Some((index, 0)) => {
+                        terminfo.enter_bold_mode()?;
+                        let category = &self.sections[index].category;
+                        write_clipped(terminfo, category, column_width, &policy)?;
+                        terminfo.exit_attribute_mode()?;
+                    }
+                    Some((index, line)) => {
+                        let text = sections[index][line].as_deref().unwrap_or_default();
+                        write_clipped(terminfo, text, column_width, &policy)?;
+                    }
+                    None => write_clipped(terminfo, "", column_width, &policy)?,
+                }
+                used += column_width;
+            }
+            write_clipped(terminfo, "", width.saturating_sub(used), &policy)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shows a [`HelpScreen`] over the application, opened with F1 by default.
+///
+/// Keys are fed to [`HelpOverlay::handle`] before the application sees them.
+/// While the overlay is open it takes every key, the open key, Esc and `q` close it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpOverlay {
+    key: Chord,
+    open: bool,
+}
+
+impl Default for HelpOverlay {
+    fn default() -> Self {
+        Self {
+            key: (KeyCode(c::F1), Modifiers::NONE),
+            open: false,
+        }
+    }
+}
+
+impl HelpOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the overlay with `key` instead of F1.
+    pub fn with_key(mut self, key: Chord) -> Self {
+        self.key = normalize(key);
+        self
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Returns whether the overlay took `event`, the application handles it otherwise.
+    pub fn handle(&mut self, event: &KeyEvent) -> bool {
+        let chord = normalize((event.key_code, event.mods));
+        if !self.open {
+            let open = chord == self.key && !matches!(event.event_type, EventType::Release);
+            self.open = open;
+            return open;
+        }
+        if matches!(event.event_type, EventType::Release) {
+            return true;
+        }
+        let close = chord == self.key
+            || chord == (KeyCode(c::ESCAPE), Modifiers::NONE)
+            || chord == (KeyCode(b'q'.into()), Modifiers::NONE);
+        self.open = !close;
+        true
+    }
+
+    /// Draws the help of `keymap` into the area while the overlay is open,
+    /// nothing otherwise. See [`HelpScreen::render`].
+    pub fn render<A: Clone>(
+        &self,
+        keymap: &Keymap<A>,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        if !self.open {
+            return Ok(());
+        }
+        HelpScreen::new(keymap).render(terminfo, row, col, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keymap::{parse_sequence, Help, LookupResult};
+    use crate::testing::{render_screen, sequences};
+
+    fn keymap() -> Keymap<&'static str> {
+        let mut keymap = Keymap::new();
+        let bindings = [
+            ("Moving", "g g", "top"),
+            ("Moving", "G", "bottom"),
+            ("Moving", "ctrl+d", "page down"),
+            ("Editing", "x", "delete"),
+            ("Editing", "u", "undo"),
+            ("Files", "ctrl+s", "save"),
+            ("Files", "space q", "quit"),
+            ("Moving", "ctrl+u", "page up"),
+        ];
+        for (category, keys, description) in bindings {
+            keymap
+                .bind_str_with_help(
+                    "normal",
+                    keys,
+                    description,
+                    Help::new(category, description),
+                )
+                .unwrap();
+        }
+        // Not described, so not listed
+        keymap.bind_str("normal", "z z", "center").unwrap();
+        keymap
+    }
+
+    #[test]
+    fn categories() {
+        let help = HelpScreen::new(&keymap());
+        assert_eq!(
+            render_screen(20, 16, |t, w, h| help.render(t, 0, 0, w, h)).text(),
+            concat!(
+                "Moving\n",
+                "    g g  top\n",
+                "      G  bottom\n",
+                " ctrl+d  page down\n",
+                " ctrl+u  page up\n",
+                "\n",
+                "Editing\n",
+                "      x  delete\n",
+                "      u  undo\n",
+                "\n",
+                "Files\n",
+                " ctrl+s  save\n",
+                "space q  quit\n",
+                "\n",
+                "\n",
+                "\n",
+            )
+        );
+    }
+
+    // Headings are bold, which only the annotated snapshot shows
+    #[cfg(feature = "terminfo")]
+    #[test]
+    fn columns_snapshot() {
+        let help = HelpScreen::new(&keymap());
+        let screen = render_screen(60, 5, |t, w, h| help.render(t, 0, 0, w, h));
+        crate::assert_buffer_snapshot!(screen, "help_screen_columns");
+    }
+
+    #[test]
+    fn overlay_takes_keys_while_open() {
+        let mut keymap = keymap();
+        let mut overlay = HelpOverlay::new();
+        let key = |text: &str| {
+            let (key_code, mods) = parse_sequence(text).unwrap()[0];
+            KeyEvent {
+                key_code,
+                mods,
+                ..Default::default()
+            }
+        };
+
+        // Keys reach the keymap only when the overlay doesn't take them
+        let mut feed = |overlay: &mut HelpOverlay, text| {
+            if !overlay.handle(&key(text)) {
+                keymap.lookup(&key(text));
+            }
+        };
+        feed(&mut overlay, "g");
+        feed(&mut overlay, "f1");
+        assert!(overlay.is_open());
+        for text in ["down", "pagedown", "g", "ctrl+d", "enter"] {
+            feed(&mut overlay, text);
+        }
+        assert!(overlay.is_open());
+        feed(&mut overlay, "esc");
+        assert!(!overlay.is_open());
+        // Still pending from before the overlay opened
+        assert_eq!(keymap.lookup(&key("g")), LookupResult::Matched("top"));
+        // Esc as a legacy byte closes it too
+        overlay.set_open(true);
+        assert!(overlay.handle(&KeyEvent {
+            key_code: 0x1B_u8.into(),
+            ..Default::default()
+        }));
+        assert!(!overlay.is_open());
+
+        let mut terminfo = sequences();
+        overlay.render(&keymap, &mut terminfo, 0, 0, 20, 3).unwrap();
+        assert!(terminfo.buffer().is_empty());
+        overlay.set_open(true);
+        overlay.render(&keymap, &mut terminfo, 0, 0, 20, 3).unwrap();
+        assert_eq!(
+            crate::vt::replay(terminfo.buffer(), (20, 3)).text(),
+            "Moving\n    g g  top\n      G  bottom\n"
+        );
+    }
+}
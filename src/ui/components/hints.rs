@@ -0,0 +1,253 @@
+use std::time::{Duration, Instant};
+
+use crate::input::keymap::{format_chord, Chord, Continuation, Keymap};
+use crate::tty::errors::CapabilityError;
+use crate::tty::Capabilities;
+use crate::width::{str_width, Policy};
+
+use super::write_clipped;
+
+/// Columns between two columns of hints.
+const GAP: usize = 2;
+
+/// Panel listing the keys that continue a pending key sequence, like which-key.
+///
+/// Actions come first, then the prefixes of longer sequences, each sorted by key.
+/// Hints are laid out in as many columns as the area fits, filled top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyHints {
+    /// Key and label of every hint.
+    hints: Vec<(String, String)>,
+}
+
+impl KeyHints {
+    /// Hints for the keys pending in `keymap`, actions are labeled by `label`.
+    pub fn new<A: Clone>(keymap: &Keymap<A>, label: impl Fn(&A) -> String) -> Self {
+        Self::for_prefix(keymap, keymap.pending(), label)
+    }
+
+    /// Hints for the keys that continue `prefix`.
+    pub fn for_prefix<A: Clone>(
+        keymap: &Keymap<A>,
+        prefix: &[Chord],
+        label: impl Fn(&A) -> String,
+    ) -> Self {
+        let mut hints: Vec<_> = keymap
+            .continuations(prefix)
+            .into_iter()
+            .map(|(chord, continuation)| {
+                let (group, label) = match continuation {
+                    Continuation::Action(action) => (false, label(action)),
+                    Continuation::Prefix(count) => (true, format!("+{count} more")),
+                };
+                (group, format_chord(&chord), label)
+            })
+            .collect();
+        hints.sort();
+        Self {
+            hints: hints
+                .into_iter()
+                .map(|(_, key, label)| (key, label))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    /// Draws the hints into the `width` x `height` area with its upper left corner
+    /// at `row`, `col`. Every row of the area is overwritten, hints that don't fit are left out.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let policy = Policy::default();
+        let key_width = self
+            .hints
+            .iter()
+            .map(|(key, _)| str_width(key, &policy))
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<String> = self
+            .hints
+            .iter()
+            .map(|(key, label)| {
+                let pad = key_width - str_width(key, &policy);
+                format!("{}{key} {label}", " ".repeat(pad))
+            })
+            .collect();
+        let column_width = lines
+            .iter()
+            .map(|line| str_width(line, &policy))
+            .max()
+            .unwrap_or(0)
+            .min(width);
+        let columns = ((width + GAP) / (column_width + GAP)).max(1);
+        let rows = lines.len().div_ceil(columns).clamp(1, height);
+
+        for r in 0..height {
+            terminfo.move_cursor(row + r, col)?;
+            let mut used = 0;
+            for c in 0..columns {
+                let Some(line) = lines.get(c * rows + r).filter(|_| r < rows) else {
+                    break;
+                };
+                if c > 0 {
+                    write_clipped(terminfo, "", GAP, &policy)?;
+                }
+                write_clipped(terminfo, line, column_width, &policy)?;
+                used += column_width + if c > 0 { GAP } else { 0 };
+            }
+            write_clipped(terminfo, "", width - used, &policy)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decides when [`KeyHints`] are shown: once the same keys have been pending for the delay,
+/// until the sequence completes or is cancelled.
+#[derive(Debug, Clone)]
+pub struct HintDelay {
+    delay: Duration,
+    pending: Vec<Chord>,
+    since: Option<Instant>,
+}
+
+impl HintDelay {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: Vec::new(),
+            since: None,
+        }
+    }
+
+    /// Called on every tick and after every key with [`Keymap::pending`],
+    /// returns whether the hints are shown.
+    pub fn update(&mut self, pending: &[Chord], now: Instant) -> bool {
+        if pending.is_empty() {
+            self.since = None;
+        } else if self.since.is_none() || self.pending != pending {
+            self.since = Some(now);
+        }
+        self.pending = pending.to_vec();
+        self.since
+            .is_some_and(|since| now.duration_since(since) >= self.delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keymap::parse_sequence;
+    use crate::testing::render_screen;
+
+    fn keymap() -> Keymap<&'static str> {
+        let mut keymap = Keymap::new();
+        let bindings = [
+            ("space f", "files"),
+            ("space b", "buffers"),
+            ("space s", "save"),
+            ("space q", "quit"),
+            ("space w", "write"),
+            ("space ctrl+r", "reload"),
+            ("space tab", "last"),
+            ("space 1", "first"),
+            ("space g s", "status"),
+            ("space g c", "commit"),
+            ("space l r", "rename"),
+            ("space l f", "format"),
+            ("space l a", "actions"),
+        ];
+        for (keys, action) in bindings {
+            keymap.bind_str("normal", keys, action).unwrap();
+        }
+        keymap
+    }
+
+    #[test]
+    fn columns() {
+        let keymap = keymap();
+        let hints = KeyHints::for_prefix(&keymap, &parse_sequence("space").unwrap(), |a| {
+            a.to_string()
+        });
+        assert_eq!(hints.hints.len(), 10);
+
+        assert_eq!(
+            render_screen(20, 12, |t, w, h| hints.render(t, 0, 0, w, h)).text(),
+            concat!(
+                "     1 first\n",
+                "     b buffers\n",
+                "ctrl+r reload\n",
+                "     f files\n",
+                "     q quit\n",
+                "     s save\n",
+                "   tab last\n",
+                "     w write\n",
+                "     g +2 more\n",
+                "     l +3 more\n",
+                "\n",
+                "\n",
+            )
+        );
+        assert_eq!(
+            render_screen(60, 4, |t, w, h| hints.render(t, 0, 0, w, h)).text(),
+            concat!(
+                "     1 first         q quit          g +2 more\n",
+                "     b buffers       s save          l +3 more\n",
+                "ctrl+r reload      tab last\n",
+                "     f files         w write\n",
+            )
+        );
+        // What doesn't fit is left out
+        assert_eq!(
+            render_screen(20, 2, |t, w, h| hints.render(t, 0, 0, w, h)).text(),
+            "     1 first\n     b buffers\n"
+        );
+    }
+
+    #[test]
+    fn pending_keys_of_the_keymap() {
+        let mut keymap = keymap();
+        let key = |ch: u8| crate::input::KeyEvent {
+            key_code: ch.into(),
+            ..Default::default()
+        };
+        assert_eq!(KeyHints::new(&keymap, |a| a.to_string()).hints.len(), 1);
+        keymap.lookup(&key(b' '));
+        keymap.lookup(&key(b'l'));
+        let hints = KeyHints::new(&keymap, |a| a.to_string());
+        assert_eq!(
+            render_screen(31, 1, |t, w, h| hints.render(t, 0, 0, w, h)).text(),
+            "a actions  f format   r rename\n"
+        );
+    }
+
+    #[test]
+    fn delay() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let space = parse_sequence("space").unwrap();
+        let space_g = parse_sequence("space g").unwrap();
+        let mut delay = HintDelay::new(Duration::from_millis(300));
+
+        assert!(!delay.update(&[], at(0)));
+        assert!(!delay.update(&space, at(0)));
+        assert!(!delay.update(&space, at(200)));
+        assert!(delay.update(&space, at(300)));
+        // Another key restarts the delay
+        assert!(!delay.update(&space_g, at(400)));
+        assert!(delay.update(&space_g, at(700)));
+        // Resolved or cancelled
+        assert!(!delay.update(&[], at(800)));
+        assert!(!delay.update(&space, at(900)));
+    }
+}
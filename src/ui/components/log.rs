@@ -0,0 +1,494 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::sanitize::{display_safe, SanitizePolicy};
+use crate::tty::errors::CapabilityError;
+use crate::tty::Capabilities;
+use crate::width::{truncate_to_width, Policy};
+
+use super::write_clipped;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => " INFO",
+            Self::Warn => " WARN",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub level: Level,
+    /// Time since the [`LogBuffer`] was created.
+    pub time: Duration,
+    pub text: String,
+    /// Position among all lines ever pushed, evicted ones included.
+    pub seq: u64,
+}
+
+#[derive(Debug)]
+struct Lines {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+    pushed: u64,
+    start: Instant,
+    /// `seq` of the newest error or line ringing the bell, see [`LogPane::is_urgent`].
+    last_urgent: Option<u64>,
+    /// `seq` of the newest line ringing the bell.
+    last_bell: Option<u64>,
+}
+
+/// Bounded store of log lines that any thread can push to through a [`LogHandle`].
+///
+/// Once full, the oldest lines are evicted. Lines are pushed and evicted whole
+/// under a lock, so readers never see a partial one.
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    inner: Arc<Mutex<Lines>>,
+}
+
+impl LogBuffer {
+    /// # Panics
+    /// If `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert_ne!(capacity, 0, "LogBuffer needs room for a line");
+        Self {
+            inner: Arc::new(Mutex::new(Lines {
+                lines: VecDeque::with_capacity(capacity),
+                capacity,
+                pushed: 0,
+                start: Instant::now(),
+                last_urgent: None,
+                last_bell: None,
+            })),
+        }
+    }
+
+    pub fn handle(&self) -> LogHandle {
+        LogHandle {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
+    /// Lines pushed so far, evicted ones included. Changes whenever a line is pushed,
+    /// so the pane only needs to be drawn again when it differs from the last draw.
+    pub fn version(&self) -> u64 {
+        self.lock().pushed
+    }
+
+    /// Copy of the lines still stored, oldest first.
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lock().lines.iter().cloned().collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lines> {
+        // A panic elsewhere can't leave a line half pushed
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Pushes lines into a [`LogBuffer`], cheap to clone and send to other threads.
+#[derive(Debug, Clone)]
+pub struct LogHandle {
+    inner: Arc<Mutex<Lines>>,
+}
+
+impl LogHandle {
+    pub fn push(&self, level: Level, text: impl Into<String>) {
+        let text = text.into();
+        let mut lines = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if lines.lines.len() == lines.capacity {
+            lines.lines.pop_front();
+        }
+        // Kept apart from the lines, so evicting them doesn't lose the alert
+        let bell = text.contains('\x07');
+        if bell {
+            lines.last_bell = Some(lines.pushed);
+        }
+        if bell || level == Level::Error {
+            lines.last_urgent = Some(lines.pushed);
+        }
+        let line = LogLine {
+            level,
+            time: lines.start.elapsed(),
+            text,
+            seq: lines.pushed,
+        };
+        lines.lines.push_back(line);
+        lines.pushed += 1;
+    }
+}
+
+/// Scrollable view of a [`LogBuffer`], long lines are wrapped.
+///
+/// In follow mode the newest lines are shown as they arrive. Scrolling up
+/// leaves follow mode and keeps the view on the same lines while new ones come in,
+/// scrolling back down to the end enters it again.
+///
+/// Errors and lines ringing the bell make the pane urgent until [`LogPane::clear_urgent`].
+#[derive(Debug, Clone)]
+pub struct LogPane {
+    buffer: LogBuffer,
+    min_level: Level,
+    /// `seq` of the first line shown when not following.
+    top: Option<u64>,
+    timestamps: bool,
+    width_policy: Policy,
+    /// Lines before this `seq` don't make the pane urgent.
+    urgent_from: u64,
+    /// Lines before this `seq` were passed to [`LogPane::forward_alerts`] already.
+    alerts_from: u64,
+}
+
+impl LogPane {
+    pub fn new(buffer: &LogBuffer) -> Self {
+        Self {
+            buffer: buffer.clone(),
+            min_level: Level::Trace,
+            top: None,
+            timestamps: false,
+            width_policy: Policy::default(),
+            urgent_from: 0,
+            alerts_from: 0,
+        }
+    }
+
+    /// Lines below `level` are hidden.
+    pub fn min_level(mut self, level: Level) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// Shows the seconds since the buffer was created in front of every line.
+    pub fn timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    pub fn set_min_level(&mut self, level: Level) {
+        self.min_level = level;
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.top.is_none()
+    }
+
+    /// Jumps to the newest lines and follows them.
+    pub fn follow(&mut self) {
+        self.top = None;
+    }
+
+    /// Moves the view `count` lines up, leaving follow mode.
+    /// `height` is the height the pane is drawn with.
+    pub fn scroll_up(&mut self, count: usize, height: usize) {
+        let lines = self.visible_lines();
+        let top = match self.top {
+            Some(top) => lines.iter().position(|line| line.seq >= top).unwrap_or(0),
+            None => lines.len().saturating_sub(height),
+        };
+        self.top = lines.get(top.saturating_sub(count)).map(|line| line.seq);
+    }
+
+    /// Moves the view `count` lines down. Reaching the last line follows it again.
+    pub fn scroll_down(&mut self, count: usize, height: usize) {
+        let Some(top) = self.top else {
+            return;
+        };
+        let lines = self.visible_lines();
+        let top = lines.iter().position(|line| line.seq >= top).unwrap_or(0) + count;
+        self.top = if top + height >= lines.len() {
+            None
+        } else {
+            Some(lines[top].seq)
+        };
+    }
+
+    /// Whether an error was logged or a line rang the bell since [`LogPane::clear_urgent`],
+    /// e. g. to highlight the border of the pane. Hidden and evicted lines count as well.
+    pub fn is_urgent(&self) -> bool {
+        let lines = self.buffer.lock();
+        lines.last_urgent.is_some_and(|seq| seq >= self.urgent_from)
+    }
+
+    pub fn clear_urgent(&mut self) {
+        self.urgent_from = self.buffer.version();
+    }
+
+    /// Rings the bell of the terminal once if a line rang it since the last call.
+    pub fn forward_alerts(
+        &mut self,
+        terminfo: &mut impl Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let (bell, pushed) = {
+            let lines = self.buffer.lock();
+            (lines.last_bell, lines.pushed)
+        };
+        if bell.is_some_and(|seq| seq >= self.alerts_from) {
+            terminfo.bell()?;
+        }
+        self.alerts_from = pushed;
+        Ok(())
+    }
+
+    fn visible_lines(&self) -> Vec<LogLine> {
+        let mut lines = self.buffer.lines();
+        lines.retain(|line| line.level >= self.min_level);
+        lines
+    }
+
+    /// Rows of `line` wrapped to `width` columns, continuation rows are indented.
+    fn wrap(&self, line: &LogLine, width: usize) -> Vec<String> {
+        let prefix = if self.timestamps {
+            let time = line.time.as_secs_f64();
+            format!("[{time:>9.3}] {} ", line.level.label())
+        } else {
+            format!("{} ", line.level.label())
+        };
+        let indent = " ".repeat(prefix.len());
+        let mut rows = Vec::new();
+        // Sanitized before wrapping, as caret notation takes more columns
+        let text = display_safe(&line.text, &SanitizePolicy::default());
+        let mut rest = text.as_ref();
+        let mut first = true;
+        loop {
+            let lead = if first { &prefix } else { &indent };
+            let room = width.saturating_sub(lead.len());
+            let (mut part, _) = truncate_to_width(rest, room, &self.width_policy);
+            if part.is_empty() && !rest.is_empty() {
+                // Too narrow for a single character, it is cut off when drawn
+                part = rest.graphemes(true).next().unwrap();
+            }
+            rows.push(format!("{lead}{part}"));
+            rest = &rest[part.len()..];
+            first = false;
+            if rest.is_empty() {
+                return rows;
+            }
+        }
+    }
+
+    /// Draws the lines into the `width` x `height` area with its upper left corner
+    /// at `row`, `col`. Every row of the area is overwritten.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let lines = self.visible_lines();
+        let mut rows: Vec<(Level, String)> = Vec::new();
+        match self.top {
+            Some(top) => {
+                for line in lines.iter().skip_while(|line| line.seq < top) {
+                    rows.extend(self.wrap(line, width).into_iter().map(|r| (line.level, r)));
+                    if rows.len() >= height {
+                        break;
+                    }
+                }
+                rows.truncate(height);
+            }
+            None => {
+                for line in lines.iter().rev() {
+                    let wrapped = self.wrap(line, width);
+                    rows.splice(0..0, wrapped.into_iter().map(|r| (line.level, r)));
+                    if rows.len() >= height {
+                        break;
+                    }
+                }
+                rows.drain(..rows.len().saturating_sub(height));
+            }
+        }
+
+        for r in 0..height {
+            terminfo.move_cursor(row + r, col)?;
+            let Some((level, text)) = rows.get(r) else {
+                write_clipped(terminfo, "", width, &self.width_policy)?;
+                continue;
+            };
+            let color = match level {
+                Level::Error => Some(1),
+                Level::Warn => Some(3),
+                _ => None,
+            };
+            match color {
+                Some(color) => terminfo.set_a_foreground(color)?,
+                None if *level < Level::Info => terminfo.enter_dim_mode()?,
+                None => {}
+            }
+            write_clipped(terminfo, text, width, &self.width_policy)?;
+            if color.is_some() || *level < Level::Info {
+                terminfo.exit_attribute_mode()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{render_screen, sequences};
+
+    #[test]
+    fn follow_filter_and_wrap() {
+        let buffer = LogBuffer::new(4);
+        let log = buffer.handle();
+        let pane = LogPane::new(&buffer);
+        log.push(Level::Info, "starting");
+        log.push(Level::Debug, "config loaded from /etc/app");
+        log.push(Level::Warn, "slow");
+        assert_eq!(
+            render_screen(16, 5, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            " INFO starting\nDEBUG config loa\n      ded from /\n      etc/app\n WARN slow\n"
+        );
+        // The oldest line is evicted, the newest shown at the bottom
+        log.push(Level::Error, "failed");
+        log.push(Level::Info, "retrying");
+        assert_eq!(buffer.version(), 5);
+        assert_eq!(buffer.lines()[0].seq, 1);
+        let mut pane = pane.min_level(Level::Info);
+        assert_eq!(
+            render_screen(16, 4, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            " WARN slow\nERROR failed\n INFO retrying\n\n"
+        );
+        let screen = render_screen(16, 4, |t, w, h| pane.render(t, 0, 0, w, h));
+        assert_eq!(
+            screen.cell(1, 0).unwrap().style.fg,
+            crate::vt::Color::Indexed(1)
+        );
+        assert_eq!(screen.cell(2, 1).unwrap().style, Default::default());
+
+        pane.set_min_level(Level::Trace);
+        assert_eq!(
+            render_screen(16, 2, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            "ERROR failed\n INFO retrying\n"
+        );
+    }
+
+    #[test]
+    fn escape_sequences_in_lines() {
+        let buffer = LogBuffer::new(4);
+        let pane = LogPane::new(&buffer);
+        buffer
+            .handle()
+            .push(Level::Info, "\x1B]0;owned\x07\x1B[2Jtab\tend");
+        assert_eq!(
+            render_screen(12, 2, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            " INFO tab^Ie\n      nd\n"
+        );
+    }
+
+    #[test]
+    fn scrolling_keeps_lines_in_view() {
+        let buffer = LogBuffer::new(100);
+        let log = buffer.handle();
+        for i in 0..10 {
+            log.push(Level::Info, format!("line {i}"));
+        }
+        let mut pane = LogPane::new(&buffer);
+        pane.scroll_up(3, 2);
+        assert!(!pane.is_following());
+        assert_eq!(
+            render_screen(12, 2, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            " INFO line 5\n INFO line 6\n"
+        );
+        log.push(Level::Info, "line 10");
+        assert_eq!(
+            render_screen(12, 2, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            " INFO line 5\n INFO line 6\n"
+        );
+        pane.scroll_down(2, 2);
+        assert_eq!(
+            render_screen(12, 2, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            " INFO line 7\n INFO line 8\n"
+        );
+        pane.scroll_down(2, 2);
+        assert!(pane.is_following());
+        assert_eq!(
+            render_screen(13, 2, |t, w, h| pane.render(t, 0, 0, w, h)).text(),
+            " INFO line 9\n INFO line 10\n"
+        );
+    }
+
+    #[test]
+    fn concurrent_pushes() {
+        let buffer = LogBuffer::new(1000);
+        let pane = LogPane::new(&buffer);
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let log = buffer.handle();
+                std::thread::spawn(move || {
+                    for i in 0..500 {
+                        log.push(Level::Info, format!("{t} {i}"));
+                    }
+                })
+            })
+            .collect();
+        while !threads.iter().all(|thread| thread.is_finished()) {
+            render_screen(20, 5, |t, w, h| pane.render(t, 0, 0, w, h));
+        }
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let lines = buffer.lines();
+        assert_eq!(lines.len(), 1000);
+        assert_eq!(buffer.version(), 2000);
+        let mut last = [None; 4];
+        for (i, line) in lines.iter().enumerate() {
+            assert_eq!(line.seq, 1000 + i as u64);
+            let (t, n) = line.text.split_once(' ').unwrap();
+            let (t, n): (usize, u32) = (t.parse().unwrap(), n.parse().unwrap());
+            assert!(last[t] < Some(n), "{}", line.text);
+            last[t] = Some(n);
+        }
+    }
+
+    #[test]
+    fn urgent_and_bell() {
+        let buffer = LogBuffer::new(2);
+        let log = buffer.handle();
+        let mut pane = LogPane::new(&buffer).min_level(Level::Warn);
+        log.push(Level::Info, "starting");
+        assert!(!pane.is_urgent());
+
+        // Hidden and evicted, the bell still counts, but rings only once
+        log.push(Level::Info, "done\x07");
+        log.push(Level::Info, "one");
+        log.push(Level::Info, "two");
+        assert!(pane.is_urgent());
+        let mut terminfo = sequences();
+        pane.forward_alerts(&mut terminfo).unwrap();
+        pane.forward_alerts(&mut terminfo).unwrap();
+        assert_eq!(terminfo.buffer(), b"\x07");
+
+        pane.clear_urgent();
+        assert!(!pane.is_urgent());
+        log.push(Level::Error, "failed");
+        assert!(pane.is_urgent());
+        let mut terminfo = sequences();
+        pane.forward_alerts(&mut terminfo).unwrap();
+        assert!(terminfo.buffer().is_empty());
+    }
+}
@@ -0,0 +1,583 @@
+use std::io::{Read, Write};
+use std::os::fd::AsFd;
+
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::termios::SetArg;
+
+use crate::input::{constants as c, EventType, KeyCode, KeyEvent, Modifiers};
+use crate::tty::errors::CapabilityError;
+use crate::tty::{Capabilities, Tty, UnixTerminal};
+use crate::width::Policy;
+
+use super::write_clipped;
+
+/// How long [`Select::run`] waits for input before re-checking the terminal size,
+/// and for the rest of a sequence after an ESC.
+const RESIZE_POLL_MS: u16 = 100;
+
+/// Action a key can be bound to in a [`Select`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectAction {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    /// Toggles the mark on the item under the cursor. Ignored unless multi-select is on.
+    Toggle,
+    Confirm,
+    Cancel,
+}
+
+/// Result of a confirmed [`Select`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// Index of the item under the cursor.
+    Single(usize),
+    /// Indices of all marked items in ascending order.
+    Multiple(Vec<usize>),
+}
+
+/// What happened to a [`Select`] after feeding it a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectEvent {
+    /// The key is not bound to anything.
+    Ignored,
+    Moved,
+    Toggled,
+    Confirmed(Selection),
+    Cancelled,
+}
+
+/// List selector, usable either standalone through [`Select::run`] or embedded
+/// in a larger application through [`Select::handle_key`] and [`Select::render`].
+#[derive(Debug, Clone)]
+pub struct Select {
+    items: Vec<String>,
+    prompt: Option<String>,
+    cursor: usize,
+    offset: usize,
+    max_visible: Option<usize>,
+    multi: bool,
+    marked: Vec<bool>,
+    bindings: Vec<(KeyCode, Modifiers, SelectAction)>,
+    last_height: usize,
+    width_policy: Policy,
+}
+
+impl Select {
+    /// # Panics
+    /// If `items` is empty.
+    pub fn new<I, S>(items: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let items: Vec<String> = items.into_iter().map(Into::into).collect();
+        assert_ne!(items.len(), 0, "Select needs at least one item");
+        Self {
+            marked: vec![false; items.len()],
+            items,
+            prompt: None,
+            cursor: 0,
+            offset: 0,
+            max_visible: None,
+            multi: false,
+            bindings: default_bindings(),
+            last_height: 1,
+            width_policy: Policy::default(),
+        }
+    }
+
+    /// Text displayed on the row above the items.
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Item the cursor starts at. Clamped to the last item.
+    pub fn initial(mut self, index: usize) -> Self {
+        self.cursor = index.min(self.items.len() - 1);
+        self
+    }
+
+    /// Upper bound for the number of item rows drawn at once. Items past it are scrolled to.
+    pub fn max_visible(mut self, rows: usize) -> Self {
+        self.max_visible = Some(rows.max(1));
+        self
+    }
+
+    /// Allows marking several items with [`SelectAction::Toggle`].
+    pub fn multi_select(mut self, multi: bool) -> Self {
+        self.multi = multi;
+        self
+    }
+
+    /// How text is measured when it is clipped to the width of the selector.
+    pub fn width_policy(mut self, policy: Policy) -> Self {
+        self.width_policy = policy;
+        self
+    }
+
+    /// Binds a key to an action. Takes precedence over the default bindings
+    /// and over bindings added earlier for the same key.
+    pub fn bind(mut self, key: impl Into<KeyCode>, mods: Modifiers, action: SelectAction) -> Self {
+        self.bindings.insert(0, (key.into(), mods, action));
+        self
+    }
+
+    /// Removes all bindings, including the default ones.
+    pub fn clear_bindings(mut self) -> Self {
+        self.bindings.clear();
+        self
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_marked(&self, index: usize) -> bool {
+        self.marked.get(index).copied().unwrap_or(false)
+    }
+
+    /// Current selection. In multi-select mode with nothing marked
+    /// the item under the cursor is selected.
+    pub fn selection(&self) -> Selection {
+        if !self.multi {
+            return Selection::Single(self.cursor);
+        }
+        let marked: Vec<usize> = (0..self.items.len()).filter(|i| self.marked[*i]).collect();
+        if marked.is_empty() {
+            Selection::Multiple(vec![self.cursor])
+        } else {
+            Selection::Multiple(marked)
+        }
+    }
+
+    pub fn action_for(&self, event: &KeyEvent) -> Option<SelectAction> {
+        self.bindings
+            .iter()
+            .find(|(code, mods, _)| *code == event.key_code && *mods == event.mods)
+            .map(|(_, _, action)| *action)
+    }
+
+    /// Releases, reported with the kitty keyboard protocol, are ignored.
+    pub fn handle_key(&mut self, event: &KeyEvent) -> SelectEvent {
+        if matches!(event.event_type, EventType::Release) {
+            return SelectEvent::Ignored;
+        }
+        match self.action_for(event) {
+            Some(action) => self.apply(action),
+            None => SelectEvent::Ignored,
+        }
+    }
+
+    pub fn apply(&mut self, action: SelectAction) -> SelectEvent {
+        let last = self.items.len() - 1;
+        let page = self.last_height.max(1);
+        match action {
+            SelectAction::Up => self.cursor = self.cursor.saturating_sub(1),
+            SelectAction::Down => self.cursor = (self.cursor + 1).min(last),
+            SelectAction::PageUp => self.cursor = self.cursor.saturating_sub(page),
+            SelectAction::PageDown => self.cursor = (self.cursor + page).min(last),
+            SelectAction::First => self.cursor = 0,
+            SelectAction::Last => self.cursor = last,
+            SelectAction::Toggle => {
+                if !self.multi {
+                    return SelectEvent::Ignored;
+                }
+                self.marked[self.cursor] = !self.marked[self.cursor];
+                return SelectEvent::Toggled;
+            }
+            SelectAction::Confirm => return SelectEvent::Confirmed(self.selection()),
+            SelectAction::Cancel => return SelectEvent::Cancelled,
+        }
+        SelectEvent::Moved
+    }
+
+    /// Index of the first item drawn by the last [`Select::render`].
+    pub fn scroll_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Draws the selector into the `width` x `height` area with its upper left corner at `row`, `col`.
+    /// Every row of the area is overwritten.
+    pub fn render(
+        &mut self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        self.draw(terminfo, width, height, |terminfo, line| {
+            terminfo.move_cursor(row + line, col)
+        })
+    }
+
+    /// Draws `height` lines of `width` columns, `goto` moves to the start of a line
+    /// given its index within the area.
+    fn draw<C: Capabilities>(
+        &mut self,
+        terminfo: &mut C,
+        width: usize,
+        height: usize,
+        mut goto: impl FnMut(&mut C, usize) -> Result<(), CapabilityError>,
+    ) -> Result<(), CapabilityError> {
+        let _span = crate::logging::enter_span!("render", width, height);
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let mut line = 0;
+        if let Some(prompt) = &self.prompt {
+            goto(terminfo, line)?;
+            write_clipped(terminfo, prompt, width, &self.width_policy)?;
+            line += 1;
+        }
+        let rows = height - line;
+        self.scroll_to_cursor(rows);
+
+        for i in 0..rows {
+            goto(terminfo, line + i)?;
+            let index = self.offset + i;
+            let Some(item) = self.items.get(index).filter(|_| i < self.last_height) else {
+                write_clipped(terminfo, "", width, &self.width_policy)?;
+                continue;
+            };
+            let mut text = String::new();
+            if self.multi {
+                text.push_str(if self.marked[index] { "[x] " } else { "[ ] " });
+            }
+            text.push_str(item);
+            if index == self.cursor {
+                terminfo.enter_reverse_mode()?;
+                write_clipped(terminfo, &text, width, &self.width_policy)?;
+                terminfo.exit_attribute_mode()?;
+            } else {
+                write_clipped(terminfo, &text, width, &self.width_policy)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn scroll_to_cursor(&mut self, rows: usize) {
+        let rows = match self.max_visible {
+            Some(max) => rows.min(max),
+            None => rows,
+        };
+        self.last_height = rows;
+        if rows == 0 {
+            return;
+        }
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + rows {
+            self.offset = self.cursor + 1 - rows;
+        }
+        // Keep the window full when it got taller, e.g. after a resize.
+        self.offset = self.offset.min(self.items.len().saturating_sub(rows));
+    }
+
+    /// Lines taken by [`Select::run_inline`] on a terminal with `rows` lines.
+    fn inline_height(&self, rows: usize) -> usize {
+        let items = self.items.len().min(self.max_visible.unwrap_or(usize::MAX));
+        (usize::from(self.prompt.is_some()) + items)
+            .min(rows)
+            .max(1)
+    }
+
+    /// Takes over the terminal until the user confirms or cancels.
+    /// Returns `None` when cancelled.
+    ///
+    /// Switches to raw mode and the alternate screen, and restores both before returning,
+    /// including on error. The terminal size is re-checked while waiting for input,
+    /// so resizes are picked up immediately. An ESC without anything following it
+    /// within that time is the Escape key.
+    pub fn run(&mut self, tty: &mut Tty) -> Result<Option<Selection>, CapabilityError> {
+        self.run_in(tty, false)
+    }
+
+    /// Like [`Select::run`], but draws below the cursor instead of on the alternate screen,
+    /// scrolling up what is above when there isn't enough room. The lines are cleared before
+    /// returning and the cursor is left where they started.
+    pub fn run_inline(&mut self, tty: &mut Tty) -> Result<Option<Selection>, CapabilityError> {
+        self.run_in(tty, true)
+    }
+
+    fn run_in(
+        &mut self,
+        tty: &mut Tty,
+        inline: bool,
+    ) -> Result<Option<Selection>, CapabilityError> {
+        let _span = crate::logging::enter_span!("select_run", inline);
+        let orig_termios = tty.get_termios()?;
+        tty.raw_mode()?;
+        crate::logging::debug_event!("raw mode applied");
+        let mut block = 0;
+        let result = self.run_raw(tty, inline, &mut block);
+
+        let sequences = tty.sequences_mut();
+        sequences.clear();
+        let _ = sequences.exit_attribute_mode();
+        let _ = sequences.cursor_normal();
+        if inline {
+            let _ = block_top(sequences, block).and_then(|()| sequences.clr_eos());
+        } else {
+            let _ = sequences.exit_ca_mode();
+        }
+        let flushed = tty.flush();
+        tty.set_termios(&orig_termios, SetArg::TCSADRAIN)?;
+        crate::logging::debug_event!("termios restored");
+        flushed?;
+        result
+    }
+
+    /// `block` is the number of lines reserved below the cursor when drawing inline,
+    /// the cursor stays on the last of them between frames.
+    fn run_raw(
+        &mut self,
+        tty: &mut Tty,
+        inline: bool,
+        block: &mut usize,
+    ) -> Result<Option<Selection>, CapabilityError> {
+        let mut parser = tty.get_parser();
+        if !inline {
+            tty.sequences_mut().enter_ca_mode()?;
+        }
+        tty.sequences_mut().cursor_invisible()?;
+
+        let mut size = (0, 0);
+        let mut redraw = true;
+        let mut buf = [0; 4096];
+        loop {
+            let winsize = tty.get_size()?;
+            let new_size = (winsize.col as usize, winsize.row as usize);
+            if new_size != size {
+                crate::logging::debug_event!(cols = new_size.0, rows = new_size.1, "resize");
+                size = new_size;
+                let sequences = tty.sequences_mut();
+                if inline {
+                    // Reserve the lines again from the top of the old block
+                    block_top(sequences, *block)?;
+                    sequences.clr_eos()?;
+                    *block = self.inline_height(size.1);
+                    for _ in 1..*block {
+                        sequences.scroll_forward()?;
+                    }
+                } else {
+                    sequences.clear_screen()?;
+                }
+                redraw = true;
+            }
+            if redraw {
+                let sequences = tty.sequences_mut();
+                if inline {
+                    let height = *block;
+                    self.draw(sequences, size.0, height, |terminfo, line| match line {
+                        0 => block_top(terminfo, height),
+                        _ => terminfo
+                            .carriage_return()
+                            .and_then(|()| terminfo.cursor_down()),
+                    })?;
+                } else {
+                    self.render(sequences, 0, 0, size.0, size.1)?;
+                }
+                tty.flush()?;
+                redraw = false;
+            }
+            let ready = {
+                let mut fds = [PollFd::new(tty.as_fd(), PollFlags::POLLIN)];
+                match poll(&mut fds, RESIZE_POLL_MS) {
+                    Ok(n) => n > 0,
+                    Err(nix::errno::Errno::EINTR) => false,
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            let events = if ready {
+                let count = tty.read(&mut buf)?;
+                if count == 0 {
+                    return Ok(None);
+                }
+                // A sequence cut at the end of the read is completed by the next one
+                parser.advance(&buf[..count])
+            } else {
+                parser.flush_pending()
+            };
+            for event in events.iter() {
+                match self.handle_key(event) {
+                    SelectEvent::Confirmed(selection) => return Ok(Some(selection)),
+                    SelectEvent::Cancelled => return Ok(None),
+                    SelectEvent::Moved | SelectEvent::Toggled => redraw = true,
+                    SelectEvent::Ignored => {}
+                }
+            }
+        }
+    }
+}
+
+/// Moves from the last line of an inline block of `height` lines to the start of its first.
+fn block_top(terminfo: &mut impl Capabilities, height: usize) -> Result<(), CapabilityError> {
+    terminfo.carriage_return()?;
+    if height > 1 {
+        terminfo.parm_up_cursor(height as u32 - 1)?;
+    }
+    Ok(())
+}
+
+fn default_bindings() -> Vec<(KeyCode, Modifiers, SelectAction)> {
+    use SelectAction as A;
+    let none = Modifiers::NONE;
+    vec![
+        (c::UP.into(), none, A::Up),
+        (b'k'.into(), none, A::Up),
+        (c::DOWN.into(), none, A::Down),
+        (b'j'.into(), none, A::Down),
+        (c::PAGE_UP.into(), none, A::PageUp),
+        (c::PAGE_DOWN.into(), none, A::PageDown),
+        (c::HOME.into(), none, A::First),
+        (c::END.into(), none, A::Last),
+        (b' '.into(), none, A::Toggle),
+        (b'\r'.into(), none, A::Confirm),
+        (c::ENTER.into(), none, A::Confirm),
+        (0x1B_u8.into(), none, A::Cancel),
+        (b'q'.into(), none, A::Cancel),
+        // Ctrl+C as delivered in raw mode, and after `KeyEventList::c0_to_ctrl`
+        (0x03_u8.into(), none, A::Cancel),
+        (b'c'.into(), Modifiers::CTRL, A::Cancel),
+    ]
+}
+
+#[cfg(all(test, any(feature = "terminfo", feature = "ansi")))]
+mod tests {
+    use super::*;
+    use crate::testing::sequences;
+
+    fn key(code: u32) -> KeyEvent {
+        KeyEvent {
+            key_code: code.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn moves_and_confirms() {
+        let mut select = Select::new(["a", "b", "c"]);
+        assert_eq!(select.handle_key(&key(c::DOWN)), SelectEvent::Moved);
+        assert_eq!(select.handle_key(&key(c::DOWN)), SelectEvent::Moved);
+        assert_eq!(select.handle_key(&key(c::DOWN)), SelectEvent::Moved);
+        assert_eq!(select.cursor(), 2);
+        assert_eq!(
+            select.handle_key(&key(b'\r' as u32)),
+            SelectEvent::Confirmed(Selection::Single(2))
+        );
+        assert_eq!(select.handle_key(&key(0x03)), SelectEvent::Cancelled);
+        assert_eq!(select.handle_key(&key(b'x' as u32)), SelectEvent::Ignored);
+    }
+
+    #[test]
+    fn releases_ignored() {
+        let mut select = Select::new(["a", "b", "c"]);
+        let mut release = key(c::DOWN);
+        release.event_type = EventType::Release;
+        assert_eq!(select.handle_key(&release), SelectEvent::Ignored);
+        assert_eq!(select.cursor(), 0);
+        let mut press = key(c::DOWN);
+        press.event_type = EventType::Press;
+        assert_eq!(select.handle_key(&press), SelectEvent::Moved);
+        assert_eq!(select.cursor(), 1);
+    }
+
+    #[test]
+    fn custom_bindings_take_precedence() {
+        let mut select = Select::new(["a", "b"]).bind(b'q', Modifiers::NONE, SelectAction::Down);
+        assert_eq!(select.handle_key(&key(b'q' as u32)), SelectEvent::Moved);
+        assert_eq!(select.cursor(), 1);
+    }
+
+    #[test]
+    fn multi_select_marks() {
+        let mut select = Select::new(["a", "b", "c"]).multi_select(true);
+        assert_eq!(select.selection(), Selection::Multiple(vec![0]));
+        select.apply(SelectAction::Toggle);
+        select.apply(SelectAction::Last);
+        select.apply(SelectAction::Toggle);
+        assert_eq!(select.selection(), Selection::Multiple(vec![0, 2]));
+        select.apply(SelectAction::Toggle);
+        assert_eq!(select.selection(), Selection::Multiple(vec![0]));
+        assert_eq!(
+            Select::new(["a"]).apply(SelectAction::Toggle),
+            SelectEvent::Ignored
+        );
+    }
+
+    #[test]
+    fn scrolls_when_items_exceed_rows() {
+        let mut terminfo = sequences();
+        let items: Vec<String> = (0..20).map(|i| format!("item {i}")).collect();
+        let mut select = Select::new(items).prompt("pick").initial(10);
+        select.render(&mut terminfo, 0, 0, 10, 5).unwrap();
+        // 4 rows left after the prompt, the cursor is on the last of them
+        assert_eq!(select.scroll_offset(), 7);
+        select.apply(SelectAction::First);
+        select.render(&mut terminfo, 0, 0, 10, 5).unwrap();
+        assert_eq!(select.scroll_offset(), 0);
+        select.apply(SelectAction::Last);
+        select.render(&mut terminfo, 0, 0, 10, 5).unwrap();
+        assert_eq!(select.scroll_offset(), 16);
+        // Growing the area pulls the window back so it stays full
+        select.render(&mut terminfo, 0, 0, 10, 11).unwrap();
+        assert_eq!(select.scroll_offset(), 10);
+        select = select.max_visible(3);
+        select.render(&mut terminfo, 0, 0, 10, 11).unwrap();
+        assert_eq!(select.scroll_offset(), 17);
+    }
+
+    #[test]
+    fn render_clips_to_width() {
+        let mut terminfo = sequences();
+        let mut select = Select::new(["abcdef", "gh"]);
+        select.render(&mut terminfo, 0, 0, 4, 2).unwrap();
+        let mut bytes = Vec::new();
+        terminfo.flush_to(&mut bytes).unwrap();
+        assert_eq!(b"\x1B[1;1H\x1B[7mabcd\x1B(B\x1B[m\x1B[2;1Hgh  ", &*bytes);
+    }
+
+    #[test]
+    fn render_clips_by_columns() {
+        let mut terminfo = sequences();
+        let mut select = Select::new(["漢字漢", "e\u{301}x"]);
+        select.render(&mut terminfo, 0, 0, 3, 2).unwrap();
+        let mut bytes = Vec::new();
+        terminfo.flush_to(&mut bytes).unwrap();
+        assert_eq!(
+            "\x1B[1;1H\x1B[7m漢 \x1B(B\x1B[m\x1B[2;1He\u{301}x ".as_bytes(),
+            &*bytes
+        );
+    }
+
+    #[test]
+    fn frames_replay_to_screen() {
+        let mut terminfo = sequences();
+        let mut select = Select::new(["one", "two", "three"]).prompt("pick");
+        select.render(&mut terminfo, 1, 2, 6, 3).unwrap();
+        select.apply(SelectAction::Down);
+        select.render(&mut terminfo, 1, 2, 6, 3).unwrap();
+        let mut bytes = Vec::new();
+        terminfo.flush_to(&mut bytes).unwrap();
+
+        let mut model = crate::vt::Model::new(10, 4);
+        model.feed(&bytes);
+        assert!(model.unknown().is_empty(), "{:?}", model.unknown());
+        let screen = model.screen();
+        assert_eq!(screen.text(), "\n  pick\n  one\n  two\n");
+        let reversed: Vec<_> = (0..4)
+            .map(|row| screen.cell(row, 2).unwrap().style.reverse)
+            .collect();
+        assert_eq!(reversed, [false, false, false, true]);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::tty::errors::CapabilityError;
+use crate::tty::{ByteOrigin, Capabilities, FrameStats};
+use crate::width::Policy;
+
+use super::write_clipped;
+
+/// Debug overlay listing where the bytes of output went, largest contributors first.
+///
+/// Rendering it adds to the output, so its own bytes show up in the next frame.
+pub struct StatsOverlay<'a> {
+    stats: &'a FrameStats,
+}
+
+impl<'a> StatsOverlay<'a> {
+    pub fn new(stats: &'a FrameStats) -> Self {
+        Self { stats }
+    }
+
+    /// Draws a summary line and as many contributors as fit into the `width` x `height`
+    /// area with its upper left corner at `row`, `col`. Every row of the area is overwritten.
+    pub fn render(
+        &self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let policy = Policy::default();
+        let stats = self.stats;
+        let summary = format!(
+            "{} B, {} frames, {} B style",
+            stats.total,
+            stats.frames,
+            stats.style()
+        );
+        terminfo.move_cursor(row, col)?;
+        terminfo.enter_reverse_mode()?;
+        write_clipped(terminfo, &summary, width, &policy)?;
+        terminfo.exit_attribute_mode()?;
+
+        let top = stats.top(height - 1);
+        for i in 0..height - 1 {
+            terminfo.move_cursor(row + 1 + i, col)?;
+            let line = match top.get(i) {
+                Some(&(origin, bytes)) => {
+                    let label = match origin {
+                        ByteOrigin::Capability(name) => name,
+                        ByteOrigin::Host => "(host)",
+                        ByteOrigin::Text => "(text)",
+                    };
+                    format!("{bytes:>7} {label}")
+                }
+                None => String::new(),
+            };
+            write_clipped(terminfo, &line, width, &policy)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "terminfo"))]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::testing::{Origin, TestTty};
+    use crate::tty::{wrap_passthrough, Multiplexer};
+    use crate::ui::components::Select;
+
+    fn chunk_bytes(tty: &TestTty, origin: Origin) -> usize {
+        tty.chunks()
+            .iter()
+            .filter(|chunk| chunk.origin == origin)
+            .map(|chunk| chunk.bytes.len())
+            .sum()
+    }
+
+    #[test]
+    fn stats_add_up_to_output() {
+        let mut tty = TestTty::new();
+        tty.terminfo.profile_output(true);
+        assert_eq!(tty.terminfo.frame_stats(), Some(&FrameStats::default()));
+
+        let mut select = Select::new(["one", "two"]);
+        select.render(&mut tty.terminfo, 0, 0, 8, 2).unwrap();
+        tty.flush().unwrap();
+        let frame = tty.terminfo.frame_stats().unwrap().clone();
+        assert_eq!(frame.frames, 1);
+        assert_eq!(frame.total, tty.output().len());
+        assert_eq!(
+            frame
+                .by_origin
+                .iter()
+                .map(|&(_, bytes)| bytes)
+                .sum::<usize>(),
+            frame.total
+        );
+        assert_eq!(
+            frame.get(ByteOrigin::Capability("cursor_address")),
+            chunk_bytes(&tty, Origin::Capability("cursor_address"))
+        );
+        assert_eq!(
+            frame.get(ByteOrigin::Text),
+            chunk_bytes(&tty, Origin::Write)
+        );
+        assert_eq!(frame.get(ByteOrigin::Text), 16);
+        assert_eq!(frame.style(), b"\x1B[7m\x1B(B\x1B[m".len());
+
+        let clipboard = b"\x1B]52;c;YQ==\x07";
+        let mut wrapped = Vec::new();
+        wrap_passthrough(Multiplexer::Tmux, clipboard, &mut wrapped);
+        tty.terminfo.set_multiplexer(Some(Multiplexer::Tmux));
+        tty.terminfo.append_host(clipboard);
+        tty.terminfo.append(b"x");
+        tty.flush().unwrap();
+        let frame = tty.terminfo.frame_stats().unwrap();
+        assert_eq!(frame.get(ByteOrigin::Host), wrapped.len());
+        assert_eq!(frame.get(ByteOrigin::Text), 1);
+
+        // Discarded bytes are never written, so they don't count
+        tty.terminfo.append(b"gone");
+        tty.terminfo.clear();
+        tty.flush().unwrap();
+        let cumulative = tty.terminfo.cumulative_stats().unwrap();
+        assert_eq!(cumulative.frames, 3);
+        assert_eq!(cumulative.total, tty.output().len());
+    }
+
+    #[test]
+    fn overlay_lists_top_contributors() {
+        let stats = FrameStats {
+            frames: 2,
+            total: 300,
+            by_origin: vec![
+                (ByteOrigin::Text, 100),
+                (ByteOrigin::Capability("cursor_address"), 180),
+                (ByteOrigin::Capability("enter_reverse_mode"), 20),
+            ],
+        };
+        let mut tty = TestTty::new();
+        StatsOverlay::new(&stats)
+            .render(&mut tty.terminfo, 0, 0, 24, 3)
+            .unwrap();
+        tty.flush().unwrap();
+        let screen = crate::vt::replay(&tty.output(), (24, 3));
+        assert_eq!(
+            screen.text(),
+            "300 B, 2 frames, 20 B st\n    180 cursor_address\n    100 (text)\n"
+        );
+        assert!(screen.cell(0, 0).unwrap().style.reverse);
+    }
+}
@@ -0,0 +1,711 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::input::{constants as c, KeyCode, KeyEvent, Modifiers};
+use crate::tty::errors::CapabilityError;
+use crate::tty::Capabilities;
+use crate::width::Policy;
+
+use super::write_clipped;
+
+/// Nodes displayed by a [`Tree`].
+///
+/// Children are only asked for when their parent is expanded,
+/// so they can be loaded lazily, e. g. by reading a directory.
+pub trait TreeSource {
+    type Id: Clone + Eq + Hash;
+
+    fn roots(&mut self) -> Vec<Self::Id>;
+    fn children(&mut self, id: &Self::Id) -> Vec<Self::Id>;
+    fn label(&self, id: &Self::Id) -> String;
+
+    /// Whether `id` can be expanded. Nodes for which this is true are drawn
+    /// with an expansion marker until expanding them turns out to give no children.
+    fn has_children(&self, id: &Self::Id) -> bool {
+        let _ = id;
+        true
+    }
+}
+
+/// Action a key can be bound to in a [`Tree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeAction {
+    Prev,
+    Next,
+    First,
+    Last,
+    /// Expands the selected node, or selects its first child if it is already expanded.
+    Expand,
+    /// Collapses the selected node, or selects its parent if it is not expanded.
+    Collapse,
+    Toggle,
+    Parent,
+}
+
+/// What happened to a [`Tree`] after feeding it a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeEvent {
+    /// The key is not bound to anything, or the action had no effect.
+    Ignored,
+    Moved,
+    Expanded,
+    Collapsed,
+}
+
+#[derive(Debug, Clone)]
+struct Row<Id> {
+    id: Id,
+    depth: usize,
+    expandable: bool,
+    /// Whether this is the last of its siblings.
+    last: bool,
+    /// For every ancestor below the roots, whether its siblings continue below it.
+    guides: Vec<bool>,
+}
+
+/// Expansion, selection and scrolling of a [`Tree`], kept apart from the source
+/// so it can outlive it, e. g. when the source is rebuilt.
+///
+/// Scrolling is anchored to the node at the top, so nodes added or removed
+/// above it don't move what is shown.
+#[derive(Debug, Clone)]
+pub struct TreeState<Id> {
+    expanded: HashSet<Id>,
+    selected: Option<Id>,
+    offset: usize,
+    /// Node at `offset` when it was last drawn or refreshed.
+    top: Option<Id>,
+}
+
+impl<Id> Default for TreeState<Id> {
+    fn default() -> Self {
+        Self {
+            expanded: HashSet::new(),
+            selected: None,
+            offset: 0,
+            top: None,
+        }
+    }
+}
+
+impl<Id: Clone + Eq + Hash> TreeState<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_expanded(&self, id: &Id) -> bool {
+        self.expanded.contains(id)
+    }
+
+    /// Marks `id` as expanded, its children are loaded once it becomes visible.
+    pub fn expand(&mut self, id: Id) {
+        self.expanded.insert(id);
+    }
+
+    pub fn collapse(&mut self, id: &Id) {
+        self.expanded.remove(id);
+    }
+
+    pub fn selected(&self) -> Option<&Id> {
+        self.selected.as_ref()
+    }
+
+    /// Node to select, ignored if it is not visible.
+    pub fn select(&mut self, id: Id) {
+        self.selected = Some(id);
+    }
+}
+
+/// Expandable tree of nodes from a [`TreeSource`], drawn with indentation guides.
+pub struct Tree<S: TreeSource> {
+    source: S,
+    state: TreeState<S::Id>,
+    /// Visible nodes in display order, only expanded subtrees are ever walked.
+    rows: Vec<Row<S::Id>>,
+    cursor: usize,
+    bindings: Vec<(KeyCode, Modifiers, TreeAction)>,
+    width_policy: Policy,
+}
+
+impl<S: TreeSource> Tree<S> {
+    pub fn new(source: S) -> Self {
+        Self::with_state(source, TreeState::default())
+    }
+
+    pub fn with_state(source: S, state: TreeState<S::Id>) -> Self {
+        let mut tree = Self {
+            source,
+            state,
+            rows: Vec::new(),
+            cursor: 0,
+            bindings: default_bindings(),
+            width_policy: Policy::default(),
+        };
+        tree.refresh();
+        tree
+    }
+
+    /// How labels are measured when they are clipped to the width of the tree.
+    pub fn width_policy(mut self, policy: Policy) -> Self {
+        self.width_policy = policy;
+        self
+    }
+
+    /// Binds a key to an action. Takes precedence over the default bindings
+    /// and over bindings added earlier for the same key.
+    pub fn bind(mut self, key: impl Into<KeyCode>, mods: Modifiers, action: TreeAction) -> Self {
+        self.bindings.insert(0, (key.into(), mods, action));
+        self
+    }
+
+    /// Removes all bindings, including the default ones.
+    pub fn clear_bindings(mut self) -> Self {
+        self.bindings.clear();
+        self
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Source to change, the changes show after [`Tree::refresh`].
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    pub fn state(&self) -> &TreeState<S::Id> {
+        &self.state
+    }
+
+    pub fn into_state(self) -> TreeState<S::Id> {
+        self.state
+    }
+
+    /// Reloads all visible nodes from the source. The selected node and the node at the top
+    /// keep their place on screen, if one is gone the nearest node that is still there
+    /// takes its place.
+    pub fn refresh(&mut self) {
+        let roots = self.source.roots();
+        let mut rows = Vec::new();
+        self.walk(roots, 0, &[], &mut rows);
+        let old = std::mem::replace(&mut self.rows, rows);
+        let index: HashMap<&S::Id, usize> = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| (&row.id, i))
+            .collect();
+        let find = |id: Option<&S::Id>, at: usize| {
+            id.and_then(|id| index.get(id).copied())
+                .or_else(|| nearest_kept(&old, at, &index))
+        };
+        let cursor = find(self.state.selected.as_ref(), self.cursor).unwrap_or(0);
+        let offset = find(self.state.top.as_ref(), self.state.offset);
+        self.cursor = cursor;
+        if let Some(offset) = offset {
+            self.state.offset = offset;
+        }
+        self.sync_selected();
+        self.sync_top();
+    }
+
+    /// Appends rows for `ids` and the expanded subtrees below them.
+    fn walk(&mut self, ids: Vec<S::Id>, depth: usize, guides: &[bool], rows: &mut Vec<Row<S::Id>>) {
+        let count = ids.len();
+        for (i, id) in ids.into_iter().enumerate() {
+            let last = i + 1 == count;
+            let mut expandable = self.source.has_children(&id);
+            let expanded = expandable && self.state.expanded.contains(&id);
+            let children = if expanded {
+                self.source.children(&id)
+            } else {
+                Vec::new()
+            };
+            if expanded && children.is_empty() {
+                expandable = false;
+            }
+            rows.push(Row {
+                id,
+                depth,
+                expandable,
+                last,
+                guides: guides.to_vec(),
+            });
+            if !children.is_empty() {
+                let mut child_guides = guides.to_vec();
+                if depth > 0 {
+                    child_guides.push(!last);
+                }
+                self.walk(children, depth + 1, &child_guides, rows);
+            }
+        }
+    }
+
+    /// Ids of the visible nodes with their depth, in display order.
+    pub fn visible(&self) -> impl Iterator<Item = (&S::Id, usize)> {
+        self.rows.iter().map(|row| (&row.id, row.depth))
+    }
+
+    pub fn selected(&self) -> Option<&S::Id> {
+        self.rows.get(self.cursor).map(|row| &row.id)
+    }
+
+    fn sync_selected(&mut self) {
+        self.state.selected = self.rows.get(self.cursor).map(|row| row.id.clone());
+    }
+
+    fn sync_top(&mut self) {
+        self.state.top = self.rows.get(self.state.offset).map(|row| row.id.clone());
+    }
+
+    fn is_expanded_at(&self, index: usize) -> bool {
+        let row = &self.rows[index];
+        row.expandable && self.state.expanded.contains(&row.id)
+    }
+
+    /// End of the subtree of the row at `index`, exclusive.
+    fn subtree_end(&self, index: usize) -> usize {
+        let depth = self.rows[index].depth;
+        self.rows[index + 1..]
+            .iter()
+            .position(|row| row.depth <= depth)
+            .map_or(self.rows.len(), |i| index + 1 + i)
+    }
+
+    /// Expands the selected node. Returns false if it has no children or is already expanded.
+    pub fn expand(&mut self) -> bool {
+        let index = self.cursor;
+        if self.rows.is_empty() || !self.rows[index].expandable || self.is_expanded_at(index) {
+            return false;
+        }
+        let row = &self.rows[index];
+        let (id, depth) = (row.id.clone(), row.depth);
+        let mut guides = row.guides.clone();
+        if depth > 0 {
+            guides.push(!row.last);
+        }
+        self.state.expanded.insert(id.clone());
+        let children = self.source.children(&id);
+        if children.is_empty() {
+            self.rows[index].expandable = false;
+            return false;
+        }
+        let mut inserted = Vec::new();
+        self.walk(children, depth + 1, &guides, &mut inserted);
+        self.rows.splice(index + 1..index + 1, inserted);
+        true
+    }
+
+    /// Collapses the selected node. Returns false if it is not expanded.
+    pub fn collapse(&mut self) -> bool {
+        let index = self.cursor;
+        if self.rows.is_empty() || !self.is_expanded_at(index) {
+            return false;
+        }
+        self.state.expanded.remove(&self.rows[index].id);
+        let end = self.subtree_end(index);
+        self.rows.drain(index + 1..end);
+        true
+    }
+
+    pub fn toggle(&mut self) -> bool {
+        if self.rows.is_empty() {
+            return false;
+        }
+        if self.is_expanded_at(self.cursor) {
+            self.collapse()
+        } else {
+            self.expand()
+        }
+    }
+
+    /// Selects the parent of the selected node. Returns false for roots.
+    pub fn select_parent(&mut self) -> bool {
+        let Some(depth) = self.rows.get(self.cursor).map(|row| row.depth) else {
+            return false;
+        };
+        let Some(parent) = self.rows[..self.cursor]
+            .iter()
+            .rposition(|row| row.depth < depth)
+        else {
+            return false;
+        };
+        self.move_to(parent)
+    }
+
+    pub fn select_next(&mut self) -> bool {
+        self.move_to((self.cursor + 1).min(self.rows.len().saturating_sub(1)))
+    }
+
+    pub fn select_prev(&mut self) -> bool {
+        self.move_to(self.cursor.saturating_sub(1))
+    }
+
+    fn move_to(&mut self, index: usize) -> bool {
+        let moved = index != self.cursor;
+        self.cursor = index;
+        self.sync_selected();
+        moved
+    }
+
+    pub fn action_for(&self, event: &KeyEvent) -> Option<TreeAction> {
+        self.bindings
+            .iter()
+            .find(|(code, mods, _)| *code == event.key_code && *mods == event.mods)
+            .map(|(_, _, action)| *action)
+    }
+
+    pub fn handle_key(&mut self, event: &KeyEvent) -> TreeEvent {
+        match self.action_for(event) {
+            Some(action) => self.apply(action),
+            None => TreeEvent::Ignored,
+        }
+    }
+
+    pub fn apply(&mut self, action: TreeAction) -> TreeEvent {
+        let moved = |moved| {
+            if moved {
+                TreeEvent::Moved
+            } else {
+                TreeEvent::Ignored
+            }
+        };
+        match action {
+            TreeAction::Prev => moved(self.select_prev()),
+            TreeAction::Next => moved(self.select_next()),
+            TreeAction::First => moved(self.move_to(0)),
+            TreeAction::Last => moved(self.move_to(self.rows.len().saturating_sub(1))),
+            TreeAction::Expand if !self.rows.is_empty() && self.is_expanded_at(self.cursor) => {
+                moved(self.select_next())
+            }
+            TreeAction::Expand => {
+                if self.expand() {
+                    TreeEvent::Expanded
+                } else {
+                    TreeEvent::Ignored
+                }
+            }
+            TreeAction::Collapse => {
+                if self.collapse() {
+                    TreeEvent::Collapsed
+                } else {
+                    moved(self.select_parent())
+                }
+            }
+            TreeAction::Toggle if self.rows.is_empty() => TreeEvent::Ignored,
+            TreeAction::Toggle if self.is_expanded_at(self.cursor) => {
+                self.collapse();
+                TreeEvent::Collapsed
+            }
+            TreeAction::Toggle => {
+                if self.expand() {
+                    TreeEvent::Expanded
+                } else {
+                    TreeEvent::Ignored
+                }
+            }
+            TreeAction::Parent => moved(self.select_parent()),
+        }
+    }
+
+    /// Index of the first visible node drawn by the last [`Tree::render`].
+    pub fn scroll_offset(&self) -> usize {
+        self.state.offset
+    }
+
+    /// Draws the tree into the `width` x `height` area with its upper left corner at `row`, `col`.
+    /// Every row of the area is overwritten.
+    pub fn render(
+        &mut self,
+        terminfo: &mut impl Capabilities,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), CapabilityError> {
+        let _span = crate::logging::enter_span!("render", width, height);
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        self.scroll_to_cursor(height);
+        self.sync_top();
+        for i in 0..height {
+            terminfo.move_cursor(row + i, col)?;
+            let index = self.state.offset + i;
+            let Some(node) = self.rows.get(index) else {
+                write_clipped(terminfo, "", width, &self.width_policy)?;
+                continue;
+            };
+            let mut text = String::new();
+            for &continues in &node.guides {
+                text.push_str(if continues { "│  " } else { "   " });
+            }
+            if node.depth > 0 {
+                text.push_str(if node.last { "└─ " } else { "├─ " });
+            }
+            if node.expandable {
+                text.push_str(if self.is_expanded_at(index) {
+                    "▾ "
+                } else {
+                    "▸ "
+                });
+            } else if node.depth == 0 {
+                text.push_str("  ");
+            }
+            text.push_str(&self.source.label(&node.id));
+            if index == self.cursor {
+                terminfo.enter_reverse_mode()?;
+                write_clipped(terminfo, &text, width, &self.width_policy)?;
+                terminfo.exit_attribute_mode()?;
+            } else {
+                write_clipped(terminfo, &text, width, &self.width_policy)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn scroll_to_cursor(&mut self, rows: usize) {
+        let offset = &mut self.state.offset;
+        if self.cursor < *offset {
+            *offset = self.cursor;
+        } else if self.cursor >= *offset + rows {
+            *offset = self.cursor + 1 - rows;
+        }
+        *offset = (*offset).min(self.rows.len().saturating_sub(rows));
+    }
+}
+
+/// Position in the new rows of the row nearest to `at` in `old` that is still there,
+/// rows below before rows above at the same distance.
+fn nearest_kept<Id: Eq + Hash>(
+    old: &[Row<Id>],
+    at: usize,
+    index: &HashMap<&Id, usize>,
+) -> Option<usize> {
+    (0..old.len()).find_map(|distance| {
+        [at.checked_add(distance), at.checked_sub(distance)]
+            .into_iter()
+            .flatten()
+            .find_map(|i| index.get(&old.get(i)?.id).copied())
+    })
+}
+
+fn default_bindings() -> Vec<(KeyCode, Modifiers, TreeAction)> {
+    use TreeAction as A;
+    let none = Modifiers::NONE;
+    vec![
+        (c::UP.into(), none, A::Prev),
+        (b'k'.into(), none, A::Prev),
+        (c::DOWN.into(), none, A::Next),
+        (b'j'.into(), none, A::Next),
+        (c::HOME.into(), none, A::First),
+        (c::END.into(), none, A::Last),
+        (c::RIGHT.into(), none, A::Expand),
+        (b'l'.into(), none, A::Expand),
+        (c::LEFT.into(), none, A::Collapse),
+        (b'h'.into(), none, A::Collapse),
+        (b' '.into(), none, A::Toggle),
+        (b'p'.into(), none, A::Parent),
+    ]
+}
+
+#[cfg(all(test, any(feature = "terminfo", feature = "ansi")))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::testing::render_screen;
+
+    /// Paths to their children, counting how often children were loaded.
+    struct Files {
+        children: HashMap<&'static str, Vec<&'static str>>,
+        loaded: Vec<&'static str>,
+    }
+
+    impl TreeSource for Files {
+        type Id = &'static str;
+
+        fn roots(&mut self) -> Vec<&'static str> {
+            vec!["src", "Cargo.toml"]
+        }
+
+        fn children(&mut self, id: &&'static str) -> Vec<&'static str> {
+            self.loaded.push(id);
+            self.children.get(id).cloned().unwrap_or_default()
+        }
+
+        fn label(&self, id: &&'static str) -> String {
+            id.rsplit('/').next().unwrap().into()
+        }
+
+        fn has_children(&self, id: &&'static str) -> bool {
+            !id.contains('.')
+        }
+    }
+
+    fn files() -> Files {
+        Files {
+            children: HashMap::from([
+                ("src", vec!["src/ui", "src/empty", "src/lib.rs"]),
+                ("src/ui", vec!["src/ui/select.rs", "src/ui/tree.rs"]),
+            ]),
+            loaded: Vec::new(),
+        }
+    }
+
+    /// Renders and returns the text of every row, with `>` marking the selection.
+    fn snapshot<S: TreeSource>(tree: &mut Tree<S>, height: usize) -> Vec<String> {
+        let screen = render_screen(20, height, |t, w, h| tree.render(t, 0, 0, w, h));
+        (0..height)
+            .map(|row| {
+                let reverse = screen.cell(row, 0).unwrap().style.reverse;
+                let selected = if reverse { ">" } else { " " };
+                format!("{selected}{}", screen.row_text(row).trim_end())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mixed_expansion() {
+        let mut tree = Tree::new(files());
+        assert!(tree.source().loaded.is_empty());
+        assert_eq!(snapshot(&mut tree, 3), [">▸ src", "   Cargo.toml", " "]);
+
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Expanded);
+        tree.select_next();
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Expanded);
+        // An empty directory loses its marker once expanded
+        tree.select_next();
+        tree.select_next();
+        tree.select_next();
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Ignored);
+        assert_eq!(
+            snapshot(&mut tree, 8),
+            [
+                " ▾ src",
+                " ├─ ▾ ui",
+                " │  ├─ select.rs",
+                " │  └─ tree.rs",
+                ">├─ empty",
+                " └─ lib.rs",
+                "   Cargo.toml",
+                " ",
+            ]
+        );
+        assert_eq!(tree.source().loaded, ["src", "src/ui", "src/empty"]);
+    }
+
+    #[test]
+    fn selection_skips_collapsed_subtree() {
+        let mut state = TreeState::new();
+        state.expand("src");
+        state.expand("src/ui");
+        state.select("src/ui");
+        let mut tree = Tree::with_state(files(), state);
+        assert_eq!(tree.selected(), Some(&"src/ui"));
+
+        assert_eq!(tree.apply(TreeAction::Collapse), TreeEvent::Collapsed);
+        assert_eq!(tree.apply(TreeAction::Next), TreeEvent::Moved);
+        assert_eq!(tree.selected(), Some(&"src/empty"));
+        assert_eq!(tree.apply(TreeAction::Parent), TreeEvent::Moved);
+        assert_eq!(tree.selected(), Some(&"src"));
+        assert_eq!(tree.apply(TreeAction::Toggle), TreeEvent::Collapsed);
+        assert!(!tree.state().is_expanded(&"src"));
+        assert_eq!(tree.apply(TreeAction::Next), TreeEvent::Moved);
+        assert_eq!(tree.selected(), Some(&"Cargo.toml"));
+        // Leaves and roots can't go further
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Ignored);
+        assert_eq!(tree.apply(TreeAction::Collapse), TreeEvent::Ignored);
+
+        tree.apply(TreeAction::First);
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Expanded);
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Moved);
+        assert_eq!(tree.selected(), Some(&"src/ui"));
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Expanded);
+        // Collapsing a node keeps the expansion of its descendants
+        tree.apply(TreeAction::Parent);
+        assert_eq!(tree.apply(TreeAction::Collapse), TreeEvent::Collapsed);
+        assert_eq!(tree.apply(TreeAction::Expand), TreeEvent::Expanded);
+        assert_eq!(
+            tree.visible()
+                .map(|(id, depth)| (*id, depth))
+                .collect::<Vec<_>>(),
+            [
+                ("src", 0),
+                ("src/ui", 1),
+                ("src/ui/select.rs", 2),
+                ("src/ui/tree.rs", 2),
+                ("src/empty", 1),
+                ("src/lib.rs", 1),
+                ("Cargo.toml", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn scrolls_to_selection() {
+        let mut state = TreeState::new();
+        state.expand("src");
+        state.expand("src/ui");
+        let mut tree = Tree::with_state(files(), state);
+        tree.apply(TreeAction::Last);
+        assert_eq!(snapshot(&mut tree, 2), [" └─ lib.rs", ">  Cargo.toml"]);
+        assert_eq!(tree.scroll_offset(), 5);
+    }
+
+    /// Lines of a log as roots without children.
+    struct Log(Vec<i32>);
+
+    impl TreeSource for Log {
+        type Id = i32;
+
+        fn roots(&mut self) -> Vec<i32> {
+            self.0.clone()
+        }
+
+        fn children(&mut self, _: &i32) -> Vec<i32> {
+            Vec::new()
+        }
+
+        fn label(&self, id: &i32) -> String {
+            format!("line {id}")
+        }
+
+        fn has_children(&self, _: &i32) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn scrolling_is_anchored() {
+        let mut tree = Tree::new(Log((0..50).collect()));
+        for _ in 0..25 {
+            tree.apply(TreeAction::Next);
+        }
+        let shown = snapshot(&mut tree, 4);
+        assert_eq!(shown[0], "   line 22");
+        assert_eq!(shown[3], ">  line 25");
+
+        // Lines prepended above the viewport don't move it
+        tree.source_mut().0.splice(0..0, -100..0);
+        tree.refresh();
+        assert_eq!(tree.selected(), Some(&25));
+        assert_eq!(snapshot(&mut tree, 4), shown);
+        assert_eq!(tree.scroll_offset(), 122);
+
+        // Removed lines are replaced by their nearest neighbor below
+        tree.source_mut().0.retain(|&line| line != 22 && line != 25);
+        tree.refresh();
+        assert_eq!(tree.selected(), Some(&26));
+        assert_eq!(
+            snapshot(&mut tree, 4),
+            ["   line 23", "   line 24", ">  line 26", "   line 27"]
+        );
+
+        // Anchors also hold for a state moved to a rebuilt tree
+        let state = tree.into_state();
+        let mut tree = Tree::with_state(Log((-200..50).collect()), state);
+        assert_eq!(tree.scroll_offset(), 223);
+        assert_eq!(snapshot(&mut tree, 4)[3], ">  line 26");
+    }
+}
@@ -0,0 +1,351 @@
+//! One-shot questions for command line tools that don't run a full screen interface.
+//!
+//! Every prompt sets up the terminal itself and restores it before returning,
+//! so prompts can be asked one after another. Ctrl+C ends any of them with
+//! [`PromptError::Cancelled`].
+//!
+//! When the input isn't a terminal, e. g. in a pipeline, [`confirm`] and [`input`]
+//! read a plain line without printing the question. [`select`] takes a [`Tty`],
+//! which is always a terminal.
+
+use std::io::{Read, Write};
+
+use nix::sys::termios::{LocalFlags, SetArg};
+
+use crate::tty::errors::CapabilityError;
+use crate::tty::{read_line_uninterrupted, Tty, UnixTerminal};
+use crate::ui::components::{Select, Selection};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PromptError {
+    #[error("Prompt cancelled.")]
+    Cancelled,
+    #[error("Answer `{0}` not understood.")]
+    InvalidAnswer(String),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    CapabilityError(#[from] CapabilityError),
+}
+
+impl From<nix::errno::Errno> for PromptError {
+    fn from(value: nix::errno::Errno) -> Self {
+        Self::IoError(value.into())
+    }
+}
+
+type Validator = Box<dyn Fn(&str) -> Result<(), String>>;
+
+/// Settings of an [`input`] prompt.
+#[derive(Default)]
+pub struct InputOptions {
+    default: Option<String>,
+    validate: Option<Validator>,
+}
+
+impl InputOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer used when the line is left empty, shown in brackets after the question.
+    pub fn default_value(mut self, value: impl Into<String>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    /// Checks every answer, the message of an error is shown and the question asked again.
+    /// Answers read without a terminal are rejected with [`PromptError::InvalidAnswer`] instead.
+    pub fn validate(mut self, validate: impl Fn(&str) -> Result<(), String> + 'static) -> Self {
+        self.validate = Some(Box::new(validate));
+        self
+    }
+}
+
+fn is_interactive(tty: &mut impl UnixTerminal) -> bool {
+    tty.get_termios().is_ok()
+}
+
+/// Reads up to a newline without any terminal setup.
+fn read_plain_line(tty: &mut impl Read) -> std::io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        match tty.read(&mut byte) {
+            Ok(0) if line.is_empty() => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) => line.push(byte[0]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let line = String::from_utf8(line)
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "not valid UTF-8"))?;
+    Ok(line.trim_end_matches('\r').to_owned())
+}
+
+fn parse_yes_no(answer: &str, default: bool) -> Result<bool, PromptError> {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "" => Ok(default),
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Err(PromptError::InvalidAnswer(answer.to_owned())),
+    }
+}
+
+/// Asks a yes or no question, answered with a single key press.
+/// Enter picks `default`.
+pub fn confirm<T>(tty: &mut T, message: &str, default: bool) -> Result<bool, PromptError>
+where
+    T: UnixTerminal + Read + Write,
+{
+    if !is_interactive(tty) {
+        let line = read_plain_line(tty)?;
+        return parse_yes_no(&line, default);
+    }
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    let orig_termios = tty.get_termios()?;
+    tty.raw_mode()?;
+    let answer = (|| {
+        write!(tty, "{message} {hint} ")?;
+        tty.flush()?;
+        let mut byte = [0];
+        loop {
+            if tty.read(&mut byte)? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            match byte[0] {
+                b'y' | b'Y' => return Ok(true),
+                b'n' | b'N' => return Ok(false),
+                b'\r' | b'\n' => return Ok(default),
+                0x03 => return Err(PromptError::Cancelled),
+                0x04 => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+                _ => {}
+            }
+        }
+    })();
+    let shown: &[u8] = match answer {
+        Ok(true) => b"yes\r\n",
+        Ok(false) => b"no\r\n",
+        Err(_) => b"\r\n",
+    };
+    let written = tty.write_all(shown).and_then(|()| tty.flush());
+    tty.set_termios(&orig_termios, SetArg::TCSADRAIN)?;
+    written?;
+    answer
+}
+
+/// Asks for a line of text, edited with the line editing of the terminal.
+pub fn input<T>(tty: &mut T, message: &str, options: InputOptions) -> Result<String, PromptError>
+where
+    T: UnixTerminal + Read + Write,
+{
+    // The answer with the default filled in, or the message of the validation error
+    let check = |answer: String| -> Result<String, String> {
+        let answer = match (&options.default, answer.is_empty()) {
+            (Some(default), true) => default.clone(),
+            _ => answer,
+        };
+        match &options.validate {
+            Some(validate) => validate(&answer).map(|()| answer),
+            None => Ok(answer),
+        }
+    };
+
+    if !is_interactive(tty) {
+        let line = read_plain_line(tty)?;
+        return check(line.clone()).map_err(|_| PromptError::InvalidAnswer(line));
+    }
+
+    let orig_termios = tty.get_termios()?;
+    let mut termios = orig_termios.clone();
+    termios.local_flags |= LocalFlags::ECHO | LocalFlags::ECHOE | LocalFlags::ECHOK;
+    tty.set_termios(&termios, SetArg::TCSADRAIN)?;
+    let answer = (|| loop {
+        let prompt = match &options.default {
+            Some(default) => format!("{message} [{default}]: "),
+            None => format!("{message}: "),
+        };
+        let line = match read_line_uninterrupted(tty, &prompt) {
+            Ok(line) => line,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+                writeln!(tty)?;
+                return Err(PromptError::Cancelled);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let line = String::from_utf8(line.to_vec())
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "not valid UTF-8"))?;
+        match check(line.trim_end_matches('\r').to_owned()) {
+            Ok(answer) => return Ok(answer),
+            Err(message) => writeln!(tty, "{message}")?,
+        }
+    })();
+    tty.set_termios(&orig_termios, SetArg::TCSADRAIN)?;
+    answer
+}
+
+/// Lets the user pick one of `items` with a [`Select`] drawn below the cursor,
+/// see [`Select::run_inline`]. The list is replaced by the message and the picked item.
+/// Returns the index of the picked item.
+///
+/// Fails with an [`InvalidInput`](std::io::ErrorKind::InvalidInput) error when `items` is empty.
+pub fn select<I, S>(tty: &mut Tty, message: &str, items: I) -> Result<usize, PromptError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let items: Vec<String> = items.into_iter().map(Into::into).collect();
+    if items.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "nothing to select from",
+        )
+        .into());
+    }
+    let mut select = Select::new(items).prompt(message);
+    match select.run_inline(tty)? {
+        Some(Selection::Single(index)) => {
+            writeln!(tty, "{message} {}", select.items()[index])?;
+            Ok(index)
+        }
+        Some(Selection::Multiple(_)) => unreachable!("multi-select is off"),
+        None => Err(PromptError::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    use nix::pty::{openpty, Winsize};
+
+    use super::*;
+    use crate::testing::read_output;
+
+    /// Runs `prompt` on the slave side of a pseudo terminal, typing `input` once `until`
+    /// was printed. Returns the result, the output and whether the termios was restored.
+    fn run<R: Send + 'static>(
+        prompt: impl FnOnce(&mut File) -> R + Send + 'static,
+        until: &[u8],
+        input: &[u8],
+    ) -> (R, Vec<u8>, bool) {
+        let size = Winsize {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let pty = openpty(&size, None).unwrap();
+        let (mut master, mut slave) = (File::from(pty.master), File::from(pty.slave));
+        let before = slave.get_termios().unwrap();
+        let asker = thread::spawn(move || {
+            let result = prompt(&mut slave);
+            let after = slave.get_termios().unwrap();
+            (result, after, slave)
+        });
+        let mut output = read_output(&mut master, Some(until));
+        master.write_all(input).unwrap();
+        let (result, after, _slave) = asker.join().unwrap();
+        output.extend(read_output(&mut master, None));
+        let restored = after.local_flags == before.local_flags
+            && after.input_flags == before.input_flags
+            && after.output_flags == before.output_flags;
+        (result, output, restored)
+    }
+
+    #[test]
+    fn confirm_keys() {
+        let ask = |tty: &mut File| confirm(tty, "Continue?", false);
+        let (answer, output, restored) = run(ask, b"[y/N] ", b"y");
+        assert!(answer.unwrap());
+        assert_eq!(output, b"Continue? [y/N] yes\r\n");
+        assert!(restored);
+
+        let (answer, _, _) = run(ask, b"[y/N] ", b"x\r");
+        assert!(!answer.unwrap());
+
+        let (answer, output, restored) = run(ask, b"[y/N] ", b"\x03");
+        assert!(matches!(answer, Err(PromptError::Cancelled)));
+        assert_eq!(output, b"Continue? [y/N] \r\n");
+        assert!(restored);
+    }
+
+    #[test]
+    fn input_default_and_validation() {
+        let ask = |tty: &mut File| {
+            let options =
+                InputOptions::new()
+                    .default_value("hello")
+                    .validate(|name| match name.len() {
+                        0..=3 => Err("at least 4 characters".into()),
+                        _ => Ok(()),
+                    });
+            input(tty, "Name", options)
+        };
+        let (answer, output, restored) = run(ask, b"Name [hello]: ", b"abc\n\n");
+        assert_eq!(answer.unwrap(), "hello");
+        let output = String::from_utf8(output).unwrap();
+        // Both lines are echoed as soon as they are typed, before the first one is read
+        assert_eq!(
+            output,
+            "Name [hello]: abc\r\n\r\nat least 4 characters\r\nName [hello]: "
+        );
+        assert!(restored);
+
+        let (answer, _, restored) = run(ask, b"Name [hello]: ", b"ab\x03");
+        assert!(matches!(answer, Err(PromptError::Cancelled)));
+        assert!(restored);
+    }
+
+    #[cfg(feature = "terminfo")]
+    #[test]
+    fn select_picks_index() {
+        let ask = |tty: &mut File| {
+            let fd = tty.try_clone().unwrap().into();
+            let mut tty = Tty::from_fd(fd, crate::testing::sequences()).unwrap();
+            select(&mut tty, "Pick", ["a", "b", "c"])
+        };
+        let (answer, output, restored) = run(ask, b"Pick", b"j\r");
+        assert_eq!(answer.unwrap(), 1);
+        assert!(output.ends_with(b"Pick b\r\n"));
+        // Inline, below the cursor
+        assert!(!output.windows(8).any(|w| w == b"\x1B[?1049h"));
+        assert!(restored);
+
+        let (answer, _, restored) = run(ask, b"Pick", b"\x03");
+        assert!(matches!(answer, Err(PromptError::Cancelled)));
+        assert!(restored);
+
+        let pty = openpty(None, None).unwrap();
+        let mut tty = Tty::from_fd(pty.slave, crate::testing::sequences()).unwrap();
+        match select(&mut tty, "Pick", Vec::<String>::new()) {
+            Err(PromptError::IoError(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput)
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn without_terminal() {
+        let (mut tty, mut other) = UnixStream::pair().unwrap();
+        other.write_all(b"yes\nmaybe\nfoo\n").unwrap();
+        drop(other);
+        assert!(confirm(&mut tty, "Continue?", false).unwrap());
+        assert!(matches!(
+            confirm(&mut tty, "Continue?", false),
+            Err(PromptError::InvalidAnswer(answer)) if answer == "maybe"
+        ));
+        assert_eq!(input(&mut tty, "Name", InputOptions::new()).unwrap(), "foo");
+        match input(&mut tty, "Name", InputOptions::new()) {
+            Err(PromptError::IoError(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof)
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+}
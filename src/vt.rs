@@ -0,0 +1,1466 @@
+//! Small terminal model for checking output by what ends up on screen.
+//!
+//! Only the sequences this crate emits are interpreted: cursor addressing and
+//! movement, SGR, erasing, the scroll region and the alternate screen.
+//! Shell integration marks of OSC 133 are collected in [`Model::marks`].
+//! Anything else is kept in [`Model::unknown`] and otherwise ignored.
+
+use std::fmt;
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::width::{char_width, str_width, Policy};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Color {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub reverse: bool,
+    pub invisible: bool,
+    pub strikethrough: bool,
+    pub overline: bool,
+}
+
+impl fmt::Display for Color {
+    /// A palette index, `#rrggbb` or `default`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Indexed(index) => write!(f, "{index}"),
+            Self::Rgb(r, g, b) => write!(f, "#{r:02x}{g:02x}{b:02x}"),
+        }
+    }
+}
+
+impl Style {
+    fn flags(&self) -> [(bool, &'static str); 9] {
+        [
+            (self.bold, "bold"),
+            (self.dim, "dim"),
+            (self.italic, "italic"),
+            (self.underline, "underline"),
+            (self.blink, "blink"),
+            (self.reverse, "reverse"),
+            (self.invisible, "invisible"),
+            (self.strikethrough, "strikethrough"),
+            (self.overline, "overline"),
+        ]
+    }
+
+    /// Parses the format of [`Style`]'s `Display`, `None` for unknown words.
+    fn parse(text: &str) -> Option<Self> {
+        let mut style = Self::default();
+        for word in text.split_whitespace() {
+            let color = |value: &str| match value.strip_prefix('#') {
+                _ if value == "default" => Some(Color::Default),
+                Some(hex) if hex.len() == 6 => {
+                    let channel = |i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+                    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+                }
+                Some(_) => None,
+                None => value.parse().ok().map(Color::Indexed),
+            };
+            match word.split_once('=') {
+                Some(("fg", value)) => style.fg = color(value)?,
+                Some(("bg", value)) => style.bg = color(value)?,
+                _ => {
+                    let flag = match word {
+                        "bold" => &mut style.bold,
+                        "dim" => &mut style.dim,
+                        "italic" => &mut style.italic,
+                        "underline" => &mut style.underline,
+                        "blink" => &mut style.blink,
+                        "reverse" => &mut style.reverse,
+                        "invisible" => &mut style.invisible,
+                        "strikethrough" => &mut style.strikethrough,
+                        "overline" => &mut style.overline,
+                        _ => return None,
+                    };
+                    *flag = true;
+                }
+            }
+        }
+        Some(style)
+    }
+}
+
+impl fmt::Display for Style {
+    /// The attributes that are set and the colors that aren't the default,
+    /// like `bold underline fg=1 bg=#ff8700`. Empty for the default style.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words = self
+            .flags()
+            .into_iter()
+            .filter(|&(set, _)| set)
+            .map(|(_, name)| name.to_owned())
+            .collect::<Vec<_>>();
+        if self.fg != Color::Default {
+            words.push(format!("fg={}", self.fg));
+        }
+        if self.bg != Color::Default {
+            words.push(format!("bg={}", self.bg));
+        }
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+/// One column of the screen. The column after a wide character holds an empty symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: String,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: " ".into(),
+            style: Style::default(),
+        }
+    }
+}
+
+/// How [`Buffer::extract_text`] turns rows into text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExtractMode {
+    /// Like copying a selection: rows that were wrapped onto the next one are joined,
+    /// other rows end with a newline and lose their trailing spaces.
+    #[default]
+    Lines,
+    /// Every row of the rectangle with all its columns, one line per row.
+    Block,
+}
+
+/// DEC line size of a row, set with `ESC # 3` to `ESC # 6`.
+///
+/// Every cell of a row that isn't [`LineSize::Single`] takes two columns on screen,
+/// so only the left half of its cells is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineSize {
+    #[default]
+    Single,
+    DoubleWidth,
+    /// Upper half of double height text, the row below usually has the lower half.
+    DoubleHeightTop,
+    DoubleHeightBottom,
+}
+
+/// Corner of a [`Buffer`] its content stays at, see [`Buffer::resize_anchored`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Corner {
+    /// For content read from the top, like an editor.
+    #[default]
+    TopLeft,
+    TopRight,
+    /// For content growing at the bottom, like a log, so the newest rows stay.
+    BottomLeft,
+    BottomRight,
+}
+
+/// What [`Buffer::resize_anchored`] cut off, to adjust scroll offsets by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResizeReport {
+    pub rows_above: usize,
+    pub rows_below: usize,
+    pub cols_left: usize,
+    pub cols_right: usize,
+    /// Wide characters replaced by the fill cell because one of their halves was cut off.
+    pub split_wide: usize,
+}
+
+/// Grid of cells, row by row, at least 1 x 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Buffer {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    /// For every row, whether the text on it continued on the next row by wrapping.
+    wrapped: Vec<bool>,
+    line_sizes: Vec<LineSize>,
+}
+
+impl Buffer {
+    /// Sizes below 1 x 1 are raised to it.
+    pub fn new(width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+            wrapped: vec![false; height],
+            line_sizes: vec![LineSize::Single; height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> Option<&Cell> {
+        (row < self.height && col < self.width).then(|| &self.cells[row * self.width + col])
+    }
+
+    fn cell_mut(&mut self, row: usize, col: usize) -> &mut Cell {
+        &mut self.cells[row * self.width + col]
+    }
+
+    /// Symbols of a row, trailing spaces included.
+    pub fn row_text(&self, row: usize) -> String {
+        self.cells[row * self.width..(row + 1) * self.width]
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect()
+    }
+
+    /// Whether the text on `row` was wrapped onto the next row rather than ending with a line break.
+    pub fn is_wrapped(&self, row: usize) -> bool {
+        self.wrapped.get(row).copied().unwrap_or(false)
+    }
+
+    pub fn line_size(&self, row: usize) -> LineSize {
+        self.line_sizes.get(row).copied().unwrap_or_default()
+    }
+
+    /// Columns text can use on `row`, half the width on double size rows.
+    pub fn row_width(&self, row: usize) -> usize {
+        match self.line_size(row) {
+            LineSize::Single => self.width,
+            _ => (self.width / 2).max(1),
+        }
+    }
+
+    /// Text of the `rows` x `cols` rectangle, without a newline after the last row.
+    pub fn extract_text(
+        &self,
+        rows: Range<usize>,
+        cols: Range<usize>,
+        mode: ExtractMode,
+    ) -> String {
+        let rows = rows.start.min(self.height)..rows.end.min(self.height);
+        let cols = cols.start.min(self.width)..cols.end.min(self.width);
+        let mut text = String::new();
+        for row in rows.clone() {
+            let start = text.len();
+            for col in cols.clone() {
+                text.push_str(&self.cells[row * self.width + col].symbol);
+            }
+            if row + 1 == rows.end {
+                if mode == ExtractMode::Lines {
+                    text.truncate(start + text[start..].trim_end_matches(' ').len());
+                }
+                break;
+            }
+            match mode {
+                ExtractMode::Lines if self.wrapped[row] && cols.end == self.width => {}
+                ExtractMode::Lines => {
+                    text.truncate(start + text[start..].trim_end_matches(' ').len());
+                    text.push('\n');
+                }
+                ExtractMode::Block => text.push('\n'),
+            }
+        }
+        text
+    }
+
+    /// Symbols of every row with trailing spaces removed, one line per row.
+    pub fn text(&self) -> String {
+        (0..self.height)
+            .map(|row| self.row_text(row).trim_end().to_owned() + "\n")
+            .collect()
+    }
+
+    /// Resizes to `width` x `height`, keeping the content at `corner`. Rows and columns
+    /// are cut off or added on the opposite sides, added cells are copies of `fill`.
+    ///
+    /// A wide character whose half is cut off is replaced by `fill` as well.
+    /// Rows keep their line size, but are no longer wrapped if the width changed.
+    /// Sizes below 1 x 1 are raised to it.
+    pub fn resize_anchored(
+        &mut self,
+        width: usize,
+        height: usize,
+        corner: Corner,
+        fill: Cell,
+    ) -> ResizeReport {
+        let (width, height) = (width.max(1), height.max(1));
+        let bottom = matches!(corner, Corner::BottomLeft | Corner::BottomRight);
+        let right = matches!(corner, Corner::TopRight | Corner::BottomRight);
+        // Rows and columns of the old buffer that come first in the new one,
+        // negative when the buffer grows towards the top or left
+        let top = if bottom {
+            self.height as isize - height as isize
+        } else {
+            0
+        };
+        let left = if right {
+            self.width as isize - width as isize
+        } else {
+            0
+        };
+        let mut report = ResizeReport {
+            rows_above: top.max(0) as usize,
+            rows_below: (self.height as isize - top - height as isize).max(0) as usize,
+            cols_left: left.max(0) as usize,
+            cols_right: (self.width as isize - left - width as isize).max(0) as usize,
+            split_wide: 0,
+        };
+
+        let mut resized = Buffer::new(width, height);
+        resized.cells.fill(fill.clone());
+        let policy = Policy::default();
+        for row in 0..height {
+            let Some(old_row) = row.checked_add_signed(top).filter(|&old| old < self.height) else {
+                continue;
+            };
+            resized.line_sizes[row] = self.line_sizes[old_row];
+            resized.wrapped[row] = self.wrapped[old_row] && width == self.width;
+            for col in 0..width {
+                if let Some(old_col) = col.checked_add_signed(left).filter(|&old| old < self.width)
+                {
+                    *resized.cell_mut(row, col) =
+                        self.cells[old_row * self.width + old_col].clone();
+                }
+            }
+            // The second half of a wide character without the first, or the other way around
+            let first = resized.cell_mut(row, 0);
+            if first.symbol.is_empty() {
+                *first = fill.clone();
+                report.split_wide += 1;
+            }
+            let last = resized.cell_mut(row, width - 1);
+            if str_width(&last.symbol, &policy) > 1 {
+                *last = fill.clone();
+                report.split_wide += 1;
+            }
+        }
+        *self = resized;
+        report
+    }
+
+    /// Symbols and styles of every row, the format of snapshots taken with
+    /// [`assert_buffer_snapshot`](crate::assert_buffer_snapshot).
+    ///
+    /// Every row is a line of its symbols between `|`, without the empty second half
+    /// of wide characters, so in a monospace font it is as wide as the buffer.
+    /// Every run of cells of a style other than the default follows on a line of its own,
+    /// with the range of columns and the style as [`Style`] displays it:
+    ///
+    /// ```text
+    /// |ab漢 |
+    ///   0..2 bold fg=1
+    ///   2..4 bg=#ff8700
+    /// ```
+    ///
+    /// Line sizes and wrapping are not included. The format only changes with the major
+    /// version of the crate, later versions of the same major version read and write
+    /// snapshots the same way.
+    pub fn annotated(&self) -> String {
+        (0..self.height)
+            .map(|row| self.annotated_row(row))
+            .collect()
+    }
+
+    /// Lines of [`Buffer::annotated`] for a single row.
+    pub(crate) fn annotated_row(&self, row: usize) -> String {
+        let cells = &self.cells[row * self.width..(row + 1) * self.width];
+        let mut text = format!("|{}|\n", self.row_text(row));
+        let mut start = 0;
+        for col in 1..=cells.len() {
+            if col < cells.len() && cells[col].style == cells[start].style {
+                continue;
+            }
+            if cells[start].style != Style::default() {
+                text.push_str(&format!("  {start}..{col} {}\n", cells[start].style));
+            }
+            start = col;
+        }
+        text
+    }
+
+    /// Reads the format of [`Buffer::annotated`], `None` if `text` isn't in it,
+    /// has no cells or its rows differ in width.
+    pub fn from_annotated(text: &str) -> Option<Self> {
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        let policy = Policy::default();
+        for line in text.lines() {
+            if let Some(symbols) = line.strip_prefix('|') {
+                let mut row = Vec::new();
+                for symbol in symbols.strip_suffix('|')?.graphemes(true) {
+                    row.push(Cell {
+                        symbol: symbol.into(),
+                        style: Style::default(),
+                    });
+                    if str_width(symbol, &policy) > 1 {
+                        row.push(Cell {
+                            symbol: String::new(),
+                            style: Style::default(),
+                        });
+                    }
+                }
+                rows.push(row);
+                continue;
+            }
+            let (range, style) = line.strip_prefix("  ")?.split_once(' ')?;
+            let (start, end) = range.split_once("..")?;
+            let (start, end): (usize, usize) = (start.parse().ok()?, end.parse().ok()?);
+            let style = Style::parse(style)?;
+            for cell in rows.last_mut()?.get_mut(start..end)? {
+                cell.style = style;
+            }
+        }
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+        let mut buffer = Buffer::new(width, rows.len());
+        buffer.cells = rows.into_iter().flatten().collect();
+        Some(buffer)
+    }
+
+    fn clear(&mut self, from: (usize, usize), to: (usize, usize), style: Style) {
+        let start = from.0 * self.width + from.1;
+        let end = (to.0 * self.width + to.1).min(self.cells.len());
+        for cell in self.cells.iter_mut().take(end).skip(start) {
+            *cell = Cell {
+                symbol: " ".into(),
+                style,
+            };
+        }
+        // Rows whose end was erased no longer continue on the next row,
+        // rows erased as a whole are back to single size
+        for row in 0..self.height {
+            let last = (row + 1) * self.width;
+            if start < last && last <= end {
+                self.wrapped[row] = false;
+                if start <= row * self.width {
+                    self.line_sizes[row] = LineSize::Single;
+                }
+            }
+        }
+    }
+
+    /// Moves the rows `top..bottom` up by `count`, blank rows come in at the bottom.
+    fn scroll_up(&mut self, top: usize, bottom: usize, count: usize, style: Style) {
+        let count = count.min(bottom - top);
+        self.cells[top * self.width..bottom * self.width].rotate_left(count * self.width);
+        self.wrapped[top..bottom].rotate_left(count);
+        self.line_sizes[top..bottom].rotate_left(count);
+        self.clear((bottom - count, 0), (bottom, 0), style);
+    }
+
+    fn scroll_down(&mut self, top: usize, bottom: usize, count: usize, style: Style) {
+        let count = count.min(bottom - top);
+        self.cells[top * self.width..bottom * self.width].rotate_right(count * self.width);
+        self.wrapped[top..bottom].rotate_right(count);
+        self.line_sizes[top..bottom].rotate_right(count);
+        self.clear((top, 0), (top + count, 0), style);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Cursor {
+    row: usize,
+    col: usize,
+    /// Set after writing to the last column, the next character goes to the next line.
+    wrap_pending: bool,
+    style: Style,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    /// Designation of a character set, the next byte is dropped.
+    Charset,
+    /// `ESC #`, the next byte picks the line size.
+    LineSize,
+    Csi,
+    /// OSC, DCS, APC and the like, up to the string terminator.
+    String,
+    StringEscape,
+}
+
+/// What an OSC 133 shell integration mark delimits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkKind {
+    PromptStart,
+    /// End of the prompt, where the typed command starts.
+    PromptEnd,
+    OutputStart,
+    /// End of the command output, with the exit code if the shell sent one.
+    OutputEnd(Option<i32>),
+}
+
+/// Shell integration mark and the cursor position it was received at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mark {
+    pub kind: MarkKind,
+    /// Row counted from the first row ever shown, including rows scrolled off the top,
+    /// see [`Model::scrolled_lines`].
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Something in the output asking for the attention of the user, see [`Model::take_alerts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Alert {
+    Bell,
+    /// Reverse video turned on, which is how terminfo's `flash` flashes the screen.
+    VisualBell,
+    /// OSC 9 or OSC 777 desktop notification, the whole sequence.
+    Notification(Vec<u8>),
+}
+
+/// Interprets bytes written to a terminal of a fixed size, at least 1 x 1.
+#[derive(Debug, Clone)]
+pub struct Model {
+    primary: Buffer,
+    alternate: Buffer,
+    alternate_active: bool,
+    cursor: Cursor,
+    saved: Cursor,
+    /// Rows `top..bottom` scroll.
+    region: (usize, usize),
+    cursor_visible: bool,
+    policy: Policy,
+    state: State,
+    sequence: Vec<u8>,
+    utf8: Vec<u8>,
+    unknown: Vec<Vec<u8>>,
+    marks: Vec<Mark>,
+    /// Rows scrolled off the top of the primary screen.
+    scrolled: usize,
+    alerts: Vec<Alert>,
+    urgent: bool,
+}
+
+impl Model {
+    /// Sizes below 1 x 1 are raised to it.
+    pub fn new(width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        Self {
+            primary: Buffer::new(width, height),
+            alternate: Buffer::new(width, height),
+            alternate_active: false,
+            cursor: Cursor::default(),
+            saved: Cursor::default(),
+            region: (0, height),
+            cursor_visible: true,
+            policy: Policy::default(),
+            state: State::Ground,
+            sequence: Vec::new(),
+            utf8: Vec::new(),
+            unknown: Vec::new(),
+            marks: Vec::new(),
+            scrolled: 0,
+            alerts: Vec::new(),
+            urgent: false,
+        }
+    }
+
+    /// Policy used for the width of characters.
+    pub fn width_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Screen that is currently shown.
+    pub fn screen(&self) -> &Buffer {
+        if self.alternate_active {
+            &self.alternate
+        } else {
+            &self.primary
+        }
+    }
+
+    pub fn is_alternate_screen(&self) -> bool {
+        self.alternate_active
+    }
+
+    /// Row and column of the cursor, zero-based.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor.row, self.cursor.col)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Sequences the model doesn't interpret, in the order they were seen.
+    pub fn unknown(&self) -> &[Vec<u8>] {
+        &self.unknown
+    }
+
+    /// Shell integration marks in the order they were received.
+    pub fn marks(&self) -> &[Mark] {
+        &self.marks
+    }
+
+    /// Rows scrolled off the top of the primary screen, a [`Mark::line`] minus
+    /// this is the row of the mark on screen.
+    pub fn scrolled_lines(&self) -> usize {
+        self.scrolled
+    }
+
+    /// Last prompt started above `line`, for jumping to the previous prompt.
+    pub fn previous_prompt(&self, line: usize) -> Option<&Mark> {
+        self.marks
+            .iter()
+            .rev()
+            .find(|mark| mark.kind == MarkKind::PromptStart && mark.line < line)
+    }
+
+    /// Alerts received since the last call, in order.
+    pub fn take_alerts(&mut self) -> Vec<Alert> {
+        std::mem::take(&mut self.alerts)
+    }
+
+    /// Whether an alert was received since [`Model::clear_urgent`],
+    /// e. g. to highlight the pane showing the model until the user looks at it.
+    pub fn is_urgent(&self) -> bool {
+        self.urgent
+    }
+
+    pub fn clear_urgent(&mut self) {
+        self.urgent = false;
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.byte(byte);
+        }
+    }
+
+    fn screen_mut(&mut self) -> &mut Buffer {
+        if self.alternate_active {
+            &mut self.alternate
+        } else {
+            &mut self.primary
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.primary.width
+    }
+
+    fn height(&self) -> usize {
+        self.primary.height
+    }
+
+    fn byte(&mut self, byte: u8) {
+        match self.state {
+            State::Ground => self.ground(byte),
+            State::Escape => self.escape(byte),
+            State::Charset => self.state = State::Ground,
+            State::LineSize => {
+                self.state = State::Ground;
+                self.sequence.push(byte);
+                self.line_size(byte);
+            }
+            State::Csi => {
+                self.sequence.push(byte);
+                if (0x40..=0x7E).contains(&byte) {
+                    self.state = State::Ground;
+                    self.csi();
+                }
+            }
+            State::String => {
+                self.sequence.push(byte);
+                match byte {
+                    0x07 => self.end_string(),
+                    0x1B => self.state = State::StringEscape,
+                    _ => {}
+                }
+            }
+            State::StringEscape => {
+                self.sequence.push(byte);
+                if byte == b'\\' {
+                    self.end_string();
+                } else {
+                    self.state = State::String;
+                }
+            }
+        }
+    }
+
+    fn end_string(&mut self) {
+        self.state = State::Ground;
+        let sequence = std::mem::take(&mut self.sequence);
+        if is_notification(&sequence) {
+            self.alert(Alert::Notification(sequence));
+            return;
+        }
+        match parse_mark(&sequence) {
+            Some(kind) => self.marks.push(Mark {
+                kind,
+                line: self.scrolled + self.cursor.row,
+                col: self.cursor.col,
+            }),
+            None => self.unknown.push(sequence),
+        }
+    }
+
+    fn alert(&mut self, alert: Alert) {
+        self.alerts.push(alert);
+        self.urgent = true;
+    }
+
+    /// Counts rows scrolled off the top of the primary screen.
+    fn scrolled_off(&mut self, top: usize, count: usize) {
+        if top == 0 && !self.alternate_active {
+            self.scrolled += count.min(self.height());
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        if !self.utf8.is_empty() || byte >= 0x80 {
+            self.utf8.push(byte);
+            match std::str::from_utf8(&self.utf8) {
+                Ok(s) => {
+                    let ch = s.chars().next().unwrap();
+                    self.utf8.clear();
+                    self.print(ch);
+                }
+                Err(e) if e.error_len().is_some() => {
+                    self.utf8.clear();
+                    self.print(char::REPLACEMENT_CHARACTER);
+                }
+                Err(_) => {}
+            }
+            return;
+        }
+        match byte {
+            0x1B => {
+                self.sequence.clear();
+                self.sequence.push(byte);
+                self.state = State::Escape;
+            }
+            b'\r' => {
+                self.cursor.col = 0;
+                self.cursor.wrap_pending = false;
+            }
+            b'\n' | 0x0B | 0x0C => self.line_feed(),
+            0x08 => {
+                self.cursor.col = self.cursor.col.saturating_sub(1);
+                self.cursor.wrap_pending = false;
+            }
+            b'\t' => {
+                self.cursor.col = ((self.cursor.col / 8 + 1) * 8).min(self.row_width() - 1);
+                self.cursor.wrap_pending = false;
+            }
+            0x07 => self.alert(Alert::Bell),
+            0x00..=0x1F | 0x7F => {}
+            _ => self.print(byte as char),
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.sequence.push(byte);
+        self.state = State::Ground;
+        match byte {
+            b'[' => self.state = State::Csi,
+            b']' | b'P' | b'_' | b'^' | b'X' => self.state = State::String,
+            b'(' | b')' | b'*' | b'+' => self.state = State::Charset,
+            b'#' => self.state = State::LineSize,
+            b'7' => self.saved = self.cursor,
+            b'8' => self.cursor = self.saved,
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.cursor.col = 0;
+                self.line_feed();
+            }
+            b'M' => self.reverse_index(),
+            // Keypad modes change nothing on screen
+            b'=' | b'>' => {}
+            _ => self.unknown.push(std::mem::take(&mut self.sequence)),
+        }
+    }
+
+    fn line_size(&mut self, byte: u8) {
+        let size = match byte {
+            b'3' => LineSize::DoubleHeightTop,
+            b'4' => LineSize::DoubleHeightBottom,
+            b'5' => LineSize::Single,
+            b'6' => LineSize::DoubleWidth,
+            _ => {
+                self.unknown.push(std::mem::take(&mut self.sequence));
+                return;
+            }
+        };
+        let row = self.cursor.row;
+        let screen = self.screen_mut();
+        screen.line_sizes[row] = size;
+        // Like xterm, the right half no longer fits and is lost
+        let half = screen.row_width(row);
+        let width = screen.width;
+        if half < width {
+            screen.clear((row, half), (row + 1, 0), Style::default());
+        }
+        self.cursor.col = self.cursor.col.min(self.row_width() - 1);
+    }
+
+    /// Columns text can use on the row of the cursor.
+    fn row_width(&self) -> usize {
+        self.screen().row_width(self.cursor.row)
+    }
+
+    fn print(&mut self, ch: char) {
+        let Some(width) = char_width(ch, &self.policy) else {
+            return;
+        };
+        let policy = self.policy;
+        if width == 0 {
+            // Combining characters join the character before the cursor
+            let (row, col) = (self.cursor.row, self.cursor.col);
+            let col = if self.cursor.wrap_pending {
+                col
+            } else {
+                col.saturating_sub(1)
+            };
+            let screen = self.screen_mut();
+            let mut col = col;
+            while col > 0 && screen.cell(row, col).unwrap().symbol.is_empty() {
+                col -= 1;
+            }
+            let cell = screen.cell_mut(row, col);
+            cell.symbol.push(ch);
+            if crate::width::grapheme_width(&cell.symbol, &policy) == 2 {
+                if let Some(next) = (col + 1 < screen.width).then(|| screen.cell_mut(row, col + 1))
+                {
+                    next.symbol.clear();
+                }
+            }
+            return;
+        }
+        if width > self.row_width() {
+            // Like xterm, a wide character never fits on a row of one column
+            return;
+        }
+        if self.cursor.wrap_pending || self.cursor.col + width > self.row_width() {
+            let row = self.cursor.row;
+            self.screen_mut().wrapped[row] = true;
+            self.cursor.col = 0;
+            self.cursor.wrap_pending = false;
+            self.line_feed();
+        }
+        let (row, col, style) = (self.cursor.row, self.cursor.col, self.cursor.style);
+        let screen = self.screen_mut();
+        *screen.cell_mut(row, col) = Cell {
+            symbol: ch.into(),
+            style,
+        };
+        if width == 2 {
+            *screen.cell_mut(row, col + 1) = Cell {
+                symbol: String::new(),
+                style,
+            };
+        }
+        if col + width >= self.row_width() {
+            self.cursor.col = self.row_width() - 1;
+            self.cursor.wrap_pending = true;
+        } else {
+            self.cursor.col += width;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.cursor.wrap_pending = false;
+        let (top, bottom) = self.region;
+        if self.cursor.row + 1 == bottom {
+            let style = self.cursor.style;
+            self.screen_mut().scroll_up(top, bottom, 1, style);
+            self.scrolled_off(top, 1);
+        } else if self.cursor.row + 1 < self.height() {
+            self.cursor.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.cursor.wrap_pending = false;
+        let (top, bottom) = self.region;
+        if self.cursor.row == top {
+            let style = self.cursor.style;
+            self.screen_mut().scroll_down(top, bottom, 1, style);
+        } else {
+            self.cursor.row = self.cursor.row.saturating_sub(1);
+        }
+    }
+
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.cursor.row = row.min(self.height() - 1);
+        self.cursor.col = col.min(self.row_width() - 1);
+        self.cursor.wrap_pending = false;
+    }
+
+    fn csi(&mut self) {
+        let sequence = std::mem::take(&mut self.sequence);
+        let body = &sequence[2..sequence.len() - 1];
+        let final_byte = sequence[sequence.len() - 1];
+        let (private, body) = match body.first() {
+            Some(b'?') => (true, &body[1..]),
+            _ => (false, body),
+        };
+        let Some(params) = std::str::from_utf8(body).ok().and_then(parse_params) else {
+            self.unknown.push(sequence);
+            return;
+        };
+        // First parameter with 0 and missing both meaning 1
+        let count = params.first().map_or(1, |p| p[0].max(1) as usize);
+        let arg = |i: usize| params.get(i).map_or(0, |p| p[0] as usize);
+        let (row, col) = (self.cursor.row, self.cursor.col);
+        let style = self.cursor.style;
+        let (width, height) = (self.width(), self.height());
+        match (private, final_byte) {
+            (false, b'H' | b'f') => {
+                self.move_to(arg(0).max(1) - 1, arg(1).max(1) - 1);
+            }
+            (false, b'A') => self.move_to(row.saturating_sub(count), col),
+            (false, b'B') => self.move_to(row + count, col),
+            (false, b'C') => self.move_to(row, col + count),
+            (false, b'D') => self.move_to(row, col.saturating_sub(count)),
+            (false, b'E') => self.move_to(row + count, 0),
+            (false, b'F') => self.move_to(row.saturating_sub(count), 0),
+            (false, b'G' | b'`') => self.move_to(row, count - 1),
+            (false, b'd') => self.move_to(count - 1, col),
+            (false, b'J' | b'K') => {
+                let range = match (final_byte, arg(0)) {
+                    (b'J', 0) => Some(((row, col), (height, 0))),
+                    (b'J', 1) => Some(((0, 0), (row, col + 1))),
+                    (b'J', 2 | 3) => Some(((0, 0), (height, 0))),
+                    (b'K', 0) => Some(((row, col), (row + 1, 0))),
+                    (b'K', 1) => Some(((row, 0), (row, col + 1))),
+                    (b'K', 2) => Some(((row, 0), (row + 1, 0))),
+                    _ => None,
+                };
+                match range {
+                    Some((from, to)) => self.screen_mut().clear(from, to, style),
+                    None => self.unknown.push(sequence),
+                }
+            }
+            (false, b'X') => {
+                let end = (col + count).min(width);
+                self.screen_mut().clear((row, col), (row, end), style);
+            }
+            (false, b'S') => {
+                let (top, bottom) = self.region;
+                self.screen_mut().scroll_up(top, bottom, count, style);
+                self.scrolled_off(top, count.min(bottom - top));
+            }
+            (false, b'T') => {
+                let (top, bottom) = self.region;
+                self.screen_mut().scroll_down(top, bottom, count, style);
+            }
+            (false, b'L' | b'M') => {
+                let (top, bottom) = self.region;
+                if (top..bottom).contains(&row) {
+                    let screen = self.screen_mut();
+                    if final_byte == b'L' {
+                        screen.scroll_down(row, bottom, count, style);
+                    } else {
+                        screen.scroll_up(row, bottom, count, style);
+                    }
+                    self.move_to(row, 0);
+                }
+            }
+            (false, b'@' | b'P') => {
+                let count = count.min(width - col);
+                let screen = self.screen_mut();
+                let line = &mut screen.cells[row * width + col..(row + 1) * width];
+                if final_byte == b'@' {
+                    line.rotate_right(count);
+                    screen.clear((row, col), (row, col + count), style);
+                } else {
+                    line.rotate_left(count);
+                    screen.clear((row, width - count), (row + 1, 0), style);
+                }
+            }
+            (false, b'r') => {
+                let top = arg(0).max(1) - 1;
+                let bottom = if arg(1) == 0 {
+                    height
+                } else {
+                    arg(1).min(height)
+                };
+                if top + 1 < bottom {
+                    self.region = (top, bottom);
+                    self.move_to(0, 0);
+                }
+            }
+            (false, b'm') => self.sgr(&params),
+            (true, b'h' | b'l') => {
+                let set = final_byte == b'h';
+                for mode in params.iter().map(|p| p[0]) {
+                    match mode {
+                        25 => self.cursor_visible = set,
+                        1049 | 1047 | 47 => self.alternate_screen(set, mode == 1049),
+                        5 if set => self.alert(Alert::VisualBell),
+                        5 => {}
+                        // Keypad, cursor blinking and bracketed paste don't show on screen
+                        1 | 12 | 2004 => {}
+                        _ => self.unknown.push(sequence.clone()),
+                    }
+                }
+            }
+            // Cursor shape
+            (false, b'q') if sequence.ends_with(b" q") => {}
+            _ => self.unknown.push(sequence),
+        }
+    }
+
+    fn alternate_screen(&mut self, enter: bool, save_cursor: bool) {
+        if enter == self.alternate_active {
+            return;
+        }
+        if enter {
+            if save_cursor {
+                self.saved = self.cursor;
+            }
+            self.alternate = Buffer::new(self.width(), self.height());
+            self.alternate_active = true;
+        } else {
+            self.alternate_active = false;
+            if save_cursor {
+                self.cursor = self.saved;
+            }
+        }
+    }
+
+    fn sgr(&mut self, params: &[Vec<u16>]) {
+        let style = &mut self.cursor.style;
+        if params.is_empty() {
+            *style = Style::default();
+            return;
+        }
+        let mut params = params.iter();
+        while let Some(param) = params.next() {
+            match param[0] {
+                0 => *style = Style::default(),
+                1 => style.bold = true,
+                2 => style.dim = true,
+                3 => style.italic = true,
+                4 => style.underline = true,
+                5 => style.blink = true,
+                7 => style.reverse = true,
+                8 => style.invisible = true,
+                9 => style.strikethrough = true,
+                22 => {
+                    style.bold = false;
+                    style.dim = false;
+                }
+                23 => style.italic = false,
+                24 => style.underline = false,
+                25 => style.blink = false,
+                27 => style.reverse = false,
+                28 => style.invisible = false,
+                29 => style.strikethrough = false,
+                53 => style.overline = true,
+                55 => style.overline = false,
+                n @ 30..=37 => style.fg = Color::Indexed(n as u8 - 30),
+                n @ 90..=97 => style.fg = Color::Indexed(n as u8 - 90 + 8),
+                n @ 40..=47 => style.bg = Color::Indexed(n as u8 - 40),
+                n @ 100..=107 => style.bg = Color::Indexed(n as u8 - 100 + 8),
+                39 => style.fg = Color::Default,
+                49 => style.bg = Color::Default,
+                n @ (38 | 48) => {
+                    // Either `38:5:n` in one parameter or `38;5;n` spread over several
+                    let mut args: Vec<u16> = param[1..].to_vec();
+                    if args.is_empty() {
+                        let want = match params.clone().next().map(|p| p[0]) {
+                            Some(2) => 4,
+                            _ => 2,
+                        };
+                        args.extend(params.by_ref().take(want).map(|p| p[0]));
+                    } else if args[0] == 2 && args.len() == 5 {
+                        // Color space id
+                        args.remove(1);
+                    }
+                    let color = match args[..] {
+                        [5, index] => Color::Indexed(index as u8),
+                        [2, r, g, b] => Color::Rgb(r as u8, g as u8, b as u8),
+                        _ => continue,
+                    };
+                    if n == 38 {
+                        style.fg = color;
+                    } else {
+                        style.bg = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parameters separated by `;`, each with its `:` separated sub-parameters.
+fn parse_params(body: &str) -> Option<Vec<Vec<u16>>> {
+    if body.is_empty() {
+        return Some(Vec::new());
+    }
+    body.split(';')
+        .map(|param| {
+            param
+                .split(':')
+                .map(|sub| {
+                    if sub.is_empty() {
+                        Some(0)
+                    } else {
+                        sub.parse().ok()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Kind of an OSC 133 sequence, terminated with BEL or ST.
+fn parse_mark(sequence: &[u8]) -> Option<MarkKind> {
+    let body = sequence.strip_prefix(b"\x1B]133;")?;
+    let body = body
+        .strip_suffix(b"\x07")
+        .or_else(|| body.strip_suffix(b"\x1B\\"))?;
+    let body = std::str::from_utf8(body).ok()?;
+    let mut params = body.split(';');
+    let kind = match params.next()? {
+        "A" => MarkKind::PromptStart,
+        "B" => MarkKind::PromptEnd,
+        "C" => MarkKind::OutputStart,
+        "D" => MarkKind::OutputEnd(params.next().and_then(|code| code.parse().ok())),
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// OSC 9 of iTerm2 or OSC 777 of urxvt, but not the OSC 9;4 progress of ConEmu.
+fn is_notification(sequence: &[u8]) -> bool {
+    match sequence.strip_prefix(b"\x1B]9;") {
+        Some(body) => !body.starts_with(b"4;"),
+        None => sequence.starts_with(b"\x1B]777;notify;"),
+    }
+}
+
+/// Screen shown after writing `bytes` to a terminal of `width` x `height`.
+pub fn replay(bytes: &[u8], (width, height): (usize, usize)) -> Buffer {
+    let mut model = Model::new(width, height);
+    model.feed(bytes);
+    model.screen().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addressing_and_styles() {
+        let mut model = Model::new(6, 3);
+        model.feed(b"\x1B[2;3Hab\x1B[1;38;5;9mc\x1B[m\x1B[1;1H\x1B[7;38:2::1:2:3mx");
+        let screen = model.screen();
+        assert_eq!(screen.text(), "x\n  abc\n\n");
+        assert_eq!(model.cursor(), (0, 1));
+        let c = &screen.cell(1, 4).unwrap().style;
+        assert!(c.bold);
+        assert_eq!(c.fg, Color::Indexed(9));
+        let x = &screen.cell(0, 0).unwrap().style;
+        assert!(x.reverse && !x.bold);
+        assert_eq!(x.fg, Color::Rgb(1, 2, 3));
+        assert_eq!(screen.cell(1, 2).unwrap().style, Style::default());
+
+        let screen = replay(b"\x1B[9;53ma\x1B[29mb\x1B[55mc", (3, 1));
+        let style = |col| screen.cell(0, col).unwrap().style;
+        assert!(style(0).strikethrough && style(0).overline);
+        assert!(!style(1).strikethrough && style(1).overline);
+        assert_eq!(style(2), Style::default());
+    }
+
+    #[test]
+    fn wrapping_and_wide_characters() {
+        let screen = replay("abcd漢字e\u{301}".as_bytes(), (5, 3));
+        assert_eq!(screen.text(), "abcd\n漢字e\u{301}\n\n");
+        assert_eq!(screen.cell(1, 1).unwrap().symbol, "");
+        assert_eq!(screen.cell(1, 4).unwrap().symbol, "e\u{301}");
+        // Writing the last column only wraps with the next character
+        let screen = replay(b"abcde\rx", (5, 2));
+        assert_eq!(screen.text(), "xbcde\n\n");
+        // Too wide for the row, also on the left half of a double width row
+        assert_eq!(replay("漢".as_bytes(), (1, 1)).text(), "\n");
+        assert_eq!(replay("漢a".as_bytes(), (1, 2)).text(), "a\n\n");
+        assert_eq!(replay("\x1B#6漢b".as_bytes(), (3, 1)).text(), "b\n");
+    }
+
+    #[test]
+    fn empty_sizes() {
+        for size in [(0, 0), (0, 3), (3, 0)] {
+            let mut model = Model::new(size.0, size.1);
+            model.feed("ab\r\n漢c".as_bytes());
+            let screen = model.screen();
+            assert_eq!(
+                (screen.width(), screen.height()),
+                (size.0.max(1), size.1.max(1))
+            );
+        }
+        assert_eq!(replay(b"ab\r\nc", (0, 0)).text(), "c\n");
+        let mut screen = Buffer::new(0, 0);
+        assert_eq!(screen.text(), "\n");
+        screen.resize_anchored(0, 0, Corner::BottomRight, Cell::default());
+        assert_eq!((screen.width(), screen.height()), (1, 1));
+    }
+
+    #[test]
+    fn resize_anchored() {
+        let fill = Cell {
+            symbol: ".".into(),
+            style: Style::default(),
+        };
+        let screen = replay(b"abc\r\ndef\r\nghi", (3, 3));
+        let resize = |width, height, corner| {
+            let mut resized = screen.clone();
+            let report = resized.resize_anchored(width, height, corner, fill.clone());
+            (resized.text(), report)
+        };
+
+        let (text, report) = resize(2, 2, Corner::TopLeft);
+        assert_eq!(text, "ab\nde\n");
+        assert_eq!((report.rows_below, report.cols_right), (1, 1));
+        let (text, report) = resize(2, 2, Corner::BottomRight);
+        assert_eq!(text, "ef\nhi\n");
+        assert_eq!((report.rows_above, report.cols_left), (1, 1));
+        assert_eq!((report.rows_below, report.cols_right), (0, 0));
+        let (text, report) = resize(4, 2, Corner::BottomLeft);
+        assert_eq!(text, "def.\nghi.\n");
+        assert_eq!(
+            report,
+            ResizeReport {
+                rows_above: 1,
+                ..ResizeReport::default()
+            }
+        );
+        let (text, report) = resize(4, 4, Corner::TopRight);
+        assert_eq!(text, ".abc\n.def\n.ghi\n....\n");
+        assert_eq!(report, ResizeReport::default());
+
+        // Wide characters cut in half at either edge
+        let wide = replay("漢字x\r\nabc漢".as_bytes(), (5, 2));
+        let mut resized = wide.clone();
+        let report = resized.resize_anchored(3, 2, Corner::TopLeft, fill.clone());
+        assert_eq!(resized.text(), "漢.\nabc\n");
+        assert_eq!((report.cols_right, report.split_wide), (2, 1));
+        let mut resized = wide;
+        let report = resized.resize_anchored(2, 2, Corner::TopRight, fill.clone());
+        assert_eq!(resized.text(), ".x\n漢\n");
+        assert_eq!(resized.cell(1, 1).unwrap().symbol, "");
+        assert_eq!((report.cols_left, report.split_wide), (3, 1));
+    }
+
+    #[test]
+    fn annotated() {
+        let screen = replay(
+            "\x1B[1;31mab\x1B[m漢\x1B[4;48;2;255;135;0m!\x1B[m\r\ne\u{301}".as_bytes(),
+            (6, 2),
+        );
+        let text = screen.annotated();
+        assert_eq!(
+            text,
+            "|ab漢! |\n  0..2 bold fg=1\n  4..5 underline bg=#ff8700\n|e\u{301}     |\n"
+        );
+        assert_eq!(Buffer::from_annotated(&text), Some(screen));
+        assert_eq!(Buffer::from_annotated("|ab|\n|abc|\n"), None);
+        assert_eq!(Buffer::from_annotated("|ab|\n  0..1 shiny\n"), None);
+        assert_eq!(Buffer::from_annotated(""), None);
+        assert_eq!(Buffer::from_annotated("||\n"), None);
+    }
+
+    #[test]
+    fn erasing() {
+        let fill = b"abcd\r\nefgh\r\nijkl";
+        let mut model = Model::new(4, 3);
+        model.feed(fill);
+        model.feed(b"\x1B[2;2H\x1B[K\x1B[3;3H\x1B[1K");
+        assert_eq!(model.screen().text(), "abcd\ne\n   l\n");
+        model.feed(b"\x1B[1;3H\x1B[J");
+        assert_eq!(model.screen().text(), "ab\n\n\n");
+        model.feed(b"\x1B[H\x1B[2J");
+        assert_eq!(model.screen().text(), "\n\n\n");
+    }
+
+    #[test]
+    fn scroll_region() {
+        let mut model = Model::new(3, 4);
+        model.feed(b"1\r\n2\r\n3\r\n4");
+        model.feed(b"\x1B[2;3r\x1B[3;1H\n");
+        assert_eq!(model.screen().text(), "1\n3\n\n4\n");
+        model.feed(b"\x1B[2;1H\x1BM");
+        assert_eq!(model.screen().text(), "1\n\n3\n4\n");
+        model.feed(b"\x1B[r\x1B[4;1H\n");
+        assert_eq!(model.screen().text(), "\n3\n4\n\n");
+    }
+
+    #[test]
+    fn line_sizes() {
+        let mut model = Model::new(6, 4);
+        model.feed(b"abcdef\r\n\x1B#3Big\r\n\x1B#4Big\r\nwide");
+        assert_eq!(model.screen().line_size(0), LineSize::Single);
+        assert_eq!(model.screen().line_size(1), LineSize::DoubleHeightTop);
+        assert_eq!(model.screen().line_size(2), LineSize::DoubleHeightBottom);
+        // Half the columns are left on double size rows, the right half is dropped
+        model.feed(b"\x1B#6");
+        assert_eq!(model.screen().row_width(3), 3);
+        assert_eq!(model.screen().text(), "abcdef\nBig\nBig\nwid\n");
+        assert_eq!(model.cursor(), (3, 2));
+        model.feed(b"\x1B[4;1Hxyzw");
+        assert!(model.screen().is_wrapped(2));
+        assert_eq!(model.screen().text(), "Big\nBig\nxyz\nw\n");
+        assert_eq!(model.screen().line_size(2), LineSize::DoubleWidth);
+        assert_eq!(model.screen().line_size(3), LineSize::Single);
+
+        model.feed(b"\x1B#5\x1B[3;9H!");
+        assert_eq!(model.screen().line_size(3), LineSize::Single);
+        assert_eq!(model.screen().text(), "Big\nBig\nxy!\nw\n");
+        // Erasing the whole screen resets every row
+        model.feed(b"\x1B[2J");
+        assert_eq!(model.screen().line_size(0), LineSize::Single);
+        assert_eq!(model.screen().line_size(2), LineSize::Single);
+    }
+
+    #[test]
+    fn alerts() {
+        let mut model = Model::new(4, 2);
+        model.feed(b"a\x07b\x1B[?5h\x1B[?5l\x1B]9;4;1;50\x07");
+        model.feed(b"\x1B]9;done\x07\x1B]777;notify;make;ok\x1B\\");
+        assert_eq!(model.screen().text(), "ab\n\n");
+        assert!(model.is_urgent());
+        assert_eq!(
+            model.take_alerts(),
+            [
+                Alert::Bell,
+                Alert::VisualBell,
+                Alert::Notification(b"\x1B]9;done\x07".to_vec()),
+                Alert::Notification(b"\x1B]777;notify;make;ok\x1B\\".to_vec()),
+            ]
+        );
+        // Progress reports aren't notifications
+        assert_eq!(model.unknown(), [b"\x1B]9;4;1;50\x07"]);
+        assert!(model.take_alerts().is_empty());
+        assert!(model.is_urgent());
+        model.clear_urgent();
+        assert!(!model.is_urgent());
+    }
+
+    #[test]
+    fn alternate_screen() {
+        let mut model = Model::new(3, 2);
+        model.feed(b"ab\x1B[?1049h\x1B[Hxy\x1B[?25l");
+        assert!(model.is_alternate_screen());
+        assert!(!model.cursor_visible());
+        assert_eq!(model.screen().text(), "xy\n\n");
+        model.feed(b"\x1B[?1049l");
+        assert_eq!(model.screen().text(), "ab\n\n");
+        assert_eq!(model.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn unknown_sequences_are_collected() {
+        let mut model = Model::new(3, 1);
+        model.feed(b"\x1B]0;title\x07a\x1B]8;;x\x1B\\b\x1B[5ic\x1B[?1000h");
+        assert_eq!(model.screen().text(), "abc\n");
+        assert_eq!(
+            model.unknown(),
+            [
+                &b"\x1B]0;title\x07"[..],
+                b"\x1B]8;;x\x1B\\",
+                b"\x1B[5i",
+                b"\x1B[?1000h"
+            ]
+        );
+    }
+
+    #[test]
+    fn shell_integration_marks() {
+        // Captured from bash with a PS0/PS1 shell integration, BEL and ST both occur
+        let output = concat!(
+            "\x1B]133;A\x07$ \x1B]133;B\x07ls\r\n",
+            "\x1B]133;C\x07a b\r\n\x1B]133;D;0\x07",
+            "\x1B]133;A;cl=m;aid=7\x1B\\$ \x1B]133;B\x1B\\false\r\n",
+            "\x1B]133;C\x07\x1B]133;D;1\x07\x1B]133;A\x07$ \x1B]133;D\x07",
+        );
+        let mut model = Model::new(10, 3);
+        model.feed(output.as_bytes());
+        assert_eq!(model.screen().text(), "a b\n$ false\n$\n");
+        assert!(model.unknown().is_empty());
+        assert_eq!(model.scrolled_lines(), 1);
+
+        let mark = |kind, line, col| Mark { kind, line, col };
+        use MarkKind::*;
+        assert_eq!(
+            model.marks(),
+            [
+                mark(PromptStart, 0, 0),
+                mark(PromptEnd, 0, 2),
+                mark(OutputStart, 1, 0),
+                mark(OutputEnd(Some(0)), 2, 0),
+                mark(PromptStart, 2, 0),
+                mark(PromptEnd, 2, 2),
+                mark(OutputStart, 3, 0),
+                mark(OutputEnd(Some(1)), 3, 0),
+                mark(PromptStart, 3, 0),
+                mark(OutputEnd(None), 3, 2),
+            ]
+        );
+        assert_eq!(model.previous_prompt(3).unwrap().line, 2);
+        assert_eq!(model.previous_prompt(2).unwrap().line, 0);
+        assert_eq!(model.previous_prompt(0), None);
+    }
+
+    #[test]
+    fn extract_wrapped_text() {
+        let text = "The quick brown fox jumps over the lazy dog.";
+        let mut model = Model::new(12, 7);
+        // The space at the end of the first row is part of the text, not padding
+        model.feed(format!("{text}\r\nhello world again\r\nend   ").as_bytes());
+        let screen = model.screen();
+        let lines = |rows| screen.extract_text(rows, 0..12, ExtractMode::Lines);
+        assert_eq!(lines(0..4), text);
+        assert_eq!(lines(4..7), "hello world again\nend");
+        assert_eq!(lines(0..7), format!("{text}\nhello world again\nend"));
+        // Cut off columns never join rows
+        assert_eq!(
+            screen.extract_text(0..2, 0..11, ExtractMode::Lines),
+            "The quick b\nown fox jum"
+        );
+        assert_eq!(
+            screen.extract_text(1..3, 2..6, ExtractMode::Block),
+            "n fo\nover"
+        );
+        assert_eq!(
+            screen.extract_text(6..7, 0..12, ExtractMode::Block),
+            "end         "
+        );
+
+        // The markers move with scrolled rows and go away with erased ones
+        model.feed(b"\r\n");
+        assert!(model.screen().is_wrapped(0) && !model.screen().is_wrapped(2));
+        assert!(model.screen().is_wrapped(3));
+        model.feed(b"\x1B[4;1H\x1B[K");
+        assert!(!model.screen().is_wrapped(3));
+        assert_eq!(
+            model.screen().extract_text(3..5, 0..12, ExtractMode::Lines),
+            "\nagain"
+        );
+    }
+}
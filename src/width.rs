@@ -0,0 +1,245 @@
+//! Number of terminal columns text takes up.
+//!
+//! Terminals disagree on a few classes of characters, [`Policy`] picks
+//! which behavior to assume. Text is measured per grapheme cluster,
+//! so combining marks never add a column of their own.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+use crate::env::EnvSnapshot;
+use crate::tty::OutputEncoding;
+
+const ZWJ: char = '\u{200D}';
+const VS16: char = '\u{FE0F}';
+
+/// Width of characters whose East Asian Width is Ambiguous, e. g. `±`, `×` or `→`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Ambiguous {
+    #[default]
+    Narrow,
+    /// Terminals usually render them wide in CJK locales.
+    Wide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub ambiguous: Ambiguous,
+    /// Emoji forced to emoji presentation with U+FE0F and flags take two columns.
+    /// When off, they are as wide as their characters on their own.
+    pub emoji_presentation: bool,
+    /// Emoji joined with U+200D take the columns of one emoji.
+    /// When off, each joined emoji is counted.
+    pub join_zwj: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            ambiguous: Ambiguous::Narrow,
+            emoji_presentation: true,
+            join_zwj: true,
+        }
+    }
+}
+
+impl Policy {
+    /// Default policy with ambiguous characters wide for Chinese, Japanese and Korean locales.
+    pub fn from_locale(locale: &str) -> Self {
+        let cjk = ["zh", "ja", "ko"].iter().any(|lang| {
+            locale
+                .get(..2)
+                .is_some_and(|l| l.eq_ignore_ascii_case(lang))
+        });
+        Self {
+            ambiguous: if cjk {
+                Ambiguous::Wide
+            } else {
+                Ambiguous::Narrow
+            },
+            ..Self::default()
+        }
+    }
+
+    /// [`Policy::from_locale`] for the locale of the process, `LC_ALL`, `LC_CTYPE` or `LANG`.
+    pub fn from_env() -> Self {
+        Self::detect(&EnvSnapshot::capture())
+    }
+
+    /// [`Policy::from_locale`] for [`EnvSnapshot::locale`], [`Policy::single_byte`]
+    /// when its codeset isn't UTF-8.
+    pub fn detect(env: &EnvSnapshot) -> Self {
+        match OutputEncoding::detect_or_ascii(env) {
+            OutputEncoding::Utf8 => Self::from_locale(env.locale().unwrap_or_default()),
+            encoding => encoding.width_policy(),
+        }
+    }
+
+    /// Terminals with a single byte encoding show every character in one column, and
+    /// text converted for them is replaced character by character, see [`OutputEncoding`].
+    pub fn single_byte() -> Self {
+        Self {
+            ambiguous: Ambiguous::Narrow,
+            emoji_presentation: false,
+            join_zwj: false,
+        }
+    }
+}
+
+/// Columns of a single character, `None` for control characters.
+pub fn char_width(ch: char, policy: &Policy) -> Option<usize> {
+    if ch.is_control() {
+        return None;
+    }
+    match policy.ambiguous {
+        Ambiguous::Narrow => ch.width(),
+        Ambiguous::Wide => ch.width_cjk(),
+    }
+}
+
+fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch)
+}
+
+/// Columns of a single grapheme cluster. Control characters take none.
+pub fn grapheme_width(grapheme: &str, policy: &Policy) -> usize {
+    if !policy.join_zwj && grapheme.contains(ZWJ) {
+        return grapheme
+            .split(ZWJ)
+            .map(|part| grapheme_width(part, policy))
+            .sum();
+    }
+    let mut chars = grapheme.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+    if is_regional_indicator(first) {
+        let count = 1 + chars.filter(|&ch| is_regional_indicator(ch)).count();
+        return if policy.emoji_presentation && count == 2 {
+            2
+        } else {
+            count
+        };
+    }
+    let width = char_width(first, policy).unwrap_or(0);
+    if policy.emoji_presentation && grapheme.contains(VS16) {
+        return 2;
+    }
+    width
+}
+
+/// Columns of `text`. Control characters take none.
+pub fn str_width(text: &str, policy: &Policy) -> usize {
+    text.graphemes(true)
+        .map(|grapheme| grapheme_width(grapheme, policy))
+        .sum()
+}
+
+/// Longest prefix of `text` that fits in `cells` columns, with the columns it takes.
+/// Grapheme clusters are never split, so the prefix can be narrower than `cells`.
+pub fn truncate_to_width<'a>(text: &'a str, cells: usize, policy: &Policy) -> (&'a str, usize) {
+    let mut used = 0;
+    for (start, grapheme) in text.grapheme_indices(true) {
+        let width = grapheme_width(grapheme, policy);
+        if used + width > cells {
+            return (&text[..start], used);
+        }
+        used += width;
+    }
+    (text, used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NARROW: Policy = Policy {
+        ambiguous: Ambiguous::Narrow,
+        emoji_presentation: true,
+        join_zwj: true,
+    };
+    const WIDE: Policy = Policy {
+        ambiguous: Ambiguous::Wide,
+        ..NARROW
+    };
+    const LEGACY: Policy = Policy {
+        emoji_presentation: false,
+        join_zwj: false,
+        ..NARROW
+    };
+
+    #[test]
+    fn widths() {
+        // text, narrow, wide, legacy emoji
+        let table = [
+            ("abc", 3, 3, 3),
+            ("caf\u{E9}", 4, 4, 4),
+            ("cafe\u{301}", 4, 4, 4),
+            ("a\u{301}\u{316}\u{35C}", 1, 1, 1),
+            ("漢字", 4, 4, 4),
+            ("\u{1F1FA}\u{1F1E6}", 2, 2, 2),
+            ("\u{1F1FA}\u{1F1E6}\u{1F1FA}", 3, 3, 3),
+            ("\u{2764}\u{FE0F}", 2, 2, 1),
+            ("\u{1F600}", 2, 2, 2),
+            ("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}", 2, 2, 6),
+            ("\u{B1}\u{D7}\u{2192}", 3, 6, 3),
+            ("a\tb\x1B", 2, 2, 2),
+            ("", 0, 0, 0),
+        ];
+        for (text, narrow, wide, legacy) in table {
+            assert_eq!(str_width(text, &NARROW), narrow, "{text:?} narrow");
+            assert_eq!(str_width(text, &WIDE), wide, "{text:?} wide");
+            assert_eq!(str_width(text, &LEGACY), legacy, "{text:?} legacy");
+        }
+        assert_eq!(char_width('\n', &NARROW), None);
+        assert_eq!(char_width('\u{301}', &NARROW), Some(0));
+    }
+
+    #[test]
+    fn truncate() {
+        let table = [
+            ("abc", 2, "ab", 2),
+            ("漢字", 3, "漢", 2),
+            ("漢字", 1, "", 0),
+            ("e\u{301}x", 1, "e\u{301}", 1),
+            ("\u{1F1FA}\u{1F1E6}!", 1, "", 0),
+            ("\u{1F1FA}\u{1F1E6}!", 2, "\u{1F1FA}\u{1F1E6}", 2),
+            (
+                "\u{1F468}\u{200D}\u{1F469}x",
+                2,
+                "\u{1F468}\u{200D}\u{1F469}",
+                2,
+            ),
+            ("abc", 10, "abc", 3),
+        ];
+        for (text, cells, prefix, used) in table {
+            assert_eq!(
+                truncate_to_width(text, cells, &NARROW),
+                (prefix, used),
+                "{text:?} in {cells}"
+            );
+        }
+        assert_eq!(truncate_to_width("\u{B1}\u{B1}", 3, &WIDE), ("\u{B1}", 2));
+    }
+
+    #[test]
+    fn locale() {
+        assert_eq!(
+            Policy::from_locale("ja_JP.UTF-8").ambiguous,
+            Ambiguous::Wide
+        );
+        assert_eq!(Policy::from_locale("zh_TW").ambiguous, Ambiguous::Wide);
+        assert_eq!(Policy::from_locale("en_US.UTF-8"), Policy::default());
+        assert_eq!(Policy::from_locale("C"), Policy::default());
+
+        let env = EnvSnapshot::default().lang("ko_KR.UTF-8");
+        assert_eq!(Policy::detect(&env).ambiguous, Ambiguous::Wide);
+        assert_eq!(
+            Policy::detect(&env.clone().lc_all("en_US")),
+            Policy::default()
+        );
+        // Converted text keeps the columns of each character
+        let latin1 = env.lc_all("ja_JP.ISO-8859-1");
+        assert_eq!(Policy::detect(&latin1), Policy::single_byte());
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
+use nix::libc;
+use nix::pty::openpty;
+use nix::unistd::setsid;
+use nixtui_core::tty::claim_terminal;
+use nixtui_core::tty::errors::TtyError;
+
+const CHILD_VAR: &str = "NIXTUI_CLAIM_CHILD";
+
+/// Runs in the child process spawned by the other test, a no-op in a normal test run.
+#[test]
+fn child() {
+    if std::env::var_os(CHILD_VAR).is_none() {
+        return;
+    }
+    let mut pts = std::io::stdout().lock();
+    let _claim = claim_terminal(&pts).unwrap();
+    let dev_tty = File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .unwrap();
+    let err = claim_terminal(&dev_tty).unwrap_err();
+    assert!(matches!(err, TtyError::AlreadyAcquired));
+    pts.write_all(b"same terminal\n").unwrap();
+}
+
+#[test]
+fn dev_tty_is_the_controlling_terminal() {
+    let pty = openpty(None, None).unwrap();
+    let (mut master, slave) = (File::from(pty.master), File::from(pty.slave));
+    let mut command = Command::new(std::env::current_exe().unwrap());
+    command
+        .args(["--exact", "child", "--nocapture", "--test-threads=1"])
+        .env(CHILD_VAR, "1")
+        .stdin(Stdio::null())
+        .stdout(slave.try_clone().unwrap())
+        .stderr(Stdio::null());
+    // The pty becomes the controlling terminal, which /dev/tty opens
+    unsafe {
+        command.pre_exec(|| {
+            setsid()?;
+            if libc::ioctl(1, libc::TIOCSCTTY, 0) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().unwrap();
+    // Reading fails instead of blocking once the child is gone
+    drop((command, slave));
+
+    let mut output = Vec::new();
+    let mut buf = [0; 256];
+    while let Ok(count @ 1..) = master.read(&mut buf) {
+        output.extend_from_slice(&buf[..count]);
+    }
+    assert!(child.wait().unwrap().success());
+    assert!(output.windows(13).any(|w| w == b"same terminal"));
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::io::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use nix::pty::openpty;
+use nix::sys::termios::LocalFlags;
+use nix::unistd::pipe;
+use nixtui_core::testing::{read_output, sequences};
+#[cfg(not(feature = "terminfo"))]
+use nixtui_core::tty::Capabilities;
+use nixtui_core::tty::{AltScreenGuard, Sequences, UnixTerminal};
+
+fn sequence(expand: impl FnOnce(&mut Sequences)) -> Vec<u8> {
+    let mut caps = sequences();
+    expand(&mut caps);
+    caps.buffer().to_vec()
+}
+
+#[test]
+fn alt_screen_left_on_drop() {
+    let (reader, writer) = pipe().unwrap();
+    let (mut reader, mut writer) = (File::from(reader), File::from(writer));
+    let mut caps = sequences();
+    caps.write_all(b"before").unwrap();
+    let mut guard = AltScreenGuard::new(&mut writer, &mut caps).unwrap();
+    guard.write_all(b"inside").unwrap();
+
+    let mut expected = b"before".to_vec();
+    expected.extend(sequence(|caps| caps.enter_ca_mode().unwrap()));
+    expected.extend_from_slice(b"inside");
+    assert_eq!(read_output(&mut reader, None), expected);
+
+    drop(guard);
+    let exit = sequence(|caps| {
+        caps.exit_attribute_mode().unwrap();
+        caps.exit_ca_mode().unwrap();
+    });
+    assert_eq!(read_output(&mut reader, None), exit);
+}
+
+#[test]
+fn alt_screen_left_on_panic() {
+    let (reader, writer) = pipe().unwrap();
+    let (mut reader, mut writer) = (File::from(reader), File::from(writer));
+    let mut caps = sequences();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let _guard = AltScreenGuard::new(&mut writer, &mut caps).unwrap();
+        panic!("drawing failed");
+    }));
+    assert!(result.is_err());
+    let output = read_output(&mut reader, None);
+    let exit = sequence(|caps| {
+        caps.exit_attribute_mode().unwrap();
+        caps.exit_ca_mode().unwrap();
+    });
+    assert!(output.ends_with(&exit));
+}
+
+#[test]
+fn nested_guards_restore_in_reverse_order() {
+    let pty = openpty(None, None).unwrap();
+    let (mut master, mut tty) = (File::from(pty.master), File::from(pty.slave));
+    let mut caps = sequences();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut raw = tty.raw_mode_guard().unwrap();
+        let mut alt = AltScreenGuard::new(&mut *raw, &mut caps).unwrap();
+        let termios = alt.get_termios().unwrap();
+        assert!(!termios.local_flags.contains(LocalFlags::ICANON));
+        alt.write_all(b"inside").unwrap();
+        panic!("drawing failed");
+    }));
+    assert!(result.is_err());
+
+    let termios = tty.get_termios().unwrap();
+    assert!(termios
+        .local_flags
+        .contains(LocalFlags::ICANON | LocalFlags::ECHO));
+    let mut expected = sequence(|caps| caps.enter_ca_mode().unwrap());
+    expected.extend_from_slice(b"inside");
+    expected.extend(sequence(|caps| {
+        caps.exit_attribute_mode().unwrap();
+        caps.exit_ca_mode().unwrap();
+    }));
+    assert_eq!(read_output(&mut master, None), expected);
+}
@@ -0,0 +1,51 @@
+//! The panic hook is process wide, so these run in a test binary of their own.
+
+use std::io::Read;
+use std::os::unix::net::UnixStream;
+use std::panic::catch_unwind;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use nixtui_core::tty::{install_panic_restore, TerminalRestorer};
+
+/// What the restorer wrote, nothing if it didn't run.
+fn written(from: &mut UnixStream) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut buf = [0; 64];
+    while let Ok(count @ 1..) = from.read(&mut buf) {
+        output.extend_from_slice(&buf[..count]);
+    }
+    output
+}
+
+#[test]
+fn hook_removed_with_guard() {
+    let (tty, mut output) = UnixStream::pair().unwrap();
+    output.set_nonblocking(true).unwrap();
+    let previous_calls = Arc::new(AtomicUsize::new(0));
+    let calls = previous_calls.clone();
+    std::panic::set_hook(Box::new(move |_| {
+        calls.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    let guard = install_panic_restore(TerminalRestorer::new(&tty).bytes(b"restored"));
+    assert!(catch_unwind(|| panic!("first")).is_err());
+    assert_eq!(written(&mut output), b"restored");
+    assert_eq!(previous_calls.load(Ordering::SeqCst), 1);
+
+    // The previous hook is back
+    drop(guard);
+    assert!(catch_unwind(|| panic!("second")).is_err());
+    assert!(written(&mut output).is_empty());
+    assert_eq!(previous_calls.load(Ordering::SeqCst), 2);
+
+    // Another hook on top, this one stays in the chain without restoring
+    let guard = install_panic_restore(TerminalRestorer::new(&tty).bytes(b"restored"));
+    let below = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| below(info)));
+    drop(guard);
+    assert!(catch_unwind(|| panic!("third")).is_err());
+    assert!(written(&mut output).is_empty());
+    assert_eq!(previous_calls.load(Ordering::SeqCst), 3);
+    let _ = std::panic::take_hook();
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use nix::pty::{openpty, Winsize};
+use nixtui_core::testing::sequences;
+use nixtui_core::tty::Tty;
+use nixtui_core::ui::components::{Select, Selection};
+
+fn open_pty(rows: u16, cols: u16) -> (File, File) {
+    let size = Winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let pty = openpty(&size, None).unwrap();
+    (File::from(pty.master), File::from(pty.slave))
+}
+
+fn run_select(select: Select, first: &str, keys: &[&[u8]]) -> Option<Selection> {
+    let (mut master, slave) = open_pty(3, 20);
+    let runner = thread::spawn(move || {
+        let mut select = select;
+        let mut tty = Tty::from_fd(slave.into(), sequences()).unwrap();
+        select.run(&mut tty).unwrap()
+    });
+
+    // Entering raw mode flushes pending input, so wait for the first item to be drawn before
+    // typing.
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    while !output.windows(first.len()).any(|w| w == first.as_bytes()) {
+        let n = master.read(&mut buf).unwrap();
+        output.extend_from_slice(&buf[..n]);
+    }
+
+    // Drain the rest so the selector never blocks on a full pty buffer.
+    let mut reader = master.try_clone().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        while matches!(reader.read(&mut buf), Ok(n) if n > 0) {}
+    });
+
+    for chunk in keys {
+        master.write_all(chunk).unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+    runner.join().unwrap()
+}
+
+#[test]
+fn selects_third_item() {
+    let select = Select::new(["foo", "bar", "baz", "cow"]);
+    assert_eq!(
+        run_select(select, "foo", &[b"\x1B[B\x1B[B\r"]),
+        Some(Selection::Single(2))
+    );
+}
+
+#[test]
+fn escape_split_across_reads() {
+    let select = Select::new(["foo", "bar", "baz", "cow"]);
+    assert_eq!(
+        run_select(select, "foo", &[b"\x1B[B\x1B", b"[B\r"]),
+        Some(Selection::Single(2))
+    );
+}
+
+#[test]
+fn scrolls_past_terminal_height() {
+    let items: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+    let select = Select::new(items).prompt("Pick one");
+    assert_eq!(
+        run_select(select, "0", &[b"\x1B[B\x1B[B\x1B[B\x1B[B\x1B[B\x1B[B\r"]),
+        Some(Selection::Single(6))
+    );
+}
+
+#[test]
+fn ctrl_c_cancels() {
+    let select = Select::new(["foo", "bar"]);
+    assert_eq!(run_select(select, "foo", &[b"\x1B[B\x03"]), None);
+}
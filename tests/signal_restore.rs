@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use nix::libc;
+use nix::pty::{openpty, Winsize};
+use nix::sys::signal::{kill, Signal};
+use nix::sys::termios::{tcgetattr, LocalFlags};
+use nix::unistd::{setsid, Pid};
+use nixtui_core::testing::sequences;
+use nixtui_core::tty::{
+    install_signal_restore, run_external, OnSignal, TerminalRestorer, UnixTerminal,
+};
+
+const CHILD_VAR: &str = "NIXTUI_SIGNAL_RESTORE_CHILD";
+const RESTORE: &[u8] = b"\x1B[?1049l<restored>";
+
+/// Runs in the child process spawned by the other tests, a no-op in a normal test run.
+#[test]
+fn child() {
+    let Some(mode) = std::env::var_os(CHILD_VAR) else {
+        return;
+    };
+    let mut tty = std::io::stdout().lock();
+    let termios = tty.get_termios().unwrap();
+    let restorer = TerminalRestorer::new(&tty).termios(&termios).bytes(RESTORE);
+    let _guard = install_signal_restore(restorer, OnSignal::Terminate).unwrap();
+    tty.raw_mode().unwrap();
+    if mode == "external" {
+        // Ctrl+C typed in the other program reaches this process too
+        run_external(&mut tty, &mut sequences(), &termios, || {
+            // Sent to this thread, the handler has run when raise returns
+            assert_eq!(unsafe { libc::raise(libc::SIGINT) }, 0);
+        })
+        .unwrap();
+        tty.write_all(b"survived\n").unwrap();
+        return;
+    }
+    if mode == "ctrl_c" {
+        let (status, _) = run_external(&mut tty, &mut sequences(), &termios, || {
+            Command::new("sh")
+                .args(["-c", "echo started; exec sleep 10"])
+                .status()
+                .unwrap()
+        })
+        .unwrap();
+        assert_eq!(status.signal(), Some(Signal::SIGINT as i32));
+        tty.write_all(b"survived\n").unwrap();
+        return;
+    }
+    tty.write_all(b"ready\n").unwrap();
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn read_until(master: &mut File, pattern: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut buf = [0; 256];
+    while !output.windows(pattern.len()).any(|w| w == pattern) {
+        let count = master.read(&mut buf).unwrap();
+        assert_ne!(count, 0, "output so far: {output:?}");
+        output.extend_from_slice(&buf[..count]);
+    }
+    output
+}
+
+fn spawn_child(mode: &str) -> (File, File, Child) {
+    let size = Winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let pty = openpty(&size, None).unwrap();
+    let (master, slave) = (File::from(pty.master), File::from(pty.slave));
+
+    let mut command = Command::new(std::env::current_exe().unwrap());
+    command
+        .args(["--exact", "child", "--nocapture", "--test-threads=1"])
+        .env(CHILD_VAR, mode)
+        .stdin(Stdio::null())
+        .stdout(slave.try_clone().unwrap())
+        .stderr(Stdio::null());
+    // The pty becomes the controlling terminal, so Ctrl+C typed on it reaches the child
+    unsafe {
+        command.pre_exec(|| {
+            setsid()?;
+            if libc::ioctl(1, libc::TIOCSCTTY, 0) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let child = command.spawn().unwrap();
+    (master, slave, child)
+}
+
+#[test]
+fn sigterm_restores_terminal() {
+    let (mut master, slave, mut child) = spawn_child("1");
+
+    read_until(&mut master, b"ready\n");
+    assert!(!tcgetattr(&slave)
+        .unwrap()
+        .local_flags
+        .contains(LocalFlags::ECHO));
+
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).unwrap();
+    read_until(&mut master, RESTORE);
+    let status = child.wait().unwrap();
+    assert_eq!(status.signal(), Some(Signal::SIGTERM as i32));
+    assert!(tcgetattr(&slave)
+        .unwrap()
+        .local_flags
+        .contains(LocalFlags::ECHO));
+}
+
+#[test]
+fn sigint_ignored_during_external_program() {
+    let (mut master, slave, mut child) = spawn_child("external");
+    // Reading fails instead of blocking once the child is gone
+    drop(slave);
+    let output = read_until(&mut master, b"survived\n");
+    assert!(child.wait().unwrap().success());
+    assert!(!output.windows(RESTORE.len()).any(|w| w == RESTORE));
+}
+
+#[test]
+fn ctrl_c_reaches_only_external_program() {
+    let (mut master, slave, mut child) = spawn_child("ctrl_c");
+    drop(slave);
+    read_until(&mut master, b"started");
+    master.write_all(b"\x03").unwrap();
+    let output = read_until(&mut master, b"survived\n");
+    assert!(child.wait().unwrap().success());
+    assert!(!output.windows(RESTORE.len()).any(|w| w == RESTORE));
+}